@@ -0,0 +1,17 @@
+/// Implements proptest's `Arbitrary` with the given strategy, when the
+/// `proptest` feature is enabled or in tests.
+macro_rules! arbitrary_impl {
+    ($type:ty, $strategy:expr) => {
+        #[cfg(any(test, feature = "proptest"))]
+        impl proptest::arbitrary::Arbitrary for $type {
+            type Parameters = ();
+            type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                #[allow(unused_imports)]
+                use proptest::prelude::*;
+                $strategy.boxed()
+            }
+        }
+    };
+}
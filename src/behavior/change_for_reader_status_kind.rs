@@ -1,5 +1,5 @@
 /// Enumeration used to indicate the status of a ChangeForReader
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ChangeForReaderStatusKind {
     UNSENT,
     UNACKNOWLEDGED,
@@ -0,0 +1,14 @@
+use crate::behavior::change_from_writer_status_kind::ChangeFromWriterStatusKind;
+
+/// The RTPS ChangeFromWriter is an association class that maintains
+/// information of a CacheChange in the RTPS Reader HistoryCache as it pertains
+/// to the RTPS Writer represented by the WriterProxy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChangeFromWriter {
+    /// Indicates the status of a CacheChange relative to the RTPS Writer
+    /// represented by the WriterProxy.
+    pub status: ChangeFromWriterStatusKind,
+
+    /// Indicates whether the change is relevant to the RTPS Reader.
+    pub is_relevant: bool,
+}
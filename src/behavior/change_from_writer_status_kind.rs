@@ -0,0 +1,9 @@
+/// Enumeration used to indicate the status of a ChangeFromWriter
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChangeFromWriterStatusKind {
+    LOST,
+    MISSING,
+    RECEIVED,
+    UNKNOWN,
+}
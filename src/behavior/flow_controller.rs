@@ -0,0 +1,284 @@
+use crate::structure::guid::GUID_t;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Limits the number of octets the writers sharing it send per period, e.g.
+/// the writers of a participant on a link of limited bandwidth.
+///
+/// The FlowController is a token bucket holding up to max_bytes_per_period
+/// tokens, which refills at the rate of max_bytes_per_period per period.
+/// Sending a message takes as many tokens as it has octets. A message larger
+/// than the bucket is sent once the bucket is full, and leaves it in debt:
+/// the tokens it took beyond the bucket are refilled before any other message
+/// is sent, so that the rate holds for the large messages as well.
+///
+/// A writer that was refused tokens waits for them for a period, during
+/// which the writers of a lower priority are refused, so that the tokens go
+/// to the writers of the highest priority first.
+#[derive(Debug)]
+pub struct FlowController {
+    max_bytes_per_period: usize,
+    period: Duration,
+
+    /// Negative while the bucket is in debt.
+    tokens: i64,
+
+    /// The tokens are added from this instant on.
+    last_refill: Instant,
+
+    /// The writers that were refused tokens, with their priority and when
+    /// they were last refused.
+    waiting: BTreeMap<GUID_t, (i32, Instant)>,
+}
+
+impl FlowController {
+    /// Creates a FlowController with a full bucket.
+    pub fn new(max_bytes_per_period: usize, period: Duration, now: Instant) -> FlowController {
+        FlowController {
+            max_bytes_per_period,
+            period,
+            tokens: max_bytes_per_period as i64,
+            last_refill: now,
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    pub fn max_bytes_per_period(&self) -> usize {
+        self.max_bytes_per_period
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// The number of octets that can be sent right away.
+    pub fn available(&mut self, now: Instant) -> usize {
+        self.refill(now);
+        self.tokens.max(0) as usize
+    }
+
+    /// Takes the tokens to send a message of len octets on behalf of the
+    /// writer, and tells whether it can be sent. If it can't, the writer
+    /// waits for the tokens for a period, unless it tries again.
+    pub fn try_send(
+        &mut self,
+        writer_guid: &GUID_t,
+        priority: i32,
+        len: usize,
+        now: Instant,
+    ) -> bool {
+        self.refill(now);
+        let period = self.period;
+        self.waiting
+            .retain(|_, (_, refused)| now.saturating_duration_since(*refused) < period);
+
+        let preceded = self
+            .waiting
+            .iter()
+            .any(|(waiting_guid, (waiting_priority, _))| {
+                waiting_guid != writer_guid && *waiting_priority > priority
+            });
+        let is_full = self.tokens == self.max_bytes_per_period as i64;
+        let enough_tokens = len as i64 <= self.tokens || is_full;
+        if preceded || !enough_tokens {
+            self.waiting.insert(*writer_guid, (priority, now));
+            return false;
+        }
+
+        self.tokens = self.tokens.saturating_sub(len as i64);
+        self.waiting.remove(writer_guid);
+        true
+    }
+
+    /// When the writer can expect try_send to let a message of len octets
+    /// through: once the bucket holds them, or is full if it can't hold them,
+    /// and no writer of a higher priority waits for tokens any longer.
+    pub fn available_at(
+        &mut self,
+        writer_guid: &GUID_t,
+        priority: i32,
+        len: usize,
+        now: Instant,
+    ) -> Instant {
+        self.refill(now);
+        let needed = len.min(self.max_bytes_per_period) as i64;
+        let refilled = if needed <= self.tokens {
+            now
+        } else {
+            let missing = (needed - self.tokens) as u128;
+            let nanos =
+                (missing * self.period.as_nanos()).div_ceil(self.max_bytes_per_period as u128);
+            self.last_refill + Duration::from_nanos(nanos as u64)
+        };
+
+        self.waiting
+            .iter()
+            .filter(|(waiting_guid, (waiting_priority, _))| {
+                *waiting_guid != writer_guid && *waiting_priority > priority
+            })
+            .map(|(_, (_, refused))| *refused + self.period)
+            .fold(refilled, Instant::max)
+    }
+
+    /// Adds the tokens accumulated since the last refill. The time of the
+    /// fractions of tokens not added yet is kept for the next refill.
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let period = self.period.as_nanos().max(1);
+        let added = elapsed.as_nanos() * self.max_bytes_per_period as u128 / period;
+        let max_tokens = self.max_bytes_per_period as i128;
+        if i128::from(self.tokens) + added as i128 >= max_tokens {
+            self.tokens = self.max_bytes_per_period as i64;
+            self.last_refill = now;
+        } else if added > 0 {
+            self.tokens += added as i64;
+            let used = added * period / self.max_bytes_per_period as u128;
+            self.last_refill += Duration::from_nanos(used as u64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer_guid(key: u8) -> GUID_t {
+        format!("01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.{:02x}.02", key)
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn throughput_is_bounded_by_the_refill_rate() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let mut flow_controller = FlowController::new(1000, period, start);
+
+        // A writer sending 300 octets every millisecond for 10 periods only
+        // gets the initial bucket plus one bucket per period through.
+        let mut sent = 0;
+        for millis in 0..1000 {
+            let now = start + Duration::from_millis(millis);
+            if flow_controller.try_send(&writer_guid(1), 0, 300, now) {
+                sent += 300;
+            }
+        }
+        assert!(sent <= 1000 + 10 * 1000, "sent {} octets", sent);
+        assert!(sent >= 10 * 1000 - 300, "sent {} octets", sent);
+    }
+
+    #[test]
+    fn tokens_refill_gradually_up_to_the_bucket_size() {
+        let start = Instant::now();
+        let period = Duration::from_secs(1);
+        let mut flow_controller = FlowController::new(1000, period, start);
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 1000, start));
+        assert_eq!(0, flow_controller.available(start));
+
+        // The fractions of tokens are not lost to frequent refills.
+        for micros in 1..=1500 {
+            flow_controller.available(start + Duration::from_micros(micros));
+        }
+        assert_eq!(
+            1,
+            flow_controller.available(start + Duration::from_micros(1500))
+        );
+        assert_eq!(
+            250,
+            flow_controller.available(start + Duration::from_millis(250))
+        );
+        assert_eq!(
+            1000,
+            flow_controller.available(start + Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn message_larger_than_the_bucket_is_sent_once_it_is_full() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let mut flow_controller = FlowController::new(1000, period, start);
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 10, start));
+        assert!(!flow_controller.try_send(&writer_guid(1), 0, 3000, start));
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 3000, start + period));
+        assert_eq!(0, flow_controller.available(start + period));
+
+        // The tokens it took beyond the bucket are refilled first.
+        let repaid = start + 3 * period;
+        assert!(!flow_controller.try_send(
+            &writer_guid(1),
+            0,
+            10,
+            repaid - Duration::from_millis(1)
+        ));
+        assert_eq!(0, flow_controller.available(repaid));
+        assert_eq!(
+            repaid + Duration::from_millis(1),
+            flow_controller.available_at(&writer_guid(1), 0, 10, repaid)
+        );
+        assert!(flow_controller.try_send(
+            &writer_guid(1),
+            0,
+            10,
+            repaid + Duration::from_millis(1)
+        ));
+    }
+
+    #[test]
+    fn available_at_tells_when_the_tokens_are_refilled() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let mut flow_controller = FlowController::new(1000, period, start);
+        assert_eq!(
+            start,
+            flow_controller.available_at(&writer_guid(1), 0, 10, start)
+        );
+
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 1000, start));
+        let now = start + Duration::from_millis(5);
+        let available_at = flow_controller.available_at(&writer_guid(1), 0, 300, now);
+        assert_eq!(start + Duration::from_millis(30), available_at);
+        assert!(!flow_controller.try_send(
+            &writer_guid(1),
+            0,
+            300,
+            available_at - Duration::from_millis(1)
+        ));
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 300, available_at));
+
+        // A message larger than the bucket waits for it to be full, and the
+        // writers of a lower priority for the waiting ones.
+        let now = start + Duration::from_millis(30);
+        assert_eq!(
+            start + Duration::from_millis(130),
+            flow_controller.available_at(&writer_guid(1), 0, 5000, now)
+        );
+        assert!(!flow_controller.try_send(&writer_guid(2), 5, 100, now));
+        assert_eq!(
+            now + period,
+            flow_controller.available_at(&writer_guid(1), 0, 10, now)
+        );
+    }
+
+    #[test]
+    fn writers_of_higher_priority_are_served_first() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let mut flow_controller = FlowController::new(1000, period, start);
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 800, start));
+
+        // The high priority writer waits for the tokens, which the low
+        // priority one can't take meanwhile.
+        assert!(!flow_controller.try_send(&writer_guid(2), 5, 400, start));
+        let now = start + Duration::from_millis(20);
+        assert!(!flow_controller.try_send(&writer_guid(1), 0, 100, now));
+        let now = start + Duration::from_millis(30);
+        assert!(flow_controller.try_send(&writer_guid(2), 5, 400, now));
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 100, now));
+
+        // A writer that stopped trying no longer holds the others back.
+        assert!(!flow_controller.try_send(&writer_guid(2), 5, 1000, now));
+        let later = now + period;
+        assert!(flow_controller.try_send(&writer_guid(1), 0, 100, later));
+    }
+}
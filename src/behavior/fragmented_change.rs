@@ -0,0 +1,309 @@
+use crate::dds::qos::LENGTH_UNLIMITED;
+use crate::messages::data::{Data, DataContext};
+use crate::messages::data_frag::DataFrag;
+use crate::messages::data_submessage_flags::DataSubmessageFlags;
+use crate::messages::fragment_number::FragmentNumber_t;
+use crate::messages::fragment_number_set::FragmentNumberSet_t;
+use crate::messages::submessage_elements::parameter_list::ParameterList;
+use crate::messages::submessage_elements::serialized_payload::SerializedPayload;
+use crate::messages::submessage_flag::SubmessageFlag;
+use crate::structure::entity_id::EntityId_t;
+use crate::structure::sequence_number::SequenceNumber_t;
+use speedy::Readable;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// A change received as DataFrag submessages, which is reassembled as its
+/// fragments arrive. The fragments still missing are requested from the
+/// writer with a NackFrag.
+pub struct FragmentedChange {
+    reader_id: EntityId_t,
+    writer_id: EntityId_t,
+    writer_sn: SequenceNumber_t,
+    flags: SubmessageFlag,
+    inline_qos: Option<ParameterList>,
+    fragment_size: u16,
+
+    /// The serialized payload of the change, of data_size octets, in which
+    /// the fragments are copied as they arrive.
+    serialized_payload: Box<[u8]>,
+
+    /// The numbers of the fragments received so far.
+    received_fragments: BTreeSet<u32>,
+
+    /// When the last fragment was received, or the missing ones requested.
+    last_progress: Instant,
+
+    /// When the NackFrag answering the last HeartbeatFrag is to be sent, if
+    /// one is.
+    nack_frag_deadline: Option<Instant>,
+
+    /// The highest fragment number the writer announced in a HeartbeatFrag.
+    last_fragment_announced: u32,
+}
+
+impl FragmentedChange {
+    /// Starts the reassembly of the change the DataFrag is a part of. Returns
+    /// None if the fragment size of the DataFrag is zero, or if its data size
+    /// exceeds max_sample_size, which is either positive or LENGTH_UNLIMITED.
+    pub fn new(
+        data_frag: &DataFrag,
+        flags: SubmessageFlag,
+        max_sample_size: i32,
+        now: Instant,
+    ) -> Option<Self> {
+        if data_frag.fragment_size == 0
+            || (max_sample_size != LENGTH_UNLIMITED
+                && data_frag.data_size as usize > max_sample_size as usize)
+        {
+            return None;
+        }
+
+        Some(FragmentedChange {
+            reader_id: data_frag.reader_id,
+            writer_id: data_frag.writer_id,
+            writer_sn: data_frag.writer_sn,
+            flags,
+            inline_qos: None,
+            fragment_size: data_frag.fragment_size,
+            serialized_payload: vec![0; data_frag.data_size as usize].into_boxed_slice(),
+            received_fragments: BTreeSet::new(),
+            last_progress: now,
+            nack_frag_deadline: None,
+            last_fragment_announced: 0,
+        })
+    }
+
+    /// The number of fragments the change is split into.
+    pub fn fragment_count(&self) -> u32 {
+        self.serialized_payload
+            .len()
+            .div_ceil(usize::from(self.fragment_size)) as u32
+    }
+
+    /// Copies the fragments of the DataFrag into the serialized payload, and
+    /// tells whether they were consistent with the ones received before. The
+    /// fragments already received are ignored, and so are the DataFrags
+    /// without any fragment of the change.
+    pub fn insert(&mut self, data_frag: DataFrag, now: Instant) -> bool {
+        if data_frag.fragment_size != self.fragment_size
+            || data_frag.data_size as usize != self.serialized_payload.len()
+        {
+            return false;
+        }
+        let fragment_count = self.fragment_count();
+        let first = u32::from(data_frag.fragment_starting_num);
+        let last = first.saturating_add(u32::from(data_frag.fragments_in_submessage));
+        if data_frag.fragments_in_submessage == 0
+            || first == 0
+            || first > fragment_count
+            || fragment_count
+                .checked_add(1)
+                .is_some_and(|past_last| last > past_last)
+        {
+            return false;
+        }
+        let start = (first as usize - 1) * usize::from(self.fragment_size);
+        let end = (start + data_frag.serialized_payload.len()).min(self.serialized_payload.len());
+        let expected_end = ((last as usize - 1) * usize::from(self.fragment_size))
+            .min(self.serialized_payload.len());
+        if end != expected_end {
+            return false;
+        }
+
+        self.serialized_payload[start..end]
+            .copy_from_slice(&data_frag.serialized_payload[..end - start]);
+        self.received_fragments.extend(first..last);
+        if self.inline_qos.is_none() {
+            self.inline_qos = data_frag.inline_qos;
+        }
+        self.last_progress = now;
+        true
+    }
+
+    /// Tells whether all the fragments were received.
+    pub fn is_complete(&self) -> bool {
+        self.received_fragments.len() as u32 == self.fragment_count()
+    }
+
+    /// Schedules a NackFrag at the deadline, unless one is scheduled already,
+    /// for the missing fragments up to last_fragment_num.
+    pub fn nack_frag_schedule(&mut self, deadline: Instant, last_fragment_num: FragmentNumber_t) {
+        let last_fragment_num = u32::from(last_fragment_num).min(self.fragment_count());
+        if last_fragment_num > self.last_fragment_announced {
+            self.last_fragment_announced = last_fragment_num;
+        }
+        self.nack_frag_deadline.get_or_insert(deadline);
+    }
+
+    /// Tells up to which fragment number the missing fragments are to be
+    /// requested, if they are: either the scheduled NackFrag is due, or no
+    /// fragment was received for fragment_timeout, in which case all the
+    /// missing fragments are requested.
+    pub fn nack_frag_due(&mut self, now: Instant, fragment_timeout: Duration) -> Option<u32> {
+        let last_fragment_num = match self.nack_frag_deadline {
+            Some(deadline) if deadline <= now => {
+                self.nack_frag_deadline = None;
+                self.last_fragment_announced
+            }
+            _ if now.saturating_duration_since(self.last_progress) >= fragment_timeout => {
+                self.fragment_count()
+            }
+            _ => return None,
+        };
+        self.last_progress = now;
+        Some(last_fragment_num)
+    }
+
+    /// The fragment_number_state of a NackFrag requesting the missing
+    /// fragments up to last_fragment_num that fit in it, or None if none is
+    /// missing.
+    pub fn missing_fragments(&self, last_fragment_num: u32) -> Option<FragmentNumberSet_t> {
+        let mut missing = (1..=last_fragment_num.min(self.fragment_count()))
+            .filter(|fragment_num| !self.received_fragments.contains(fragment_num))
+            .map(FragmentNumber_t::from)
+            .peekable();
+        let mut fragment_number_state = FragmentNumberSet_t::new(*missing.peek()?);
+        for fragment_num in missing {
+            if !fragment_number_state.insert(fragment_num) {
+                break;
+            }
+        }
+        Some(fragment_number_state)
+    }
+
+    /// The Data submessage of the reassembled change, with its flags.
+    pub fn into_data(self) -> Result<(Data, SubmessageFlag), speedy::Error> {
+        let mut flags = SubmessageFlag {
+            flags: self.flags.flags & 0x01,
+        };
+        if self.inline_qos.is_some() {
+            flags.set_flag(DataSubmessageFlags::INLINE_QOS_FLAG_MASK);
+        }
+        flags.set_flag(if self.flags.is_flag_set(DataFrag::KEY_FLAG_MASK) {
+            DataSubmessageFlags::KEY_FLAG_MASK
+        } else {
+            DataSubmessageFlags::DATA_FLAG_MASK
+        });
+
+        let context = DataContext::new(flags, self.serialized_payload.len());
+        let serialized_payload =
+            SerializedPayload::read_from_buffer_with_ctx(context, &self.serialized_payload)?;
+        let data = Data {
+            reader_id: self.reader_id,
+            writer_id: self.writer_id,
+            writer_sn: self.writer_sn,
+            inline_qos: self.inline_qos,
+            serialized_payload: Some(serialized_payload),
+        };
+        Ok((data, flags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::data::Data as DataValue;
+    use std::convert::TryFrom;
+
+    fn data_frag(fragment_starting_num: u32, serialized_payload: &[u8]) -> DataFrag {
+        DataFrag {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_sn: SequenceNumber_t::from(1),
+            fragment_starting_num: FragmentNumber_t::from(fragment_starting_num),
+            fragments_in_submessage: 1,
+            data_size: 10,
+            fragment_size: 4,
+            inline_qos: None,
+            serialized_payload: serialized_payload.into(),
+        }
+    }
+
+    fn fragments(fragment_number_state: Option<FragmentNumberSet_t>) -> Vec<u32> {
+        fragment_number_state
+            .map(|set| set.iter().map(u32::from).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn fragments_are_reassembled_in_any_order() {
+        let now = Instant::now();
+        let flags = SubmessageFlag { flags: 0x01 };
+        let mut change =
+            FragmentedChange::new(&data_frag(3, &[]), flags, LENGTH_UNLIMITED, now).unwrap();
+        assert_eq!(3, change.fragment_count());
+
+        assert!(change.insert(data_frag(3, &[0x62, 0x63]), now));
+        assert!(change.insert(data_frag(1, &[0x00, 0x01, 0x00, 0x00]), now));
+        assert_eq!(vec![2], fragments(change.missing_fragments(3)));
+        assert!(!change.is_complete());
+
+        // Fragments of the wrong size are rejected.
+        assert!(!change.insert(data_frag(2, &[0x2a]), now));
+        assert!(change.insert(data_frag(2, &[0x2a, 0x00, 0x00, 0x61]), now));
+        assert!(change.is_complete());
+        assert_eq!(None, change.missing_fragments(3));
+
+        let (data, flags) = change.into_data().unwrap();
+        assert_eq!(SubmessageFlag { flags: 0x05 }, flags);
+        let value = DataValue::try_from(data.serialized_payload.unwrap()).unwrap();
+        assert_eq!(&b"\x2a\x00\x00\x61bc"[..], &value.value[..]);
+    }
+
+    #[test]
+    fn data_frags_without_fragments_of_the_change_are_rejected() {
+        let now = Instant::now();
+        let flags = SubmessageFlag { flags: 0x01 };
+        let mut change =
+            FragmentedChange::new(&data_frag(1, &[]), flags, LENGTH_UNLIMITED, now).unwrap();
+
+        assert!(!change.insert(
+            DataFrag {
+                fragments_in_submessage: 0,
+                ..data_frag(4, &[])
+            },
+            now
+        ));
+        assert!(!change.insert(
+            DataFrag {
+                fragments_in_submessage: 0,
+                ..data_frag(2, &[0x2a, 0x00, 0x00, 0x61])
+            },
+            now
+        ));
+        assert!(!change.insert(data_frag(4, &[0x2a]), now));
+        assert!(!change.insert(data_frag(0, &[0x00, 0x01, 0x00, 0x00]), now));
+        assert_eq!(vec![1, 2, 3], fragments(change.missing_fragments(3)));
+    }
+
+    #[test]
+    fn changes_larger_than_max_sample_size_are_not_reassembled() {
+        let now = Instant::now();
+        let flags = SubmessageFlag { flags: 0x01 };
+        assert!(FragmentedChange::new(&data_frag(1, &[]), flags, 9, now).is_none());
+        assert!(FragmentedChange::new(&data_frag(1, &[]), flags, 10, now).is_some());
+    }
+
+    #[test]
+    fn missing_fragments_are_requested_after_heartbeat_frag_or_timeout() {
+        let now = Instant::now();
+        let flags = SubmessageFlag { flags: 0x01 };
+        let timeout = Duration::from_millis(100);
+        let mut change =
+            FragmentedChange::new(&data_frag(2, &[]), flags, LENGTH_UNLIMITED, now).unwrap();
+        change.insert(data_frag(2, &[0x00; 4]), now);
+
+        change.nack_frag_schedule(now + Duration::from_millis(10), FragmentNumber_t::from(1));
+        assert_eq!(None, change.nack_frag_due(now, timeout));
+        let last_fragment_num = change.nack_frag_due(now + Duration::from_millis(10), timeout);
+        assert_eq!(Some(1), last_fragment_num);
+        assert_eq!(vec![1], fragments(change.missing_fragments(1)));
+
+        // The timeout starts over once the fragments were requested.
+        let later = now + Duration::from_millis(10) + timeout;
+        assert_eq!(Some(3), change.nack_frag_due(later, timeout));
+        assert_eq!(vec![1, 3], fragments(change.missing_fragments(3)));
+        assert_eq!(None, change.nack_frag_due(later, timeout));
+    }
+}
@@ -1,7 +1,15 @@
 pub mod change_for_reader;
 pub mod change_for_reader_status_kind;
+pub mod change_from_writer;
+pub mod change_from_writer_status_kind;
+pub mod flow_controller;
+pub mod fragmented_change;
+pub mod reader;
 pub mod reader_locator;
 pub mod reader_proxy;
+pub mod stateful_reader;
 pub mod stateful_writer;
+pub mod stateless_reader;
 pub mod stateless_writer;
 pub mod writer;
+pub mod writer_proxy;
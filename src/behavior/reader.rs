@@ -0,0 +1,24 @@
+use crate::structure::duration::Duration_t;
+use crate::structure::history_cache::HistoryCache;
+
+pub struct ReaderAttributes {
+    /// Protocol tuning parameter that allows the RTPS Reader to delay the
+    /// sending of a positive or negative acknowledgment.
+    pub heartbeat_response_delay: Duration_t,
+
+    /// Protocol tuning parameter that allows the RTPS Reader to ignore
+    /// HEARTBEATs that arrive ‘too soon’ after a previous HEARTBEAT was
+    /// received.
+    pub heartbeat_suppression_duration: Duration_t,
+
+    /// Specifies whether the RTPS Reader expects in-line QoS to be sent along
+    /// with any data.
+    pub expects_inline_qos: bool,
+
+    /// Contains the history of the CacheChange changes for this Reader.
+    pub reader_cache: HistoryCache,
+}
+
+pub trait Reader {
+    fn as_reader(&self) -> &ReaderAttributes;
+}
@@ -1,19 +1,22 @@
-use crate::behavior::change_for_reader::ChangeForReader;
 use crate::structure::cache_change::CacheChange;
+use crate::structure::history_cache::HistoryCache;
 use crate::structure::locator::Locator_t;
 use crate::structure::sequence_number::SequenceNumber_t;
+use std::collections::BTreeSet;
+use std::ops::Bound;
 
 /// Valuetype used by the RTPS StatelessWriter to keep track
 /// of the locators of all matching remote Readers
 #[derive(Debug, PartialEq)]
 pub struct ReaderLocator {
-    /// A list of changes in the writer’s HistoryCache that
+    /// The sequence numbers of the changes in the writer’s HistoryCache that
     /// were requested by remote Readers at this ReaderLocator
-    requested_changes: Vec<CacheChange>,
+    requested_changes: BTreeSet<SequenceNumber_t>,
 
-    /// A list of changes in the writer’s HistoryCache that
-    /// have not been sent yet to this ReaderLocator
-    unsent_changes: Vec<CacheChange>,
+    /// The highest sequence number of the changes sent to this
+    /// ReaderLocator. The changes in the writer’s HistoryCache with a higher
+    /// sequence number have not been sent yet.
+    highest_seq_num_sent: SequenceNumber_t,
 
     /// Unicast or multicast locator through which the readers
     /// represented by this ReaderLocator can be reached
@@ -25,27 +28,224 @@ pub struct ReaderLocator {
 }
 
 impl ReaderLocator {
-    pub fn new(_locator: Locator_t, _expects_inline_qos: bool) -> Self {
-        unimplemented!();
+    pub fn new(locator: Locator_t, expects_inline_qos: bool) -> Self {
+        ReaderLocator {
+            requested_changes: BTreeSet::new(),
+            highest_seq_num_sent: SequenceNumber_t::from(0),
+            locator,
+            expects_inline_qos,
+        }
     }
 
-    pub fn next_requested_change() -> ChangeForReader {
-        unimplemented!();
+    pub fn locator(&self) -> &Locator_t {
+        &self.locator
     }
 
-    pub fn next_unset_change() -> ChangeForReader {
-        unimplemented!();
+    pub fn expects_inline_qos(&self) -> bool {
+        self.expects_inline_qos
     }
 
-    pub fn requested_changes() -> Vec<CacheChange> {
-        unimplemented!();
+    /// Takes the requested change with the lowest sequence number. The
+    /// requested changes that are no longer in the HistoryCache are skipped.
+    pub fn next_requested_change<'a>(
+        &mut self,
+        writer_cache: &'a HistoryCache,
+    ) -> Option<&'a CacheChange> {
+        while let Some(sequence_number) = self.requested_changes.iter().next().copied() {
+            self.requested_changes.remove(&sequence_number);
+            if let Some(change) = writer_cache.get_change(sequence_number) {
+                return Some(change);
+            }
+        }
+        None
     }
 
-    pub fn requested_changes_set(_req_seq_num_set: &[SequenceNumber_t]) {
-        unimplemented!();
+    /// Takes the unsent change with the lowest sequence number, which is
+    /// considered sent from then on.
+    pub fn next_unsent_change<'a>(
+        &mut self,
+        writer_cache: &'a HistoryCache,
+    ) -> Option<&'a CacheChange> {
+        let change = self.unsent_changes(writer_cache).next()?;
+        self.highest_seq_num_sent = change.sequence_number;
+        Some(change)
     }
 
-    pub fn unset_changes() -> Vec<CacheChange> {
-        unimplemented!();
+    /// The changes requested by remote Readers that are still in the
+    /// HistoryCache.
+    pub fn requested_changes<'a>(
+        &'a self,
+        writer_cache: &'a HistoryCache,
+    ) -> impl Iterator<Item = &'a CacheChange> {
+        self.requested_changes
+            .iter()
+            .filter_map(move |sequence_number| writer_cache.get_change(*sequence_number))
+    }
+
+    /// Adds the changes with the sequence numbers to the requested changes.
+    pub fn requested_changes_set(&mut self, req_seq_num_set: &[SequenceNumber_t]) {
+        self.requested_changes.extend(req_seq_num_set)
+    }
+
+    pub fn unsent_changes<'a>(
+        &self,
+        writer_cache: &'a HistoryCache,
+    ) -> impl Iterator<Item = &'a CacheChange> {
+        writer_cache
+            .changes_in_range((Bound::Excluded(self.highest_seq_num_sent), Bound::Unbounded))
+    }
+
+    /// Gives back the change taken with next_requested_change, if it was
+    /// requested, or with next_unsent_change otherwise, that could not be
+    /// sent. It is then the next one taken again.
+    pub fn change_not_sent(&mut self, sequence_number: SequenceNumber_t, requested: bool) {
+        if requested {
+            self.requested_changes.insert(sequence_number);
+        } else if sequence_number <= self.highest_seq_num_sent {
+            self.highest_seq_num_sent = sequence_number - SequenceNumber_t::from(1);
+        }
+    }
+
+    /// Makes all the changes in the HistoryCache unsent again.
+    pub fn unsent_changes_reset(&mut self) {
+        self.highest_seq_num_sent = SequenceNumber_t::from(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::change_kind::ChangeKind_t;
+    use crate::structure::guid::GUID_t;
+    use crate::structure::instance_handle::InstanceHandle_t;
+
+    fn writer_cache(sequence_numbers: &[i64]) -> HistoryCache {
+        let mut writer_cache = HistoryCache::new();
+        for sequence_number in sequence_numbers {
+            writer_cache.add_change(CacheChange::new(
+                ChangeKind_t::ALIVE,
+                GUID_t::GUID_UNKNOWN,
+                InstanceHandle_t::default(),
+                SequenceNumber_t::from(*sequence_number),
+                None,
+            ));
+        }
+        writer_cache
+    }
+
+    fn sequence_numbers<'a>(changes: impl Iterator<Item = &'a CacheChange>) -> Vec<i64> {
+        changes
+            .map(|change| i64::from(change.sequence_number))
+            .collect()
+    }
+
+    #[test]
+    fn unsent_changes_are_sent_in_order() {
+        let mut reader_locator = ReaderLocator::new(Locator_t::LOCATOR_INVALID, false);
+        let mut writer_cache = writer_cache(&[3, 1, 2]);
+
+        assert_eq!(
+            vec![1, 2, 3],
+            sequence_numbers(reader_locator.unsent_changes(&writer_cache))
+        );
+        assert_eq!(
+            Some(SequenceNumber_t::from(1)),
+            reader_locator
+                .next_unsent_change(&writer_cache)
+                .map(|change| change.sequence_number)
+        );
+        assert_eq!(
+            vec![2, 3],
+            sequence_numbers(reader_locator.unsent_changes(&writer_cache))
+        );
+
+        reader_locator.next_unsent_change(&writer_cache);
+        reader_locator.next_unsent_change(&writer_cache);
+        assert_eq!(None, reader_locator.next_unsent_change(&writer_cache));
+
+        writer_cache.add_change(CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(4),
+            None,
+        ));
+        assert_eq!(
+            vec![4],
+            sequence_numbers(reader_locator.unsent_changes(&writer_cache))
+        );
+    }
+
+    #[test]
+    fn unsent_changes_reset_makes_all_changes_unsent() {
+        let mut reader_locator = ReaderLocator::new(Locator_t::LOCATOR_INVALID, false);
+        let writer_cache = writer_cache(&[1, 2]);
+
+        while reader_locator.next_unsent_change(&writer_cache).is_some() {}
+        assert_eq!(0, reader_locator.unsent_changes(&writer_cache).count());
+
+        reader_locator.unsent_changes_reset();
+        assert_eq!(
+            vec![1, 2],
+            sequence_numbers(reader_locator.unsent_changes(&writer_cache))
+        );
+    }
+
+    #[test]
+    fn requested_changes_skip_the_ones_no_longer_in_the_history_cache() {
+        let mut reader_locator = ReaderLocator::new(Locator_t::LOCATOR_INVALID, false);
+        let mut writer_cache = writer_cache(&[1, 2, 3]);
+
+        reader_locator.requested_changes_set(&[
+            SequenceNumber_t::from(3),
+            SequenceNumber_t::from(1),
+            SequenceNumber_t::from(2),
+        ]);
+        writer_cache
+            .remove_change(SequenceNumber_t::from(2))
+            .unwrap();
+        assert_eq!(
+            vec![1, 3],
+            sequence_numbers(reader_locator.requested_changes(&writer_cache))
+        );
+
+        assert_eq!(
+            Some(SequenceNumber_t::from(1)),
+            reader_locator
+                .next_requested_change(&writer_cache)
+                .map(|change| change.sequence_number)
+        );
+        assert_eq!(
+            Some(SequenceNumber_t::from(3)),
+            reader_locator
+                .next_requested_change(&writer_cache)
+                .map(|change| change.sequence_number)
+        );
+        assert_eq!(None, reader_locator.next_requested_change(&writer_cache));
+
+        // Sending the requested changes does not send the unsent ones.
+        assert_eq!(2, reader_locator.unsent_changes(&writer_cache).count());
+    }
+
+    #[test]
+    fn changes_not_sent_are_taken_again() {
+        let mut reader_locator = ReaderLocator::new(Locator_t::LOCATOR_INVALID, false);
+        let writer_cache = writer_cache(&[1, 2]);
+        reader_locator.requested_changes_set(&[SequenceNumber_t::from(2)]);
+
+        let requested = reader_locator.next_requested_change(&writer_cache).unwrap();
+        reader_locator.change_not_sent(requested.sequence_number, true);
+        assert_eq!(
+            vec![2],
+            sequence_numbers(reader_locator.requested_changes(&writer_cache))
+        );
+
+        reader_locator.next_unsent_change(&writer_cache);
+        let unsent = reader_locator.next_unsent_change(&writer_cache).unwrap();
+        reader_locator.change_not_sent(unsent.sequence_number, false);
+        assert_eq!(
+            vec![2],
+            sequence_numbers(reader_locator.unsent_changes(&writer_cache))
+        );
     }
 }
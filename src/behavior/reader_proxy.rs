@@ -1,6 +1,15 @@
-use crate::structure::cache_change::CacheChange;
+use crate::behavior::change_for_reader::ChangeForReader;
+use crate::behavior::change_for_reader_status_kind::ChangeForReaderStatusKind;
+use crate::messages::fragment_number::FragmentNumber_t;
+use crate::structure::count::Count_t;
 use crate::structure::guid::GUID_t;
+use crate::structure::history_cache::HistoryCache;
+use crate::structure::locator::LocatorList_t;
 use crate::structure::sequence_number::SequenceNumber_t;
+use num_traits::CheckedAdd;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+use std::time::{Duration, Instant};
 
 /// The RTPS ReaderProxy class represents the information an RTPS StatefulWriter
 /// maintains on each matched RTPS Reader.
@@ -9,6 +18,14 @@ pub struct ReaderProxy {
     /// ReaderProxy.
     pub remote_reader_guid: GUID_t,
 
+    /// List of unicast locators (transport, address, port combinations) that
+    /// can be used to send messages to the matched RTPS Reader.
+    pub unicast_locator_list: LocatorList_t,
+
+    /// List of multicast locators (transport, address, port combinations)
+    /// that can be used to send messages to the matched RTPS Reader.
+    pub multicast_locator_list: LocatorList_t,
+
     /// Specifies whether the remote matched RTPS Reader expects in-line QoS to
     /// be sent along with any data
     expects_inline_qos: bool,
@@ -16,140 +33,774 @@ pub struct ReaderProxy {
     /// Specifies whether the remote Reader is responsive to the Writer.
     is_active: bool,
 
-    highest_seq_num_sent: SequenceNumber_t,
+    /// The number of HEARTBEATs sent to the remote Reader since it last sent
+    /// an ACKNACK.
+    unanswered_heartbeats: u32,
+
+    /// The status of the changes in the writer’s HistoryCache that were not
+    /// acknowledged yet by the remote Reader, by sequence number.
+    changes_for_reader: BTreeMap<SequenceNumber_t, ChangeForReader>,
+
+    /// All the changes up to this sequence number were acknowledged by the
+    /// remote Reader.
+    highest_seq_num_acked: SequenceNumber_t,
+
+    /// The highest sequence number of the changes added to
+    /// changes_for_reader.
+    highest_seq_num_added: SequenceNumber_t,
+
+    /// The changes up to this sequence number were added at once as
+    /// irrelevant. The ones not acknowledged yet have a single entry in
+    /// changes_for_reader, the one of the first of them, and are sent to the
+    /// remote Reader as a single GAP.
+    highest_seq_num_irrelevant: SequenceNumber_t,
+
+    /// The count of the last ACKNACK received from the remote Reader, to
+    /// detect the duplicate ones.
+    last_acknack_count: Option<Count_t>,
+
+    /// The count of the last NACK_FRAG received from the remote Reader, to
+    /// detect the duplicate ones.
+    last_nack_frag_count: Option<Count_t>,
+
+    /// The fragments of the changes the remote Reader requested with a
+    /// NACK_FRAG, by sequence number.
+    requested_fragments: BTreeMap<SequenceNumber_t, BTreeSet<FragmentNumber_t>>,
+
+    /// When the changes that were not acknowledged yet were last sent to the
+    /// remote Reader, by sequence number.
+    last_sent: BTreeMap<SequenceNumber_t, Instant>,
 }
 
 impl ReaderProxy {
-    pub fn new(remote_reader_guid: GUID_t, expects_inline_qos: bool) -> ReaderProxy {
+    pub fn new(
+        remote_reader_guid: GUID_t,
+        unicast_locator_list: LocatorList_t,
+        multicast_locator_list: LocatorList_t,
+        expects_inline_qos: bool,
+    ) -> ReaderProxy {
         ReaderProxy {
             remote_reader_guid,
+            unicast_locator_list,
+            multicast_locator_list,
             expects_inline_qos,
             is_active: true,
-            highest_seq_num_sent: SequenceNumber_t::from(std::i64::MIN),
+            unanswered_heartbeats: 0,
+            changes_for_reader: BTreeMap::new(),
+            highest_seq_num_acked: SequenceNumber_t::from(0),
+            highest_seq_num_added: SequenceNumber_t::from(0),
+            highest_seq_num_irrelevant: SequenceNumber_t::from(0),
+            last_acknack_count: None,
+            last_nack_frag_count: None,
+            requested_fragments: BTreeMap::new(),
+            last_sent: BTreeMap::new(),
+        }
+    }
+
+    pub fn expects_inline_qos(&self) -> bool {
+        self.expects_inline_qos
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    /// The Writer sent a HEARTBEAT the remote Reader has to respond to.
+    pub fn heartbeat_sent(&mut self) {
+        self.unanswered_heartbeats = self.unanswered_heartbeats.saturating_add(1);
+    }
+
+    pub fn unanswered_heartbeats(&self) -> u32 {
+        self.unanswered_heartbeats
+    }
+
+    /// The remote Reader stopped responding to the Writer.
+    pub fn deactivate(&mut self) {
+        self.is_active = false;
+    }
+
+    /// The remote Reader responded with an ACKNACK: it is active, and no
+    /// HEARTBEAT is left unanswered. Returns whether it was inactive.
+    pub fn acknack_received(&mut self) -> bool {
+        self.unanswered_heartbeats = 0;
+        !std::mem::replace(&mut self.is_active, true)
+    }
+
+    /// Adds the changes of the HistoryCache that were added to it since the
+    /// last call, with the given status. The sequence numbers skipped since
+    /// the first added change are the ones of changes removed in between,
+    /// which are added as irrelevant.
+    pub fn add_new_changes(
+        &mut self,
+        writer_cache: &HistoryCache,
+        status: ChangeForReaderStatusKind,
+    ) {
+        for change in writer_cache.changes_in_range((
+            Bound::Excluded(self.highest_seq_num_added),
+            Bound::Unbounded,
+        )) {
+            if self.highest_seq_num_added != SequenceNumber_t::from(0) {
+                let removed =
+                    i64::from(self.highest_seq_num_added) + 1..change.sequence_number.into();
+                for sequence_number in removed.map(SequenceNumber_t::from) {
+                    self.add_change(sequence_number, status, false);
+                }
+            }
+            self.add_change(change.sequence_number, status, true);
+        }
+    }
+
+    /// Adds the changes up to last_seq_num that were not added yet as
+    /// irrelevant, with the given status. The remote Reader is then informed
+    /// with a single GAP that it starts after them.
+    pub fn add_irrelevant_changes_up_to(
+        &mut self,
+        last_seq_num: SequenceNumber_t,
+        status: ChangeForReaderStatusKind,
+    ) {
+        if last_seq_num <= self.highest_seq_num_added {
+            return;
+        }
+        let first = self.highest_seq_num_added + SequenceNumber_t::from(1);
+        self.add_change(first, status, false);
+        self.highest_seq_num_added = last_seq_num;
+        self.highest_seq_num_irrelevant = last_seq_num;
+    }
+
+    /// The end of the GAP informing the remote Reader that the change is
+    /// irrelevant: the GAP covers the changes added as irrelevant with it.
+    pub fn gap_end(&self, sequence_number: SequenceNumber_t) -> SequenceNumber_t {
+        if sequence_number <= self.highest_seq_num_irrelevant {
+            self.highest_seq_num_irrelevant + SequenceNumber_t::from(1)
+        } else {
+            sequence_number + SequenceNumber_t::from(1)
+        }
+    }
+
+    /// Tells whether the remote Reader acknowledged all the changes added to
+    /// the ReaderProxy.
+    pub fn all_changes_acknowledged(&self) -> bool {
+        self.changes_for_reader.is_empty()
+    }
+
+    /// The status of the change for the remote Reader, or None if the change
+    /// was not added to the ReaderProxy.
+    pub fn change_for_reader(&self, sequence_number: SequenceNumber_t) -> Option<ChangeForReader> {
+        if sequence_number <= self.highest_seq_num_acked {
+            Some(ChangeForReader {
+                status: ChangeForReaderStatusKind::ACKNOWLEDGED,
+                is_relevant: true,
+            })
+        } else {
+            self.changes_for_reader
+                .get(&self.entry_of(sequence_number))
+                .copied()
+        }
+    }
+
+    /// Marks the change as irrelevant to the remote Reader, which is then
+    /// informed with a GAP instead of the DATA.
+    pub fn set_irrelevant(&mut self, sequence_number: SequenceNumber_t) {
+        if let Some(change_for_reader) = self.changes_for_reader.get_mut(&sequence_number) {
+            change_for_reader.is_relevant = false;
+        }
+    }
+
+    /// All the changes up to committed_seq_num are acknowledged by the remote
+    /// Reader. The changes that were not added to the ReaderProxy yet can't
+    /// be acknowledged.
+    pub fn acked_changes_set(&mut self, committed_seq_num: SequenceNumber_t) {
+        let committed_seq_num = committed_seq_num.min(self.highest_seq_num_added);
+        if committed_seq_num <= self.highest_seq_num_acked {
+            return;
+        }
+
+        self.highest_seq_num_acked = committed_seq_num;
+        match committed_seq_num.checked_add(&SequenceNumber_t::from(1)) {
+            Some(first_unacked) => {
+                // The entry of the irrelevant changes that are still not
+                // acknowledged moves to the first of them.
+                let irrelevant_entry = self.entry_of(first_unacked);
+                let irrelevant_change = self.changes_for_reader.get(&irrelevant_entry).copied();
+                let irrelevant_last_sent = self.last_sent.get(&irrelevant_entry).copied();
+                self.changes_for_reader = self.changes_for_reader.split_off(&first_unacked);
+                self.last_sent = self.last_sent.split_off(&first_unacked);
+                self.requested_fragments = self.requested_fragments.split_off(&first_unacked);
+                if irrelevant_entry != first_unacked {
+                    if let Some(irrelevant_change) = irrelevant_change {
+                        self.changes_for_reader
+                            .insert(first_unacked, irrelevant_change);
+                    }
+                    if let Some(irrelevant_last_sent) = irrelevant_last_sent {
+                        self.last_sent.insert(first_unacked, irrelevant_last_sent);
+                    }
+                }
+            }
+            None => {
+                self.changes_for_reader.clear();
+                self.last_sent.clear();
+                self.requested_fragments.clear();
+            }
+        }
+    }
+
+    /// The remote Reader requests the changes with the sequence numbers. The
+    /// acknowledged changes and the ones that were never added are ignored.
+    pub fn requested_changes_set(&mut self, req_seq_num_set: &[SequenceNumber_t]) {
+        for sequence_number in req_seq_num_set {
+            let entry = self.entry_of(*sequence_number);
+            if let Some(change_for_reader) = self.changes_for_reader.get_mut(&entry) {
+                change_for_reader.status = ChangeForReaderStatusKind::REQUESTED;
+            }
+        }
+    }
+
+    /// The remote Reader requests the fragments of the change. The requests
+    /// of acknowledged changes and of the ones that were never added are
+    /// ignored.
+    pub fn requested_fragments_set(
+        &mut self,
+        sequence_number: SequenceNumber_t,
+        fragment_numbers: impl Iterator<Item = FragmentNumber_t>,
+    ) {
+        if self.changes_for_reader.contains_key(&sequence_number) {
+            self.requested_fragments
+                .entry(sequence_number)
+                .or_default()
+                .extend(fragment_numbers);
+        }
+    }
+
+    /// Takes the fragments requested by the remote Reader, by sequence number.
+    pub fn take_requested_fragments(
+        &mut self,
+    ) -> BTreeMap<SequenceNumber_t, BTreeSet<FragmentNumber_t>> {
+        std::mem::take(&mut self.requested_fragments)
+    }
+
+    pub fn has_requested_fragments(&self) -> bool {
+        !self.requested_fragments.is_empty()
+    }
+
+    /// Tells whether the NACK_FRAG with the count is new, and remembers its
+    /// count if so. The count wraps around.
+    pub fn is_new_nack_frag(&mut self, count: Count_t) -> bool {
+        if self
+            .last_nack_frag_count
+            .is_some_and(|last| !count.is_after(last))
+        {
+            return false;
+        }
+        self.last_nack_frag_count = Some(count);
+        true
+    }
+
+    /// Tells whether the ACKNACK with the count is new, and remembers its
+    /// count if so. The count wraps around.
+    pub fn is_new_acknack(&mut self, count: Count_t) -> bool {
+        if self
+            .last_acknack_count
+            .is_some_and(|last| !count.is_after(last))
+        {
+            return false;
+        }
+        self.last_acknack_count = Some(count);
+        true
+    }
+
+    /// Remembers that the change was sent to the remote Reader at the given
+    /// time.
+    pub fn change_sent(&mut self, sequence_number: SequenceNumber_t, now: Instant) {
+        if self.changes_for_reader.contains_key(&sequence_number) {
+            self.last_sent.insert(sequence_number, now);
+        }
+    }
+
+    /// Remembers that the changes from start up to end excluded were sent to
+    /// the remote Reader at the given time.
+    pub fn changes_sent(&mut self, start: SequenceNumber_t, end: SequenceNumber_t, now: Instant) {
+        for sequence_number in self.changes_for_reader.range(start..end).map(|(sn, _)| *sn) {
+            self.last_sent.insert(sequence_number, now);
         }
     }
 
-    pub fn next_unsent_change<'a>(
-        &'a mut self,
-        changes: &'a [CacheChange],
-    ) -> Option<&'a CacheChange> {
-        self.unsent_changes(&changes)
-            .min_by(|x, y| x.sequence_number.cmp(&y.sequence_number))
-            .and_then(|change| {
-                self.highest_seq_num_sent = change.sequence_number;
-                Some(change)
+    /// Gives back the change taken with next_requested_change or
+    /// next_unsent_change that could not be sent, which is then in the status
+    /// it was taken in again.
+    pub fn change_not_sent(
+        &mut self,
+        sequence_number: SequenceNumber_t,
+        status: ChangeForReaderStatusKind,
+    ) {
+        if let Some(change_for_reader) = self.changes_for_reader.get_mut(&sequence_number) {
+            if change_for_reader.status == ChangeForReaderStatusKind::UNDERWAY {
+                change_for_reader.status = status;
+            }
+        }
+    }
+
+    /// Tells whether the change was sent to the remote Reader less than
+    /// nack_suppression_duration ago, in which case a request of the change is
+    /// ignored: the remote Reader may not have received it yet.
+    pub fn is_nack_suppressed(
+        &self,
+        sequence_number: SequenceNumber_t,
+        now: Instant,
+        nack_suppression_duration: Duration,
+    ) -> bool {
+        self.last_sent
+            .get(&self.entry_of(sequence_number))
+            .is_some_and(|last_sent| {
+                now.saturating_duration_since(*last_sent) < nack_suppression_duration
             })
     }
 
-    pub fn unsent_changes<'a>(
+    /// Takes the requested change with the lowest sequence number, which is
+    /// then UNDERWAY.
+    pub fn next_requested_change(&mut self) -> Option<(SequenceNumber_t, ChangeForReader)> {
+        self.next_change_with_status(ChangeForReaderStatusKind::REQUESTED)
+    }
+
+    /// Takes the unsent change with the lowest sequence number, which is then
+    /// UNDERWAY.
+    pub fn next_unsent_change(&mut self) -> Option<(SequenceNumber_t, ChangeForReader)> {
+        self.next_change_with_status(ChangeForReaderStatusKind::UNSENT)
+    }
+
+    pub fn requested_changes(&self) -> impl Iterator<Item = SequenceNumber_t> + '_ {
+        self.changes_with_status(ChangeForReaderStatusKind::REQUESTED)
+    }
+
+    pub fn unsent_changes(&self) -> impl Iterator<Item = SequenceNumber_t> + '_ {
+        self.changes_with_status(ChangeForReaderStatusKind::UNSENT)
+    }
+
+    /// The changes that were sent, or announced, and are not acknowledged
+    /// yet.
+    pub fn unacked_changes(&self) -> impl Iterator<Item = SequenceNumber_t> + '_ {
+        self.changes_for_reader
+            .iter()
+            .filter(|(_, change_for_reader)| {
+                change_for_reader.status == ChangeForReaderStatusKind::UNACKNOWLEDGED
+                    || change_for_reader.status == ChangeForReaderStatusKind::UNDERWAY
+            })
+            .map(|(sequence_number, _)| *sequence_number)
+    }
+
+    fn add_change(
+        &mut self,
+        sequence_number: SequenceNumber_t,
+        status: ChangeForReaderStatusKind,
+        is_relevant: bool,
+    ) {
+        self.highest_seq_num_added = sequence_number;
+        if sequence_number > self.highest_seq_num_acked {
+            self.changes_for_reader.insert(
+                sequence_number,
+                ChangeForReader {
+                    status,
+                    is_relevant,
+                },
+            );
+        }
+    }
+
+    /// The sequence number of the entry of the change in changes_for_reader:
+    /// the irrelevant changes added at once share the entry of the first one
+    /// that is not acknowledged.
+    fn entry_of(&self, sequence_number: SequenceNumber_t) -> SequenceNumber_t {
+        if sequence_number > self.highest_seq_num_irrelevant {
+            return sequence_number;
+        }
+        match self.changes_for_reader.keys().next() {
+            Some(first) if *first <= sequence_number => *first,
+            _ => sequence_number,
+        }
+    }
+
+    fn changes_with_status(
         &self,
-        changes: &'a [CacheChange],
-    ) -> impl Iterator<Item = &'a CacheChange> {
-        let highest_seq_num_sent = self.highest_seq_num_sent;
-        changes
+        status: ChangeForReaderStatusKind,
+    ) -> impl Iterator<Item = SequenceNumber_t> + '_ {
+        self.changes_for_reader
             .iter()
-            .filter(move |change| change.sequence_number > highest_seq_num_sent)
+            .filter(move |(_, change_for_reader)| change_for_reader.status == status)
+            .map(|(sequence_number, _)| *sequence_number)
+    }
+
+    fn next_change_with_status(
+        &mut self,
+        status: ChangeForReaderStatusKind,
+    ) -> Option<(SequenceNumber_t, ChangeForReader)> {
+        let (sequence_number, change_for_reader) = self
+            .changes_for_reader
+            .iter_mut()
+            .find(|(_, change_for_reader)| change_for_reader.status == status)?;
+        change_for_reader.status = ChangeForReaderStatusKind::UNDERWAY;
+        Some((*sequence_number, *change_for_reader))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::structure::cache_change::CacheChange;
     use crate::structure::change_kind::ChangeKind_t;
-    use crate::structure::data::Data;
     use crate::structure::instance_handle::InstanceHandle_t;
 
     fn default_cache_change(sequence_number: i64) -> CacheChange {
-        CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(sequence_number),
-            data_value: Data {},
+        CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(sequence_number),
+            None,
+        )
+    }
+
+    fn writer_cache(sequence_numbers: &[i64]) -> HistoryCache {
+        let mut writer_cache = HistoryCache::new();
+        for sequence_number in sequence_numbers {
+            writer_cache.add_change(default_cache_change(*sequence_number));
         }
+        writer_cache
+    }
+
+    fn reader_proxy() -> ReaderProxy {
+        ReaderProxy::new(GUID_t::GUID_UNKNOWN, vec![], vec![], true)
+    }
+
+    fn sequence_numbers(sequence_numbers: impl Iterator<Item = SequenceNumber_t>) -> Vec<i64> {
+        sequence_numbers.map(i64::from).collect()
+    }
+
+    fn status(reader_proxy: &ReaderProxy, sequence_number: i64) -> ChangeForReaderStatusKind {
+        reader_proxy
+            .change_for_reader(SequenceNumber_t::from(sequence_number))
+            .unwrap()
+            .status
     }
 
     #[test]
-    fn unsent_changes_returns_not_consumed_changes_by_default() {
-        let reader_proxy = ReaderProxy::new(GUID_t::GUID_UNKNOWN, true);
+    fn unsent_changes_returns_added_changes() {
+        let mut reader_proxy = reader_proxy();
+        let writer_cache = writer_cache(&[0, 1, 2]);
 
-        let changes = vec![
-            default_cache_change(0),
-            default_cache_change(1),
-            default_cache_change(2),
-        ];
+        assert_eq!(0, reader_proxy.unsent_changes().count());
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
+        assert_eq!(
+            vec![1, 2],
+            sequence_numbers(reader_proxy.unsent_changes()),
+            "sequence numbers start at 1"
+        );
+    }
 
-        let mut unsent_changes = reader_proxy.unsent_changes(&changes);
-        assert_eq!(Some(&changes[0]), unsent_changes.next());
-        assert_eq!(Some(&changes[1]), unsent_changes.next());
-        assert_eq!(Some(&changes[2]), unsent_changes.next());
-        assert_eq!(None, unsent_changes.next());
+    #[test]
+    fn next_unsent_change_returns_change_with_smallest_sequence_number() {
+        let mut reader_proxy = reader_proxy();
+        let writer_cache = writer_cache(&[6, 1, 3]);
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
+
+        // The changes missing between the first and the last change were
+        // removed from the HistoryCache, and are irrelevant.
+        let next = |reader_proxy: &mut ReaderProxy| {
+            reader_proxy
+                .next_unsent_change()
+                .map(|(sequence_number, change_for_reader)| {
+                    (i64::from(sequence_number), change_for_reader.is_relevant)
+                })
+        };
+        assert_eq!(Some((1, true)), next(&mut reader_proxy));
+        assert_eq!(Some((2, false)), next(&mut reader_proxy));
+        assert_eq!(Some((3, true)), next(&mut reader_proxy));
+        assert_eq!(Some((4, false)), next(&mut reader_proxy));
+        assert_eq!(Some((5, false)), next(&mut reader_proxy));
+        assert_eq!(Some((6, true)), next(&mut reader_proxy));
+        assert_eq!(None, next(&mut reader_proxy));
     }
 
     #[test]
-    fn next_unsent_change_returns_cache_change_with_smallest_sequence_number() {
-        let mut reader_proxy = ReaderProxy::new(GUID_t::GUID_UNKNOWN, true);
+    fn next_unsent_change_once_taken_is_underway() {
+        let mut reader_proxy = reader_proxy();
+        let writer_cache = writer_cache(&[3, 1, 2]);
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
+        assert_eq!(3, reader_proxy.unsent_changes().count());
 
-        let changes = vec![
-            default_cache_change(6),
-            default_cache_change(1),
-            default_cache_change(3),
-        ];
+        reader_proxy.next_unsent_change();
 
-        assert_eq!(Some(&changes[1]), reader_proxy.next_unsent_change(&changes));
-        assert_eq!(Some(&changes[2]), reader_proxy.next_unsent_change(&changes));
-        assert_eq!(Some(&changes[0]), reader_proxy.next_unsent_change(&changes));
-        assert_eq!(None, reader_proxy.next_unsent_change(&changes));
+        assert_eq!(vec![2, 3], sequence_numbers(reader_proxy.unsent_changes()));
+        assert_eq!(
+            ChangeForReaderStatusKind::UNDERWAY,
+            status(&reader_proxy, 1)
+        );
+        assert_eq!(vec![1], sequence_numbers(reader_proxy.unacked_changes()));
     }
 
     #[test]
-    fn next_unsent_change_once_requested_does_not_belong_to_unsent_changes() {
-        let mut reader_proxy = ReaderProxy::new(GUID_t::GUID_UNKNOWN, true);
+    fn change_not_sent_is_given_back() {
+        let mut reader_proxy = reader_proxy();
+        let writer_cache = writer_cache(&[1, 2]);
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
+        reader_proxy.next_unsent_change();
+        reader_proxy.next_unsent_change();
 
-        let changes = vec![
-            default_cache_change(6),
-            default_cache_change(1),
-            default_cache_change(3),
-        ];
+        reader_proxy.change_not_sent(SequenceNumber_t::from(2), ChangeForReaderStatusKind::UNSENT);
+        assert_eq!(vec![2], sequence_numbers(reader_proxy.unsent_changes()));
+        assert_eq!(
+            Some((
+                SequenceNumber_t::from(2),
+                ChangeForReader {
+                    status: ChangeForReaderStatusKind::UNDERWAY,
+                    is_relevant: true,
+                }
+            )),
+            reader_proxy.next_unsent_change()
+        );
+    }
 
-        assert_eq!(3, reader_proxy.unsent_changes(&changes).count());
-        assert!(reader_proxy
-            .unsent_changes(&changes)
-            .any(|change| change == &changes[1]));
+    #[test]
+    fn only_new_changes_are_added() {
+        let mut reader_proxy = reader_proxy();
+        let mut writer_cache = writer_cache(&[1, 2]);
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
+        while reader_proxy.next_unsent_change().is_some() {}
 
-        let _next_unsent_change = reader_proxy.next_unsent_change(&changes);
+        writer_cache.add_change(default_cache_change(3));
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNACKNOWLEDGED);
 
-        assert_eq!(2, reader_proxy.unsent_changes(&changes).count());
-        assert!(!reader_proxy
-            .unsent_changes(&changes)
-            .any(|change| change == &changes[1]));
+        assert_eq!(0, reader_proxy.unsent_changes().count());
+        assert_eq!(
+            ChangeForReaderStatusKind::UNDERWAY,
+            status(&reader_proxy, 2)
+        );
+        assert_eq!(
+            ChangeForReaderStatusKind::UNACKNOWLEDGED,
+            status(&reader_proxy, 3)
+        );
     }
 
     #[test]
-    fn unsent_changes_returns_only_changes_that_were_not_sent() {
-        let mut reader_proxy = ReaderProxy::new(GUID_t::GUID_UNKNOWN, true);
+    fn changes_written_before_the_reader_matched_are_irrelevant() {
+        let mut reader_proxy = reader_proxy();
+        let writer_cache = writer_cache(&[2, 3]);
+        reader_proxy.add_irrelevant_changes_up_to(
+            SequenceNumber_t::from(3),
+            ChangeForReaderStatusKind::UNSENT,
+        );
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
 
-        let mut changes = vec![
-            default_cache_change(6),
-            default_cache_change(1),
-            default_cache_change(3),
-        ];
+        // They are sent at once, as a single GAP.
+        assert_eq!(vec![1], sequence_numbers(reader_proxy.unsent_changes()));
+        assert_eq!(
+            SequenceNumber_t::from(4),
+            reader_proxy.gap_end(SequenceNumber_t::from(1))
+        );
+        for sequence_number in 1..4 {
+            let change_for_reader = reader_proxy
+                .change_for_reader(SequenceNumber_t::from(sequence_number))
+                .unwrap();
+            assert!(!change_for_reader.is_relevant);
+        }
+    }
+
+    #[test]
+    fn irrelevant_changes_are_added_at_once() {
+        let mut reader_proxy = reader_proxy();
+        let last_seq_num = SequenceNumber_t::from(i64::MAX / 2);
+        reader_proxy.add_irrelevant_changes_up_to(last_seq_num, ChangeForReaderStatusKind::UNSENT);
+        let now = Instant::now();
+        let (first, _) = reader_proxy.next_unsent_change().unwrap();
+        reader_proxy.changes_sent(first, reader_proxy.gap_end(first), now);
+        assert!(reader_proxy.next_unsent_change().is_none());
 
-        // consume all changes
-        reader_proxy.next_unsent_change(&changes);
-        reader_proxy.next_unsent_change(&changes);
-        reader_proxy.next_unsent_change(&changes);
+        // The ones that are not acknowledged yet can be requested again.
+        reader_proxy.acked_changes_set(SequenceNumber_t::from(5));
+        assert_eq!(
+            ChangeForReaderStatusKind::ACKNOWLEDGED,
+            status(&reader_proxy, 5)
+        );
+        assert_eq!(
+            ChangeForReaderStatusKind::UNDERWAY,
+            status(&reader_proxy, 6)
+        );
+        assert!(reader_proxy.is_nack_suppressed(
+            SequenceNumber_t::from(7),
+            now,
+            Duration::from_millis(1)
+        ));
+        reader_proxy.requested_changes_set(&[SequenceNumber_t::from(7)]);
+        assert_eq!(vec![6], sequence_numbers(reader_proxy.requested_changes()));
+        assert_eq!(
+            last_seq_num + SequenceNumber_t::from(1),
+            reader_proxy.gap_end(SequenceNumber_t::from(6))
+        );
 
-        // add new changes with smaller sequence numbers than highest sent
-        changes.push(default_cache_change(0));
-        changes.push(default_cache_change(5));
-        changes.push(default_cache_change(3));
+        reader_proxy.acked_changes_set(last_seq_num);
+        assert!(reader_proxy.all_changes_acknowledged());
+    }
 
-        assert_eq!(None, reader_proxy.next_unsent_change(&changes));
+    #[test]
+    fn acknowledged_and_requested_changes() {
+        let mut reader_proxy = reader_proxy();
+        let writer_cache = writer_cache(&[1, 2, 3, 4]);
+        reader_proxy.add_new_changes(&writer_cache, ChangeForReaderStatusKind::UNSENT);
+        while reader_proxy.next_unsent_change().is_some() {}
+
+        reader_proxy.acked_changes_set(SequenceNumber_t::from(2));
+        reader_proxy.requested_changes_set(&[
+            SequenceNumber_t::from(1),
+            SequenceNumber_t::from(4),
+            SequenceNumber_t::from(5),
+        ]);
+
+        assert_eq!(
+            ChangeForReaderStatusKind::ACKNOWLEDGED,
+            status(&reader_proxy, 1)
+        );
+        assert_eq!(
+            ChangeForReaderStatusKind::ACKNOWLEDGED,
+            status(&reader_proxy, 2)
+        );
+        assert_eq!(
+            ChangeForReaderStatusKind::UNDERWAY,
+            status(&reader_proxy, 3)
+        );
+        assert_eq!(
+            ChangeForReaderStatusKind::REQUESTED,
+            status(&reader_proxy, 4)
+        );
+        assert_eq!(
+            None,
+            reader_proxy.change_for_reader(SequenceNumber_t::from(5))
+        );
+        assert_eq!(vec![4], sequence_numbers(reader_proxy.requested_changes()));
+
+        assert_eq!(
+            Some(SequenceNumber_t::from(4)),
+            reader_proxy
+                .next_requested_change()
+                .map(|(sequence_number, _)| sequence_number)
+        );
+        assert_eq!(None, reader_proxy.next_requested_change());
+
+        // An older acknowledgment does not undo a newer one.
+        reader_proxy.acked_changes_set(SequenceNumber_t::from(1));
+        assert_eq!(
+            ChangeForReaderStatusKind::ACKNOWLEDGED,
+            status(&reader_proxy, 2)
+        );
+    }
+
+    #[test]
+    fn duplicate_acknacks_are_detected() {
+        let mut reader_proxy = reader_proxy();
+
+        assert!(reader_proxy.is_new_acknack(Count_t::from(1)));
+        assert!(!reader_proxy.is_new_acknack(Count_t::from(1)));
+        assert!(reader_proxy.is_new_acknack(Count_t::from(3)));
+        assert!(!reader_proxy.is_new_acknack(Count_t::from(2)));
+
+        // The count wraps around.
+        assert!(reader_proxy.is_new_acknack(Count_t::from(i32::MAX)));
+        assert!(reader_proxy.is_new_acknack(Count_t::from(i32::MIN)));
+        assert!(!reader_proxy.is_new_acknack(Count_t::from(i32::MAX)));
+        assert!(reader_proxy.is_new_nack_frag(Count_t::from(i32::MAX)));
+        assert!(reader_proxy.is_new_nack_frag(Count_t::from(i32::MIN)));
+        assert!(!reader_proxy.is_new_nack_frag(Count_t::from(i32::MAX)));
+    }
+
+    #[test]
+    fn requests_of_recently_sent_changes_are_suppressed() {
+        let mut reader_proxy = reader_proxy();
+        reader_proxy.add_new_changes(&writer_cache(&[1, 2]), ChangeForReaderStatusKind::UNSENT);
+        let sent = Instant::now();
+        let nack_suppression_duration = Duration::from_millis(100);
+
+        reader_proxy.next_unsent_change();
+        reader_proxy.change_sent(SequenceNumber_t::from(1), sent);
+        assert!(reader_proxy.is_nack_suppressed(
+            SequenceNumber_t::from(1),
+            sent + Duration::from_millis(50),
+            nack_suppression_duration
+        ));
+        assert!(!reader_proxy.is_nack_suppressed(
+            SequenceNumber_t::from(1),
+            sent + nack_suppression_duration,
+            nack_suppression_duration
+        ));
+        assert!(!reader_proxy.is_nack_suppressed(
+            SequenceNumber_t::from(2),
+            sent,
+            nack_suppression_duration
+        ));
+
+        reader_proxy.acked_changes_set(SequenceNumber_t::from(1));
+        assert!(!reader_proxy.is_nack_suppressed(
+            SequenceNumber_t::from(1),
+            sent,
+            nack_suppression_duration
+        ));
+    }
+
+    #[test]
+    fn requested_fragments_are_taken_until_acknowledged() {
+        let mut reader_proxy = reader_proxy();
+        reader_proxy.add_new_changes(&writer_cache(&[1, 2]), ChangeForReaderStatusKind::UNSENT);
+        let fragment_numbers = |numbers: &[u32]| {
+            numbers
+                .iter()
+                .map(|number| FragmentNumber_t::from(*number))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(reader_proxy.is_new_nack_frag(Count_t::from(1)));
+        assert!(!reader_proxy.is_new_nack_frag(Count_t::from(1)));
+        reader_proxy.requested_fragments_set(
+            SequenceNumber_t::from(1),
+            fragment_numbers(&[2, 5]).into_iter(),
+        );
+        reader_proxy.requested_fragments_set(
+            SequenceNumber_t::from(1),
+            fragment_numbers(&[3]).into_iter(),
+        );
+        reader_proxy.requested_fragments_set(
+            SequenceNumber_t::from(3),
+            fragment_numbers(&[1]).into_iter(),
+        );
+        assert!(reader_proxy.has_requested_fragments());
+
+        let requested = reader_proxy.take_requested_fragments();
+        assert_eq!(1, requested.len());
+        assert_eq!(
+            fragment_numbers(&[2, 3, 5]),
+            requested[&SequenceNumber_t::from(1)]
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        );
+        assert!(!reader_proxy.has_requested_fragments());
+
+        reader_proxy.requested_fragments_set(
+            SequenceNumber_t::from(2),
+            fragment_numbers(&[1]).into_iter(),
+        );
+        reader_proxy.acked_changes_set(SequenceNumber_t::from(2));
+        assert!(!reader_proxy.has_requested_fragments());
+    }
+
+    #[test]
+    fn acknack_reactivates_reader() {
+        let mut reader_proxy = reader_proxy();
+        reader_proxy.heartbeat_sent();
+        reader_proxy.heartbeat_sent();
+        assert_eq!(2, reader_proxy.unanswered_heartbeats());
 
-        // add new change with sequence number higher than highest sent
-        changes.push(default_cache_change(10));
+        assert!(!reader_proxy.acknack_received());
+        assert_eq!(0, reader_proxy.unanswered_heartbeats());
 
-        assert_eq!(changes.last(), reader_proxy.next_unsent_change(&changes));
+        reader_proxy.deactivate();
+        assert!(!reader_proxy.is_active());
+        assert!(reader_proxy.acknack_received());
+        assert!(reader_proxy.is_active());
     }
 }
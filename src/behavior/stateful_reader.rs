@@ -0,0 +1,1180 @@
+use crate::behavior::change_from_writer_status_kind::ChangeFromWriterStatusKind;
+use crate::behavior::fragmented_change::FragmentedChange;
+use crate::behavior::reader::{Reader, ReaderAttributes};
+use crate::behavior::writer_proxy::WriterProxy;
+use crate::messages::data::Data;
+use crate::messages::data_frag::DataFrag;
+use crate::messages::gap::Gap;
+use crate::messages::heartbeat::Heartbeat;
+use crate::messages::heartbeat_frag::HeartbeatFrag;
+use crate::messages::message_builder::MessageBuilder;
+use crate::messages::receiver::{MessageReceiver, Receiver};
+use crate::messages::submessage::EntitySubmessage;
+use crate::messages::submessage_flag::SubmessageFlag;
+use crate::structure::cache_change::CacheChange;
+use crate::structure::duration::Duration_t;
+use crate::structure::endpoint::{Endpoint, EndpointAttributes};
+use crate::structure::entity::{Entity, EntityAttributes};
+use crate::structure::entity_id::EntityId_t;
+use crate::structure::guid::GUID_t;
+use crate::structure::guid_prefix::GuidPrefix_t;
+use crate::structure::history_cache::{AddChangeResult, HistoryCache, RejectedReason};
+use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::time::Time_t;
+use crate::transport::message_sender::MessageSender;
+use bytes::{Bytes, BytesMut};
+use num_traits::CheckedAdd;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio_util::codec::Decoder;
+
+/// Specialization of RTPS Reader used for the Stateful Reference
+/// Implementation. The RTPS StatefulReader keeps state on each matched RTPS
+/// Writer, which allows the reliable reception of the changes: the missing
+/// ones are requested with ACKNACKs, and the changes are delivered to the
+/// reader’s HistoryCache strictly in order.
+pub struct StatefulReader {
+    /// Used to maintain state on the remote Writers matched up with the
+    /// Reader.
+    matched_writers: Vec<WriterProxy>,
+
+    /// The changes received out of order from each matched writer, which are
+    /// delivered once the ones before are available.
+    pending_changes: BTreeMap<GUID_t, BTreeMap<SequenceNumber_t, CacheChange>>,
+
+    /// The changes partially received as DATA_FRAGs from each matched writer,
+    /// which are reassembled as their fragments arrive.
+    fragmented_changes: BTreeMap<GUID_t, BTreeMap<SequenceNumber_t, FragmentedChange>>,
+
+    /// How long a partially received change can go without receiving a
+    /// fragment before the missing ones are requested.
+    fragment_timeout: Duration,
+
+    /// How many changes of each writer can be partially received at once.
+    max_fragmented_changes: usize,
+
+    entity: EntityAttributes,
+    endpoint: EndpointAttributes,
+    reader: ReaderAttributes,
+}
+
+impl Entity for StatefulReader {
+    fn as_entity(&self) -> &EntityAttributes {
+        &self.entity
+    }
+}
+
+impl Endpoint for StatefulReader {
+    fn as_endpoint(&self) -> &EndpointAttributes {
+        &self.endpoint
+    }
+}
+
+impl Reader for StatefulReader {
+    fn as_reader(&self) -> &ReaderAttributes {
+        &self.reader
+    }
+}
+
+impl StatefulReader {
+    pub const DEFAULT_FRAGMENT_TIMEOUT: Duration = Duration::from_millis(100);
+    pub const DEFAULT_MAX_FRAGMENTED_CHANGES: usize = 16;
+
+    pub fn new(
+        entity: EntityAttributes,
+        endpoint: EndpointAttributes,
+        reader: ReaderAttributes,
+    ) -> Self {
+        StatefulReader {
+            matched_writers: vec![],
+            pending_changes: BTreeMap::new(),
+            fragmented_changes: BTreeMap::new(),
+            fragment_timeout: Self::DEFAULT_FRAGMENT_TIMEOUT,
+            max_fragmented_changes: Self::DEFAULT_MAX_FRAGMENTED_CHANGES,
+            entity,
+            endpoint,
+            reader,
+        }
+    }
+
+    pub fn reader_cache(&mut self) -> &mut HistoryCache {
+        &mut self.reader.reader_cache
+    }
+
+    /// Delivers the available changes the reader’s HistoryCache rejected
+    /// before, e.g. once changes were taken from it to make room.
+    pub fn deliver_pending_changes(&mut self) {
+        let writer_guids: Vec<_> = self.pending_changes.keys().copied().collect();
+        for writer_guid in writer_guids {
+            self.deliver_available_changes(&writer_guid);
+        }
+    }
+
+    /// Sets how long a partially received change can go without receiving a
+    /// fragment before the missing ones are requested with a NACK_FRAG.
+    pub fn set_fragment_timeout(&mut self, fragment_timeout: Duration_t) {
+        self.fragment_timeout = Duration::from(fragment_timeout);
+    }
+
+    /// Sets how many changes of each writer can be partially received at
+    /// once. The DATA_FRAGs of the changes beyond are ignored, and the
+    /// changes requested again once they are missing.
+    pub fn set_max_fragmented_changes(&mut self, max_fragmented_changes: usize) {
+        self.max_fragmented_changes = max_fragmented_changes;
+    }
+
+    pub fn matched_writer_add(&mut self, a_writer_proxy: WriterProxy) {
+        self.matched_writers.push(a_writer_proxy)
+    }
+
+    /// Removes the WriterProxy of the writer, dropping the changes received
+    /// from it that were not delivered.
+    pub fn matched_writer_remove(&mut self, writer_guid: &GUID_t) {
+        self.matched_writers
+            .retain(|proxy| &proxy.remote_writer_guid != writer_guid);
+        self.pending_changes.remove(writer_guid);
+        self.fragmented_changes.remove(writer_guid);
+    }
+
+    pub fn matched_writer_lookup(&self, a_writer_guid: GUID_t) -> Option<&WriterProxy> {
+        self.matched_writers
+            .iter()
+            .find(|proxy| proxy.remote_writer_guid == a_writer_guid)
+    }
+
+    /// Processes the DATA, DATA_FRAG, GAP, HEARTBEAT and HEARTBEAT_FRAG
+    /// submessages the MessageReceiver decodes from the bytes.
+    pub fn receive(
+        &mut self,
+        message_receiver: &mut MessageReceiver,
+        bytes: &mut BytesMut,
+        reception_timestamp: Time_t,
+        now: Instant,
+    ) -> std::io::Result<()> {
+        while let Some(submessage) = message_receiver.decode(bytes)? {
+            let receiver = message_receiver.receiver();
+            match submessage {
+                EntitySubmessage::Data(data, flags) => {
+                    self.process_data(receiver, data, flags, reception_timestamp)?;
+                }
+                EntitySubmessage::DataFrag(data_frag, flags) => {
+                    self.process_data_frag(receiver, data_frag, flags, reception_timestamp, now)?;
+                }
+                EntitySubmessage::Gap(gap) => self.process_gap(receiver, &gap),
+                EntitySubmessage::Heartbeat(heartbeat, flags) => {
+                    self.process_heartbeat(receiver, &heartbeat, flags, now)
+                }
+                EntitySubmessage::HeartbeatFrag(heartbeat_frag) => {
+                    self.process_heartbeat_frag(receiver, &heartbeat_frag, now)
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Processes a DATA of a matched writer: the change is delivered to the
+    /// reader’s HistoryCache once all the changes before it are available
+    /// and delivered.
+    /// The changes already received, and the DATA meant for other readers or
+    /// sent by writers that are not matched, are ignored.
+    pub fn process_data(
+        &mut self,
+        receiver: &Receiver,
+        data: Data,
+        flags: SubmessageFlag,
+        reception_timestamp: Time_t,
+    ) -> Result<(), speedy::Error> {
+        if !self.is_destination(receiver, data.reader_id) {
+            return Ok(());
+        }
+        let writer_guid = GUID_t {
+            guid_prefix: receiver.source_guid_prefix,
+            entity_id: data.writer_id,
+        };
+        let proxy = match self
+            .matched_writers
+            .iter_mut()
+            .find(|proxy| proxy.remote_writer_guid == writer_guid)
+        {
+            Some(proxy) => proxy,
+            None => return Ok(()),
+        };
+        if data.writer_sn <= proxy.available_changes_max() {
+            return Ok(());
+        }
+
+        let change = CacheChange::from_data(data, flags, receiver, reception_timestamp)?;
+        proxy.received_change_set(change.sequence_number);
+        if let Some(fragmented_changes) = self.fragmented_changes.get_mut(&writer_guid) {
+            fragmented_changes.remove(&change.sequence_number);
+        }
+        self.pending_changes
+            .entry(writer_guid)
+            .or_default()
+            .entry(change.sequence_number)
+            .or_insert(change);
+        self.deliver_available_changes(&writer_guid);
+        Ok(())
+    }
+
+    /// Processes a DATA_FRAG of a matched writer: its fragments are added to
+    /// the partially received change, which is processed as a DATA once all
+    /// of them were received. The fragments of changes already received, and
+    /// the DATA_FRAG meant for other readers or sent by writers that are not
+    /// matched, are ignored. So are the changes larger than the max_sample_size
+    /// of the reader’s resource limits, and the ones beyond the
+    /// max_fragmented_changes of the writer: the lowest sequence numbers are
+    /// reassembled first, since the changes are delivered in order.
+    pub fn process_data_frag(
+        &mut self,
+        receiver: &Receiver,
+        data_frag: DataFrag,
+        flags: SubmessageFlag,
+        reception_timestamp: Time_t,
+        now: Instant,
+    ) -> Result<(), speedy::Error> {
+        if !self.is_destination(receiver, data_frag.reader_id) {
+            return Ok(());
+        }
+        let writer_guid = GUID_t {
+            guid_prefix: receiver.source_guid_prefix,
+            entity_id: data_frag.writer_id,
+        };
+        let writer_sn = data_frag.writer_sn;
+        let is_received = match self.matched_writer_lookup(writer_guid) {
+            Some(proxy) => {
+                proxy.change_from_writer(writer_sn).status == ChangeFromWriterStatusKind::RECEIVED
+            }
+            None => return Ok(()),
+        };
+        if is_received {
+            return Ok(());
+        }
+
+        let max_sample_size = self.reader.reader_cache.resource_limits().max_sample_size;
+        let max_fragmented_changes = self.max_fragmented_changes;
+        let fragmented_changes = self.fragmented_changes.entry(writer_guid).or_default();
+        if !fragmented_changes.contains_key(&writer_sn) {
+            let fragmented_change =
+                match FragmentedChange::new(&data_frag, flags, max_sample_size, now) {
+                    Some(fragmented_change) => fragmented_change,
+                    None => return Ok(()),
+                };
+            if fragmented_changes.len() >= max_fragmented_changes {
+                match fragmented_changes.keys().next_back() {
+                    Some(highest_sn) if *highest_sn > writer_sn => {
+                        let highest_sn = *highest_sn;
+                        fragmented_changes.remove(&highest_sn);
+                    }
+                    _ => return Ok(()),
+                }
+            }
+            fragmented_changes.insert(writer_sn, fragmented_change);
+        }
+        let fragmented_change = match fragmented_changes.get_mut(&writer_sn) {
+            Some(fragmented_change) => fragmented_change,
+            None => return Ok(()),
+        };
+        fragmented_change.insert(data_frag, now);
+        if !fragmented_change.is_complete() {
+            return Ok(());
+        }
+
+        if let Some(fragmented_change) = fragmented_changes.remove(&writer_sn) {
+            let (data, flags) = fragmented_change.into_data()?;
+            self.process_data(receiver, data, flags, reception_timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Processes a GAP of a matched writer: the changes in the GAP are
+    /// irrelevant, and are never delivered. The GAPs with a gap_start below 1
+    /// are invalid (8.3.7.4.3), and ignored.
+    pub fn process_gap(&mut self, receiver: &Receiver, gap: &Gap) {
+        if !self.is_destination(receiver, gap.reader_id)
+            || gap.gap_start < SequenceNumber_t::from(1)
+        {
+            return;
+        }
+        let writer_guid = GUID_t {
+            guid_prefix: receiver.source_guid_prefix,
+            entity_id: gap.writer_id,
+        };
+        let proxy = match self
+            .matched_writers
+            .iter_mut()
+            .find(|proxy| proxy.remote_writer_guid == writer_guid)
+        {
+            Some(proxy) => proxy,
+            None => return,
+        };
+
+        // The range of the GAP is recorded at once, whatever its size.
+        let gap_start = gap.gap_start;
+        let gap_end = *gap.gap_list.base();
+        proxy.irrelevant_changes_set(gap_start, gap_end);
+        let pending_changes = self.pending_changes.entry(writer_guid).or_default();
+        let fragmented_changes = self.fragmented_changes.entry(writer_guid).or_default();
+        if gap_start < gap_end {
+            let is_outside_gap = |sequence_number: &SequenceNumber_t| {
+                *sequence_number < gap_start || *sequence_number >= gap_end
+            };
+            pending_changes.retain(|sequence_number, _| is_outside_gap(sequence_number));
+            fragmented_changes.retain(|sequence_number, _| is_outside_gap(sequence_number));
+        }
+        for sequence_number in gap.gap_list.iter() {
+            proxy.irrelevant_change_set(sequence_number);
+            pending_changes.remove(&sequence_number);
+            fragmented_changes.remove(&sequence_number);
+        }
+        self.deliver_available_changes(&writer_guid);
+    }
+
+    /// Processes a HEARTBEAT of a matched writer: the changes it no longer
+    /// has are lost, and the ones it has that were not received are missing.
+    /// An ACKNACK is scheduled heartbeat_response_delay later, unless the
+    /// HEARTBEAT is final and no change is missing. Duplicate HEARTBEATs are
+    /// ignored, as are the invalid ones, with a first_sn below 1 or a last_sn
+    /// below first_sn - 1 (8.3.7.5).
+    pub fn process_heartbeat(
+        &mut self,
+        receiver: &Receiver,
+        heartbeat: &Heartbeat,
+        flags: SubmessageFlag,
+        now: Instant,
+    ) {
+        if !self.is_destination(receiver, heartbeat.reader_id) {
+            return;
+        }
+        let first_sn = heartbeat.first_sn;
+        if first_sn < SequenceNumber_t::from(1)
+            || heartbeat.last_sn < first_sn - SequenceNumber_t::from(1)
+        {
+            return;
+        }
+        let writer_guid = GUID_t {
+            guid_prefix: receiver.source_guid_prefix,
+            entity_id: heartbeat.writer_id,
+        };
+        let proxy = match self
+            .matched_writers
+            .iter_mut()
+            .find(|proxy| proxy.remote_writer_guid == writer_guid)
+        {
+            Some(proxy) => proxy,
+            None => return,
+        };
+        if !proxy.is_new_heartbeat(heartbeat.count) {
+            return;
+        }
+
+        proxy.missing_changes_update(heartbeat.last_sn);
+        proxy.lost_changes_update(heartbeat.first_sn);
+        let is_final = flags.is_flag_set(Heartbeat::FINAL_FLAG_MASK);
+        if !is_final || proxy.missing_changes().next().is_some() {
+            let heartbeat_response_delay = Duration::from(self.reader.heartbeat_response_delay);
+            proxy.acknack_schedule(now + heartbeat_response_delay);
+        }
+        self.deliver_available_changes(&writer_guid);
+    }
+
+    /// Processes a HEARTBEAT_FRAG of a matched writer: if the change is
+    /// partially received, a NACK_FRAG requesting its missing fragments up to
+    /// last_fragment_num is scheduled heartbeat_response_delay later.
+    /// Duplicate HEARTBEAT_FRAGs are ignored.
+    pub fn process_heartbeat_frag(
+        &mut self,
+        receiver: &Receiver,
+        heartbeat_frag: &HeartbeatFrag,
+        now: Instant,
+    ) {
+        if !self.is_destination(receiver, heartbeat_frag.reader_id) {
+            return;
+        }
+        let writer_guid = GUID_t {
+            guid_prefix: receiver.source_guid_prefix,
+            entity_id: heartbeat_frag.writer_id,
+        };
+        let proxy = match self
+            .matched_writers
+            .iter_mut()
+            .find(|proxy| proxy.remote_writer_guid == writer_guid)
+        {
+            Some(proxy) => proxy,
+            None => return,
+        };
+        if !proxy.is_new_heartbeat_frag(heartbeat_frag.count) {
+            return;
+        }
+
+        let fragmented_change = self
+            .fragmented_changes
+            .get_mut(&writer_guid)
+            .and_then(|fragmented_changes| fragmented_changes.get_mut(&heartbeat_frag.writer_sn));
+        if let Some(fragmented_change) = fragmented_change {
+            let heartbeat_response_delay = Duration::from(self.reader.heartbeat_response_delay);
+            fragmented_change.nack_frag_schedule(
+                now + heartbeat_response_delay,
+                heartbeat_frag.last_fragment_num,
+            );
+        }
+    }
+
+    /// Sends the ACKNACKs and NACK_FRAGs that are due to the matched writers.
+    /// The missing changes that are partially received are requested with a
+    /// NACK_FRAG, for their missing fragments only, rather than in the
+    /// ACKNACK. A NACK_FRAG is also sent when a partially received change
+    /// did not receive any fragment for fragment_timeout.
+    pub fn send_acknacks<S: MessageSender>(
+        &mut self,
+        sender: &mut S,
+        now: Instant,
+    ) -> std::io::Result<()> {
+        let reader_id = self.entity.guid.entity_id;
+        for proxy in &mut self.matched_writers {
+            let mut submessages = vec![];
+            let fragmented_changes = self
+                .fragmented_changes
+                .entry(proxy.remote_writer_guid)
+                .or_default();
+
+            let acknack_due = proxy.acknack_due(now);
+            if acknack_due {
+                let acknack = proxy.acknack(reader_id, |sequence_number| {
+                    fragmented_changes.contains_key(&sequence_number)
+                });
+                let mut flags = SubmessageFlag { flags: 0x01 };
+                if acknack.reader_sn_state.iter().next().is_none() {
+                    flags.set_flag(0x02);
+                }
+                submessages.push(EntitySubmessage::AckNack(acknack, flags));
+            }
+
+            for (writer_sn, fragmented_change) in fragmented_changes.iter_mut() {
+                let nack_frag_due = fragmented_change.nack_frag_due(now, self.fragment_timeout);
+                let is_missing = proxy.change_from_writer(*writer_sn).status
+                    == ChangeFromWriterStatusKind::MISSING;
+                let last_fragment_num = if acknack_due && is_missing {
+                    fragmented_change.fragment_count()
+                } else {
+                    match nack_frag_due {
+                        Some(last_fragment_num) => last_fragment_num,
+                        None => continue,
+                    }
+                };
+                if let Some(missing) = fragmented_change.missing_fragments(last_fragment_num) {
+                    let nack_frag = proxy.nack_frag(reader_id, *writer_sn, missing);
+                    submessages.push(EntitySubmessage::NackFrag(nack_frag));
+                }
+            }
+
+            if submessages.is_empty() {
+                continue;
+            }
+            let mut message_builder = MessageBuilder::new(self.entity.guid.guid_prefix);
+            message_builder.info_destination(proxy.remote_writer_guid.guid_prefix)?;
+            for submessage in &submessages {
+                message_builder.entity_submessage(submessage)?;
+            }
+            Self::send_to(sender, proxy, message_builder.build())?;
+        }
+        Ok(())
+    }
+
+    /// Tells whether the submessage is meant for the reader.
+    fn is_destination(&self, receiver: &Receiver, reader_id: EntityId_t) -> bool {
+        let guid = &self.entity.guid;
+        (receiver.dest_guid_prefix == GuidPrefix_t::GUIDPREFIX_UNKNOWN
+            || receiver.dest_guid_prefix == guid.guid_prefix)
+            && (reader_id == EntityId_t::ENTITYID_UNKNOWN || reader_id == guid.entity_id)
+    }
+
+    /// Moves the changes of the writer that are available to the reader’s
+    /// HistoryCache, in order. Once the HistoryCache rejects a change, e.g.
+    /// because it is full, the change and the ones after it are kept pending
+    /// until a later delivery, so that they are still delivered in order.
+    fn deliver_available_changes(&mut self, writer_guid: &GUID_t) {
+        let available_changes_max = match self.matched_writer_lookup(*writer_guid) {
+            Some(proxy) => proxy.available_changes_max(),
+            None => return,
+        };
+        // Past the last sequence number, all the changes are available.
+        let first_unavailable = available_changes_max.checked_add(&SequenceNumber_t::from(1));
+
+        // The partially received changes that are lost are dropped.
+        if let Some(fragmented_changes) = self.fragmented_changes.get_mut(writer_guid) {
+            *fragmented_changes = Self::split_unavailable(fragmented_changes, first_unavailable);
+        }
+
+        let pending_changes = match self.pending_changes.get_mut(writer_guid) {
+            Some(pending_changes) => pending_changes,
+            None => return,
+        };
+        let undelivered = Self::split_unavailable(pending_changes, first_unavailable);
+        let available = std::mem::replace(pending_changes, undelivered);
+        let mut available = available.into_values();
+        while let Some(change) = available.next() {
+            match self.reader.reader_cache.add_change(change) {
+                AddChangeResult::Rejected(change, reason)
+                    if reason != RejectedReason::Duplicate =>
+                {
+                    pending_changes.insert(change.sequence_number, change);
+                    pending_changes
+                        .extend(available.map(|change| (change.sequence_number, change)));
+                    break;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Splits the changes that are not available off the changes.
+    fn split_unavailable<T>(
+        changes: &mut BTreeMap<SequenceNumber_t, T>,
+        first_unavailable: Option<SequenceNumber_t>,
+    ) -> BTreeMap<SequenceNumber_t, T> {
+        match first_unavailable {
+            Some(first_unavailable) => changes.split_off(&first_unavailable),
+            None => BTreeMap::new(),
+        }
+    }
+
+    /// Sends the message to the unicast locators of the writer or, if it has
+    /// none, to its multicast locators.
+    fn send_to<S: MessageSender>(
+        sender: &mut S,
+        proxy: &WriterProxy,
+        message: Bytes,
+    ) -> std::io::Result<()> {
+        let locators = if proxy.unicast_locator_list.is_empty() {
+            &proxy.multicast_locator_list
+        } else {
+            &proxy.unicast_locator_list
+        };
+        for locator in locators {
+            sender.send(locator, message.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behavior::reader_proxy::ReaderProxy;
+    use crate::behavior::stateful_writer::StatefulWriter;
+    use crate::behavior::writer::{Writer, WriterAttributes};
+    use crate::dds::qos::{HistoryQosPolicy, ResourceLimitsQosPolicy};
+    use crate::messages::ack_nack::AckNack;
+    use crate::messages::fragment_number::FragmentNumber_t;
+    use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+    use crate::structure::change_kind::ChangeKind_t;
+    use crate::structure::count::Count_t;
+    use crate::structure::data::Data;
+    use crate::structure::duration::Duration_t;
+    use crate::structure::instance_handle::InstanceHandle_t;
+    use crate::structure::locator::{Ipv4Addr, LocatorKind_t, Locator_t, SocketAddr};
+    use crate::structure::reliability_kind::ReliabilityKind_t;
+    use crate::structure::sequence_number_set::SequenceNumberSet_t;
+    use crate::structure::topic_kind::TopicKind_t;
+    use crate::transport::in_memory::InMemoryTransport;
+    use speedy::Endianness;
+
+    fn reader_guid() -> GUID_t {
+        GUID_t {
+            guid_prefix: GuidPrefix_t::from([0x01; 12]),
+            entity_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_READER,
+        }
+    }
+
+    fn writer_guid() -> GUID_t {
+        GUID_t {
+            guid_prefix: GuidPrefix_t::from([0x02; 12]),
+            entity_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+        }
+    }
+
+    fn locator(port: u16) -> Locator_t {
+        Locator_t::from(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+    }
+
+    fn endpoint() -> EndpointAttributes {
+        EndpointAttributes {
+            topic_kind: TopicKind_t::NO_KEY,
+            reliability_level: ReliabilityKind_t::RELIABLE,
+            unicast_locator_list: Locator_t::LOCATOR_INVALID,
+            multicast_locator_list: Locator_t::LOCATOR_INVALID,
+        }
+    }
+
+    fn stateful_reader(heartbeat_response_delay: Duration) -> StatefulReader {
+        let mut reader = StatefulReader::new(
+            EntityAttributes {
+                guid: reader_guid(),
+            },
+            endpoint(),
+            ReaderAttributes {
+                heartbeat_response_delay: Duration_t::from(heartbeat_response_delay),
+                heartbeat_suppression_duration: Duration_t::DURATION_ZERO,
+                expects_inline_qos: false,
+                reader_cache: HistoryCache::new(),
+            },
+        );
+        reader.matched_writer_add(WriterProxy::new(writer_guid(), vec![locator(7410)], vec![]));
+        reader
+    }
+
+    fn data(sequence_number: i64) -> EntitySubmessage {
+        let change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            writer_guid(),
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(sequence_number),
+            Some(Data::new(
+                RepresentationIdentifier::CDR_LE,
+                Bytes::from_static(b"data"),
+            )),
+        );
+        let (data, flags) = change
+            .to_data(
+                EntityId_t::ENTITYID_UNKNOWN,
+                &TopicKind_t::NO_KEY,
+                Endianness::LittleEndian,
+            )
+            .unwrap();
+        EntitySubmessage::Data(data, flags)
+    }
+
+    /// The DATA_FRAGs of a change with 24 octets of serialized payload, in
+    /// fragments of 8 octets.
+    fn data_frags(sequence_number: i64) -> Vec<EntitySubmessage> {
+        let change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            writer_guid(),
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(sequence_number),
+            Some(Data::new(
+                RepresentationIdentifier::CDR_LE,
+                Bytes::from_static(b"0123456789abcdefghij"),
+            )),
+        );
+        change
+            .to_data_frags(
+                EntityId_t::ENTITYID_UNKNOWN,
+                &TopicKind_t::NO_KEY,
+                Endianness::LittleEndian,
+                8,
+            )
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|(data_frag, flags)| EntitySubmessage::DataFrag(data_frag, flags))
+            .collect()
+    }
+
+    fn heartbeat_frag(writer_sn: i64, last_fragment_num: u32, count: i32) -> EntitySubmessage {
+        EntitySubmessage::HeartbeatFrag(HeartbeatFrag {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: writer_guid().entity_id,
+            writer_sn: SequenceNumber_t::from(writer_sn),
+            last_fragment_num: FragmentNumber_t::from(last_fragment_num),
+            count: Count_t::from(count),
+        })
+    }
+
+    fn gap(gap_start: i64, gap_end: i64) -> EntitySubmessage {
+        EntitySubmessage::Gap(Gap {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: writer_guid().entity_id,
+            gap_start: SequenceNumber_t::from(gap_start),
+            gap_list: SequenceNumberSet_t::new(SequenceNumber_t::from(gap_end)),
+        })
+    }
+
+    fn heartbeat(first_sn: i64, last_sn: i64, count: i32, flags: u8) -> EntitySubmessage {
+        EntitySubmessage::Heartbeat(
+            Heartbeat {
+                reader_id: EntityId_t::ENTITYID_UNKNOWN,
+                writer_id: writer_guid().entity_id,
+                first_sn: SequenceNumber_t::from(first_sn),
+                last_sn: SequenceNumber_t::from(last_sn),
+                count: Count_t::from(count),
+            },
+            SubmessageFlag {
+                flags: 0x01 | flags,
+            },
+        )
+    }
+
+    fn receive(reader: &mut StatefulReader, submessages: &[EntitySubmessage], now: Instant) {
+        let mut message_builder = MessageBuilder::new(writer_guid().guid_prefix);
+        for submessage in submessages {
+            message_builder.entity_submessage(submessage).unwrap();
+        }
+        receive_message(reader, message_builder.build(), now);
+    }
+
+    fn receive_message(reader: &mut StatefulReader, message: Bytes, now: Instant) {
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        let mut bytes = BytesMut::from(&message[..]);
+        reader
+            .receive(&mut message_receiver, &mut bytes, Time_t::TIME_ZERO, now)
+            .unwrap();
+    }
+
+    fn received(reader: &mut StatefulReader) -> Vec<i64> {
+        reader
+            .reader_cache()
+            .changes()
+            .map(|change| i64::from(change.sequence_number))
+            .collect()
+    }
+
+    fn acknacks(transport: &mut InMemoryTransport) -> Vec<(AckNack, bool)> {
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        let mut acknacks = vec![];
+        for message in transport.receive_all(&locator(7410)) {
+            let mut bytes = BytesMut::from(&message[..]);
+            while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+                match submessage {
+                    EntitySubmessage::AckNack(acknack, flags) => {
+                        assert_eq!(reader_guid().entity_id, acknack.reader_id);
+                        acknacks.push((acknack, flags.is_flag_set(0x02)));
+                    }
+                    submessage => panic!("unexpected submessage {:?}", submessage),
+                }
+            }
+        }
+        acknacks
+    }
+
+    /// The sequence number, requested fragments and count of the NACK_FRAGs
+    /// sent to the writer.
+    fn nack_frags(transport: &mut InMemoryTransport) -> Vec<(i64, Vec<u32>, i32)> {
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        let mut nack_frags = vec![];
+        for message in transport.receive_all(&locator(7410)) {
+            let mut bytes = BytesMut::from(&message[..]);
+            while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+                match submessage {
+                    EntitySubmessage::NackFrag(nack_frag) => nack_frags.push((
+                        nack_frag.writer_sn.into(),
+                        nack_frag
+                            .fragment_number_state
+                            .iter()
+                            .map(u32::from)
+                            .collect(),
+                        nack_frag.count.into(),
+                    )),
+                    submessage => panic!("unexpected submessage {:?}", submessage),
+                }
+            }
+        }
+        nack_frags
+    }
+
+    fn requested(acknack: &AckNack) -> (i64, Vec<i64>) {
+        (
+            i64::from(*acknack.reader_sn_state.base()),
+            acknack.reader_sn_state.iter().map(i64::from).collect(),
+        )
+    }
+
+    #[test]
+    fn changes_are_delivered_in_order() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let now = Instant::now();
+
+        receive(&mut reader, &[data(2), data(3)], now);
+        assert!(received(&mut reader).is_empty());
+
+        receive(&mut reader, &[data(1), data(2)], now);
+        assert_eq!(vec![1, 2, 3], received(&mut reader));
+
+        // The changes of writers that are not matched are ignored.
+        reader.matched_writer_remove(&writer_guid());
+        receive(&mut reader, &[data(4)], now);
+        assert_eq!(vec![1, 2, 3], received(&mut reader));
+    }
+
+    #[test]
+    fn changes_rejected_by_the_reader_cache_are_delivered_later_in_order() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        *reader.reader_cache() = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy {
+                max_samples: 2,
+                ..ResourceLimitsQosPolicy::UNLIMITED
+            },
+        );
+        let now = Instant::now();
+
+        receive(&mut reader, &[data(1), data(2), data(3), data(4)], now);
+        assert_eq!(vec![1, 2], received(&mut reader));
+
+        // Further changes wait for the rejected ones.
+        reader
+            .reader_cache()
+            .remove_change(SequenceNumber_t::from(1))
+            .unwrap();
+        receive(&mut reader, &[data(5)], now);
+        assert_eq!(vec![2, 3], received(&mut reader));
+
+        reader
+            .reader_cache()
+            .remove_changes_up_to(SequenceNumber_t::from(3))
+            .unwrap();
+        reader.deliver_pending_changes();
+        assert_eq!(vec![4, 5], received(&mut reader));
+    }
+
+    #[test]
+    fn missing_changes_are_requested_after_heartbeat_response_delay() {
+        let mut reader = stateful_reader(Duration::from_millis(100));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+
+        receive(&mut reader, &[data(1), data(3), heartbeat(1, 4, 1, 0)], now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+
+        // A duplicate HEARTBEAT does not schedule another ACKNACK.
+        receive(&mut reader, &[heartbeat(1, 4, 1, 0)], now);
+        reader
+            .send_acknacks(&mut transport, now + Duration::from_millis(100))
+            .unwrap();
+        let sent = acknacks(&mut transport);
+        assert_eq!(1, sent.len());
+        assert_eq!((2, vec![2, 4]), requested(&sent[0].0));
+        assert_eq!(Count_t::from(1), sent[0].0.count);
+        assert!(!sent[0].1);
+
+        reader
+            .send_acknacks(&mut transport, now + Duration::from_millis(200))
+            .unwrap();
+        assert!(transport.is_empty());
+    }
+
+    #[test]
+    fn final_heartbeat_is_only_answered_when_changes_are_missing() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+
+        receive(
+            &mut reader,
+            &[data(1), heartbeat(1, 1, 1, Heartbeat::FINAL_FLAG_MASK)],
+            now,
+        );
+        reader.send_acknacks(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+
+        receive(
+            &mut reader,
+            &[heartbeat(1, 2, 2, Heartbeat::FINAL_FLAG_MASK)],
+            now,
+        );
+        reader.send_acknacks(&mut transport, now).unwrap();
+        let sent = acknacks(&mut transport);
+        assert_eq!((2, vec![2]), requested(&sent[0].0));
+
+        // A non-final HEARTBEAT is acknowledged even if nothing is missing.
+        receive(&mut reader, &[data(2), heartbeat(1, 2, 3, 0)], now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        let sent = acknacks(&mut transport);
+        assert_eq!((3, vec![]), requested(&sent[0].0));
+        assert!(sent[0].1);
+    }
+
+    #[test]
+    fn irrelevant_and_lost_changes_are_skipped() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let now = Instant::now();
+
+        receive(&mut reader, &[data(2), data(5)], now);
+        receive(&mut reader, &[gap(1, 2)], now);
+        assert_eq!(vec![2], received(&mut reader));
+
+        // The writer no longer has the changes 3 and 4.
+        receive(&mut reader, &[heartbeat(5, 5, 1, 0)], now);
+        assert_eq!(vec![2, 5], received(&mut reader));
+        assert_eq!(
+            SequenceNumber_t::from(5),
+            reader
+                .matched_writer_lookup(writer_guid())
+                .unwrap()
+                .available_changes_max()
+        );
+    }
+
+    #[test]
+    fn gap_following_the_available_changes_moves_them_in_one_step() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let now = Instant::now();
+        let gap_end = i64::MAX / 2;
+
+        receive(&mut reader, &[data(2), data(gap_end)], now);
+        receive(&mut reader, &[gap(1, gap_end)], now);
+        assert_eq!(vec![gap_end], received(&mut reader));
+        assert_eq!(
+            SequenceNumber_t::from(gap_end),
+            reader
+                .matched_writer_lookup(writer_guid())
+                .unwrap()
+                .available_changes_max()
+        );
+
+        // GAPs starting before 1 are invalid.
+        receive(&mut reader, &[gap(0, gap_end + 2)], now);
+        assert_eq!(
+            SequenceNumber_t::from(gap_end),
+            reader
+                .matched_writer_lookup(writer_guid())
+                .unwrap()
+                .available_changes_max()
+        );
+    }
+
+    #[test]
+    fn gap_apart_from_the_available_changes_is_recorded_at_once() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+
+        receive(
+            &mut reader,
+            &[
+                data(1),
+                data(5),
+                gap(3, i64::MAX),
+                heartbeat(1, i64::MAX, 1, 0),
+            ],
+            now,
+        );
+        assert_eq!(vec![1], received(&mut reader));
+        reader.send_acknacks(&mut transport, now).unwrap();
+        let sent = acknacks(&mut transport);
+        assert_eq!((2, vec![2]), requested(&sent[0].0));
+
+        receive(&mut reader, &[data(2), data(i64::MAX)], now);
+        assert_eq!(vec![1, 2, i64::MAX], received(&mut reader));
+        receive(&mut reader, &[heartbeat(1, i64::MAX, 2, 0)], now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        let sent = acknacks(&mut transport);
+        assert_eq!((i64::MAX, vec![]), requested(&sent[0].0));
+    }
+
+    #[test]
+    fn invalid_heartbeats_are_ignored() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+
+        receive(
+            &mut reader,
+            &[
+                heartbeat(0, 4, 1, 0),
+                heartbeat(i64::MIN, 4, 1, 0),
+                heartbeat(3, 1, 1, 0),
+            ],
+            now,
+        );
+        reader.send_acknacks(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+
+        // An empty history is announced with last_sn = first_sn - 1, and the
+        // count of the invalid HEARTBEATs was not taken.
+        receive(&mut reader, &[heartbeat(3, 2, 1, 0)], now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        let sent = acknacks(&mut transport);
+        assert_eq!((3, vec![]), requested(&sent[0].0));
+    }
+
+    #[test]
+    fn missing_fragments_are_requested_after_heartbeat_frag_or_timeout() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        let mut data_frags = data_frags(1);
+        let second_fragment = data_frags.remove(1);
+
+        receive(&mut reader, &data_frags, now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+
+        // A duplicate HEARTBEAT_FRAG does not schedule another NACK_FRAG.
+        receive(&mut reader, &[heartbeat_frag(1, 2, 1)], now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        receive(&mut reader, &[heartbeat_frag(1, 2, 1)], now);
+        reader.send_acknacks(&mut transport, now).unwrap();
+        assert_eq!(vec![(1, vec![2], 1)], nack_frags(&mut transport));
+
+        let timeout = now + StatefulReader::DEFAULT_FRAGMENT_TIMEOUT;
+        reader.send_acknacks(&mut transport, timeout).unwrap();
+        assert_eq!(vec![(1, vec![2], 2)], nack_frags(&mut transport));
+
+        receive(&mut reader, &[second_fragment], timeout);
+        assert_eq!(vec![1], received(&mut reader));
+        let change = reader
+            .reader_cache()
+            .get_writer_change(&writer_guid(), SequenceNumber_t::from(1))
+            .unwrap();
+        assert_eq!(
+            &b"0123456789abcdefghij"[..],
+            &change.data_value.as_ref().unwrap().value[..]
+        );
+    }
+
+    #[test]
+    fn fragmented_changes_are_bounded() {
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let now = Instant::now();
+
+        // The changes larger than max_sample_size are not reassembled.
+        *reader.reader_cache() = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy {
+                max_sample_size: 23,
+                ..ResourceLimitsQosPolicy::UNLIMITED
+            },
+        );
+        receive(&mut reader, &data_frags(1), now);
+        assert!(received(&mut reader).is_empty());
+
+        // Beyond max_fragmented_changes, the lowest sequence numbers are
+        // reassembled first.
+        *reader.reader_cache() = HistoryCache::new();
+        reader.set_max_fragmented_changes(1);
+        let mut second = data_frags(2);
+        let mut third = data_frags(3);
+        receive(&mut reader, &third.drain(..1).collect::<Vec<_>>(), now);
+        receive(&mut reader, &second.drain(..1).collect::<Vec<_>>(), now);
+        receive(&mut reader, &third, now);
+        receive(&mut reader, &second, now);
+        receive(&mut reader, &data_frags(1), now);
+        assert_eq!(vec![1, 2], received(&mut reader));
+    }
+
+    #[test]
+    fn lost_data_is_repaired_by_the_stateful_writer() {
+        let mut writer = StatefulWriter::new(
+            EntityAttributes {
+                guid: writer_guid(),
+            },
+            endpoint(),
+            WriterAttributes {
+                push_mode: true,
+                heartbeat_period: Duration_t::DURATION_INFINITE,
+                nack_response_delay: Duration_t::DURATION_ZERO,
+                nack_suppression_duration: Duration_t::DURATION_ZERO,
+                last_change_sequence_number: SequenceNumber_t::from(0),
+                writer_cache: HistoryCache::new(),
+            },
+        );
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(),
+            vec![locator(7400)],
+            vec![],
+            false,
+        ));
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+
+        for value in &[b"first", b"secnd", b"third"] {
+            let data = Data::new(RepresentationIdentifier::CDR_LE, Bytes::from_static(*value));
+            let change = writer.new_change(ChangeKind_t::ALIVE, data, InstanceHandle_t::default());
+            writer.writer_cache().add_change(change);
+        }
+        writer.send_changes(&mut transport, now).unwrap();
+
+        // The second message is lost.
+        let messages = transport.receive_all(&locator(7400));
+        assert_eq!(3, messages.len());
+        receive_message(&mut reader, messages[0].clone(), now);
+        receive_message(&mut reader, messages[2].clone(), now);
+        assert_eq!(vec![1], received(&mut reader));
+
+        reader.send_acknacks(&mut transport, now).unwrap();
+        for (acknack, _) in acknacks(&mut transport) {
+            writer.process_acknack(&reader_guid(), &acknack, now);
+        }
+        writer.send_changes(&mut transport, now).unwrap();
+        for message in transport.receive_all(&locator(7400)) {
+            receive_message(&mut reader, message, now);
+        }
+        assert_eq!(vec![1, 2, 3], received(&mut reader));
+    }
+
+    #[test]
+    fn lost_fragment_is_repaired_by_the_stateful_writer() {
+        let mut writer = StatefulWriter::new(
+            EntityAttributes {
+                guid: writer_guid(),
+            },
+            endpoint(),
+            WriterAttributes {
+                push_mode: true,
+                heartbeat_period: Duration_t::DURATION_INFINITE,
+                nack_response_delay: Duration_t::DURATION_ZERO,
+                nack_suppression_duration: Duration_t::DURATION_ZERO,
+                last_change_sequence_number: SequenceNumber_t::from(0),
+                writer_cache: HistoryCache::new(),
+            },
+        );
+        writer.set_fragment_size(8);
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(),
+            vec![locator(7400)],
+            vec![],
+            false,
+        ));
+        let mut reader = stateful_reader(Duration::from_millis(0));
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+
+        for value in &[&b"0123456789abcdefghij"[..], &b"abc"[..]] {
+            let data = Data::new(RepresentationIdentifier::CDR_LE, Bytes::from_static(value));
+            let change = writer.new_change(ChangeKind_t::ALIVE, data, InstanceHandle_t::default());
+            writer.writer_cache().add_change(change);
+        }
+        writer.send_changes(&mut transport, now).unwrap();
+
+        // The message with the second fragment of the first change is lost.
+        let messages = transport.receive_all(&locator(7400));
+        assert_eq!(4, messages.len());
+        for (index, message) in messages.into_iter().enumerate() {
+            if index != 1 {
+                receive_message(&mut reader, message, now);
+            }
+        }
+        assert!(received(&mut reader).is_empty());
+
+        // The HEARTBEAT is answered with a NACK_FRAG for the lost fragment,
+        // and an ACKNACK that does not request the whole change.
+        reader.send_acknacks(&mut transport, now).unwrap();
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        for message in transport.receive_all(&locator(7410)) {
+            let mut bytes = BytesMut::from(&message[..]);
+            while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+                match submessage {
+                    EntitySubmessage::AckNack(acknack, _) => {
+                        assert_eq!((1, vec![]), requested(&acknack));
+                        writer.process_acknack(&reader_guid(), &acknack, now);
+                    }
+                    EntitySubmessage::NackFrag(nack_frag) => {
+                        writer.process_nack_frag(&reader_guid(), &nack_frag, now);
+                    }
+                    submessage => panic!("unexpected submessage {:?}", submessage),
+                }
+            }
+        }
+
+        writer.send_changes(&mut transport, now).unwrap();
+        let messages = transport.receive_all(&locator(7400));
+        assert_eq!(1, messages.len());
+        receive_message(&mut reader, messages[0].clone(), now);
+        assert_eq!(vec![1, 2], received(&mut reader));
+    }
+}
@@ -1,18 +1,101 @@
+use crate::behavior::change_for_reader_status_kind::ChangeForReaderStatusKind;
+use crate::behavior::flow_controller::FlowController;
 use crate::behavior::reader_proxy::ReaderProxy;
 use crate::behavior::writer::{Writer, WriterAttributes};
+use crate::dds::qos::{DurabilityQosPolicy, PublishModeQosPolicy};
+use crate::messages::ack_nack::AckNack;
+use crate::messages::fragment_number::FragmentNumber_t;
+use crate::messages::gap::Gap;
+use crate::messages::heartbeat::Heartbeat;
+use crate::messages::message_builder::MessageBuilder;
+use crate::messages::nack_frag::NackFrag;
+use crate::messages::submessage::EntitySubmessage;
+use crate::messages::submessage_flag::SubmessageFlag;
 use crate::structure::cache_change::CacheChange;
 use crate::structure::change_kind::ChangeKind_t;
+use crate::structure::count::Count_t;
 use crate::structure::data::Data;
+use crate::structure::duration::Duration_t;
 use crate::structure::endpoint::{Endpoint, EndpointAttributes};
 use crate::structure::entity::{Entity, EntityAttributes};
 use crate::structure::guid::GUID_t;
+use crate::structure::history_cache::{AddChangeResult, HistoryCache, HistoryCacheEvent};
 use crate::structure::instance_handle::InstanceHandle_t;
+use crate::structure::locator::LocatorList_t;
+use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::sequence_number_set::SequenceNumberSet_t;
+use crate::transport::message_sender::MessageSender;
+use bytes::Bytes;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use num_traits::CheckedSub;
+use speedy::Endianness;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Change of the responsiveness of a matched reader, as notified to the
+/// subscribers of the StatefulWriter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReaderActivityEvent {
+    /// The reader did not respond to max_unanswered_heartbeats HEARTBEATs in
+    /// a row. Its acknowledgements are no longer waited for.
+    Inactive { reader_guid: GUID_t },
+
+    /// The inactive reader responded with an ACKNACK again.
+    Active { reader_guid: GUID_t },
+}
+
+/// Stream of the ReaderActivityEvents of a StatefulWriter, returned by
+/// [`StatefulWriter::subscribe`]. It ends when the StatefulWriter is dropped.
+pub type ReaderActivityEvents = UnboundedReceiver<ReaderActivityEvent>;
 
 pub struct StatefulWriter {
     /// The StatefulWriter keeps track of all the RTPS Readers matched with it.
     /// Each matched reader is represented by an instance of the ReaderProxy
     /// class.
     matched_readers: Vec<ReaderProxy>,
+
+    /// The count of the last HEARTBEAT sent by the writer.
+    heartbeat_count: Count_t,
+
+    /// When the changes requested since the last response to the ACKNACKs
+    /// are to be sent. The requests are aggregated until then.
+    nack_response_deadline: Option<Instant>,
+
+    /// The number of HEARTBEATs in a row a matched reader can leave
+    /// unanswered before it is considered inactive.
+    max_unanswered_heartbeats: u32,
+
+    /// Whether the changes acknowledged by all the matched readers are kept
+    /// for the late-joining ones.
+    durability: DurabilityQosPolicy,
+
+    /// All the changes up to this sequence number were acknowledged by all
+    /// the active matched readers.
+    highest_seq_num_acked_by_all: SequenceNumber_t,
+
+    /// The changes with a serialized payload larger than this number of
+    /// octets are sent as DATA_FRAGs of this size.
+    fragment_size: u16,
+
+    /// Limits the octets sent by the writer, shared with other writers, and
+    /// the priority of the writer among them.
+    flow_controller: Option<(Arc<Mutex<FlowController>>, i32)>,
+
+    /// Whether the changes are sent by the publish call or by
+    /// send_changes_asynchronously.
+    publish_mode: PublishModeQosPolicy,
+
+    /// The octets of the first change the FlowController held back at the
+    /// last send, if it did.
+    held_back: Option<usize>,
+
+    /// Wakes send_changes_asynchronously up when there are changes to send.
+    publisher_wakeup: Option<UnboundedSender<()>>,
+
+    subscribers: Vec<UnboundedSender<ReaderActivityEvent>>,
+
     entity: EntityAttributes,
     endpoint: EndpointAttributes,
     writer: WriterAttributes,
@@ -41,25 +124,128 @@ impl Writer for StatefulWriter {
     /// one.
     fn new_change(
         &mut self,
-        _kind: ChangeKind_t,
-        _data: Data,
-        _handle: InstanceHandle_t,
+        kind: ChangeKind_t,
+        data: Data,
+        handle: InstanceHandle_t,
     ) -> CacheChange {
-        unimplemented!();
+        self.writer.last_change_sequence_number =
+            self.writer.last_change_sequence_number + SequenceNumber_t::from(1);
+
+        CacheChange::new(
+            kind,
+            self.entity.guid,
+            handle,
+            self.writer.last_change_sequence_number,
+            Some(data),
+        )
     }
 }
 
 impl StatefulWriter {
-    pub fn new() -> Self {
-        unimplemented!();
+    pub const DEFAULT_MAX_UNANSWERED_HEARTBEATS: u32 = 10;
+
+    /// Leaves room for the headers of the message in a UDP datagram.
+    pub const DEFAULT_FRAGMENT_SIZE: u16 = 64000;
+
+    /// Creates the writer. If the writer_cache has a HistoryStore, the
+    /// sequence numbers resume after the last change the writer stored in
+    /// it, and the stored changes are sent to the readers matched unless the
+    /// writer is VOLATILE.
+    pub fn new(
+        entity: EntityAttributes,
+        endpoint: EndpointAttributes,
+        mut writer: WriterAttributes,
+    ) -> Self {
+        writer.resume_sequence_numbers(&entity.guid);
+        StatefulWriter {
+            matched_readers: vec![],
+            heartbeat_count: Count_t::from(0),
+            nack_response_deadline: None,
+            max_unanswered_heartbeats: Self::DEFAULT_MAX_UNANSWERED_HEARTBEATS,
+            subscribers: vec![],
+            durability: DurabilityQosPolicy::default(),
+            highest_seq_num_acked_by_all: SequenceNumber_t::from(0),
+            fragment_size: Self::DEFAULT_FRAGMENT_SIZE,
+            flow_controller: None,
+            publish_mode: PublishModeQosPolicy::default(),
+            held_back: None,
+            publisher_wakeup: None,
+            entity,
+            endpoint,
+            writer,
+        }
+    }
+
+    pub fn writer_cache(&mut self) -> &mut HistoryCache {
+        &mut self.writer.writer_cache
+    }
+
+    /// Sets the number of HEARTBEATs in a row a matched reader can leave
+    /// unanswered before it is considered inactive.
+    pub fn set_max_unanswered_heartbeats(&mut self, max_unanswered_heartbeats: u32) {
+        self.max_unanswered_heartbeats = max_unanswered_heartbeats;
+    }
+
+    /// Sets whether the changes acknowledged by all the matched readers are
+    /// kept for the late-joining ones. Unless the writer is VOLATILE they are,
+    /// within the limits of the HISTORY of the HistoryCache.
+    pub fn set_durability(&mut self, durability: DurabilityQosPolicy) {
+        self.durability = durability;
+    }
+
+    /// Sets the size of the fragments the changes with a larger serialized
+    /// payload are split into, in octets. Zero disables the fragmentation.
+    pub fn set_fragment_size(&mut self, fragment_size: u16) {
+        self.fragment_size = fragment_size;
+    }
+
+    /// Limits the octets of the changes sent by the writer, the requested
+    /// ones as well as the new ones, with the FlowController, which can be
+    /// shared with other writers. The writers of a higher priority get to
+    /// send first.
+    pub fn set_flow_controller(
+        &mut self,
+        flow_controller: Arc<Mutex<FlowController>>,
+        priority: i32,
+    ) {
+        self.flow_controller = Some((flow_controller, priority));
     }
 
-    pub fn matched_reader_add(&mut self, a_reader_proxy: ReaderProxy) {
-        self.matched_readers.push(a_reader_proxy)
+    /// Sets whether the changes are sent by the publish call, in the task of
+    /// the caller, or by send_changes_asynchronously.
+    pub fn set_publish_mode(&mut self, publish_mode: PublishModeQosPolicy) {
+        self.publish_mode = publish_mode;
     }
 
-    pub fn matched_reader_remove(&mut self, _a_reader_proxy: ReaderProxy) {
-        unimplemented!();
+    /// Subscribes to the changes of the responsiveness of the matched
+    /// readers.
+    pub fn subscribe(&mut self) -> ReaderActivityEvents {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Adds the matched reader. Unless the writer is VOLATILE, the changes
+    /// it retains in its HistoryCache are sent to the reader as to the ones
+    /// matched before. Otherwise the reader starts after the last change
+    /// written, and the earlier ones are irrelevant to it: they are sent to
+    /// it as a GAP.
+    pub fn matched_reader_add(&mut self, mut a_reader_proxy: ReaderProxy) {
+        if self.durability == DurabilityQosPolicy::Volatile {
+            let status = self.new_change_status();
+            let last_change_sequence_number = self.writer.last_change_sequence_number;
+            a_reader_proxy.add_irrelevant_changes_up_to(last_change_sequence_number, status);
+        }
+        self.matched_readers.push(a_reader_proxy);
+        self.wake_publisher();
+    }
+
+    pub fn matched_reader_remove(&mut self, a_reader_guid: &GUID_t) {
+        self.matched_readers
+            .retain(|proxy| &proxy.remote_reader_guid != a_reader_guid);
+        // A failure of the store is reported by the next send_heartbeats,
+        // which removes the changes again.
+        let _ = self.remove_acked_changes();
     }
 
     pub fn matched_reader_lookup(
@@ -71,10 +257,1838 @@ impl StatefulWriter {
             .filter(move |proxy| proxy.remote_reader_guid == a_reader_guid)
     }
 
-    pub fn is_acked_by_all(&self, _a_change: &CacheChange) {
-        unimplemented!();
+    /// Tells whether all the active matched readers acknowledged the change.
+    pub fn is_acked_by_all(&self, a_change: &CacheChange) -> bool {
+        let mut active_readers = self
+            .matched_readers
+            .iter()
+            .filter(|proxy| proxy.is_active());
+        active_readers.all(|proxy| {
+            proxy
+                .change_for_reader(a_change.sequence_number)
+                .is_some_and(|change_for_reader| {
+                    change_for_reader.status == ChangeForReaderStatusKind::ACKNOWLEDGED
+                })
+        })
+    }
+
+    /// Processes an ACKNACK of the matched reader: the changes before the base
+    /// of the reader_sn_state are acknowledged, and the ones in the set are
+    /// requested again. Duplicate ACKNACKs, and the ones of unknown readers,
+    /// are ignored, as are the requests of changes sent to the reader less
+    /// than nack_suppression_duration ago. The requested changes are sent
+    /// nack_response_delay after the first request, together with the ones
+    /// requested in the meantime by any reader.
+    pub fn process_acknack(&mut self, reader_guid: &GUID_t, ack_nack: &AckNack, now: Instant) {
+        // The base of the reader_sn_state is at least 1 (8.3.5.5), the
+        // ACKNACKs with an invalid one are ignored.
+        let committed_seq_num = match ack_nack
+            .reader_sn_state
+            .base()
+            .checked_sub(&SequenceNumber_t::from(1))
+        {
+            Some(committed_seq_num) if committed_seq_num >= SequenceNumber_t::from(0) => {
+                committed_seq_num
+            }
+            _ => return,
+        };
+        let writer_cache = &self.writer.writer_cache;
+        let status = self.new_change_status();
+        let proxy = match self
+            .matched_readers
+            .iter_mut()
+            .find(|proxy| &proxy.remote_reader_guid == reader_guid)
+        {
+            Some(proxy) => proxy,
+            None => return,
+        };
+        if !proxy.is_new_acknack(ack_nack.count) {
+            return;
+        }
+        if proxy.acknack_received() {
+            let reader_guid = proxy.remote_reader_guid;
+            Self::notify(
+                &mut self.subscribers,
+                ReaderActivityEvent::Active { reader_guid },
+            );
+        }
+
+        proxy.add_new_changes(writer_cache, status);
+        let reader_sn_state = &ack_nack.reader_sn_state;
+        proxy.acked_changes_set(committed_seq_num);
+        let nack_suppression_duration = Duration::from(self.writer.nack_suppression_duration);
+        let req_seq_num_set: Vec<_> = reader_sn_state
+            .iter()
+            .filter(|sequence_number| {
+                !proxy.is_nack_suppressed(*sequence_number, now, nack_suppression_duration)
+            })
+            .collect();
+        proxy.requested_changes_set(&req_seq_num_set);
+
+        if self.nack_response_deadline.is_none() && proxy.requested_changes().next().is_some() {
+            let nack_response_delay = Duration::from(self.writer.nack_response_delay);
+            self.nack_response_deadline = Some(now + nack_response_delay);
+            self.wake_publisher();
+        }
+        // A failure of the store is reported by the next send_heartbeats,
+        // which removes the changes again.
+        let _ = self.remove_acked_changes();
+    }
+
+    /// Processes a NACK_FRAG of the matched reader: the fragments of the
+    /// change in the fragment_number_state are requested again. They are sent
+    /// along with the changes requested with ACKNACKs, nack_response_delay
+    /// after the first request. Duplicate NACK_FRAGs, and the ones of unknown
+    /// readers, are ignored.
+    pub fn process_nack_frag(&mut self, reader_guid: &GUID_t, nack_frag: &NackFrag, now: Instant) {
+        let writer_cache = &self.writer.writer_cache;
+        let status = self.new_change_status();
+        let proxy = match self
+            .matched_readers
+            .iter_mut()
+            .find(|proxy| &proxy.remote_reader_guid == reader_guid)
+        {
+            Some(proxy) => proxy,
+            None => return,
+        };
+        if !proxy.is_new_nack_frag(nack_frag.count) {
+            return;
+        }
+
+        proxy.add_new_changes(writer_cache, status);
+        proxy.requested_fragments_set(nack_frag.writer_sn, nack_frag.fragment_number_state.iter());
+        if self.nack_response_deadline.is_none() && proxy.has_requested_fragments() {
+            let nack_response_delay = Duration::from(self.writer.nack_response_delay);
+            self.nack_response_deadline = Some(now + nack_response_delay);
+            self.wake_publisher();
+        }
+    }
+
+    /// Removes from the HistoryCache the changes acknowledged by all the
+    /// active matched readers, unless the durability of the writer keeps them
+    /// for the late-joining readers, and notifies the subscribers of the
+    /// HistoryCache that they were acknowledged. Returns the number of
+    /// removed changes. The HEARTBEATs then announce that the changes are no
+    /// longer available, and the requests of the readers that missed them are
+    /// answered with a GAP. The changes the store of the HistoryCache fails to
+    /// remove are kept, and removed by a later call.
+    pub fn remove_acked_changes(&mut self) -> std::io::Result<usize> {
+        let highest_seq_num_acked_by_all = self
+            .writer
+            .writer_cache
+            .changes()
+            .skip_while(|change| change.sequence_number <= self.highest_seq_num_acked_by_all)
+            .take_while(|change| self.is_acked_by_all(change))
+            .map(|change| change.sequence_number)
+            .last();
+        let highest_seq_num_acked_by_all = match highest_seq_num_acked_by_all {
+            Some(sequence_number) => sequence_number,
+            None => return Ok(0),
+        };
+
+        let writer_guid = self.entity.guid;
+        let writer_cache = &mut self.writer.writer_cache;
+        writer_cache.acknowledge_changes_up_to(writer_guid, highest_seq_num_acked_by_all);
+        let removed = match self.durability {
+            DurabilityQosPolicy::Volatile => writer_cache
+                .remove_writer_changes_up_to(&writer_guid, highest_seq_num_acked_by_all)?,
+            _ => 0,
+        };
+        self.highest_seq_num_acked_by_all = highest_seq_num_acked_by_all;
+        Ok(removed)
+    }
+
+    /// Sends to every matched reader the changes and fragments it requested,
+    /// once the nack_response_delay is over, and, in push mode, the changes
+    /// not sent yet, each DATA or DATA_FRAG in a message of its own. The
+    /// changes that are no longer in the HistoryCache, or are irrelevant to
+    /// the reader, are sent as a GAP. A HEARTBEAT is piggybacked on the last
+    /// message to each reader, so that it acknowledges the changes.
+    ///
+    /// With a FlowController, the changes are sent as long as it has room for
+    /// all the messages of each of them. The others are sent by a later call,
+    /// in the same order.
+    pub fn send_changes<S: MessageSender>(
+        &mut self,
+        sender: &mut S,
+        now: Instant,
+    ) -> std::io::Result<()> {
+        let status = self.new_change_status();
+        let push_mode = self.writer.push_mode;
+        let send_requested_changes = self
+            .nack_response_deadline
+            .is_some_and(|nack_response_deadline| nack_response_deadline <= now);
+        if send_requested_changes {
+            self.nack_response_deadline = None;
+        }
+
+        let mut flow_controller = self
+            .flow_controller
+            .as_ref()
+            .map(|(flow_controller, priority)| (flow_controller.lock().unwrap(), *priority));
+        let mut requests_left = false;
+        let mut held_back = None;
+
+        for proxy in &mut self.matched_readers {
+            proxy.add_new_changes(&self.writer.writer_cache, status);
+
+            // The changes taken from the ReaderProxy, with the status they are
+            // given back if they can't be sent.
+            let mut next_changes = BTreeMap::new();
+            loop {
+                let next_requested_change = if send_requested_changes {
+                    proxy.next_requested_change()
+                } else {
+                    None
+                };
+                let ((sequence_number, change_for_reader), taken_status) =
+                    match next_requested_change {
+                        Some(next_change) => (next_change, ChangeForReaderStatusKind::REQUESTED),
+                        None if push_mode => match proxy.next_unsent_change() {
+                            Some(next_change) => (next_change, ChangeForReaderStatusKind::UNSENT),
+                            None => break,
+                        },
+                        None => break,
+                    };
+                next_changes.insert(
+                    sequence_number,
+                    (change_for_reader, Some(taken_status), None),
+                );
+            }
+            if send_requested_changes {
+                // The fragments of the changes sent in full are not sent apart.
+                for (sequence_number, fragment_numbers) in proxy.take_requested_fragments() {
+                    if let Some(change_for_reader) = proxy.change_for_reader(sequence_number) {
+                        next_changes.entry(sequence_number).or_insert((
+                            change_for_reader,
+                            None,
+                            Some(fragment_numbers),
+                        ));
+                    }
+                }
+            }
+
+            let mut submessages = vec![];
+            for (sequence_number, (change_for_reader, _, fragment_numbers)) in &next_changes {
+                let sequence_number = *sequence_number;
+                let change = self
+                    .writer
+                    .writer_cache
+                    .get_change(sequence_number)
+                    .filter(|_| change_for_reader.is_relevant);
+                // The consecutive changes sent as a GAP are sent in one.
+                if let (None, Some((_, None, EntitySubmessage::Gap(gap)))) =
+                    (change, submessages.last_mut())
+                {
+                    if *gap.gap_list.base() == sequence_number {
+                        gap.gap_list = SequenceNumberSet_t::new(proxy.gap_end(sequence_number));
+                        continue;
+                    }
+                }
+                let change_submessages = Self::change_submessages(
+                    &self.entity,
+                    &self.endpoint,
+                    proxy,
+                    sequence_number,
+                    change,
+                    self.fragment_size,
+                    fragment_numbers.as_ref(),
+                )?;
+                for submessage in change_submessages {
+                    submessages.push((sequence_number, change, submessage));
+                }
+            }
+
+            // The messages of each change, or GAP, which are sent together, with
+            // the range of sequence numbers they send.
+            let mut messages: Vec<(SequenceNumber_t, SequenceNumber_t, Vec<Bytes>)> = vec![];
+            let last = submessages.len().saturating_sub(1);
+            for (index, (sequence_number, change, submessage)) in
+                submessages.into_iter().enumerate()
+            {
+                let mut message_builder = Self::message_builder(&self.entity, proxy)?;
+                if let Some(change) = change {
+                    message_builder.info_timestamp(change.source_timestamp)?;
+                }
+                message_builder.entity_submessage(&submessage)?;
+                if index == last {
+                    let heartbeat = Self::heartbeat(
+                        &self.entity,
+                        &self.writer,
+                        &mut self.heartbeat_count,
+                        proxy,
+                        0,
+                    );
+                    message_builder.entity_submessage(&heartbeat)?;
+                }
+                let message = message_builder.build();
+                let end = match submessage {
+                    EntitySubmessage::Gap(gap) => *gap.gap_list.base(),
+                    _ => sequence_number + SequenceNumber_t::from(1),
+                };
+                match messages.last_mut() {
+                    Some((start, _, change_messages)) if *start == sequence_number => {
+                        change_messages.push(message)
+                    }
+                    _ => messages.push((sequence_number, end, vec![message])),
+                }
+            }
+
+            let mut not_sent = None;
+            for (start, end, change_messages) in messages {
+                if let Some((flow_controller, priority)) = flow_controller.as_mut() {
+                    let len = change_messages.iter().map(Bytes::len).sum::<usize>()
+                        * Self::locators(proxy).len();
+                    if not_sent.is_some()
+                        || !flow_controller.try_send(&self.entity.guid, *priority, len, now)
+                    {
+                        not_sent.get_or_insert(start);
+                        held_back.get_or_insert(len);
+                        continue;
+                    }
+                }
+
+                for message in change_messages {
+                    Self::send_to(sender, proxy, message)?;
+                }
+                proxy.changes_sent(start, end, now);
+            }
+
+            // The changes that could not be sent are given back, to be sent
+            // later in the same order.
+            if let Some(not_sent) = not_sent {
+                for (sequence_number, (_, taken_status, fragment_numbers)) in
+                    next_changes.range(not_sent..)
+                {
+                    if let Some(taken_status) = taken_status {
+                        proxy.change_not_sent(*sequence_number, *taken_status);
+                        requests_left |= *taken_status == ChangeForReaderStatusKind::REQUESTED;
+                    }
+                    if let Some(fragment_numbers) = fragment_numbers {
+                        proxy.requested_fragments_set(
+                            *sequence_number,
+                            fragment_numbers.iter().copied(),
+                        );
+                        requests_left = true;
+                    }
+                }
+            }
+        }
+
+        drop(flow_controller);
+        self.held_back = held_back;
+        if requests_left {
+            self.nack_response_deadline.get_or_insert(now);
+        }
+        Ok(())
+    }
+
+    /// Sends a HEARTBEAT to every matched reader that did not acknowledge all
+    /// the changes yet, which makes it request the changes it misses. The
+    /// HEARTBEATs are suppressed once all the readers acknowledged all the
+    /// changes. A reader that left max_unanswered_heartbeats of them
+    /// unanswered in a row is considered inactive, until it sends an ACKNACK
+    /// again.
+    pub fn send_heartbeats<S: MessageSender>(&mut self, sender: &mut S) -> std::io::Result<()> {
+        let status = self.new_change_status();
+
+        for proxy in &mut self.matched_readers {
+            proxy.add_new_changes(&self.writer.writer_cache, status);
+            if !proxy.all_changes_acknowledged()
+                && proxy.is_active()
+                && proxy.unanswered_heartbeats() >= self.max_unanswered_heartbeats
+            {
+                proxy.deactivate();
+                let reader_guid = proxy.remote_reader_guid;
+                Self::notify(
+                    &mut self.subscribers,
+                    ReaderActivityEvent::Inactive { reader_guid },
+                );
+            }
+        }
+        self.remove_acked_changes()?;
+
+        for proxy in &mut self.matched_readers {
+            if proxy.all_changes_acknowledged() {
+                continue;
+            }
+
+            let mut message_builder = Self::message_builder(&self.entity, proxy)?;
+            let heartbeat = Self::heartbeat(
+                &self.entity,
+                &self.writer,
+                &mut self.heartbeat_count,
+                proxy,
+                0,
+            );
+            message_builder.entity_submessage(&heartbeat)?;
+            Self::send_to(sender, proxy, message_builder.build())?;
+            proxy.heartbeat_sent();
+        }
+        Ok(())
+    }
+
+    /// Sends a HEARTBEAT with the Liveliness flag to every matched reader, as
+    /// the DDS DataWriter asserts its liveliness. The readers are not required
+    /// to respond to it.
+    pub fn assert_liveliness<S: MessageSender>(&mut self, sender: &mut S) -> std::io::Result<()> {
+        for proxy in &self.matched_readers {
+            let mut message_builder = Self::message_builder(&self.entity, proxy)?;
+            let heartbeat = Self::heartbeat(
+                &self.entity,
+                &self.writer,
+                &mut self.heartbeat_count,
+                proxy,
+                Heartbeat::FINAL_FLAG_MASK | Heartbeat::LIVELINESS_FLAG_MASK,
+            );
+            message_builder.entity_submessage(&heartbeat)?;
+            Self::send_to(sender, proxy, message_builder.build())?;
+        }
+        Ok(())
+    }
+
+    /// Writes a new change to the HistoryCache of the writer and returns its
+    /// sequence number. While the HistoryCache is full, because the readers
+    /// did not acknowledge the changes, the write waits for changes to be
+    /// removed, at most max_blocking_time, after which it fails with a
+    /// TimedOut error.
+    ///
+    /// The change takes its sequence number in the same lock of the writer
+    /// it is added in, so that the changes are added in the order of their
+    /// sequence numbers, and a change that is not written takes none. A
+    /// change added after a later one would be taken for a removed one by
+    /// the ReaderProxies, and never sent.
+    pub async fn write(
+        writer: Arc<Mutex<StatefulWriter>>,
+        kind: ChangeKind_t,
+        data: Data,
+        handle: InstanceHandle_t,
+        max_blocking_time: Duration_t,
+    ) -> std::io::Result<SequenceNumber_t> {
+        let mut events = writer.lock().unwrap().writer_cache().subscribe();
+        let deadline = if max_blocking_time == Duration_t::DURATION_INFINITE {
+            None
+        } else {
+            Some(tokio::time::Instant::now() + Duration::from(max_blocking_time))
+        };
+
+        let mut data = Some(data);
+        loop {
+            {
+                let mut writer = writer.lock().unwrap();
+                let change = writer.new_change(kind, data.take().unwrap(), handle);
+                let sequence_number = change.sequence_number;
+                match writer.writer_cache().add_change(change) {
+                    AddChangeResult::Rejected(change, _) => {
+                        writer.writer.last_change_sequence_number =
+                            sequence_number - SequenceNumber_t::from(1);
+                        data = change.data_value;
+                    }
+                    _ => return Ok(sequence_number),
+                }
+            }
+
+            let removed = async {
+                while let Some(event) = events.next().await {
+                    if let HistoryCacheEvent::Removed { .. } = event {
+                        break;
+                    }
+                }
+            };
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, removed).await.is_err() {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            "change not written within max_blocking_time",
+                        ));
+                    }
+                }
+                None => removed.await,
+            }
+        }
+    }
+
+    /// Writes a new change as [`StatefulWriter::write`] does, and sends it
+    /// according to the publish mode of the writer: right away in the task
+    /// of the caller if it is synchronous, and otherwise by
+    /// send_changes_asynchronously, which is woken up. In both cases the
+    /// FlowController of the writer applies, and the changes it holds back
+    /// are sent by a later send.
+    pub async fn publish<S: MessageSender>(
+        writer: Arc<Mutex<StatefulWriter>>,
+        sender: Arc<Mutex<S>>,
+        kind: ChangeKind_t,
+        data: Data,
+        handle: InstanceHandle_t,
+        max_blocking_time: Duration_t,
+    ) -> std::io::Result<SequenceNumber_t> {
+        let sequence_number =
+            Self::write(writer.clone(), kind, data, handle, max_blocking_time).await?;
+
+        let mut writer = writer.lock().unwrap();
+        match writer.publish_mode {
+            PublishModeQosPolicy::Synchronous => {
+                let now = tokio::time::Instant::now().into_std();
+                writer.send_changes(&mut *sender.lock().unwrap(), now)?;
+            }
+            PublishModeQosPolicy::Asynchronous => writer.wake_publisher(),
+        }
+        Ok(sequence_number)
+    }
+
+    /// Sends the changes of the writer as they are published, requested by
+    /// the readers or let through by the FlowController, on a tokio timer,
+    /// until sending fails. This is the task of the writers in asynchronous
+    /// publish mode.
+    pub async fn send_changes_asynchronously<S: MessageSender>(
+        writer: Arc<Mutex<StatefulWriter>>,
+        sender: Arc<Mutex<S>>,
+    ) -> std::io::Result<()> {
+        let (wakeup, mut wakeups) = unbounded();
+        writer.lock().unwrap().publisher_wakeup = Some(wakeup);
+
+        loop {
+            let next_send_time = {
+                let mut writer = writer.lock().unwrap();
+                // The wakeups received so far are for the changes sent now.
+                while wakeups.try_recv().is_ok() {}
+                let now = tokio::time::Instant::now().into_std();
+                writer.send_changes(&mut *sender.lock().unwrap(), now)?;
+                writer.next_send_time(now)
+            };
+
+            match next_send_time {
+                Some(next_send_time) => {
+                    let deadline = tokio::time::Instant::from_std(next_send_time);
+                    let _ = tokio::time::timeout_at(deadline, wakeups.next()).await;
+                }
+                None => {
+                    wakeups.next().await;
+                }
+            }
+        }
+    }
+
+    /// Sends the periodic HEARTBEATs of the writer each heartbeat_period, on a
+    /// tokio timer, until sending fails. Returns right away if the period is
+    /// infinite. Like publish, it locks the writer before the sender.
+    pub async fn send_heartbeats_periodically<S: MessageSender>(
+        writer: Arc<Mutex<StatefulWriter>>,
+        sender: Arc<Mutex<S>>,
+    ) -> std::io::Result<()> {
+        let heartbeat_period = writer.lock().unwrap().writer.heartbeat_period;
+        if heartbeat_period == Duration_t::DURATION_INFINITE {
+            return Ok(());
+        }
+
+        let heartbeat_period = Duration::from(heartbeat_period);
+        let start = tokio::time::Instant::now() + heartbeat_period;
+        let mut interval = tokio::time::interval_at(start, heartbeat_period);
+        loop {
+            interval.tick().await;
+            let mut writer = writer.lock().unwrap();
+            writer.send_heartbeats(&mut *sender.lock().unwrap())?;
+        }
+    }
+
+    /// When the changes left by the last send are to be sent: once the
+    /// FlowController lets the first one held back through, or at the
+    /// nack_response_deadline.
+    fn next_send_time(&mut self, now: Instant) -> Option<Instant> {
+        match (&self.flow_controller, self.held_back) {
+            (Some((flow_controller, priority)), Some(len)) => {
+                let mut flow_controller = flow_controller.lock().unwrap();
+                Some(flow_controller.available_at(&self.entity.guid, *priority, len, now))
+            }
+            _ => self.nack_response_deadline,
+        }
+    }
+
+    /// Wakes send_changes_asynchronously up, if it runs.
+    fn wake_publisher(&mut self) {
+        if let Some(publisher_wakeup) = &self.publisher_wakeup {
+            if publisher_wakeup.unbounded_send(()).is_err() {
+                self.publisher_wakeup = None;
+            }
+        }
+    }
+
+    /// The status of the changes added to the ReaderProxies: in push mode they
+    /// are sent right away, otherwise they are only announced.
+    fn new_change_status(&self) -> ChangeForReaderStatusKind {
+        if self.writer.push_mode {
+            ChangeForReaderStatusKind::UNSENT
+        } else {
+            ChangeForReaderStatusKind::UNACKNOWLEDGED
+        }
+    }
+
+    /// Starts a message to the reader.
+    fn message_builder(
+        entity: &EntityAttributes,
+        proxy: &ReaderProxy,
+    ) -> Result<MessageBuilder, speedy::Error> {
+        let mut message_builder = MessageBuilder::new(entity.guid.guid_prefix);
+        message_builder.info_destination(proxy.remote_reader_guid.guid_prefix)?;
+        Ok(message_builder)
+    }
+
+    /// The submessages sending the change to the reader: its DATA or, if
+    /// its serialized payload is larger than fragment_size, its DATA_FRAGs,
+    /// restricted to the given fragments if any. If there is no change, a GAP
+    /// for its sequence number.
+    fn change_submessages(
+        entity: &EntityAttributes,
+        endpoint: &EndpointAttributes,
+        proxy: &ReaderProxy,
+        sequence_number: SequenceNumber_t,
+        change: Option<&CacheChange>,
+        fragment_size: u16,
+        fragment_numbers: Option<&BTreeSet<FragmentNumber_t>>,
+    ) -> Result<Vec<EntitySubmessage>, speedy::Error> {
+        let reader_id = proxy.remote_reader_guid.entity_id;
+        let change = match change {
+            Some(change) => change,
+            None => {
+                let gap = Gap {
+                    reader_id,
+                    writer_id: entity.guid.entity_id,
+                    gap_start: sequence_number,
+                    gap_list: SequenceNumberSet_t::new(proxy.gap_end(sequence_number)),
+                };
+                return Ok(vec![EntitySubmessage::Gap(gap)]);
+            }
+        };
+
+        let topic_kind = &endpoint.topic_kind;
+        match change.to_data_frags(
+            reader_id,
+            topic_kind,
+            Endianness::LittleEndian,
+            fragment_size,
+        )? {
+            Some(data_frags) => Ok(data_frags
+                .into_iter()
+                .filter(|(data_frag, _)| {
+                    fragment_numbers.is_none_or(|fragment_numbers| {
+                        fragment_numbers.contains(&data_frag.fragment_starting_num)
+                    })
+                })
+                .map(|(data_frag, flags)| EntitySubmessage::DataFrag(data_frag, flags))
+                .collect()),
+            None => {
+                let (data, flags) =
+                    change.to_data(reader_id, topic_kind, Endianness::LittleEndian)?;
+                Ok(vec![EntitySubmessage::Data(data, flags)])
+            }
+        }
+    }
+
+    /// A HEARTBEAT to the reader with the range of sequence numbers of the
+    /// HistoryCache, and the next count of the writer. When the HistoryCache
+    /// is empty, first_sn is past last_sn.
+    fn heartbeat(
+        entity: &EntityAttributes,
+        writer: &WriterAttributes,
+        heartbeat_count: &mut Count_t,
+        proxy: &ReaderProxy,
+        flags: u8,
+    ) -> EntitySubmessage {
+        let last_sn = writer
+            .writer_cache
+            .get_seq_num_max()
+            .copied()
+            .unwrap_or(writer.last_change_sequence_number);
+        let first_sn = writer
+            .writer_cache
+            .get_seq_num_min()
+            .copied()
+            .unwrap_or(last_sn + SequenceNumber_t::from(1));
+        *heartbeat_count = heartbeat_count.wrapping_add(1);
+
+        let mut submessage_flag = SubmessageFlag { flags: 0x01 };
+        submessage_flag.set_flag(flags);
+        EntitySubmessage::Heartbeat(
+            Heartbeat {
+                reader_id: proxy.remote_reader_guid.entity_id,
+                writer_id: entity.guid.entity_id,
+                first_sn,
+                last_sn,
+                count: *heartbeat_count,
+            },
+            submessage_flag,
+        )
+    }
+
+    fn notify(
+        subscribers: &mut Vec<UnboundedSender<ReaderActivityEvent>>,
+        event: ReaderActivityEvent,
+    ) {
+        subscribers.retain(|subscriber| subscriber.unbounded_send(event).is_ok());
+    }
+
+    /// The unicast locators of the reader, or its multicast locators if it has
+    /// no unicast one.
+    fn locators(proxy: &ReaderProxy) -> &LocatorList_t {
+        if proxy.unicast_locator_list.is_empty() {
+            &proxy.multicast_locator_list
+        } else {
+            &proxy.unicast_locator_list
+        }
+    }
+
+    /// Sends the message to the locators of the reader.
+    fn send_to<S: MessageSender>(
+        sender: &mut S,
+        proxy: &ReaderProxy,
+        message: Bytes,
+    ) -> std::io::Result<()> {
+        for locator in Self::locators(proxy) {
+            sender.send(locator, message.clone())?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::dds::qos::{HistoryQosPolicy, ResourceLimitsQosPolicy, LENGTH_UNLIMITED};
+    use crate::messages::fragment_number_set::FragmentNumberSet_t;
+    use crate::messages::receiver::MessageReceiver;
+    use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+    use crate::structure::entity_id::EntityId_t;
+    use crate::structure::guid_prefix::GuidPrefix_t;
+    use crate::structure::history_store::FileHistoryStore;
+    use crate::structure::locator::{Ipv4Addr, LocatorKind_t, Locator_t, SocketAddr};
+    use crate::structure::reliability_kind::ReliabilityKind_t;
+    use crate::structure::topic_kind::TopicKind_t;
+    use crate::transport::in_memory::InMemoryTransport;
+    use bytes::BytesMut;
+    use tokio_util::codec::Decoder;
+
+    fn writer_guid() -> GUID_t {
+        GUID_t {
+            guid_prefix: GuidPrefix_t {
+                entity_key: [0x01; 12],
+            },
+            entity_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+        }
+    }
+
+    fn reader_guid(key: u8) -> GUID_t {
+        GUID_t {
+            guid_prefix: GuidPrefix_t {
+                entity_key: [key; 12],
+            },
+            entity_id: EntityId_t::ENTITYID_PARTICIPANT,
+        }
+    }
+
+    fn locator(port: u16) -> Locator_t {
+        Locator_t::from(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+    }
+
+    fn stateful_writer(push_mode: bool) -> StatefulWriter {
+        stateful_writer_with_cache(push_mode, HistoryCache::new())
+    }
+
+    fn stateful_writer_with_cache(push_mode: bool, writer_cache: HistoryCache) -> StatefulWriter {
+        StatefulWriter::new(
+            EntityAttributes {
+                guid: writer_guid(),
+            },
+            EndpointAttributes {
+                topic_kind: TopicKind_t::NO_KEY,
+                reliability_level: ReliabilityKind_t::RELIABLE,
+                unicast_locator_list: Locator_t::LOCATOR_INVALID,
+                multicast_locator_list: Locator_t::LOCATOR_INVALID,
+            },
+            WriterAttributes {
+                push_mode,
+                heartbeat_period: Duration_t::DURATION_INFINITE,
+                nack_response_delay: Duration_t::DURATION_ZERO,
+                nack_suppression_duration: Duration_t::DURATION_ZERO,
+                last_change_sequence_number: SequenceNumber_t::from(0),
+                writer_cache,
+            },
+        )
+    }
+
+    fn write(writer: &mut StatefulWriter, value: &'static [u8]) -> CacheChange {
+        let data = Data::new(RepresentationIdentifier::CDR_LE, Bytes::from_static(value));
+        let change = writer.new_change(ChangeKind_t::ALIVE, data, InstanceHandle_t::default());
+        writer.writer_cache().add_change(change.clone());
+        change
+    }
+
+    fn acknack(base: i64, missing: &[i64], count: i32) -> AckNack {
+        let mut reader_sn_state = SequenceNumberSet_t::new(SequenceNumber_t::from(base));
+        for sequence_number in missing {
+            reader_sn_state.insert(SequenceNumber_t::from(*sequence_number));
+        }
+        AckNack {
+            reader_id: EntityId_t::ENTITYID_PARTICIPANT,
+            writer_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+            reader_sn_state,
+            count: Count_t::from(count),
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Received {
+        Data(i64),
+        DataFrag(i64, u32),
+        Gap(i64, i64),
+        Heartbeat(i64, i64, i32, u8),
+    }
+
+    fn received(messages: Vec<Bytes>) -> Vec<Received> {
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        let mut received = vec![];
+        for message in messages {
+            let mut bytes = BytesMut::from(&message[..]);
+            while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+                received.push(match submessage {
+                    EntitySubmessage::Data(data, _) => {
+                        assert_eq!(EntityId_t::ENTITYID_PARTICIPANT, data.reader_id);
+                        Received::Data(data.writer_sn.into())
+                    }
+                    EntitySubmessage::DataFrag(data_frag, _) => {
+                        assert_eq!(EntityId_t::ENTITYID_PARTICIPANT, data_frag.reader_id);
+                        Received::DataFrag(
+                            data_frag.writer_sn.into(),
+                            data_frag.fragment_starting_num.into(),
+                        )
+                    }
+                    EntitySubmessage::Gap(gap) => {
+                        Received::Gap(gap.gap_start.into(), (*gap.gap_list.base()).into())
+                    }
+                    EntitySubmessage::Heartbeat(heartbeat, flags) => {
+                        assert_eq!(EntityId_t::ENTITYID_PARTICIPANT, heartbeat.reader_id);
+                        Received::Heartbeat(
+                            heartbeat.first_sn.into(),
+                            heartbeat.last_sn.into(),
+                            heartbeat.count.into(),
+                            flags.flags & !0x01,
+                        )
+                    }
+                    submessage => panic!("unexpected submessage {:?}", submessage),
+                });
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn requested_changes_are_sent_again_until_acknowledged() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+
+        let changes = [
+            write(&mut writer, b"first"),
+            write(&mut writer, b"second"),
+            write(&mut writer, b"third"),
+        ];
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![
+                Received::Data(1),
+                Received::Data(2),
+                Received::Data(3),
+                Received::Heartbeat(1, 3, 1, 0)
+            ],
+            received(transport.receive_all(&locator(7410)))
+        );
+        assert!(!writer.is_acked_by_all(&changes[0]));
+
+        // The reader received the first and the third changes.
+        writer.process_acknack(&reader_guid(2), &acknack(2, &[2], 1), now);
+        assert!(writer.is_acked_by_all(&changes[0]));
+        assert!(!writer.is_acked_by_all(&changes[1]));
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![Received::Data(2), Received::Heartbeat(2, 3, 2, 0)],
+            received(transport.receive_all(&locator(7410)))
+        );
+
+        // A duplicate ACKNACK does not request the change again.
+        writer.process_acknack(&reader_guid(2), &acknack(2, &[2], 1), now);
+        writer.send_changes(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+
+        writer.process_acknack(&reader_guid(2), &acknack(4, &[], 2), now);
+        assert!(changes.iter().all(|change| writer.is_acked_by_all(change)));
+    }
+
+    #[test]
+    fn removed_and_irrelevant_changes_are_sent_as_gap() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        write(&mut writer, b"third");
+        writer
+            .writer_cache()
+            .remove_change(SequenceNumber_t::from(2))
+            .unwrap();
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![
+                Received::Data(1),
+                Received::Gap(2, 3),
+                Received::Data(3),
+                Received::Heartbeat(1, 3, 1, 0)
+            ],
+            received(transport.receive_all(&locator(7410)))
+        );
+
+        writer.matched_readers[0].set_irrelevant(SequenceNumber_t::from(3));
+        writer.process_acknack(&reader_guid(2), &acknack(3, &[3], 1), now);
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![Received::Gap(3, 4), Received::Heartbeat(3, 3, 2, 0)],
+            received(transport.receive_all(&locator(7410)))
+        );
+    }
+
+    #[test]
+    fn changes_are_only_sent_on_request_in_pull_mode() {
+        let mut writer = stateful_writer(false);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![],
+            vec![locator(7400)],
+            false,
+        ));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+
+        writer.process_acknack(&reader_guid(2), &acknack(1, &[2], 1), now);
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![Received::Data(2), Received::Heartbeat(1, 2, 1, 0)],
+            received(transport.receive_all(&locator(7400)))
+        );
+    }
+
+    #[test]
+    fn change_is_acked_by_all_once_every_reader_acknowledged_it() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        for key in 2..4 {
+            writer.matched_reader_add(ReaderProxy::new(
+                reader_guid(key),
+                vec![locator(7410 + u16::from(key))],
+                vec![],
+                false,
+            ));
+        }
+
+        let change = write(&mut writer, b"first");
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(2, transport.len());
+
+        writer.process_acknack(&reader_guid(2), &acknack(2, &[], 1), now);
+        assert!(!writer.is_acked_by_all(&change));
+
+        // ACKNACKs of unknown readers are ignored.
+        writer.process_acknack(&reader_guid(4), &acknack(2, &[], 1), now);
+        assert!(!writer.is_acked_by_all(&change));
+
+        writer.process_acknack(&reader_guid(3), &acknack(2, &[], 1), now);
+        assert!(writer.is_acked_by_all(&change));
+
+        writer.matched_reader_remove(&reader_guid(3));
+        assert_eq!(0, writer.matched_reader_lookup(reader_guid(3)).count());
+        assert_eq!(1, writer.matched_reader_lookup(reader_guid(2)).count());
+    }
+
+    #[test]
+    fn acknack_only_acknowledges_the_changes_added_for_the_reader() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+        let first = write(&mut writer, b"first");
+        writer.send_changes(&mut transport, now).unwrap();
+
+        // ACKNACKs with a base below 1 are invalid, and ignored.
+        writer.process_acknack(&reader_guid(2), &acknack(0, &[], 1), now);
+        writer.process_acknack(&reader_guid(2), &acknack(i64::MIN, &[], 2), now);
+        assert!(!writer.is_acked_by_all(&first));
+
+        writer.process_acknack(&reader_guid(2), &acknack(i64::MAX, &[], 1), now);
+        assert!(writer.is_acked_by_all(&first));
+
+        let second = write(&mut writer, b"second");
+        writer.send_changes(&mut transport, now).unwrap();
+        assert!(!writer.is_acked_by_all(&second));
+    }
+
+    #[test]
+    fn heartbeats_are_sent_until_all_changes_are_acknowledged() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        for key in 2..4 {
+            writer.matched_reader_add(ReaderProxy::new(
+                reader_guid(key),
+                vec![locator(7410 + u16::from(key))],
+                vec![],
+                false,
+            ));
+        }
+
+        // The HistoryCache is empty.
+        writer.send_heartbeats(&mut transport).unwrap();
+        assert!(transport.is_empty());
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_heartbeats(&mut transport).unwrap();
+        assert_eq!(
+            vec![Received::Heartbeat(1, 2, 1, 0)],
+            received(transport.receive_all(&locator(7412)))
+        );
+        assert_eq!(
+            vec![Received::Heartbeat(1, 2, 2, 0)],
+            received(transport.receive_all(&locator(7413)))
+        );
+
+        writer.process_acknack(&reader_guid(2), &acknack(3, &[], 1), now);
+        writer.send_heartbeats(&mut transport).unwrap();
+        assert!(transport.receive_all(&locator(7412)).is_empty());
+        assert_eq!(
+            vec![Received::Heartbeat(1, 2, 3, 0)],
+            received(transport.receive_all(&locator(7413)))
+        );
+
+        writer.process_acknack(&reader_guid(3), &acknack(3, &[], 1), now);
+        writer.send_heartbeats(&mut transport).unwrap();
+        assert!(transport.is_empty());
+    }
+
+    #[test]
+    fn heartbeat_count_wraps_around() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+        writer.heartbeat_count = Count_t::from(i32::MAX - 1);
+
+        let flags = Heartbeat::FINAL_FLAG_MASK | Heartbeat::LIVELINESS_FLAG_MASK;
+        for _ in 0..2 {
+            writer.assert_liveliness(&mut transport).unwrap();
+        }
+        assert_eq!(
+            vec![
+                Received::Heartbeat(1, 0, i32::MAX, flags),
+                Received::Heartbeat(1, 0, i32::MIN, flags)
+            ],
+            received(transport.receive_all(&locator(7410)))
+        );
+    }
+
+    #[test]
+    fn heartbeat_of_empty_history_cache_has_first_sn_past_last_sn() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        for sequence_number in 1..3 {
+            writer
+                .writer_cache()
+                .remove_change(SequenceNumber_t::from(sequence_number))
+                .unwrap();
+        }
+        writer.assert_liveliness(&mut transport).unwrap();
+        assert_eq!(
+            vec![Received::Heartbeat(
+                3,
+                2,
+                1,
+                Heartbeat::FINAL_FLAG_MASK | Heartbeat::LIVELINESS_FLAG_MASK
+            )],
+            received(transport.receive_all(&locator(7410)))
+        );
+    }
+
+    #[test]
+    fn heartbeats_are_sent_each_heartbeat_period() {
+        let mut writer = stateful_writer(true);
+        writer.writer.heartbeat_period = Duration_t::from(Duration::from_millis(10));
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+        write(&mut writer, b"first");
+
+        let writer = Arc::new(Mutex::new(writer));
+        let transport = Arc::new(Mutex::new(InMemoryTransport::new()));
+        let result = runtime().block_on(async {
+            tokio::time::timeout(
+                Duration::from_millis(55),
+                StatefulWriter::send_heartbeats_periodically(writer, transport.clone()),
+            )
+            .await
+        });
+        assert!(result.is_err());
+
+        assert_eq!(
+            (1..=5)
+                .map(|count| Received::Heartbeat(1, 1, count, 0))
+                .collect::<Vec<_>>(),
+            received(transport.lock().unwrap().receive_all(&locator(7410)))
+        );
+    }
+
+    #[test]
+    fn requests_are_aggregated_during_nack_response_delay() {
+        let mut writer = stateful_writer(true);
+        writer.writer.nack_response_delay = Duration_t::from(Duration::from_millis(200));
+        writer.writer.nack_suppression_duration = Duration_t::from(Duration::from_millis(500));
+        let mut transport = InMemoryTransport::new();
+        for key in 2..4 {
+            writer.matched_reader_add(ReaderProxy::new(
+                reader_guid(key),
+                vec![locator(7410 + u16::from(key))],
+                vec![],
+                false,
+            ));
+        }
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, at(0)).unwrap();
+        transport.receive_all(&locator(7412));
+        transport.receive_all(&locator(7413));
+
+        // Requests of changes sent less than nack_suppression_duration ago are
+        // ignored.
+        writer.process_acknack(&reader_guid(2), &acknack(1, &[1, 2], 1), at(100));
+        writer.send_changes(&mut transport, at(400)).unwrap();
+        assert!(transport.is_empty());
+
+        writer.process_acknack(&reader_guid(2), &acknack(1, &[1, 2], 2), at(600));
+        writer.process_acknack(&reader_guid(3), &acknack(2, &[2], 1), at(700));
+        writer.process_acknack(&reader_guid(2), &acknack(1, &[1], 3), at(750));
+        writer.send_changes(&mut transport, at(700)).unwrap();
+        assert!(transport.is_empty());
+
+        // The requests received during nack_response_delay are answered
+        // together.
+        writer.send_changes(&mut transport, at(800)).unwrap();
+        assert_eq!(
+            vec![
+                Received::Data(1),
+                Received::Data(2),
+                Received::Heartbeat(1, 2, 3, 0)
+            ],
+            received(transport.receive_all(&locator(7412)))
+        );
+        assert_eq!(
+            vec![Received::Data(2), Received::Heartbeat(1, 2, 4, 0)],
+            received(transport.receive_all(&locator(7413)))
+        );
+
+        // The repeated requests of the changes just sent are suppressed.
+        writer.process_acknack(&reader_guid(3), &acknack(2, &[2], 2), at(900));
+        writer.send_changes(&mut transport, at(1500)).unwrap();
+        assert!(transport.is_empty());
+    }
+
+    #[test]
+    fn unresponsive_reader_becomes_inactive() {
+        let mut writer = stateful_writer(true);
+        writer.set_max_unanswered_heartbeats(2);
+        let mut events = writer.subscribe();
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        for key in 2..4 {
+            writer.matched_reader_add(ReaderProxy::new(
+                reader_guid(key),
+                vec![locator(7410 + u16::from(key))],
+                vec![],
+                false,
+            ));
+        }
+
+        let change = write(&mut writer, b"first");
+        writer.send_changes(&mut transport, now).unwrap();
+        writer.process_acknack(&reader_guid(2), &acknack(2, &[], 1), now);
+        for _ in 0..2 {
+            writer.send_heartbeats(&mut transport).unwrap();
+        }
+        assert!(!writer.is_acked_by_all(&change));
+        assert!(events.try_recv().is_err());
+
+        // The reader 3 left two HEARTBEATs unanswered.
+        writer.send_heartbeats(&mut transport).unwrap();
+        assert_eq!(
+            ReaderActivityEvent::Inactive {
+                reader_guid: reader_guid(3)
+            },
+            events.try_recv().unwrap()
+        );
+        assert!(writer.is_acked_by_all(&change));
+
+        writer.process_acknack(&reader_guid(3), &acknack(1, &[1], 1), now);
+        assert_eq!(
+            ReaderActivityEvent::Active {
+                reader_guid: reader_guid(3)
+            },
+            events.try_recv().unwrap()
+        );
+        assert!(!writer.is_acked_by_all(&change));
+    }
+
+    #[test]
+    fn changes_acknowledged_by_all_active_readers_are_removed() {
+        let mut writer = stateful_writer(true);
+        writer.set_max_unanswered_heartbeats(1);
+        let mut events = writer.writer_cache().subscribe();
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        for key in 2..4 {
+            writer.matched_reader_add(ReaderProxy::new(
+                reader_guid(key),
+                vec![locator(7410 + u16::from(key))],
+                vec![],
+                false,
+            ));
+        }
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        write(&mut writer, b"third");
+        writer.send_changes(&mut transport, now).unwrap();
+        transport.receive_all(&locator(7412));
+        transport.receive_all(&locator(7413));
+        writer.process_acknack(&reader_guid(2), &acknack(4, &[], 1), now);
+        writer.process_acknack(&reader_guid(3), &acknack(2, &[], 1), now);
+        assert_eq!(
+            Some(SequenceNumber_t::from(2)),
+            writer.writer_cache().get_seq_num_min().copied()
+        );
+        let acknowledged: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|event| matches!(event, HistoryCacheEvent::Acknowledged { .. }))
+            .collect();
+        assert_eq!(
+            vec![HistoryCacheEvent::Acknowledged {
+                writer_guid: writer_guid(),
+                sequence_number: SequenceNumber_t::from(1),
+            }],
+            acknowledged
+        );
+
+        // The reader 3 no longer blocks the removal once inactive.
+        writer.send_heartbeats(&mut transport).unwrap();
+        writer.send_heartbeats(&mut transport).unwrap();
+        assert!(writer.writer_cache().is_empty());
+        assert_eq!(
+            vec![
+                Received::Heartbeat(2, 3, 3, 0),
+                Received::Heartbeat(4, 3, 4, 0)
+            ],
+            received(transport.receive_all(&locator(7413)))
+        );
+
+        // The removed changes it requests are answered with a GAP.
+        writer.process_acknack(&reader_guid(3), &acknack(2, &[2], 2), now);
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![Received::Gap(2, 3), Received::Heartbeat(4, 3, 5, 0)],
+            received(transport.receive_all(&locator(7413)))
+        );
+    }
+
+    #[test]
+    fn acknowledged_changes_are_kept_unless_volatile() {
+        let mut writer = stateful_writer(true);
+        writer.set_durability(DurabilityQosPolicy::TransientLocal);
+        let mut events = writer.writer_cache().subscribe();
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+
+        write(&mut writer, b"first");
+        writer.send_changes(&mut transport, now).unwrap();
+        writer.process_acknack(&reader_guid(2), &acknack(2, &[], 1), now);
+        assert_eq!(1, writer.writer_cache().len());
+        assert!(std::iter::from_fn(|| events.try_recv().ok())
+            .any(|event| matches!(event, HistoryCacheEvent::Acknowledged { .. })));
+    }
+
+    #[test]
+    fn late_joining_reader_receives_the_retained_changes() {
+        let mut writer = stateful_writer(true);
+        writer.set_durability(DurabilityQosPolicy::TransientLocal);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7412)],
+            vec![],
+            false,
+        ));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, now).unwrap();
+        transport.receive_all(&locator(7412));
+        writer.process_acknack(&reader_guid(2), &acknack(3, &[], 1), now);
+
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(3),
+            vec![locator(7413)],
+            vec![],
+            false,
+        ));
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![
+                Received::Data(1),
+                Received::Data(2),
+                Received::Heartbeat(1, 2, 2, 0)
+            ],
+            received(transport.receive_all(&locator(7413)))
+        );
+        assert!(transport.is_empty());
+    }
+
+    #[test]
+    fn late_joining_reader_of_volatile_writer_starts_at_last_sn() {
+        let mut writer = stateful_writer(true);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7412)],
+            vec![],
+            false,
+        ));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        write(&mut writer, b"third");
+        writer.send_changes(&mut transport, now).unwrap();
+        transport.receive_all(&locator(7412));
+        writer.process_acknack(&reader_guid(2), &acknack(2, &[], 1), now);
+
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(3),
+            vec![locator(7413)],
+            vec![],
+            false,
+        ));
+        write(&mut writer, b"fourth");
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![Received::Data(4), Received::Heartbeat(2, 4, 2, 0)],
+            received(transport.receive_all(&locator(7412)))
+        );
+        assert_eq!(
+            vec![
+                Received::Gap(1, 4),
+                Received::Data(4),
+                Received::Heartbeat(2, 4, 3, 0)
+            ],
+            received(transport.receive_all(&locator(7413)))
+        );
+
+        // The late-joining reader acknowledges the GAP with the change.
+        writer.process_acknack(&reader_guid(2), &acknack(5, &[], 2), now);
+        writer.process_acknack(&reader_guid(3), &acknack(5, &[], 1), now);
+        assert!(writer.writer_cache().is_empty());
+    }
+
+    #[test]
+    fn flow_controller_bounds_the_octets_sent_and_resent() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let flow_controller = Arc::new(Mutex::new(FlowController::new(400, period, start)));
+        let mut writer = stateful_writer(true);
+        writer.set_flow_controller(flow_controller, 0);
+        let mut transport = InMemoryTransport::new();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7415)],
+            vec![],
+            false,
+        ));
+        for _ in 0..10 {
+            write(&mut writer, &[0x2a; 100]);
+        }
+
+        // At most the bucket and then 4 octets per millisecond are sent, and
+        // the changes held back are sent in order later on.
+        let mut send_for =
+            |writer: &mut StatefulWriter, from: u64| {
+                let mut octets = 0;
+                let mut data = vec![];
+                for millis in (from..from + 1000).step_by(10) {
+                    writer
+                        .send_changes(&mut transport, start + Duration::from_millis(millis))
+                        .unwrap();
+                    let messages = transport.receive_all(&locator(7415));
+                    octets += messages.iter().map(Bytes::len).sum::<usize>();
+                    assert!(octets <= 400 + 4 * (millis - from) as usize);
+                    data.extend(received(messages).into_iter().filter_map(
+                        |received| match received {
+                            Received::Data(sequence_number) => Some(sequence_number),
+                            _ => None,
+                        },
+                    ));
+                }
+                data
+            };
+        assert_eq!((1..=10).collect::<Vec<_>>(), send_for(&mut writer, 0));
+
+        // The requested changes are held back the same way.
+        let now = start + Duration::from_millis(1000);
+        let missing: Vec<i64> = (1..=10).collect();
+        writer.process_acknack(&reader_guid(2), &acknack(1, &missing, 1), now);
+        assert_eq!((1..=10).collect::<Vec<_>>(), send_for(&mut writer, 1000));
+    }
+
+    #[test]
+    fn restarted_persistent_writer_resumes_from_its_store() {
+        let directory =
+            std::env::temp_dir().join(format!("rtps-stateful-writer-{}-store", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let restart = || {
+            let store = FileHistoryStore::open(&directory).unwrap();
+            let writer_cache = HistoryCache::with_store(
+                &writer_guid(),
+                HistoryQosPolicy::KeepAll,
+                ResourceLimitsQosPolicy::UNLIMITED,
+                Box::new(store),
+            )
+            .unwrap();
+            let mut writer = stateful_writer_with_cache(true, writer_cache);
+            writer.set_durability(DurabilityQosPolicy::Persistent);
+            writer
+        };
+
+        let mut writer = restart();
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        drop(writer);
+
+        let mut writer = restart();
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        assert_eq!(3, i64::from(write(&mut writer, b"third").sequence_number));
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7414)],
+            vec![],
+            false,
+        ));
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![
+                Received::Data(1),
+                Received::Data(2),
+                Received::Data(3),
+                Received::Heartbeat(1, 3, 1, 0)
+            ],
+            received(transport.receive_all(&locator(7414)))
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn only_the_requested_fragments_are_sent_again() {
+        let mut writer = stateful_writer(true);
+        writer.set_fragment_size(8);
+        let mut transport = InMemoryTransport::new();
+        let now = Instant::now();
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+
+        // The SerializedPayloadHeader and the value make 24 octets.
+        write(&mut writer, b"0123456789abcdefghij");
+        write(&mut writer, b"abc");
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![
+                Received::DataFrag(1, 1),
+                Received::DataFrag(1, 2),
+                Received::DataFrag(1, 3),
+                Received::Data(2),
+                Received::Heartbeat(1, 2, 1, 0)
+            ],
+            received(transport.receive_all(&locator(7410)))
+        );
+
+        let mut fragment_number_state = FragmentNumberSet_t::new(FragmentNumber_t::from(1));
+        fragment_number_state.insert(FragmentNumber_t::from(1));
+        fragment_number_state.insert(FragmentNumber_t::from(3));
+        let nack_frag = NackFrag {
+            reader_id: EntityId_t::ENTITYID_PARTICIPANT,
+            writer_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+            writer_sn: SequenceNumber_t::from(1),
+            fragment_number_state,
+            count: Count_t::from(1),
+        };
+        writer.process_nack_frag(&reader_guid(2), &nack_frag, now);
+        writer.send_changes(&mut transport, now).unwrap();
+        assert_eq!(
+            vec![
+                Received::DataFrag(1, 1),
+                Received::DataFrag(1, 3),
+                Received::Heartbeat(1, 2, 2, 0)
+            ],
+            received(transport.receive_all(&locator(7410)))
+        );
+
+        // A duplicate NACK_FRAG does not request the fragments again.
+        writer.process_nack_frag(&reader_guid(2), &nack_frag, now);
+        writer.send_changes(&mut transport, now).unwrap();
+        assert!(transport.is_empty());
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap()
+    }
+
+    fn keep_all_writer(max_samples: i32) -> Arc<Mutex<StatefulWriter>> {
+        let mut writer = stateful_writer(true);
+        writer.writer.writer_cache = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy {
+                max_samples,
+                ..ResourceLimitsQosPolicy::UNLIMITED
+            },
+        );
+        writer.matched_reader_add(ReaderProxy::new(
+            reader_guid(2),
+            vec![locator(7410)],
+            vec![],
+            false,
+        ));
+        Arc::new(Mutex::new(writer))
+    }
+
+    fn write_async(
+        writer: &Arc<Mutex<StatefulWriter>>,
+        max_blocking_time: Duration,
+    ) -> impl std::future::Future<Output = std::io::Result<SequenceNumber_t>> {
+        let data = Data::new(
+            RepresentationIdentifier::CDR_LE,
+            Bytes::from_static(b"data"),
+        );
+        StatefulWriter::write(
+            writer.clone(),
+            ChangeKind_t::ALIVE,
+            data,
+            InstanceHandle_t::default(),
+            Duration_t::from(max_blocking_time),
+        )
+    }
+
+    #[test]
+    fn write_fails_once_max_blocking_time_elapsed() {
+        let writer = keep_all_writer(2);
+        runtime().block_on(async {
+            for _ in 0..2 {
+                write_async(&writer, Duration::from_secs(1)).await.unwrap();
+            }
+
+            let start = tokio::time::Instant::now();
+            let error = write_async(&writer, Duration::from_secs(1))
+                .await
+                .unwrap_err();
+            assert_eq!(std::io::ErrorKind::TimedOut, error.kind());
+            assert_eq!(Duration::from_secs(1), start.elapsed());
+        });
+        assert_eq!(2, writer.lock().unwrap().writer_cache().len());
+    }
+
+    #[test]
+    fn write_waits_until_changes_are_acknowledged() {
+        let writer = keep_all_writer(2);
+        runtime().block_on(async {
+            for _ in 0..2 {
+                write_async(&writer, Duration::from_secs(1)).await.unwrap();
+            }
+
+            let start = tokio::time::Instant::now();
+            let acknowledge = async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                writer.lock().unwrap().process_acknack(
+                    &reader_guid(2),
+                    &acknack(2, &[], 1),
+                    Instant::now(),
+                );
+            };
+            let (written, _) =
+                futures::future::join(write_async(&writer, Duration::from_secs(1)), acknowledge)
+                    .await;
+            assert_eq!(SequenceNumber_t::from(3), written.unwrap());
+            assert_eq!(Duration::from_millis(300), start.elapsed());
+        });
+        assert_eq!(
+            Some(SequenceNumber_t::from(2)),
+            writer
+                .lock()
+                .unwrap()
+                .writer_cache()
+                .get_seq_num_min()
+                .copied()
+        );
+    }
+
+    #[test]
+    fn writes_waiting_together_are_all_sent_in_order() {
+        let writer = keep_all_writer(2);
+        let mut transport = InMemoryTransport::new();
+        runtime().block_on(async {
+            for _ in 0..2 {
+                write_async(&writer, Duration::from_secs(1)).await.unwrap();
+            }
+            writer
+                .lock()
+                .unwrap()
+                .send_changes(&mut transport, Instant::now())
+                .unwrap();
+            transport.receive_all(&locator(7410));
+
+            // The write that times out takes no sequence number from the one
+            // that waits longer.
+            let start = tokio::time::Instant::now();
+            let acknowledge = async {
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                writer.lock().unwrap().process_acknack(
+                    &reader_guid(2),
+                    &acknack(3, &[], 1),
+                    Instant::now(),
+                );
+            };
+            let (timed_out, written, _) = futures::future::join3(
+                write_async(&writer, Duration::from_secs(1)),
+                write_async(&writer, Duration::from_secs(10)),
+                acknowledge,
+            )
+            .await;
+            assert!(timed_out.is_err());
+            assert_eq!(SequenceNumber_t::from(3), written.unwrap());
+            assert_eq!(Duration::from_secs(2), start.elapsed());
+        });
+
+        writer
+            .lock()
+            .unwrap()
+            .send_changes(&mut transport, Instant::now())
+            .unwrap();
+        assert_eq!(
+            vec![Received::Data(3), Received::Heartbeat(3, 3, 2, 0)],
+            received(transport.receive_all(&locator(7410)))
+        );
+    }
+
+    fn publish(
+        writer: &Arc<Mutex<StatefulWriter>>,
+        transport: &Arc<Mutex<InMemoryTransport>>,
+        value: &'static [u8],
+    ) -> impl std::future::Future<Output = std::io::Result<SequenceNumber_t>> {
+        let data = Data::new(RepresentationIdentifier::CDR_LE, Bytes::from_static(value));
+        StatefulWriter::publish(
+            writer.clone(),
+            transport.clone(),
+            ChangeKind_t::ALIVE,
+            data,
+            InstanceHandle_t::default(),
+            Duration_t::DURATION_INFINITE,
+        )
+    }
+
+    fn received_data(messages: Vec<Bytes>) -> Vec<i64> {
+        received(messages)
+            .into_iter()
+            .filter_map(|received| match received {
+                Received::Data(sequence_number) => Some(sequence_number),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn synchronous_publish_sends_from_the_caller_task() {
+        let writer = keep_all_writer(LENGTH_UNLIMITED);
+        let transport = Arc::new(Mutex::new(InMemoryTransport::new()));
+        runtime().block_on(async {
+            publish(&writer, &transport, b"first").await.unwrap();
+            assert_eq!(
+                vec![Received::Data(1), Received::Heartbeat(1, 1, 1, 0)],
+                received(transport.lock().unwrap().receive_all(&locator(7410)))
+            );
+        });
+    }
+
+    #[test]
+    fn concurrent_synchronous_publishes_and_heartbeats_send_every_change() {
+        let writer = keep_all_writer(LENGTH_UNLIMITED);
+        {
+            let mut writer = writer.lock().unwrap();
+            writer.writer.heartbeat_period = Duration_t::from(Duration::from_micros(100));
+            // The reader does not answer, but must not be left out.
+            writer.set_max_unanswered_heartbeats(u32::MAX);
+        }
+        let transport = Arc::new(Mutex::new(InMemoryTransport::new()));
+
+        // The tasks run on several threads, which hang for good if the
+        // locks are taken in different orders.
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(4)
+                .enable_time()
+                .build()
+                .unwrap();
+            let sent = runtime.block_on(async {
+                let heartbeats = tokio::spawn(StatefulWriter::send_heartbeats_periodically(
+                    writer.clone(),
+                    transport.clone(),
+                ));
+                let sent = Arc::new(Mutex::new(vec![]));
+                let publishers: Vec<_> = (0..3)
+                    .map(|_| {
+                        let writer = writer.clone();
+                        let transport = transport.clone();
+                        let sent = sent.clone();
+                        tokio::spawn(async move {
+                            let mut published = vec![];
+                            for _ in 0..200 {
+                                let sequence_number =
+                                    publish(&writer, &transport, b"value").await.unwrap();
+                                published.push(i64::from(sequence_number));
+                                let messages =
+                                    transport.lock().unwrap().receive_all(&locator(7410));
+                                sent.lock().unwrap().extend(received_data(messages));
+                            }
+                            published
+                        })
+                    })
+                    .collect();
+                let mut published = vec![];
+                for publisher in publishers {
+                    published.extend(publisher.await.unwrap());
+                }
+                heartbeats.abort();
+                let mut sent = sent.lock().unwrap().clone();
+                sent.sort_unstable();
+                published.sort_unstable();
+                (published, sent)
+            });
+            done_tx.send(sent).unwrap();
+        });
+
+        // Every change published is sent as DATA, none as a GAP.
+        let (published, sent) = done_rx.recv_timeout(Duration::from_secs(30)).unwrap();
+        assert_eq!((1..=600).collect::<Vec<_>>(), published);
+        assert_eq!(published, sent);
+    }
+
+    #[test]
+    fn asynchronous_publish_drains_the_history_through_the_flow_controller() {
+        let writer = keep_all_writer(LENGTH_UNLIMITED);
+        let transport = Arc::new(Mutex::new(InMemoryTransport::new()));
+        runtime().block_on(async {
+            let start = tokio::time::Instant::now();
+            let period = Duration::from_millis(100);
+            let flow_controller = FlowController::new(400, period, start.into_std());
+            {
+                let mut writer = writer.lock().unwrap();
+                writer.set_publish_mode(PublishModeQosPolicy::Asynchronous);
+                writer.set_flow_controller(Arc::new(Mutex::new(flow_controller)), 0);
+            }
+            let background = Box::pin(StatefulWriter::send_changes_asynchronously(
+                writer.clone(),
+                transport.clone(),
+            ));
+
+            let publisher = Box::pin(async {
+                for _ in 0..10 {
+                    publish(&writer, &transport, &[0x2a; 100]).await.unwrap();
+                }
+                assert!(transport.lock().unwrap().is_empty());
+
+                // At most the bucket and then 4 octets per millisecond are
+                // sent, until all the changes are.
+                let mut octets = 0;
+                let mut data = vec![];
+                while data.len() < 10 {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    let messages = transport.lock().unwrap().receive_all(&locator(7410));
+                    octets += messages.iter().map(Bytes::len).sum::<usize>();
+                    assert!(octets <= 400 + 4 * start.elapsed().as_millis() as usize);
+                    data.extend(received_data(messages));
+                }
+                assert_eq!((1..=10).collect::<Vec<_>>(), data);
+                assert!(start.elapsed() > period);
+            });
+            match futures::future::select(background, publisher).await {
+                futures::future::Either::Left((result, _)) => panic!("{:?}", result),
+                futures::future::Either::Right(_) => {}
+            };
+        });
+    }
+}
@@ -0,0 +1,373 @@
+use crate::behavior::reader::{Reader, ReaderAttributes};
+use crate::messages::data::Data;
+use crate::messages::receiver::{MessageReceiver, Receiver};
+use crate::messages::submessage::EntitySubmessage;
+use crate::messages::submessage_flag::SubmessageFlag;
+use crate::structure::cache_change::CacheChange;
+use crate::structure::endpoint::{Endpoint, EndpointAttributes};
+use crate::structure::entity::{Entity, EntityAttributes};
+use crate::structure::entity_id::EntityId_t;
+use crate::structure::guid::GUID_t;
+use crate::structure::guid_prefix::GuidPrefix_t;
+use crate::structure::history_cache::{AddChangeResult, HistoryCache};
+use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::time::Time_t;
+use bytes::BytesMut;
+use std::collections::{BTreeMap, BTreeSet};
+use tokio_util::codec::Decoder;
+
+/// Specialization of RTPS Reader used for the Stateless Reference
+/// Implementation. The RTPS StatelessReader has no knowledge of the number of
+/// matched writers, nor does it maintain any state for each matched RTPS
+/// Writer. It is meant for best-effort communication.
+pub struct StatelessReader {
+    /// The writers the reader accepts changes from. A GUID with the
+    /// GUIDPREFIX_UNKNOWN stands for the writer entity of every participant,
+    /// e.g. the SPDP built-in participant writer.
+    matched_writers: BTreeSet<GUID_t>,
+
+    /// The highest sequence number received from each writer. The changes
+    /// received after one with a higher sequence number are out of order, and
+    /// are dropped.
+    highest_seq_num_received: BTreeMap<GUID_t, SequenceNumber_t>,
+
+    entity: EntityAttributes,
+    endpoint: EndpointAttributes,
+    reader: ReaderAttributes,
+}
+
+impl Entity for StatelessReader {
+    fn as_entity(&self) -> &EntityAttributes {
+        &self.entity
+    }
+}
+
+impl Endpoint for StatelessReader {
+    fn as_endpoint(&self) -> &EndpointAttributes {
+        &self.endpoint
+    }
+}
+
+impl Reader for StatelessReader {
+    fn as_reader(&self) -> &ReaderAttributes {
+        &self.reader
+    }
+}
+
+impl StatelessReader {
+    pub fn new(
+        entity: EntityAttributes,
+        endpoint: EndpointAttributes,
+        reader: ReaderAttributes,
+    ) -> Self {
+        StatelessReader {
+            matched_writers: BTreeSet::new(),
+            highest_seq_num_received: BTreeMap::new(),
+            entity,
+            endpoint,
+            reader,
+        }
+    }
+
+    pub fn reader_cache(&mut self) -> &mut HistoryCache {
+        &mut self.reader.reader_cache
+    }
+
+    /// Accepts the changes of the writer from then on. The GUIDPREFIX_UNKNOWN
+    /// accepts the ones of the writer entity of every participant.
+    pub fn matched_writer_add(&mut self, writer_guid: GUID_t) {
+        self.matched_writers.insert(writer_guid);
+    }
+
+    /// Ignores the changes of the writer from then on.
+    pub fn matched_writer_remove(&mut self, writer_guid: &GUID_t) {
+        self.matched_writers.remove(writer_guid);
+        if writer_guid.guid_prefix == GuidPrefix_t::GUIDPREFIX_UNKNOWN {
+            let entity_id = writer_guid.entity_id;
+            self.highest_seq_num_received
+                .retain(|received_guid, _| received_guid.entity_id != entity_id);
+        } else {
+            self.highest_seq_num_received.remove(writer_guid);
+        }
+    }
+
+    /// Tells whether the reader accepts the changes of the writer.
+    fn is_matched_writer(&self, writer_guid: &GUID_t) -> bool {
+        self.matched_writers.contains(writer_guid)
+            || self.matched_writers.contains(&GUID_t {
+                guid_prefix: GuidPrefix_t::GUIDPREFIX_UNKNOWN,
+                entity_id: writer_guid.entity_id,
+            })
+    }
+
+    /// Processes the DATA submessages the MessageReceiver decodes from the
+    /// bytes. The other submessages are ignored: the StatelessReader keeps no
+    /// state on the writers, so it neither acknowledges nor requests changes.
+    pub fn receive(
+        &mut self,
+        message_receiver: &mut MessageReceiver,
+        bytes: &mut BytesMut,
+        reception_timestamp: Time_t,
+    ) -> std::io::Result<()> {
+        while let Some(submessage) = message_receiver.decode(bytes)? {
+            if let EntitySubmessage::Data(data, flags) = submessage {
+                self.process_data(
+                    message_receiver.receiver(),
+                    data,
+                    flags,
+                    reception_timestamp,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the change of the DATA to the reader’s HistoryCache, and tells
+    /// whether it was added. The DATA is ignored if it is meant for another
+    /// reader, if its writer is not matched, or if its writer already sent a
+    /// change with a higher sequence number.
+    pub fn process_data(
+        &mut self,
+        receiver: &Receiver,
+        data: Data,
+        flags: SubmessageFlag,
+        reception_timestamp: Time_t,
+    ) -> Result<bool, speedy::Error> {
+        let guid = &self.entity.guid;
+        if receiver.dest_guid_prefix != GuidPrefix_t::GUIDPREFIX_UNKNOWN
+            && receiver.dest_guid_prefix != guid.guid_prefix
+        {
+            return Ok(false);
+        }
+        if data.reader_id != EntityId_t::ENTITYID_UNKNOWN && data.reader_id != guid.entity_id {
+            return Ok(false);
+        }
+        let writer_guid = GUID_t {
+            guid_prefix: receiver.source_guid_prefix,
+            entity_id: data.writer_id,
+        };
+        if !self.is_matched_writer(&writer_guid) {
+            return Ok(false);
+        }
+
+        let change = CacheChange::from_data(data, flags, receiver, reception_timestamp)?;
+        let highest_seq_num_received = self
+            .highest_seq_num_received
+            .entry(change.writer_guid)
+            .or_insert_with(|| SequenceNumber_t::from(0));
+        if change.sequence_number <= *highest_seq_num_received {
+            return Ok(false);
+        }
+        *highest_seq_num_received = change.sequence_number;
+
+        match self.reader.reader_cache.add_change(change) {
+            AddChangeResult::Added | AddChangeResult::ReplacedOldest(_) => Ok(true),
+            AddChangeResult::Rejected(_, _) => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::message_builder::MessageBuilder;
+    use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+    use crate::structure::change_kind::ChangeKind_t;
+    use crate::structure::data::Data;
+    use crate::structure::duration::Duration_t;
+    use crate::structure::instance_handle::InstanceHandle_t;
+    use crate::structure::locator::{LocatorKind_t, Locator_t};
+    use crate::structure::reliability_kind::ReliabilityKind_t;
+    use crate::structure::topic_kind::TopicKind_t;
+    use bytes::Bytes;
+    use speedy::Endianness;
+
+    fn reader_guid() -> GUID_t {
+        GUID_t {
+            guid_prefix: GuidPrefix_t::from([0x01; 12]),
+            entity_id: EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER,
+        }
+    }
+
+    fn writer_guid(key: u8) -> GUID_t {
+        GUID_t {
+            guid_prefix: GuidPrefix_t::from([key; 12]),
+            entity_id: EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+        }
+    }
+
+    fn stateless_reader() -> StatelessReader {
+        let mut reader = StatelessReader::new(
+            EntityAttributes {
+                guid: reader_guid(),
+            },
+            EndpointAttributes {
+                topic_kind: TopicKind_t::NO_KEY,
+                reliability_level: ReliabilityKind_t::BEST_EFFORT,
+                unicast_locator_list: Locator_t::LOCATOR_INVALID,
+                multicast_locator_list: Locator_t::LOCATOR_INVALID,
+            },
+            ReaderAttributes {
+                heartbeat_response_delay: Duration_t::DURATION_ZERO,
+                heartbeat_suppression_duration: Duration_t::DURATION_ZERO,
+                expects_inline_qos: false,
+                reader_cache: HistoryCache::new(),
+            },
+        );
+        reader.matched_writer_add(GUID_t {
+            guid_prefix: GuidPrefix_t::GUIDPREFIX_UNKNOWN,
+            entity_id: EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+        });
+        reader
+    }
+
+    /// A message of the writer with a DATA for each sequence number.
+    fn message(
+        writer_guid: GUID_t,
+        destination: Option<GuidPrefix_t>,
+        reader_id: EntityId_t,
+        sequence_numbers: &[i64],
+    ) -> BytesMut {
+        let mut message_builder = MessageBuilder::new(writer_guid.guid_prefix);
+        if let Some(destination) = destination {
+            message_builder.info_destination(destination).unwrap();
+        }
+        for sequence_number in sequence_numbers {
+            let change = CacheChange::new(
+                ChangeKind_t::ALIVE,
+                writer_guid,
+                InstanceHandle_t::default(),
+                SequenceNumber_t::from(*sequence_number),
+                Some(Data::new(
+                    RepresentationIdentifier::CDR_LE,
+                    Bytes::from_static(b"data"),
+                )),
+            );
+            let (data, flags) = change
+                .to_data(reader_id, &TopicKind_t::NO_KEY, Endianness::LittleEndian)
+                .unwrap();
+            message_builder.info_timestamp(None).unwrap();
+            message_builder
+                .entity_submessage(&EntitySubmessage::Data(data, flags))
+                .unwrap();
+        }
+        BytesMut::from(&message_builder.build()[..])
+    }
+
+    fn receive(reader: &mut StatelessReader, mut message: BytesMut) {
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        reader
+            .receive(&mut message_receiver, &mut message, Time_t::TIME_ZERO)
+            .unwrap();
+    }
+
+    fn received(reader: &mut StatelessReader, writer_guid: &GUID_t) -> Vec<i64> {
+        reader
+            .reader_cache()
+            .changes()
+            .filter(|change| &change.writer_guid == writer_guid)
+            .map(|change| i64::from(change.sequence_number))
+            .collect()
+    }
+
+    #[test]
+    fn out_of_order_changes_are_dropped_per_writer() {
+        let mut reader = stateless_reader();
+
+        receive(
+            &mut reader,
+            message(
+                writer_guid(2),
+                None,
+                EntityId_t::ENTITYID_UNKNOWN,
+                &[1, 3, 2],
+            ),
+        );
+        receive(
+            &mut reader,
+            message(writer_guid(3), None, EntityId_t::ENTITYID_UNKNOWN, &[2]),
+        );
+        receive(
+            &mut reader,
+            message(writer_guid(2), None, EntityId_t::ENTITYID_UNKNOWN, &[3, 4]),
+        );
+
+        assert_eq!(vec![1, 3, 4], received(&mut reader, &writer_guid(2)));
+        assert_eq!(vec![2], received(&mut reader, &writer_guid(3)));
+        let change = reader
+            .reader_cache()
+            .get_writer_change(&writer_guid(3), SequenceNumber_t::from(2))
+            .unwrap();
+        assert_eq!(Some(Time_t::TIME_ZERO), change.reception_timestamp);
+        assert_eq!(None, change.source_timestamp);
+    }
+
+    #[test]
+    fn data_for_other_readers_is_ignored() {
+        let mut reader = stateless_reader();
+
+        receive(
+            &mut reader,
+            message(
+                writer_guid(2),
+                None,
+                EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_READER,
+                &[1],
+            ),
+        );
+        receive(
+            &mut reader,
+            message(
+                writer_guid(2),
+                Some(GuidPrefix_t::from([0x04; 12])),
+                EntityId_t::ENTITYID_UNKNOWN,
+                &[2],
+            ),
+        );
+        assert!(reader.reader_cache().is_empty());
+
+        receive(
+            &mut reader,
+            message(
+                writer_guid(2),
+                Some(reader_guid().guid_prefix),
+                EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER,
+                &[3],
+            ),
+        );
+        assert_eq!(vec![3], received(&mut reader, &writer_guid(2)));
+    }
+
+    #[test]
+    fn data_of_unmatched_writers_is_ignored() {
+        let mut reader = stateless_reader();
+        let other_writer = GUID_t {
+            guid_prefix: GuidPrefix_t::from([0x02; 12]),
+            entity_id: EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_WRITER,
+        };
+
+        receive(
+            &mut reader,
+            message(other_writer, None, EntityId_t::ENTITYID_UNKNOWN, &[1]),
+        );
+        assert!(reader.reader_cache().is_empty());
+
+        reader.matched_writer_add(other_writer);
+        receive(
+            &mut reader,
+            message(other_writer, None, EntityId_t::ENTITYID_UNKNOWN, &[2]),
+        );
+        assert_eq!(vec![2], received(&mut reader, &other_writer));
+
+        // Removing the writers of every participant removes each of them.
+        reader.matched_writer_remove(&GUID_t {
+            guid_prefix: GuidPrefix_t::GUIDPREFIX_UNKNOWN,
+            entity_id: EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+        });
+        receive(
+            &mut reader,
+            message(writer_guid(2), None, EntityId_t::ENTITYID_UNKNOWN, &[1]),
+        );
+        assert!(received(&mut reader, &writer_guid(2)).is_empty());
+    }
+}
@@ -1,30 +1,53 @@
+use crate::behavior::flow_controller::FlowController;
 use crate::behavior::reader_locator::ReaderLocator;
 use crate::behavior::writer::{Writer, WriterAttributes};
+use crate::messages::message_builder::MessageBuilder;
+use crate::messages::submessage::EntitySubmessage;
 use crate::structure::cache_change::CacheChange;
 use crate::structure::change_kind::ChangeKind_t;
 use crate::structure::data::Data;
+use crate::structure::duration::Duration_t;
 use crate::structure::endpoint::{Endpoint, EndpointAttributes};
 use crate::structure::entity::{Entity, EntityAttributes};
+use crate::structure::entity_id::EntityId_t;
+use crate::structure::history_cache::HistoryCache;
 use crate::structure::instance_handle::InstanceHandle_t;
+use crate::structure::locator::Locator_t;
 use crate::structure::sequence_number::SequenceNumber_t;
-use crate::structure::time::Time_t;
+use crate::transport::message_sender::MessageSender;
+use futures::StreamExt;
+use speedy::Endianness;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Specialization of RTPS Writer used for the Stateless Reference
 /// Implementation. The RTPS StatelessWriter has no knowledge of the number of
 /// matched readers, nor does it maintain any state for each matched RTPS Reader
 /// endpoint. The RTPS StatelessWriter maintains only the RTPS Locator_t list
 /// that should be used to send information to the matched readers
-struct StatelessWriter {
+pub struct StatelessWriter {
     /// Protocol tuning parameter that indicates that the StatelessWriter
     /// re-sends all the changes in the writer’s HistoryCache to
     /// all the Locators periodically each resendPeriod
-    resend_data_period: Time_t,
+    resend_data_period: Duration_t,
+
+    /// When the changes were last re-sent, or when the StatelessWriter was
+    /// created.
+    last_resend: Instant,
 
     /// The StatelessWriter maintains the list of locators
     /// to which it sends the CacheChanges. This list may include
     /// both unicast and multicast locator
     reader_locators: Vec<ReaderLocator>,
 
+    /// Limits the octets sent by the writer, shared with other writers, and
+    /// the priority of the writer among them.
+    flow_controller: Option<(Arc<Mutex<FlowController>>, i32)>,
+
+    /// The octets of the first change the FlowController held back at the
+    /// last send, if it did.
+    held_back: Option<usize>,
+
     entity_attributes: EntityAttributes,
     endpoint_attributes: EndpointAttributes,
     writer_attributes: WriterAttributes,
@@ -56,13 +79,13 @@ impl Writer for StatelessWriter {
         self.writer_attributes.last_change_sequence_number =
             self.writer_attributes.last_change_sequence_number + SequenceNumber_t::from(1);
 
-        CacheChange {
-            kind: kind,
-            writer_guid: self.entity_attributes.guid,
-            data_value: data,
-            instance_handle: handle,
-            sequence_number: self.writer_attributes.last_change_sequence_number,
-        }
+        CacheChange::new(
+            kind,
+            self.entity_attributes.guid,
+            handle,
+            self.writer_attributes.last_change_sequence_number,
+            Some(data),
+        )
     }
 }
 
@@ -70,30 +93,457 @@ impl StatelessWriter {
     pub fn new(
         entity_attributes: EntityAttributes,
         endpoint_attributes: EndpointAttributes,
-        writer_attributes: WriterAttributes,
-        resend_data_period: Time_t,
+        mut writer_attributes: WriterAttributes,
+        resend_data_period: Duration_t,
     ) -> Self {
+        writer_attributes.resume_sequence_numbers(&entity_attributes.guid);
         StatelessWriter {
             entity_attributes: entity_attributes,
             endpoint_attributes: endpoint_attributes,
             writer_attributes: writer_attributes,
             resend_data_period: resend_data_period,
+            last_resend: Instant::now(),
             reader_locators: vec![],
+            flow_controller: None,
+            held_back: None,
         }
     }
 
+    pub fn writer_cache(&mut self) -> &mut HistoryCache {
+        &mut self.writer_attributes.writer_cache
+    }
+
     pub fn reader_locator_add(&mut self, a_locator: ReaderLocator) {
         self.reader_locators.push(a_locator)
     }
 
-    pub fn reader_locator_remove(&mut self, a_locator: ReaderLocator) {
-        self.reader_locators.retain(|x| x != &a_locator)
+    pub fn reader_locator_remove(&mut self, a_locator: &Locator_t) {
+        self.reader_locators.retain(|x| x.locator() != a_locator)
     }
 
+    /// Limits the octets of the changes sent by the writer, the requested
+    /// ones as well as the new and re-sent ones, with the FlowController,
+    /// which can be shared with other writers. The writers of a higher
+    /// priority get to send first.
+    pub fn set_flow_controller(
+        &mut self,
+        flow_controller: Arc<Mutex<FlowController>>,
+        priority: i32,
+    ) {
+        self.flow_controller = Some((flow_controller, priority));
+    }
+
+    /// Makes all the changes in the HistoryCache unsent again, for all the
+    /// ReaderLocators.
     pub fn unsent_changes_reset(&mut self) {
-        unimplemented!();
+        for reader_locator in &mut self.reader_locators {
+            reader_locator.unsent_changes_reset();
+        }
+    }
+
+    /// Resets the unsent changes once resend_data_period elapsed since they
+    /// were last reset, so that the next send re-sends the whole
+    /// HistoryCache. Returns whether the changes were reset.
+    pub fn resend_data_if_due(&mut self, now: Instant) -> bool {
+        let resend_data_period = Duration::from(self.resend_data_period);
+        if now.saturating_duration_since(self.last_resend) < resend_data_period {
+            return false;
+        }
+
+        self.last_resend = now;
+        self.unsent_changes_reset();
+        true
+    }
+
+    /// When resend_data_if_due is next due, or None if the changes are never
+    /// re-sent.
+    fn next_resend_time(&self) -> Option<Instant> {
+        if self.resend_data_period == Duration_t::DURATION_INFINITE {
+            return None;
+        }
+        self.last_resend
+            .checked_add(Duration::from(self.resend_data_period))
+    }
+
+    /// When the changes are next to be sent: once the FlowController lets the
+    /// first one held back by the last send through, or when they are re-sent.
+    fn next_send_time(&mut self, now: Instant) -> Option<Instant> {
+        let available_at = match (&self.flow_controller, self.held_back) {
+            (Some((flow_controller, priority)), Some(len)) => {
+                let mut flow_controller = flow_controller.lock().unwrap();
+                let guid = &self.entity_attributes.guid;
+                Some(flow_controller.available_at(guid, *priority, len, now))
+            }
+            _ => None,
+        };
+        match (available_at, self.next_resend_time()) {
+            (Some(available_at), Some(next_resend_time)) => {
+                Some(available_at.min(next_resend_time))
+            }
+            (available_at, next_resend_time) => available_at.or(next_resend_time),
+        }
+    }
+
+    /// Sends the changes of the writer as they are added to its HistoryCache,
+    /// and all of them again each resend_data_period, on a tokio timer, until
+    /// sending fails. The changes the FlowController holds back are sent once
+    /// it lets them through.
+    pub async fn send_changes_periodically<S: MessageSender>(
+        writer: Arc<Mutex<StatelessWriter>>,
+        sender: Arc<Mutex<S>>,
+    ) -> std::io::Result<()> {
+        let mut events = writer.lock().unwrap().writer_cache().subscribe();
+
+        loop {
+            let next_send_time = {
+                let mut writer = writer.lock().unwrap();
+                // The changes added so far are sent now.
+                while events.try_recv().is_ok() {}
+                let now = tokio::time::Instant::now().into_std();
+                writer.resend_data_if_due(now);
+                writer.send_changes(&mut *sender.lock().unwrap(), now)?;
+                writer.next_send_time(now)
+            };
+
+            match next_send_time {
+                Some(next_send_time) => {
+                    let deadline = tokio::time::Instant::from_std(next_send_time);
+                    let _ = tokio::time::timeout_at(deadline, events.next()).await;
+                }
+                None => {
+                    events.next().await;
+                }
+            }
+        }
+    }
+
+    /// Sends the requested and then the unsent changes to every
+    /// ReaderLocator, each change in a DATA Message of its own.
+    ///
+    /// With a FlowController, the changes are sent to each ReaderLocator as
+    /// long as it has room for their messages. The others are sent by a later
+    /// call, in the same order.
+    pub fn send_changes<S: MessageSender>(
+        &mut self,
+        sender: &mut S,
+        now: Instant,
+    ) -> std::io::Result<()> {
+        let writer_cache = &self.writer_attributes.writer_cache;
+        let topic_kind = &self.endpoint_attributes.topic_kind;
+        let guid = &self.entity_attributes.guid;
+
+        let mut flow_controller = self
+            .flow_controller
+            .as_ref()
+            .map(|(flow_controller, priority)| (flow_controller.lock().unwrap(), *priority));
+        let mut held_back = None;
+
+        for reader_locator in &mut self.reader_locators {
+            loop {
+                let (change, requested) = match reader_locator.next_requested_change(writer_cache) {
+                    Some(change) => (change, true),
+                    None => match reader_locator.next_unsent_change(writer_cache) {
+                        Some(change) => (change, false),
+                        None => break,
+                    },
+                };
+                let (data, flags) = change.to_data(
+                    EntityId_t::ENTITYID_UNKNOWN,
+                    topic_kind,
+                    Endianness::LittleEndian,
+                )?;
+
+                let mut message_builder = MessageBuilder::new(guid.guid_prefix);
+                message_builder.info_timestamp(change.source_timestamp)?;
+                message_builder.entity_submessage(&EntitySubmessage::Data(data, flags))?;
+                let message = message_builder.build();
+                if let Some((flow_controller, priority)) = flow_controller.as_mut() {
+                    if !flow_controller.try_send(guid, *priority, message.len(), now) {
+                        reader_locator.change_not_sent(change.sequence_number, requested);
+                        held_back.get_or_insert(message.len());
+                        break;
+                    }
+                }
+                sender.send(reader_locator.locator(), message)?;
+            }
+        }
+
+        drop(flow_controller);
+        self.held_back = held_back;
+        Ok(())
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::dds::instance::{InstanceStateKind, Instances};
+    use crate::messages::receiver::MessageReceiver;
+    use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+    use crate::messages::submessage_elements::serialized_payload::SerializedPayloadContent;
+    use crate::structure::guid::GUID_t;
+    use crate::structure::locator::{Ipv4Addr, LocatorKind_t, SocketAddr};
+    use crate::structure::reliability_kind::ReliabilityKind_t;
+    use crate::structure::time::Time_t;
+    use crate::structure::topic_kind::TopicKind_t;
+    use crate::transport::in_memory::InMemoryTransport;
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::Decoder;
+
+    fn stateless_writer() -> StatelessWriter {
+        StatelessWriter::new(
+            EntityAttributes {
+                guid: GUID_t::GUID_UNKNOWN,
+            },
+            EndpointAttributes {
+                topic_kind: TopicKind_t::WITH_KEY,
+                reliability_level: ReliabilityKind_t::BEST_EFFORT,
+                unicast_locator_list: Locator_t::LOCATOR_INVALID,
+                multicast_locator_list: Locator_t::LOCATOR_INVALID,
+            },
+            WriterAttributes {
+                push_mode: true,
+                heartbeat_period: Duration_t::DURATION_INFINITE,
+                nack_response_delay: Duration_t::DURATION_ZERO,
+                nack_suppression_duration: Duration_t::DURATION_ZERO,
+                last_change_sequence_number: SequenceNumber_t::from(0),
+                writer_cache: HistoryCache::new(),
+            },
+            Duration_t::from(Duration::from_secs(30)),
+        )
+    }
+
+    fn locator(port: u16) -> Locator_t {
+        Locator_t::from(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+    }
+
+    fn write(writer: &mut StatelessWriter, value: &'static [u8]) {
+        let data = Data::new(RepresentationIdentifier::CDR_LE, Bytes::from_static(value));
+        let mut change = writer.new_change(ChangeKind_t::ALIVE, data, InstanceHandle_t::default());
+        change.source_timestamp = Some(Time_t::TIME_ZERO);
+        writer.writer_cache().add_change(change);
+    }
+
+    /// The sequence numbers and values of the DATA submessages of the messages.
+    fn received(messages: Vec<Bytes>) -> Vec<(i64, Vec<u8>)> {
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        let mut received = vec![];
+        for message in messages {
+            let mut bytes = BytesMut::from(&message[..]);
+            while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+                match submessage {
+                    EntitySubmessage::Data(data, _) => {
+                        let value = match data.serialized_payload.unwrap().content {
+                            SerializedPayloadContent::UserDefined(value) => value.to_vec(),
+                            content => panic!("unexpected payload {:?}", content),
+                        };
+                        received.push((i64::from(data.writer_sn), value));
+                    }
+                    submessage => panic!("unexpected submessage {:?}", submessage),
+                }
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn changes_are_sent_once_to_every_reader_locator() {
+        let mut writer = stateless_writer();
+        let mut transport = InMemoryTransport::new();
+        writer.reader_locator_add(ReaderLocator::new(locator(7400), false));
+        writer.reader_locator_add(ReaderLocator::new(locator(7410), false));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+
+        let expected = vec![(1, b"first".to_vec()), (2, b"second".to_vec())];
+        assert_eq!(expected, received(transport.receive_all(&locator(7400))));
+        assert_eq!(expected, received(transport.receive_all(&locator(7410))));
+
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        assert!(transport.is_empty());
+
+        write(&mut writer, b"third");
+        writer.reader_locator_remove(&locator(7410));
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        assert_eq!(
+            vec![(3, b"third".to_vec())],
+            received(transport.receive_all(&locator(7400)))
+        );
+        assert!(transport.is_empty());
+    }
+
+    #[test]
+    fn changes_are_resent_each_resend_data_period() {
+        let mut writer = stateless_writer();
+        let mut transport = InMemoryTransport::new();
+        writer.reader_locator_add(ReaderLocator::new(locator(7400), false));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        assert_eq!(2, transport.receive_all(&locator(7400)).len());
+
+        let now = writer.last_resend;
+        assert!(!writer.resend_data_if_due(now + Duration::from_secs(29)));
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        assert!(transport.is_empty());
+
+        assert!(writer.resend_data_if_due(now + Duration::from_secs(30)));
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        assert_eq!(
+            vec![(1, b"first".to_vec()), (2, b"second".to_vec())],
+            received(transport.receive_all(&locator(7400)))
+        );
+
+        // The period starts over from the last resend.
+        assert!(!writer.resend_data_if_due(now + Duration::from_secs(59)));
+        assert!(writer.resend_data_if_due(now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn changes_are_sent_as_they_are_written_and_resent_periodically() {
+        let mut writer = stateless_writer();
+        writer.reader_locator_add(ReaderLocator::new(locator(7400), false));
+        write(&mut writer, b"first");
+        let writer = Arc::new(Mutex::new(writer));
+        let transport = Arc::new(Mutex::new(InMemoryTransport::new()));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let sending = tokio::spawn(StatelessWriter::send_changes_periodically(
+                writer.clone(),
+                transport.clone(),
+            ));
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert_eq!(
+                vec![(1, b"first".to_vec())],
+                received(transport.lock().unwrap().receive_all(&locator(7400)))
+            );
+
+            write(&mut writer.lock().unwrap(), b"second");
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert_eq!(
+                vec![(2, b"second".to_vec())],
+                received(transport.lock().unwrap().receive_all(&locator(7400)))
+            );
+
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            assert_eq!(
+                vec![(1, b"first".to_vec()), (2, b"second".to_vec())],
+                received(transport.lock().unwrap().receive_all(&locator(7400)))
+            );
+            sending.abort();
+        });
+    }
+
+    #[test]
+    fn requested_changes_are_sent_again() {
+        let mut writer = stateless_writer();
+        let mut transport = InMemoryTransport::new();
+        writer.reader_locator_add(ReaderLocator::new(locator(7400), false));
+
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        transport.receive_all(&locator(7400));
+
+        writer.reader_locators[0].requested_changes_set(&[SequenceNumber_t::from(1)]);
+        write(&mut writer, b"third");
+        writer.send_changes(&mut transport, Instant::now()).unwrap();
+        assert_eq!(
+            vec![(1, b"first".to_vec()), (3, b"third".to_vec())],
+            received(transport.receive_all(&locator(7400)))
+        );
+    }
+
+    #[test]
+    fn flow_controller_holds_changes_back_in_order() {
+        let mut writer = stateless_writer();
+        let mut transport = InMemoryTransport::new();
+        writer.reader_locator_add(ReaderLocator::new(locator(7400), false));
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let flow_controller = Arc::new(Mutex::new(FlowController::new(1000, period, start)));
+        writer.set_flow_controller(flow_controller.clone(), 0);
+
+        // Another writer empties the bucket.
+        let other_writer_guid = "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.01.02"
+            .parse()
+            .unwrap();
+        assert!(flow_controller
+            .lock()
+            .unwrap()
+            .try_send(&other_writer_guid, 0, 1000, start));
+        write(&mut writer, b"first");
+        write(&mut writer, b"second");
+        writer.send_changes(&mut transport, start).unwrap();
+        assert!(transport.is_empty());
+        let next_send_time = writer.next_send_time(start).unwrap();
+        assert!(next_send_time > start && next_send_time < start + period);
+
+        writer.send_changes(&mut transport, start + period).unwrap();
+        assert_eq!(
+            vec![(1, b"first".to_vec()), (2, b"second".to_vec())],
+            received(transport.receive_all(&locator(7400)))
+        );
+
+        // The requested changes are held back the same way.
+        let later = start + 2 * period;
+        assert!(flow_controller
+            .lock()
+            .unwrap()
+            .try_send(&other_writer_guid, 0, 1000, later));
+        writer.reader_locators[0].requested_changes_set(&[SequenceNumber_t::from(2)]);
+        writer.send_changes(&mut transport, later).unwrap();
+        assert!(transport.is_empty());
+        writer.send_changes(&mut transport, later + period).unwrap();
+        assert_eq!(
+            vec![(2, b"second".to_vec())],
+            received(transport.receive_all(&locator(7400)))
+        );
+    }
+
+    #[test]
+    fn instance_lifecycle() {
+        let mut writer = stateless_writer();
+        let key = Data::new(
+            RepresentationIdentifier::CDR_BE,
+            Bytes::from_static(&[0x00, 0x00, 0x00, 0x2a]),
+        );
+        let handle = writer.register_instance(&key.value, 4);
+        assert_eq!(InstanceHandle_t::from_serialized_key(&key.value, 4), handle);
+        let mut instances = Instances::new();
+
+        let change = writer.new_change(ChangeKind_t::ALIVE, key.clone(), handle);
+        assert_eq!(InstanceStateKind::ALIVE, instances.apply(&change));
+
+        let change = writer.dispose(key.clone(), handle);
+        assert_eq!(ChangeKind_t::NOT_ALIVE_DISPOSED, change.kind);
+        assert_eq!(SequenceNumber_t::from(2), change.sequence_number);
+        assert_eq!(
+            InstanceStateKind::NOT_ALIVE_DISPOSED,
+            instances.apply(&change)
+        );
+
+        let change = writer.new_change(ChangeKind_t::ALIVE, key.clone(), handle);
+        assert_eq!(InstanceStateKind::ALIVE, instances.apply(&change));
+
+        let change = writer.unregister(key, handle);
+        assert_eq!(ChangeKind_t::NOT_ALIVE_UNREGISTERED, change.kind);
+        assert_eq!(handle, change.instance_handle);
+        assert_eq!(
+            InstanceStateKind::NOT_ALIVE_NO_WRITERS,
+            instances.apply(&change)
+        );
+
+        let instance = instances.get(&handle).unwrap();
+        assert_eq!(1, instance.disposed_generation_count);
+        assert_eq!(0, instance.no_writers_generation_count);
+    }
+}
@@ -2,6 +2,8 @@ use crate::structure::cache_change::CacheChange;
 use crate::structure::change_kind::ChangeKind_t;
 use crate::structure::data::Data;
 use crate::structure::duration::Duration_t;
+use crate::structure::guid::GUID_t;
+use crate::structure::history_cache::HistoryCache;
 use crate::structure::instance_handle::InstanceHandle_t;
 use crate::structure::sequence_number::SequenceNumber_t;
 
@@ -11,6 +13,22 @@ pub struct WriterAttributes {
     pub nack_response_delay: Duration_t,
     pub nack_suppression_duration: Duration_t,
     pub last_change_sequence_number: SequenceNumber_t,
+
+    /// Contains the history of the CacheChange changes for this Writer.
+    pub writer_cache: HistoryCache,
+}
+
+impl WriterAttributes {
+    /// Resumes the sequence numbers of the writer after the last one of its
+    /// changes ever stored by the HistoryStore of the writer_cache, so that a
+    /// writer restarted with the same GUID doesn't reuse them.
+    pub fn resume_sequence_numbers(&mut self, writer_guid: &GUID_t) {
+        if let Some(last_stored) = self.writer_cache.last_stored_sequence_number(writer_guid) {
+            if self.last_change_sequence_number < last_stored {
+                self.last_change_sequence_number = last_stored;
+            }
+        }
+    }
 }
 
 pub trait Writer {
@@ -21,4 +39,33 @@ pub trait Writer {
         data: Data,
         handle: InstanceHandle_t,
     ) -> CacheChange;
+
+    /// Returns the handle of the instance with the key, to be passed along
+    /// with the changes of the instance. The key is the big endian CDR
+    /// serialization of the key fields, and max_serialized_key_size the
+    /// maximum size of that serialization for the type of the topic.
+    fn register_instance(
+        &self,
+        serialized_key: &[u8],
+        max_serialized_key_size: usize,
+    ) -> InstanceHandle_t {
+        InstanceHandle_t::from_serialized_key(serialized_key, max_serialized_key_size)
+    }
+
+    /// Creates the change that disposes the instance, which carries only the
+    /// serialized key of the instance.
+    fn dispose(&mut self, key: Data, handle: InstanceHandle_t) -> CacheChange {
+        self.new_change(ChangeKind_t::NOT_ALIVE_DISPOSED, key, handle)
+    }
+
+    /// Creates the change that tells that the writer will no longer write
+    /// the instance, which carries only the serialized key of the instance.
+    fn unregister(&mut self, key: Data, handle: InstanceHandle_t) -> CacheChange {
+        self.new_change(ChangeKind_t::NOT_ALIVE_UNREGISTERED, key, handle)
+    }
+
+    /// Creates the change that both disposes and unregisters the instance.
+    fn dispose_and_unregister(&mut self, key: Data, handle: InstanceHandle_t) -> CacheChange {
+        self.new_change(ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED, key, handle)
+    }
 }
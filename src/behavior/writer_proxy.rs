@@ -0,0 +1,612 @@
+use crate::behavior::change_from_writer::ChangeFromWriter;
+use crate::behavior::change_from_writer_status_kind::ChangeFromWriterStatusKind;
+use crate::messages::ack_nack::AckNack;
+use crate::messages::fragment_number_set::FragmentNumberSet_t;
+use crate::messages::nack_frag::NackFrag;
+use crate::structure::count::Count_t;
+use crate::structure::entity_id::EntityId_t;
+use crate::structure::guid::GUID_t;
+use crate::structure::locator::LocatorList_t;
+use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::sequence_number_set::SequenceNumberSet_t;
+use num_traits::{CheckedAdd, CheckedSub};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// The RTPS WriterProxy class represents the information an RTPS
+/// StatefulReader maintains on each matched RTPS Writer.
+pub struct WriterProxy {
+    /// Identifies the matched Writer.
+    pub remote_writer_guid: GUID_t,
+
+    /// List of unicast (address, port) combinations that can be used to send
+    /// messages to the matched Writer or Writers.
+    pub unicast_locator_list: LocatorList_t,
+
+    /// List of multicast (address, port) combinations that can be used to
+    /// send messages to the matched Writer or Writers.
+    pub multicast_locator_list: LocatorList_t,
+
+    /// All the changes up to this sequence number were either received or
+    /// are lost, which is not remembered.
+    highest_seq_num_available: SequenceNumber_t,
+
+    /// The highest sequence number the remote Writer announced in a
+    /// HEARTBEAT. The changes up to it that were not received are missing.
+    highest_seq_num_announced: SequenceNumber_t,
+
+    /// The status of the changes received after highest_seq_num_available, by
+    /// sequence number.
+    changes_from_writer: BTreeMap<SequenceNumber_t, ChangeFromWriter>,
+
+    /// The ranges of irrelevant changes after highest_seq_num_available, from
+    /// their first sequence number to the one following their last, so that
+    /// a GAP takes a single entry whatever its size.
+    irrelevant_ranges: BTreeMap<SequenceNumber_t, SequenceNumber_t>,
+
+    /// The count of the last HEARTBEAT received from the remote Writer, to
+    /// detect the duplicate ones.
+    last_heartbeat_count: Option<Count_t>,
+
+    /// The count of the last HEARTBEAT_FRAG received from the remote Writer,
+    /// to detect the duplicate ones.
+    last_heartbeat_frag_count: Option<Count_t>,
+
+    /// The count of the last ACKNACK sent to the remote Writer.
+    acknack_count: Count_t,
+
+    /// The count of the last NACK_FRAG sent to the remote Writer.
+    nack_frag_count: Count_t,
+
+    /// When the ACKNACK answering the last HEARTBEATs is to be sent, if one
+    /// is.
+    acknack_deadline: Option<Instant>,
+}
+
+impl WriterProxy {
+    pub fn new(
+        remote_writer_guid: GUID_t,
+        unicast_locator_list: LocatorList_t,
+        multicast_locator_list: LocatorList_t,
+    ) -> WriterProxy {
+        WriterProxy {
+            remote_writer_guid,
+            unicast_locator_list,
+            multicast_locator_list,
+            highest_seq_num_available: SequenceNumber_t::from(0),
+            highest_seq_num_announced: SequenceNumber_t::from(0),
+            changes_from_writer: BTreeMap::new(),
+            irrelevant_ranges: BTreeMap::new(),
+            last_heartbeat_count: None,
+            last_heartbeat_frag_count: None,
+            acknack_count: Count_t::from(0),
+            nack_frag_count: Count_t::from(0),
+            acknack_deadline: None,
+        }
+    }
+
+    /// The highest sequence number of the changes that are available: all
+    /// the changes up to it were either received or are lost.
+    pub fn available_changes_max(&self) -> SequenceNumber_t {
+        self.highest_seq_num_available
+    }
+
+    /// The status of the change relative to the remote Writer. The available
+    /// changes are RECEIVED, even the lost ones.
+    pub fn change_from_writer(&self, sequence_number: SequenceNumber_t) -> ChangeFromWriter {
+        if sequence_number <= self.highest_seq_num_available {
+            return ChangeFromWriter {
+                status: ChangeFromWriterStatusKind::RECEIVED,
+                is_relevant: true,
+            };
+        }
+
+        if self.irrelevant_range_end(sequence_number).is_some() {
+            return ChangeFromWriter {
+                status: ChangeFromWriterStatusKind::RECEIVED,
+                is_relevant: false,
+            };
+        }
+        match self.changes_from_writer.get(&sequence_number) {
+            Some(change_from_writer) => *change_from_writer,
+            None => ChangeFromWriter {
+                status: if sequence_number <= self.highest_seq_num_announced {
+                    ChangeFromWriterStatusKind::MISSING
+                } else {
+                    ChangeFromWriterStatusKind::UNKNOWN
+                },
+                is_relevant: true,
+            },
+        }
+    }
+
+    /// The change was received from the remote Writer.
+    pub fn received_change_set(&mut self, sequence_number: SequenceNumber_t) {
+        self.change_set(sequence_number, true)
+    }
+
+    /// The remote Writer informed that the change is irrelevant, e.g. with a
+    /// GAP. It counts as received.
+    pub fn irrelevant_change_set(&mut self, sequence_number: SequenceNumber_t) {
+        self.change_set(sequence_number, false)
+    }
+
+    /// The changes before first_available_seq_num are no longer available in
+    /// the remote Writer: the ones that were not received are lost.
+    pub fn lost_changes_update(&mut self, first_available_seq_num: SequenceNumber_t) {
+        self.available_changes_update(first_available_seq_num)
+    }
+
+    /// The remote Writer informed that the changes from gap_start up to
+    /// gap_end excluded are irrelevant, e.g. with a GAP. They count as
+    /// received, and are recorded as a single range.
+    pub fn irrelevant_changes_set(
+        &mut self,
+        gap_start: SequenceNumber_t,
+        gap_end: SequenceNumber_t,
+    ) {
+        if gap_end <= gap_start {
+            return;
+        }
+        match self
+            .highest_seq_num_available
+            .checked_add(&SequenceNumber_t::from(1))
+        {
+            Some(first_unavailable) if gap_start > first_unavailable => {}
+            _ => {
+                self.available_changes_update(gap_end);
+                return;
+            }
+        }
+
+        // The range is merged with the ones it overlaps or adjoins.
+        let (mut start, mut end) = (gap_start, gap_end);
+        if let Some((first, last_end)) = self.irrelevant_ranges.range(..start).next_back() {
+            if *last_end >= start {
+                start = *first;
+                end = end.max(*last_end);
+            }
+        }
+        let merged: Vec<_> = self
+            .irrelevant_ranges
+            .range(start..=end)
+            .map(|(first, range_end)| (*first, *range_end))
+            .collect();
+        for (first, range_end) in merged {
+            self.irrelevant_ranges.remove(&first);
+            end = end.max(range_end);
+        }
+        let received: Vec<_> = self
+            .changes_from_writer
+            .range(start..end)
+            .map(|(sequence_number, _)| *sequence_number)
+            .collect();
+        for sequence_number in received {
+            self.changes_from_writer.remove(&sequence_number);
+        }
+        self.irrelevant_ranges.insert(start, end);
+    }
+
+    /// The changes up to last_available_seq_num are available in the remote
+    /// Writer: the ones that were not received are missing.
+    pub fn missing_changes_update(&mut self, last_available_seq_num: SequenceNumber_t) {
+        if last_available_seq_num > self.highest_seq_num_announced {
+            self.highest_seq_num_announced = last_available_seq_num;
+        }
+    }
+
+    /// The sequence numbers of the missing changes, in order.
+    pub fn missing_changes(&self) -> impl Iterator<Item = SequenceNumber_t> + '_ {
+        let one = SequenceNumber_t::from(1);
+        let first = self
+            .highest_seq_num_available
+            .checked_add(&one)
+            .and_then(|first| self.next_not_received(first));
+        std::iter::successors(first, move |sequence_number| {
+            sequence_number
+                .checked_add(&one)
+                .and_then(|next| self.next_not_received(next))
+        })
+        .take_while(move |sequence_number| *sequence_number <= self.highest_seq_num_announced)
+    }
+
+    /// Tells whether the HEARTBEAT with the count is new, and remembers its
+    /// count if so. The count wraps around.
+    pub fn is_new_heartbeat(&mut self, count: Count_t) -> bool {
+        if self
+            .last_heartbeat_count
+            .is_some_and(|last| !count.is_after(last))
+        {
+            return false;
+        }
+        self.last_heartbeat_count = Some(count);
+        true
+    }
+
+    /// Tells whether the HEARTBEAT_FRAG with the count is new, and remembers
+    /// its count if so. The count wraps around.
+    pub fn is_new_heartbeat_frag(&mut self, count: Count_t) -> bool {
+        if self
+            .last_heartbeat_frag_count
+            .is_some_and(|last| !count.is_after(last))
+        {
+            return false;
+        }
+        self.last_heartbeat_frag_count = Some(count);
+        true
+    }
+
+    /// Schedules an ACKNACK to the remote Writer at the deadline, unless one
+    /// is scheduled already.
+    pub fn acknack_schedule(&mut self, deadline: Instant) {
+        self.acknack_deadline.get_or_insert(deadline);
+    }
+
+    /// Tells whether the scheduled ACKNACK is due, in which case it is no
+    /// longer scheduled.
+    pub fn acknack_due(&mut self, now: Instant) -> bool {
+        match self.acknack_deadline {
+            Some(deadline) if deadline <= now => {
+                self.acknack_deadline = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The ACKNACK of the reader to the remote Writer, which acknowledges the
+    /// available changes and requests the missing ones that fit in its
+    /// reader_sn_state, with the next count. The partially received changes
+    /// are not requested: their missing fragments are, with a NACK_FRAG.
+    pub fn acknack(
+        &mut self,
+        reader_id: EntityId_t,
+        is_partially_received: impl Fn(SequenceNumber_t) -> bool,
+    ) -> AckNack {
+        // There is no base past the last sequence number, so once it is
+        // available only the changes before it are acknowledged.
+        let base = self
+            .highest_seq_num_available
+            .checked_add(&SequenceNumber_t::from(1))
+            .unwrap_or(self.highest_seq_num_available);
+        let mut reader_sn_state = SequenceNumberSet_t::new(base);
+        let missing_changes = self
+            .missing_changes()
+            .filter(|sequence_number| !is_partially_received(*sequence_number));
+        for sequence_number in missing_changes {
+            if !reader_sn_state.insert(sequence_number) {
+                break;
+            }
+        }
+
+        self.acknack_count = self.acknack_count.wrapping_add(1);
+        AckNack {
+            reader_id,
+            writer_id: self.remote_writer_guid.entity_id,
+            reader_sn_state,
+            count: self.acknack_count,
+        }
+    }
+
+    /// The NACK_FRAG of the reader to the remote Writer, which requests the
+    /// fragments of the change in the fragment_number_state, with the next
+    /// count.
+    pub fn nack_frag(
+        &mut self,
+        reader_id: EntityId_t,
+        writer_sn: SequenceNumber_t,
+        fragment_number_state: FragmentNumberSet_t,
+    ) -> NackFrag {
+        self.nack_frag_count = self.nack_frag_count.wrapping_add(1);
+        NackFrag {
+            reader_id,
+            writer_id: self.remote_writer_guid.entity_id,
+            writer_sn,
+            fragment_number_state,
+            count: self.nack_frag_count,
+        }
+    }
+
+    fn change_set(&mut self, sequence_number: SequenceNumber_t, is_relevant: bool) {
+        if sequence_number <= self.highest_seq_num_available
+            || self.irrelevant_range_end(sequence_number).is_some()
+        {
+            return;
+        }
+
+        self.changes_from_writer.insert(
+            sequence_number,
+            ChangeFromWriter {
+                status: ChangeFromWriterStatusKind::RECEIVED,
+                is_relevant,
+            },
+        );
+        self.compact();
+    }
+
+    /// Makes the changes before first_unavailable_seq_num available at once,
+    /// whatever their status.
+    fn available_changes_update(&mut self, first_unavailable_seq_num: SequenceNumber_t) {
+        let last_available = match first_unavailable_seq_num.checked_sub(&SequenceNumber_t::from(1))
+        {
+            Some(last_available) => last_available,
+            None => return,
+        };
+        if last_available > self.highest_seq_num_available {
+            self.highest_seq_num_available = last_available;
+            self.changes_from_writer = self
+                .changes_from_writer
+                .split_off(&first_unavailable_seq_num);
+
+            // The part of a range that goes past the available changes is
+            // kept.
+            let ranges = self.irrelevant_ranges.split_off(&first_unavailable_seq_num);
+            let straddling = std::mem::replace(&mut self.irrelevant_ranges, ranges)
+                .into_values()
+                .filter(|end| *end > first_unavailable_seq_num)
+                .max();
+            if let Some(end) = straddling {
+                self.irrelevant_ranges
+                    .insert(first_unavailable_seq_num, end);
+            }
+            self.compact();
+        }
+    }
+
+    /// The end of the range of irrelevant changes the change is in, if it is
+    /// in one.
+    fn irrelevant_range_end(&self, sequence_number: SequenceNumber_t) -> Option<SequenceNumber_t> {
+        self.irrelevant_ranges
+            .range(..=sequence_number)
+            .next_back()
+            .map(|(_, end)| *end)
+            .filter(|end| sequence_number < *end)
+    }
+
+    /// The first sequence number from sequence_number on of a change that
+    /// was neither received nor is irrelevant.
+    fn next_not_received(&self, mut sequence_number: SequenceNumber_t) -> Option<SequenceNumber_t> {
+        loop {
+            if let Some(end) = self.irrelevant_range_end(sequence_number) {
+                sequence_number = end;
+            } else if self.changes_from_writer.contains_key(&sequence_number) {
+                sequence_number = sequence_number.checked_add(&SequenceNumber_t::from(1))?;
+            } else {
+                return Some(sequence_number);
+            }
+        }
+    }
+
+    /// Makes the received and irrelevant changes that follow the available
+    /// ones available.
+    fn compact(&mut self) {
+        let one = SequenceNumber_t::from(1);
+        while let Some(next) = self.highest_seq_num_available.checked_add(&one) {
+            if self.changes_from_writer.remove(&next).is_some() {
+                self.highest_seq_num_available = next;
+            } else if let Some(end) = self.irrelevant_ranges.remove(&next) {
+                self.highest_seq_num_available = end - one;
+                self.changes_from_writer = self.changes_from_writer.split_off(&end);
+            } else {
+                break;
+            }
+        }
+        if self.highest_seq_num_available > self.highest_seq_num_announced {
+            self.highest_seq_num_announced = self.highest_seq_num_available;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::fragment_number::FragmentNumber_t;
+
+    fn writer_proxy() -> WriterProxy {
+        WriterProxy::new(GUID_t::GUID_UNKNOWN, vec![], vec![])
+    }
+
+    fn sequence_numbers(sequence_numbers: impl Iterator<Item = SequenceNumber_t>) -> Vec<i64> {
+        sequence_numbers.map(i64::from).collect()
+    }
+
+    fn status(writer_proxy: &WriterProxy, sequence_number: i64) -> ChangeFromWriterStatusKind {
+        writer_proxy
+            .change_from_writer(SequenceNumber_t::from(sequence_number))
+            .status
+    }
+
+    #[test]
+    fn received_changes_become_available_in_order() {
+        let mut writer_proxy = writer_proxy();
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(4));
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            sequence_numbers(writer_proxy.missing_changes())
+        );
+
+        writer_proxy.received_change_set(SequenceNumber_t::from(2));
+        writer_proxy.irrelevant_change_set(SequenceNumber_t::from(3));
+        assert_eq!(
+            SequenceNumber_t::from(0),
+            writer_proxy.available_changes_max()
+        );
+        assert_eq!(vec![1, 4], sequence_numbers(writer_proxy.missing_changes()));
+        assert_eq!(
+            ChangeFromWriterStatusKind::MISSING,
+            status(&writer_proxy, 1)
+        );
+        assert!(
+            !writer_proxy
+                .change_from_writer(SequenceNumber_t::from(3))
+                .is_relevant
+        );
+        assert_eq!(
+            ChangeFromWriterStatusKind::UNKNOWN,
+            status(&writer_proxy, 5)
+        );
+
+        writer_proxy.received_change_set(SequenceNumber_t::from(1));
+        assert_eq!(
+            SequenceNumber_t::from(3),
+            writer_proxy.available_changes_max()
+        );
+        assert_eq!(vec![4], sequence_numbers(writer_proxy.missing_changes()));
+        assert_eq!(
+            ChangeFromWriterStatusKind::RECEIVED,
+            status(&writer_proxy, 2)
+        );
+    }
+
+    #[test]
+    fn changes_no_longer_available_are_lost() {
+        let mut writer_proxy = writer_proxy();
+        writer_proxy.received_change_set(SequenceNumber_t::from(4));
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(5));
+
+        writer_proxy.lost_changes_update(SequenceNumber_t::from(3));
+        assert_eq!(
+            SequenceNumber_t::from(2),
+            writer_proxy.available_changes_max()
+        );
+        assert_eq!(vec![3, 5], sequence_numbers(writer_proxy.missing_changes()));
+
+        writer_proxy.lost_changes_update(SequenceNumber_t::from(4));
+        assert_eq!(
+            SequenceNumber_t::from(4),
+            writer_proxy.available_changes_max()
+        );
+        assert_eq!(vec![5], sequence_numbers(writer_proxy.missing_changes()));
+
+        // Older HEARTBEATs do not make changes missing again.
+        writer_proxy.lost_changes_update(SequenceNumber_t::from(1));
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(3));
+        assert_eq!(vec![5], sequence_numbers(writer_proxy.missing_changes()));
+
+        // Nor do the ones with a sequence number that doesn't exist.
+        writer_proxy.lost_changes_update(SequenceNumber_t::from(i64::MIN));
+        assert_eq!(
+            SequenceNumber_t::from(4),
+            writer_proxy.available_changes_max()
+        );
+    }
+
+    #[test]
+    fn irrelevant_changes_are_recorded_as_ranges() {
+        let mut writer_proxy = writer_proxy();
+        writer_proxy.received_change_set(SequenceNumber_t::from(5));
+        writer_proxy.irrelevant_changes_set(SequenceNumber_t::from(3), SequenceNumber_t::from(6));
+        writer_proxy.irrelevant_changes_set(SequenceNumber_t::from(8), SequenceNumber_t::from(10));
+        writer_proxy.irrelevant_changes_set(SequenceNumber_t::from(6), SequenceNumber_t::from(7));
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(12));
+        assert_eq!(
+            vec![1, 2, 7, 10, 11, 12],
+            sequence_numbers(writer_proxy.missing_changes())
+        );
+        assert!(
+            !writer_proxy
+                .change_from_writer(SequenceNumber_t::from(5))
+                .is_relevant
+        );
+
+        writer_proxy.lost_changes_update(SequenceNumber_t::from(4));
+        writer_proxy.received_change_set(SequenceNumber_t::from(7));
+        assert_eq!(
+            SequenceNumber_t::from(9),
+            writer_proxy.available_changes_max()
+        );
+        assert_eq!(
+            vec![10, 11, 12],
+            sequence_numbers(writer_proxy.missing_changes())
+        );
+
+        // The size of a range does not matter.
+        writer_proxy
+            .irrelevant_changes_set(SequenceNumber_t::from(11), SequenceNumber_t::from(i64::MAX));
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(i64::MAX));
+        assert_eq!(
+            vec![10, i64::MAX],
+            sequence_numbers(writer_proxy.missing_changes())
+        );
+    }
+
+    #[test]
+    fn acknack_requests_missing_changes() {
+        let mut writer_proxy = writer_proxy();
+        writer_proxy.received_change_set(SequenceNumber_t::from(1));
+        writer_proxy.received_change_set(SequenceNumber_t::from(3));
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(300));
+
+        let acknack = writer_proxy.acknack(EntityId_t::ENTITYID_UNKNOWN, |_| false);
+        assert_eq!(SequenceNumber_t::from(2), *acknack.reader_sn_state.base());
+        let requested = sequence_numbers(acknack.reader_sn_state.iter());
+        assert_eq!(255, requested.len());
+        assert_eq!(Some(&2), requested.first());
+        assert_eq!(Some(&4), requested.get(1));
+        assert_eq!(Some(&257), requested.last());
+        assert_eq!(Count_t::from(1), acknack.count);
+
+        assert_eq!(
+            Count_t::from(2),
+            writer_proxy
+                .acknack(EntityId_t::ENTITYID_UNKNOWN, |_| false)
+                .count
+        );
+
+        // The count wraps around.
+        writer_proxy.acknack_count = Count_t::from(i32::MAX);
+        assert_eq!(
+            Count_t::from(i32::MIN),
+            writer_proxy
+                .acknack(EntityId_t::ENTITYID_UNKNOWN, |_| false)
+                .count
+        );
+    }
+
+    #[test]
+    fn partially_received_changes_are_requested_with_nack_frag() {
+        let mut writer_proxy = writer_proxy();
+        writer_proxy.missing_changes_update(SequenceNumber_t::from(3));
+
+        let acknack = writer_proxy.acknack(EntityId_t::ENTITYID_UNKNOWN, |sequence_number| {
+            sequence_number == SequenceNumber_t::from(2)
+        });
+        assert_eq!(SequenceNumber_t::from(1), *acknack.reader_sn_state.base());
+        assert_eq!(vec![1, 3], sequence_numbers(acknack.reader_sn_state.iter()));
+
+        let fragment_number_state = FragmentNumberSet_t::new(FragmentNumber_t::from(4));
+        let nack_frag = writer_proxy.nack_frag(
+            EntityId_t::ENTITYID_UNKNOWN,
+            SequenceNumber_t::from(2),
+            fragment_number_state,
+        );
+        assert_eq!(SequenceNumber_t::from(2), nack_frag.writer_sn);
+        assert_eq!(Count_t::from(1), nack_frag.count);
+
+        writer_proxy.nack_frag_count = Count_t::from(i32::MAX);
+        let nack_frag = writer_proxy.nack_frag(
+            EntityId_t::ENTITYID_UNKNOWN,
+            SequenceNumber_t::from(2),
+            FragmentNumberSet_t::new(FragmentNumber_t::from(4)),
+        );
+        assert_eq!(Count_t::from(i32::MIN), nack_frag.count);
+    }
+
+    #[test]
+    fn duplicate_heartbeats_are_detected() {
+        let mut writer_proxy = writer_proxy();
+        assert!(writer_proxy.is_new_heartbeat(Count_t::from(1)));
+        assert!(!writer_proxy.is_new_heartbeat(Count_t::from(1)));
+        assert!(writer_proxy.is_new_heartbeat(Count_t::from(3)));
+        assert!(!writer_proxy.is_new_heartbeat(Count_t::from(2)));
+
+        // HEARTBEAT_FRAGs are counted apart.
+        assert!(writer_proxy.is_new_heartbeat_frag(Count_t::from(1)));
+        assert!(!writer_proxy.is_new_heartbeat_frag(Count_t::from(1)));
+
+        // The count wraps around.
+        assert!(writer_proxy.is_new_heartbeat(Count_t::from(i32::MAX)));
+        assert!(writer_proxy.is_new_heartbeat(Count_t::from(i32::MIN)));
+        assert!(!writer_proxy.is_new_heartbeat(Count_t::from(i32::MAX)));
+    }
+}
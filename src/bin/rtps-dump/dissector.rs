@@ -0,0 +1,736 @@
+use crate::output::Value;
+
+use rtps_rs::common::validity_trait::Validity;
+use rtps_rs::messages::ack_nack::AckNack;
+use rtps_rs::messages::data::{Data, DataContext};
+use rtps_rs::messages::data_frag::DataFrag;
+use rtps_rs::messages::gap::Gap;
+use rtps_rs::messages::header::Header;
+use rtps_rs::messages::heartbeat::Heartbeat;
+use rtps_rs::messages::heartbeat_frag::HeartbeatFrag;
+use rtps_rs::messages::info_destination::InfoDestination;
+use rtps_rs::messages::info_source::InfoSource;
+use rtps_rs::messages::nack_frag::NackFrag;
+use rtps_rs::messages::submessage_elements::parameter_list::ParameterList;
+use rtps_rs::messages::submessage_elements::serialized_payload::SerializedPayloadContent;
+use rtps_rs::messages::submessage_flag::SubmessageFlag;
+use rtps_rs::messages::submessage_header::SubmessageHeader;
+use rtps_rs::messages::submessage_kind::SubmessageKind;
+use rtps_rs::messages::vendor_id::VendorId_t;
+use rtps_rs::structure::entity_id::EntityId_t;
+use rtps_rs::structure::guid::GUID_t;
+use rtps_rs::structure::guid_prefix::GuidPrefix_t;
+use rtps_rs::structure::locator::{LocatorList_t, Locator_t};
+use rtps_rs::structure::locator_udp_v4::LocatorUDPv4_t;
+use rtps_rs::structure::parameter_id::ParameterId;
+use rtps_rs::structure::sequence_number::SequenceNumber_t;
+use rtps_rs::structure::time::Time_t;
+
+use speedy::{Endianness, Readable};
+use std::convert::TryFrom;
+use std::net::SocketAddrV4;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HEADER_LENGTH: usize = 20;
+const SUBMESSAGE_HEADER_LENGTH: usize = 4;
+
+/// Longest user payload printed in full; longer ones are cut.
+const MAX_PAYLOAD_OCTETS: usize = 256;
+
+const VENDORS: &[([u8; 2], &str)] = &[
+    ([0x01, 0x01], "RTI Connext DDS"),
+    ([0x01, 0x02], "ADLINK OpenSplice DDS"),
+    ([0x01, 0x03], "OCI OpenDDS"),
+    ([0x01, 0x04], "MilSoft"),
+    ([0x01, 0x05], "Gallium InterCOM DDS"),
+    ([0x01, 0x06], "TwinOaks CoreDX DDS"),
+    ([0x01, 0x07], "Lakota Technical Solutions"),
+    ([0x01, 0x08], "ICOUP Consulting"),
+    (
+        [0x01, 0x09],
+        "ETRI Electronics and Telecommunication Research Institute",
+    ),
+    ([0x01, 0x0A], "RTI Connext DDS Micro"),
+    ([0x01, 0x0B], "ADLINK Vortex Cafe"),
+    ([0x01, 0x0C], "PrismTech"),
+    ([0x01, 0x0D], "ADLINK Vortex Lite"),
+    ([0x01, 0x0E], "Technicolor Qeo"),
+    ([0x01, 0x0F], "eProsima Fast DDS"),
+    ([0x01, 0x10], "Eclipse Cyclone DDS"),
+    ([0x01, 0x11], "GurumNetworks GurumDDS"),
+    ([0x01, 0x12], "Atostek RustDDS"),
+];
+
+/// Dissects a single RTPS message, as carried by one UDP datagram.
+///
+/// Decoding errors do not abort the dissection: everything decoded up to the
+/// error is returned together with the error description.
+pub fn dissect_message(message: &[u8]) -> Value {
+    let mut fields = vec![("length", Value::from(message.len() as i64))];
+
+    let header = match message
+        .get(..HEADER_LENGTH)
+        .ok_or_else(|| "message shorter than the RTPS header".to_owned())
+        .and_then(|bytes| {
+            Header::read_from_buffer_with_ctx(Endianness::NATIVE, bytes)
+                .map_err(|err| format!("{:?}", err))
+        }) {
+        Ok(header) if header.valid() => header,
+        Ok(_) => {
+            fields.push(("error", "not an RTPS message".into()));
+            return Value::Record(fields);
+        }
+        Err(err) => {
+            fields.push(("error", err.into()));
+            return Value::Record(fields);
+        }
+    };
+
+    fields.push((
+        "header",
+        Value::Record(vec![
+            (
+                "protocol_version",
+                format!(
+                    "{}.{}",
+                    header.protocol_version.major, header.protocol_version.minor
+                )
+                .into(),
+            ),
+            ("vendor_id", vendor(&header.vendor_id)),
+            ("guid_prefix", header.guid_prefix.to_string().into()),
+        ]),
+    ));
+
+    let mut state = State {
+        source_guid_prefix: header.guid_prefix,
+        dest_guid_prefix: GuidPrefix_t::GUIDPREFIX_UNKNOWN,
+    };
+    let mut submessages = vec![];
+    let mut offset = HEADER_LENGTH;
+
+    while offset < message.len() {
+        match dissect_submessage(&mut state, &message[offset..]) {
+            Ok((submessage, length)) => {
+                submessages.push(submessage);
+                offset += length;
+            }
+            Err(err) => {
+                submessages.push(Value::Record(vec![
+                    ("offset", (offset as i64).into()),
+                    ("error", err.into()),
+                ]));
+                break;
+            }
+        }
+    }
+
+    fields.push(("submessages", Value::List(submessages)));
+    Value::Record(fields)
+}
+
+struct State {
+    source_guid_prefix: GuidPrefix_t,
+    dest_guid_prefix: GuidPrefix_t,
+}
+
+type DissectResult<T> = Result<T, String>;
+
+fn read<'a, T: Readable<'a, SubmessageFlag>>(
+    flags: SubmessageFlag,
+    bytes: &'a [u8],
+) -> DissectResult<T> {
+    T::read_from_buffer_with_ctx(flags, bytes).map_err(|err| format!("{:?}", err))
+}
+
+/// Returns the dissected submessage and the number of octets it occupies,
+/// including its header.
+fn dissect_submessage(state: &mut State, bytes: &[u8]) -> DissectResult<(Value, usize)> {
+    let submessage_header: SubmessageHeader = bytes
+        .get(..SUBMESSAGE_HEADER_LENGTH)
+        .ok_or_else(|| "truncated submessage header".to_owned())
+        .and_then(|header| {
+            SubmessageHeader::read_from_buffer_with_ctx(Endianness::NATIVE, header)
+                .map_err(|err| format!("{:?}", err))
+        })?;
+
+    let kind = submessage_header.submessage_id;
+    let flags = submessage_header.flags;
+    let remaining = bytes.len() - SUBMESSAGE_HEADER_LENGTH;
+
+    // A zero length means that the submessage extends up to the end of the
+    // message, except for the submessages that may be legitimately empty.
+    let length = match submessage_header.submessage_length as usize {
+        0 if kind != SubmessageKind::PAD && kind != SubmessageKind::INFO_TS => remaining,
+        length if length > remaining => {
+            return Err(format!(
+                "{} submessage length {} exceeds the {} remaining octets",
+                kind_name(kind),
+                length,
+                remaining
+            ))
+        }
+        length => length,
+    };
+    let body = &bytes[SUBMESSAGE_HEADER_LENGTH..SUBMESSAGE_HEADER_LENGTH + length];
+
+    let mut fields = vec![
+        ("kind", kind_name(kind).into()),
+        ("flags", submessage_flags(kind, flags).into()),
+        ("length", (length as i64).into()),
+    ];
+
+    match kind {
+        SubmessageKind::ACKNACK => {
+            let ack_nack: AckNack = read(flags, body)?;
+            fields.push(("final", flags.is_flag_set(0x02).into()));
+            fields.push((
+                "reader_guid",
+                guid(&state.dest_guid_prefix, &ack_nack.reader_id),
+            ));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &ack_nack.writer_id),
+            ));
+            fields.push((
+                "reader_sn_state_base",
+                sequence_number(*ack_nack.reader_sn_state.base()),
+            ));
+            fields.push((
+                "reader_sn_state",
+                Value::List(
+                    ack_nack
+                        .reader_sn_state
+                        .iter()
+                        .map(sequence_number)
+                        .collect(),
+                ),
+            ));
+            fields.push(("count", i64::from(i32::from(ack_nack.count)).into()));
+        }
+        SubmessageKind::DATA => {
+            let data = Data::read_from_buffer_with_ctx(DataContext::new(flags, length), body)
+                .map_err(|err| format!("{:?}", err))?;
+            fields.push((
+                "reader_guid",
+                guid(&state.dest_guid_prefix, &data.reader_id),
+            ));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &data.writer_id),
+            ));
+            fields.push(("writer_sn", sequence_number(data.writer_sn)));
+            if let Some(ref inline_qos) = data.inline_qos {
+                fields.push((
+                    "inline_qos",
+                    parameter_list(inline_qos, flags.endianness_flag()),
+                ));
+            }
+            if let Some(ref serialized_payload) = data.serialized_payload {
+                let representation_identifier = serialized_payload.header.representation_identifier;
+                fields.push((
+                    "representation_identifier",
+                    format!("{:?}", representation_identifier).into(),
+                ));
+                match serialized_payload.content {
+                    SerializedPayloadContent::ParameterList(ref parameters) => {
+                        let endianness = speedy::Context::endianness(&representation_identifier);
+                        fields.push(("parameters", parameter_list(parameters, endianness)));
+                    }
+                    SerializedPayloadContent::UserDefined(ref payload) => {
+                        fields.push(("payload_length", (payload.len() as i64).into()));
+                        fields.push(("payload", payload_octets(payload).into()));
+                    }
+                }
+            }
+        }
+        SubmessageKind::DATA_FRAG => {
+            let data_frag =
+                DataFrag::read_from_buffer_with_ctx(DataContext::new(flags, length), body)
+                    .map_err(|err| format!("{:?}", err))?;
+            fields.push((
+                "reader_guid",
+                guid(&state.dest_guid_prefix, &data_frag.reader_id),
+            ));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &data_frag.writer_id),
+            ));
+            fields.push(("writer_sn", sequence_number(data_frag.writer_sn)));
+            fields.push((
+                "fragment_starting_num",
+                i64::from(u32::from(data_frag.fragment_starting_num)).into(),
+            ));
+            fields.push((
+                "fragments_in_submessage",
+                i64::from(data_frag.fragments_in_submessage).into(),
+            ));
+            fields.push(("fragment_size", i64::from(data_frag.fragment_size).into()));
+            fields.push(("data_size", i64::from(data_frag.data_size).into()));
+            if let Some(ref inline_qos) = data_frag.inline_qos {
+                fields.push((
+                    "inline_qos",
+                    parameter_list(inline_qos, flags.endianness_flag()),
+                ));
+            }
+            fields.push((
+                "payload_length",
+                (data_frag.serialized_payload.len() as i64).into(),
+            ));
+            fields.push((
+                "payload",
+                payload_octets(&data_frag.serialized_payload).into(),
+            ));
+        }
+        SubmessageKind::GAP => {
+            let gap: Gap = read(flags, body)?;
+            fields.push(("reader_guid", guid(&state.dest_guid_prefix, &gap.reader_id)));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &gap.writer_id),
+            ));
+            fields.push(("gap_start", sequence_number(gap.gap_start)));
+            fields.push(("gap_list_base", sequence_number(*gap.gap_list.base())));
+            fields.push((
+                "gap_list",
+                Value::List(gap.gap_list.iter().map(sequence_number).collect()),
+            ));
+        }
+        SubmessageKind::HEARTBEAT => {
+            let heartbeat: Heartbeat = read(flags, body)?;
+            fields.push(("final", flags.is_flag_set(0x02).into()));
+            fields.push(("liveliness", flags.is_flag_set(0x04).into()));
+            fields.push((
+                "reader_guid",
+                guid(&state.dest_guid_prefix, &heartbeat.reader_id),
+            ));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &heartbeat.writer_id),
+            ));
+            fields.push(("first_sn", sequence_number(heartbeat.first_sn)));
+            fields.push(("last_sn", sequence_number(heartbeat.last_sn)));
+            fields.push(("count", i64::from(i32::from(heartbeat.count)).into()));
+        }
+        SubmessageKind::HEARTBEAT_FRAG => {
+            let heartbeat_frag: HeartbeatFrag = read(flags, body)?;
+            fields.push((
+                "reader_guid",
+                guid(&state.dest_guid_prefix, &heartbeat_frag.reader_id),
+            ));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &heartbeat_frag.writer_id),
+            ));
+            fields.push(("writer_sn", sequence_number(heartbeat_frag.writer_sn)));
+            fields.push((
+                "last_fragment_num",
+                i64::from(u32::from(heartbeat_frag.last_fragment_num)).into(),
+            ));
+            fields.push(("count", i64::from(i32::from(heartbeat_frag.count)).into()));
+        }
+        SubmessageKind::NACK_FRAG => {
+            let nack_frag: NackFrag = read(flags, body)?;
+            fields.push((
+                "reader_guid",
+                guid(&state.dest_guid_prefix, &nack_frag.reader_id),
+            ));
+            fields.push((
+                "writer_guid",
+                guid(&state.source_guid_prefix, &nack_frag.writer_id),
+            ));
+            fields.push(("writer_sn", sequence_number(nack_frag.writer_sn)));
+            fields.push((
+                "fragment_number_state_base",
+                i64::from(u32::from(*nack_frag.fragment_number_state.base())).into(),
+            ));
+            fields.push((
+                "fragment_number_state",
+                Value::List(
+                    nack_frag
+                        .fragment_number_state
+                        .iter()
+                        .map(|fragment_number| i64::from(u32::from(fragment_number)).into())
+                        .collect(),
+                ),
+            ));
+            fields.push(("count", i64::from(i32::from(nack_frag.count)).into()));
+        }
+        SubmessageKind::INFO_DST => {
+            let info_dst: InfoDestination = read(flags, body)?;
+            if info_dst.guid_prefix != GuidPrefix_t::GUIDPREFIX_UNKNOWN {
+                state.dest_guid_prefix = info_dst.guid_prefix;
+            }
+            fields.push(("guid_prefix", info_dst.guid_prefix.to_string().into()));
+        }
+        SubmessageKind::INFO_SRC => {
+            let info_src: InfoSource = read(flags, body)?;
+            state.source_guid_prefix = info_src.guid_prefix;
+            fields.push((
+                "protocol_version",
+                format!(
+                    "{}.{}",
+                    info_src.protocol_version.major, info_src.protocol_version.minor
+                )
+                .into(),
+            ));
+            fields.push(("vendor_id", vendor(&info_src.vendor_id)));
+            fields.push(("guid_prefix", info_src.guid_prefix.to_string().into()));
+        }
+        SubmessageKind::INFO_TS => {
+            if flags.is_flag_set(0x02) {
+                fields.push(("timestamp", "invalidated".into()));
+            } else {
+                let timestamp: Time_t = read(flags, body)?;
+                fields.push(("timestamp", time(timestamp).into()));
+            }
+        }
+        SubmessageKind::INFO_REPLY => {
+            let (unicast_locator_list, read_octets) =
+                LocatorList_t::read_with_length_from_buffer_with_ctx(flags, body);
+            let unicast_locator_list = unicast_locator_list.map_err(|err| format!("{:?}", err))?;
+            fields.push((
+                "unicast_locator_list",
+                Value::List(unicast_locator_list.iter().map(locator).collect()),
+            ));
+            if flags.is_flag_set(0x02) {
+                let multicast_locator_list: LocatorList_t = read(flags, &body[read_octets..])?;
+                fields.push((
+                    "multicast_locator_list",
+                    Value::List(multicast_locator_list.iter().map(locator).collect()),
+                ));
+            }
+        }
+        SubmessageKind::INFO_REPLY_IP4 => {
+            let unicast_locator: LocatorUDPv4_t = read(flags, body)?;
+            fields.push((
+                "unicast_locator",
+                SocketAddrV4::from(unicast_locator).to_string().into(),
+            ));
+            if flags.is_flag_set(0x02) {
+                let multicast_locator: LocatorUDPv4_t = read(flags, body.get(8..).unwrap_or(&[]))?;
+                fields.push((
+                    "multicast_locator",
+                    SocketAddrV4::from(multicast_locator).to_string().into(),
+                ));
+            }
+        }
+        _ => {
+            fields.push(("octets", payload_octets(body).into()));
+        }
+    }
+
+    Ok((Value::Record(fields), SUBMESSAGE_HEADER_LENGTH + length))
+}
+
+fn kind_name(kind: SubmessageKind) -> String {
+    match kind.name() {
+        Some(name) => name.to_owned(),
+        None => format!("UNKNOWN(0x{:02x})", u8::from(kind)),
+    }
+}
+
+/// Formats the flags as a hex value followed by the letters the specification
+/// uses for the flags that are set.
+fn submessage_flags(kind: SubmessageKind, flags: SubmessageFlag) -> String {
+    let names: &[(u8, &str)] = match kind {
+        SubmessageKind::ACKNACK => &[(0x01, "E"), (0x02, "F")],
+        SubmessageKind::DATA => &[
+            (0x01, "E"),
+            (0x02, "Q"),
+            (0x04, "D"),
+            (0x08, "K"),
+            (0x10, "N"),
+        ],
+        SubmessageKind::DATA_FRAG => &[(0x01, "E"), (0x02, "Q"), (0x04, "K"), (0x08, "N")],
+        SubmessageKind::HEARTBEAT => &[(0x01, "E"), (0x02, "F"), (0x04, "L")],
+        SubmessageKind::INFO_TS => &[(0x01, "E"), (0x02, "I")],
+        SubmessageKind::INFO_REPLY | SubmessageKind::INFO_REPLY_IP4 => &[(0x01, "E"), (0x02, "M")],
+        _ => &[(0x01, "E")],
+    };
+
+    let set = names
+        .iter()
+        .filter(|(mask, _)| flags.is_flag_set(*mask))
+        .map(|(_, name)| *name)
+        .collect::<Vec<&str>>();
+
+    format!("0x{:02x} [{}]", flags.flags, set.join(", "))
+}
+
+fn hex_octets(octets: &[u8]) -> String {
+    octets
+        .iter()
+        .map(|octet| format!("{:02x}", octet))
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+fn payload_octets(octets: &[u8]) -> String {
+    let shown = octets.len().min(MAX_PAYLOAD_OCTETS);
+    let mut text = octets[..shown]
+        .iter()
+        .map(|octet| format!("{:02x}", octet))
+        .collect::<Vec<String>>()
+        .join(" ");
+    if shown < octets.len() {
+        text.push_str(" ...");
+    }
+    text
+}
+
+fn vendor(vendor_id: &VendorId_t) -> Value {
+    let id = hex_octets(&vendor_id.vendor_id);
+    match VENDORS
+        .iter()
+        .find(|(known, _)| *known == vendor_id.vendor_id)
+    {
+        Some((_, name)) => format!("{} ({})", id, name).into(),
+        None => id.into(),
+    }
+}
+
+fn guid(guid_prefix: &GuidPrefix_t, entity_id: &EntityId_t) -> Value {
+    GUID_t {
+        guid_prefix: *guid_prefix,
+        entity_id: *entity_id,
+    }
+    .to_string()
+    .into()
+}
+
+fn sequence_number(sequence_number: SequenceNumber_t) -> Value {
+    i64::from(sequence_number).into()
+}
+
+fn time(time: Time_t) -> String {
+    match SystemTime::try_from(time) {
+        Ok(system_time) => match system_time.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => {
+                format!(
+                    "{}.{:09}",
+                    since_epoch.as_secs(),
+                    since_epoch.subsec_nanos()
+                )
+            }
+            Err(_) => "invalid".to_owned(),
+        },
+        Err(_) => "invalid".to_owned(),
+    }
+}
+
+fn locator(locator: &Locator_t) -> Value {
+    locator.to_string().into()
+}
+
+fn parameter_list(parameter_list: &ParameterList, endianness: Endianness) -> Value {
+    Value::List(
+        parameter_list
+            .iter()
+            .map(|parameter| {
+                let id = parameter.get_id();
+                let value = parameter.get_value();
+                let mut fields = vec![
+                    (
+                        "parameter_id",
+                        match id.name() {
+                            Some(name) => name.to_owned(),
+                            None => format!("0x{:04x}", u16::from(id)),
+                        }
+                        .into(),
+                    ),
+                    ("length", (value.len() as i64).into()),
+                ];
+                if let Some(decoded) = parameter_value(id, value, endianness) {
+                    fields.push(("value", decoded));
+                }
+                fields.push(("octets", payload_octets(value).into()));
+                Value::Record(fields)
+            })
+            .collect(),
+    )
+}
+
+/// Decodes the values of the most common parameters; the remaining ones are
+/// only shown as octets.
+fn parameter_value(id: ParameterId, value: &[u8], endianness: Endianness) -> Option<Value> {
+    match id {
+        ParameterId::PID_TOPIC_NAME
+        | ParameterId::PID_TYPE_NAME
+        | ParameterId::PID_ENTITY_NAME
+        | ParameterId::PID_DOMAIN_TAG => {
+            let length = u32::read_from_buffer_with_ctx(endianness, value).ok()? as usize;
+            let string = value.get(4..4 + length)?;
+            let string = string.strip_suffix(&[0]).unwrap_or(string);
+            Some(String::from_utf8_lossy(string).into_owned().into())
+        }
+        ParameterId::PID_PARTICIPANT_GUID
+        | ParameterId::PID_GROUP_GUID
+        | ParameterId::PID_ENDPOINT_GUID => {
+            let guid_prefix_value =
+                GuidPrefix_t::read_from_buffer_with_ctx(endianness, value).ok()?;
+            let entity_id_value =
+                EntityId_t::read_from_buffer_with_ctx(endianness, value.get(12..)?).ok()?;
+            Some(guid(&guid_prefix_value, &entity_id_value))
+        }
+        ParameterId::PID_UNICAST_LOCATOR
+        | ParameterId::PID_MULTICAST_LOCATOR
+        | ParameterId::PID_DEFAULT_UNICAST_LOCATOR
+        | ParameterId::PID_DEFAULT_MULTICAST_LOCATOR
+        | ParameterId::PID_METATRAFFIC_UNICAST_LOCATOR
+        | ParameterId::PID_METATRAFFIC_MULTICAST_LOCATOR => {
+            let locator_value = Locator_t::read_from_buffer_with_ctx(endianness, value).ok()?;
+            Some(locator(&locator_value))
+        }
+        ParameterId::PID_PROTOCOL_VERSION => {
+            Some(format!("{}.{}", value.first()?, value.get(1)?).into())
+        }
+        ParameterId::PID_VENDOR_ID => {
+            Some(vendor(&VendorId_t::from([*value.first()?, *value.get(1)?])))
+        }
+        ParameterId::PID_STATUS_INFO => {
+            // the status info is always sent in big endian
+            let flags = *value.get(3)?;
+            let mut names = vec![];
+            if flags & 0x01 != 0 {
+                names.push("DISPOSED");
+            }
+            if flags & 0x02 != 0 {
+                names.push("UNREGISTERED");
+            }
+            Some(format!("0x{:02x} [{}]", flags, names.join(", ")).into())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Format;
+
+    // The same ACKNACK preceded by INFO_DST that is used by the receiver tests
+    const WIRESHARK_ACK_NACK: [u8; 64] = [
+        0x52, 0x54, 0x50, 0x53, 0x02, 0x01, 0x01, 0x0f, 0x01, 0x0f, 0xbb, 0x1d, 0xdf, 0x2b, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0e, 0x01, 0x0c, 0x00, 0x01, 0x0f, 0xbb, 0x1d, 0xe6, 0x2b,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x01, 0x18, 0x00, 0x00, 0x00, 0x04, 0xc7, 0x00,
+        0x00, 0x04, 0xc2, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn dissect_wireshark_ack_nack() {
+        assert_eq!(
+            "length: 64\n\
+             header:\n\
+             \x20 protocol_version: 2.1\n\
+             \x20 vendor_id: 01.0f (eProsima Fast DDS)\n\
+             \x20 guid_prefix: 01.0f.bb.1d.df.2b.00.00.00.00.00.00\n\
+             submessages:\n\
+             \x20 -\n\
+             \x20   kind: INFO_DST\n\
+             \x20   flags: 0x01 [E]\n\
+             \x20   length: 12\n\
+             \x20   guid_prefix: 01.0f.bb.1d.e6.2b.00.00.00.00.00.00\n\
+             \x20 -\n\
+             \x20   kind: ACKNACK\n\
+             \x20   flags: 0x01 [E]\n\
+             \x20   length: 24\n\
+             \x20   final: false\n\
+             \x20   reader_guid: 01.0f.bb.1d.e6.2b.00.00.00.00.00.00|00.00.04.c7\n\
+             \x20   writer_guid: 01.0f.bb.1d.df.2b.00.00.00.00.00.00|00.00.04.c2\n\
+             \x20   reader_sn_state_base: 0\n\
+             \x20   reader_sn_state: []\n\
+             \x20   count: 1\n",
+            Format::Tree.render(&dissect_message(&WIRESHARK_ACK_NACK))
+        );
+    }
+
+    #[test]
+    fn dissect_heartbeat_with_sequence_numbers_as_json() {
+        let mut message = WIRESHARK_ACK_NACK[..20].to_vec();
+        message.extend_from_slice(&[
+            0x07, 0x03, 0x1c, 0x00, // HEARTBEAT, final, little endian
+            0x00, 0x00, 0x03, 0xc7, 0x00, 0x00, 0x03, 0xc2, // reader and writer ids
+            0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, // first_sn
+            0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, // last_sn
+            0x02, 0x00, 0x00, 0x00, // count
+        ]);
+
+        let json = Format::Json.render(&dissect_message(&message));
+        assert!(json.contains(
+            "{\"kind\":\"HEARTBEAT\",\"flags\":\"0x03 [E, F]\",\"length\":28,\"final\":true,\
+             \"liveliness\":false,\
+             \"reader_guid\":\"00.00.00.00.00.00.00.00.00.00.00.00|00.00.03.c7\",\
+             \"writer_guid\":\"01.0f.bb.1d.df.2b.00.00.00.00.00.00|00.00.03.c2\",\
+             \"first_sn\":5,\"last_sn\":9,\"count\":2}"
+        ));
+    }
+
+    #[test]
+    fn dissect_data_frag() {
+        let mut message = WIRESHARK_ACK_NACK[..20].to_vec();
+        message.extend_from_slice(&[
+            0x16, 0x01, 0x24, 0x00, // DATA_FRAG, little endian
+            0x00, 0x00, 0x1c, 0x00, // extra flags, octets to inline QoS
+            0x00, 0x00, 0x01, 0x07, 0x00, 0x00, 0x01, 0x02, // reader and writer ids
+            0x00, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, // writer_sn
+            0x02, 0x00, 0x00, 0x00, // fragment_starting_num
+            0x01, 0x00, 0x08, 0x00, // fragments_in_submessage, fragment_size
+            0x0c, 0x00, 0x00, 0x00, // data_size
+            0x2a, 0x00, 0x00, 0x00, // fragment
+        ]);
+
+        let json = Format::Json.render(&dissect_message(&message));
+        assert!(json.contains(
+            "{\"kind\":\"DATA_FRAG\",\"flags\":\"0x01 [E]\",\"length\":36,\
+             \"reader_guid\":\"00.00.00.00.00.00.00.00.00.00.00.00|00.00.01.07\",\
+             \"writer_guid\":\"01.0f.bb.1d.df.2b.00.00.00.00.00.00|00.00.01.02\",\
+             \"writer_sn\":7,\"fragment_starting_num\":2,\"fragments_in_submessage\":1,\
+             \"fragment_size\":8,\"data_size\":12,\"payload_length\":4,\
+             \"payload\":\"2a 00 00 00\"}"
+        ));
+    }
+
+    #[test]
+    fn dissect_truncated_submessage() {
+        let message = &WIRESHARK_ACK_NACK[..50];
+        let dissected = Format::Json.render(&dissect_message(message));
+
+        assert!(dissected.contains("\"kind\":\"INFO_DST\""));
+        assert!(dissected.contains(
+            "{\"offset\":36,\"error\":\"ACKNACK submessage length 24 exceeds the 10 remaining octets\"}"
+        ));
+    }
+
+    #[test]
+    fn dissect_non_rtps_datagram() {
+        assert_eq!(
+            Value::Record(vec![
+                ("length", 4.into()),
+                ("error", "message shorter than the RTPS header".into()),
+            ]),
+            dissect_message(b"RTPS")
+        );
+    }
+
+    #[test]
+    fn decode_string_parameter() {
+        assert_eq!(
+            Some(Value::from("Square")),
+            parameter_value(
+                ParameterId::PID_TOPIC_NAME,
+                &[0x07, 0x00, 0x00, 0x00, b'S', b'q', b'u', b'a', b'r', b'e', 0x00, 0x00],
+                Endianness::LittleEndian
+            )
+        );
+    }
+
+    #[test]
+    fn flags_of_data_submessage() {
+        assert_eq!(
+            "0x07 [E, Q, D]",
+            submessage_flags(SubmessageKind::DATA, SubmessageFlag { flags: 0x07 })
+        );
+    }
+}
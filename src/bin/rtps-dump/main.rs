@@ -0,0 +1,317 @@
+//! Command line dissector of RTPS traffic.
+//!
+//! Reads RTPS messages either from a classic pcap capture or from a UDP socket
+//! and prints every message as an indented tree, as line-delimited JSON or as
+//! a hex dump in the layout of the interoperability corpus.
+
+mod dissector;
+mod output;
+mod pcap;
+
+use output::{Format, Value};
+use pcap::PcapReader;
+
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "\
+usage: rtps-dump [--json | --hex] --pcap FILE
+       rtps-dump [--json | --hex] --udp PORT [--multicast GROUP]
+
+Dissects RTPS messages and prints them to the standard output.
+
+options:
+  --json             print one JSON object per message instead of a tree
+  --hex              print the octets of every message, as in the files of
+                     the interoperability corpus
+  --pcap FILE        read the messages from a pcap capture
+  --udp PORT         listen for the messages on a UDP port
+  --multicast GROUP  join an IPv4 multicast group, e.g. 239.255.0.1
+  --help             print this help";
+
+const RTPS_PROTOCOL_ID: &[u8] = b"RTPS";
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+enum Input {
+    Pcap(String),
+    Udp {
+        port: u16,
+        multicast_group: Option<Ipv4Addr>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+    Dissected(Format),
+    Hex,
+}
+
+struct Options {
+    output: Output,
+    input: Input,
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut output = None;
+    let mut pcap = None;
+    let mut port = None;
+    let mut multicast_group = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value of {}", name))
+        };
+        match arg.as_str() {
+            "--json" | "--hex" if output.is_some() => {
+                return Err("--json and --hex are exclusive".to_owned())
+            }
+            "--json" => output = Some(Output::Dissected(Format::Json)),
+            "--hex" => output = Some(Output::Hex),
+            "--pcap" => pcap = Some(value("--pcap")?),
+            "--udp" => {
+                let text = value("--udp")?;
+                port = Some(
+                    text.parse::<u16>()
+                        .map_err(|_| format!("invalid port: {}", text))?,
+                );
+            }
+            "--multicast" => {
+                let text = value("--multicast")?;
+                multicast_group = Some(
+                    text.parse::<Ipv4Addr>()
+                        .map_err(|_| format!("invalid multicast group: {}", text))?,
+                );
+            }
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    let input = match (pcap, port) {
+        (Some(path), None) if multicast_group.is_none() => Input::Pcap(path),
+        (Some(_), None) => return Err("--multicast requires --udp".to_owned()),
+        (None, Some(port)) => Input::Udp {
+            port,
+            multicast_group,
+        },
+        (None, None) => return Err("one of --pcap or --udp is required".to_owned()),
+        (Some(_), Some(_)) => return Err("--pcap and --udp are exclusive".to_owned()),
+    };
+
+    Ok(Options {
+        output: output.unwrap_or(Output::Dissected(Format::Tree)),
+        input,
+    })
+}
+
+fn timestamp(since_epoch: Duration) -> String {
+    format!(
+        "{}.{:06}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_micros()
+    )
+}
+
+/// Formats the octets of the message like the files of the interoperability
+/// corpus, after a comment telling where the message comes from.
+fn hex_dump(
+    timestamp_value: Duration,
+    source: SocketAddr,
+    destination: Option<SocketAddr>,
+    payload: &[u8],
+) -> String {
+    let mut text = format!(
+        "# Captured at {} from {}",
+        timestamp(timestamp_value),
+        source
+    );
+    if let Some(destination) = destination {
+        text.push_str(&format!(" to {}", destination));
+    }
+    text.push_str(".\n\n");
+    for line in payload.chunks(16) {
+        let octets = line
+            .iter()
+            .map(|octet| format!("{:02x}", octet))
+            .collect::<Vec<String>>();
+        text.push_str(&octets.join(" "));
+        text.push('\n');
+    }
+    text.push('\n');
+    text
+}
+
+fn print(
+    output: Output,
+    timestamp_value: Duration,
+    source: SocketAddr,
+    destination: Option<SocketAddr>,
+    payload: &[u8],
+) -> io::Result<()> {
+    let format = match output {
+        Output::Dissected(format) => format,
+        Output::Hex => {
+            let text = hex_dump(timestamp_value, source, destination, payload);
+            return io::stdout().write_all(text.as_bytes());
+        }
+    };
+
+    let mut fields = vec![
+        ("timestamp", timestamp(timestamp_value).into()),
+        ("source", source.to_string().into()),
+    ];
+    if let Some(destination) = destination {
+        fields.push(("destination", destination.to_string().into()));
+    }
+    fields.push(("message", dissector::dissect_message(payload)));
+
+    let mut text = format.render(&Value::Record(fields));
+    if format == Format::Tree {
+        text.push('\n');
+    }
+
+    io::stdout().write_all(text.as_bytes())
+}
+
+fn dump_pcap(output: Output, path: &str) -> io::Result<()> {
+    let mut reader = PcapReader::new(BufReader::new(File::open(path)?))?;
+
+    while let Some(packet) = reader.next_packet()? {
+        if let Some(datagram) = packet.udp_datagram() {
+            if datagram.payload.starts_with(RTPS_PROTOCOL_ID) {
+                print(
+                    output,
+                    packet.timestamp,
+                    datagram.source,
+                    Some(datagram.destination),
+                    datagram.payload,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn dump_udp(output: Output, port: u16, multicast_group: Option<Ipv4Addr>) -> io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    if let Some(group) = multicast_group {
+        socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+    }
+    let destination = socket.local_addr()?;
+
+    let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        let (length, source) = socket.recv_from(&mut buffer)?;
+        let payload = &buffer[..length];
+        if payload.starts_with(RTPS_PROTOCOL_ID) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            print(output, now, source, Some(destination), payload)?;
+        }
+    }
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let options = match parse_options(args.into_iter()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("rtps-dump: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match options.input {
+        Input::Pcap(ref path) => dump_pcap(options.output, path),
+        Input::Udp {
+            port,
+            multicast_group,
+        } => dump_udp(options.output, port, multicast_group),
+    };
+
+    match result {
+        Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(err) => {
+            eprintln!("rtps-dump: {}", err);
+            process::exit(1);
+        }
+        Ok(()) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn parse_pcap_options() {
+        let options = parse_options(args(&["--json", "--pcap", "capture.pcap"])).unwrap();
+        assert_eq!(Output::Dissected(Format::Json), options.output);
+        assert!(matches!(options.input, Input::Pcap(ref path) if path == "capture.pcap"));
+    }
+
+    #[test]
+    fn parse_udp_options() {
+        let options =
+            parse_options(args(&["--udp", "7400", "--multicast", "239.255.0.1"])).unwrap();
+        assert_eq!(Output::Dissected(Format::Tree), options.output);
+        assert!(matches!(
+            options.input,
+            Input::Udp {
+                port: 7400,
+                multicast_group: Some(group)
+            } if group == Ipv4Addr::new(239, 255, 0, 1)
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        assert!(parse_options(args(&[])).is_err());
+        assert!(parse_options(args(&["--udp", "port"])).is_err());
+        assert!(parse_options(args(&["--udp", "7400", "--pcap", "capture.pcap"])).is_err());
+        assert!(parse_options(args(&[
+            "--pcap",
+            "capture.pcap",
+            "--multicast",
+            "239.255.0.1"
+        ]))
+        .is_err());
+        assert!(parse_options(args(&["--pcap"])).is_err());
+        assert!(parse_options(args(&["--json", "--hex", "--pcap", "capture.pcap"])).is_err());
+    }
+
+    #[test]
+    fn hex_dump_of_a_message() {
+        let payload = (0..20).collect::<Vec<u8>>();
+        assert_eq!(
+            "# Captured at 1600000000.250000 from 10.0.0.1:7410 to 239.255.0.1:7400.\n\
+             \n\
+             00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n\
+             10 11 12 13\n\
+             \n",
+            hex_dump(
+                Duration::new(1_600_000_000, 250_000_000),
+                "10.0.0.1:7410".parse().unwrap(),
+                Some("239.255.0.1:7400".parse().unwrap()),
+                &payload
+            )
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::Write;
+
+/// Dissected representation of a message, independent of the output format.
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+    List(Vec<Value>),
+    Record(Vec<(&'static str, Value)>),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_owned())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Indented, human readable tree
+    Tree,
+    /// One JSON object per line
+    Json,
+}
+
+impl Format {
+    pub fn render(self, value: &Value) -> String {
+        let mut output = String::new();
+        match self {
+            Format::Tree => {
+                render_tree(&mut output, value, 0);
+            }
+            Format::Json => {
+                render_json(&mut output, value);
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
+
+fn indent(output: &mut String, depth: usize) {
+    for _ in 0..depth {
+        output.push_str("  ");
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    match value {
+        Value::List(list) => list.is_empty(),
+        Value::Record(record) => record.is_empty(),
+        _ => true,
+    }
+}
+
+fn render_scalar(output: &mut String, value: &Value) {
+    match value {
+        Value::Bool(value) => write!(output, "{}", value).unwrap(),
+        Value::Integer(value) => write!(output, "{}", value).unwrap(),
+        Value::Text(value) => output.push_str(value),
+        Value::List(_) => output.push_str("[]"),
+        Value::Record(_) => output.push_str("{}"),
+    }
+}
+
+fn render_tree(output: &mut String, value: &Value, depth: usize) {
+    match value {
+        Value::Record(fields) if !fields.is_empty() => {
+            for (name, field) in fields {
+                indent(output, depth);
+                output.push_str(name);
+                output.push(':');
+                if is_scalar(field) {
+                    output.push(' ');
+                    render_scalar(output, field);
+                    output.push('\n');
+                } else {
+                    output.push('\n');
+                    render_tree(output, field, depth + 1);
+                }
+            }
+        }
+        Value::List(items) if !items.is_empty() => {
+            for item in items {
+                indent(output, depth);
+                output.push('-');
+                if is_scalar(item) {
+                    output.push(' ');
+                    render_scalar(output, item);
+                    output.push('\n');
+                } else {
+                    output.push('\n');
+                    render_tree(output, item, depth + 1);
+                }
+            }
+        }
+        scalar => {
+            indent(output, depth);
+            render_scalar(output, scalar);
+            output.push('\n');
+        }
+    }
+}
+
+fn render_json(output: &mut String, value: &Value) {
+    match value {
+        Value::Bool(value) => write!(output, "{}", value).unwrap(),
+        Value::Integer(value) => write!(output, "{}", value).unwrap(),
+        Value::Text(value) => render_json_string(output, value),
+        Value::List(items) => {
+            output.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                render_json(output, item);
+            }
+            output.push(']');
+        }
+        Value::Record(fields) => {
+            output.push('{');
+            for (i, (name, field)) in fields.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                render_json_string(output, name);
+                output.push(':');
+                render_json(output, field);
+            }
+            output.push('}');
+        }
+    }
+}
+
+fn render_json_string(output: &mut String, value: &str) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(output, "\\u{:04x}", c as u32).unwrap(),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Value {
+        Value::Record(vec![
+            ("kind", "HEARTBEAT".into()),
+            ("final", true.into()),
+            ("count", 7.into()),
+            ("set", Value::List(vec![1.into(), 3.into()])),
+            ("empty", Value::List(vec![])),
+            (
+                "nested",
+                Value::Record(vec![("name", "with \"quotes\"\n".into())]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn render_tree() {
+        assert_eq!(
+            "kind: HEARTBEAT\n\
+             final: true\n\
+             count: 7\n\
+             set:\n\
+             \x20 - 1\n\
+             \x20 - 3\n\
+             empty: []\n\
+             nested:\n\
+             \x20 name: with \"quotes\"\n\n",
+            Format::Tree.render(&example())
+        );
+    }
+
+    #[test]
+    fn render_json() {
+        assert_eq!(
+            "{\"kind\":\"HEARTBEAT\",\"final\":true,\"count\":7,\"set\":[1,3],\"empty\":[],\
+             \"nested\":{\"name\":\"with \\\"quotes\\\"\\n\"}}\n",
+            Format::Json.render(&example())
+        );
+    }
+
+    #[test]
+    fn json_escapes_control_characters() {
+        let mut output = String::new();
+        render_json_string(&mut output, "a\u{1}b");
+        assert_eq!("\"a\\u0001b\"", output);
+    }
+}
@@ -0,0 +1,452 @@
+use std::io::{Error, ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Reader of the classic libpcap capture format, as written by tcpdump and
+/// Wireshark. Only the parts needed to pull UDP datagrams out of a capture are
+/// supported; the pcapng format is not.
+pub struct PcapReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    nanosecond_resolution: bool,
+    snapshot_length: u32,
+    link_type: u32,
+}
+
+/// A single captured frame, starting at the link layer header.
+#[derive(Debug, PartialEq)]
+pub struct Packet {
+    pub timestamp: Duration,
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+/// Payload of a UDP datagram extracted from a captured frame.
+#[derive(Debug, PartialEq)]
+pub struct UdpDatagram<'a> {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    pub payload: &'a [u8],
+}
+
+const MAGIC_MICROSECONDS: u32 = 0xa1b2_c3d4;
+const MAGIC_NANOSECONDS: u32 = 0xa1b2_3c4d;
+
+/// Largest snapshot length libpcap supports; larger ones in a file header
+/// are taken as this one, so that a corrupt header can't make a record
+/// allocate gigabytes.
+const MAXIMUM_SNAPLEN: u32 = 262_144;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+const IP_PROTOCOL_HOP_BY_HOP: u8 = 0;
+const IP_PROTOCOL_UDP: u8 = 17;
+const IP_PROTOCOL_ROUTING: u8 = 43;
+const IP_PROTOCOL_FRAGMENT: u8 = 44;
+const IP_PROTOCOL_AUTHENTICATION: u8 = 51;
+const IP_PROTOCOL_DESTINATION_OPTIONS: u8 = 60;
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut global_header = [0; 24];
+        reader.read_exact(&mut global_header)?;
+
+        let magic = [
+            global_header[0],
+            global_header[1],
+            global_header[2],
+            global_header[3],
+        ];
+        let (big_endian, nanosecond_resolution) =
+            match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                (MAGIC_MICROSECONDS, _) => (false, false),
+                (MAGIC_NANOSECONDS, _) => (false, true),
+                (_, MAGIC_MICROSECONDS) => (true, false),
+                (_, MAGIC_NANOSECONDS) => (true, true),
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "Not a pcap file (pcapng is not supported)",
+                    ))
+                }
+            };
+
+        let mut pcap_reader = PcapReader {
+            reader,
+            big_endian,
+            nanosecond_resolution,
+            snapshot_length: 0,
+            link_type: 0,
+        };
+        pcap_reader.snapshot_length = match pcap_reader.u32_at(&global_header, 16) {
+            0 => MAXIMUM_SNAPLEN,
+            snapshot_length => snapshot_length.min(MAXIMUM_SNAPLEN),
+        };
+        pcap_reader.link_type = pcap_reader.u32_at(&global_header, 20);
+        Ok(pcap_reader)
+    }
+
+    /// Reads the next packet of the capture, or returns None at the end of
+    /// the file.
+    pub fn next_packet(&mut self) -> Result<Option<Packet>, Error> {
+        let mut record_header = [0; 16];
+        match self.reader.read_exact(&mut record_header) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+
+        let seconds = self.u32_at(&record_header, 0);
+        let fraction = self.u32_at(&record_header, 4);
+        let captured_length = self.u32_at(&record_header, 8);
+        if captured_length > self.snapshot_length {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Packet of {} octets exceeds the snapshot length of {} octets",
+                    captured_length, self.snapshot_length
+                ),
+            ));
+        }
+
+        let mut data = vec![0; captured_length as usize];
+        self.reader.read_exact(&mut data)?;
+
+        let nanoseconds = if self.nanosecond_resolution {
+            fraction
+        } else {
+            fraction.saturating_mul(1000)
+        };
+
+        Ok(Some(Packet {
+            timestamp: Duration::new(u64::from(seconds), nanoseconds),
+            link_type: self.link_type,
+            data,
+        }))
+    }
+
+    fn u32_at(&self, bytes: &[u8], offset: usize) -> u32 {
+        let value = [
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ];
+        if self.big_endian {
+            u32::from_be_bytes(value)
+        } else {
+            u32::from_le_bytes(value)
+        }
+    }
+}
+
+impl Packet {
+    /// Decodes the link and network layers of the packet and returns the UDP
+    /// datagram it carries, if any. Fragmented IP packets are not reassembled.
+    pub fn udp_datagram(&self) -> Option<UdpDatagram<'_>> {
+        let frame = &self.data[..];
+        let (ethertype, network) = match self.link_type {
+            LINKTYPE_NULL => {
+                // the family is stored in the byte order of the capturing host
+                let family = u32::from_le_bytes([
+                    *frame.first()?,
+                    *frame.get(1)?,
+                    *frame.get(2)?,
+                    *frame.get(3)?,
+                ]);
+                let family = if family > 0xFFFF {
+                    family.swap_bytes()
+                } else {
+                    family
+                };
+                match family {
+                    2 => (ETHERTYPE_IPV4, frame.get(4..)?),
+                    24 | 28 | 30 => (ETHERTYPE_IPV6, frame.get(4..)?),
+                    _ => return None,
+                }
+            }
+            LINKTYPE_ETHERNET => {
+                let mut ethertype = read_u16(frame, 12)?;
+                let mut offset = 14;
+                while ethertype == ETHERTYPE_VLAN {
+                    ethertype = read_u16(frame, offset + 2)?;
+                    offset += 4;
+                }
+                (ethertype, frame.get(offset..)?)
+            }
+            LINKTYPE_RAW => match frame.first()? >> 4 {
+                4 => (ETHERTYPE_IPV4, frame),
+                6 => (ETHERTYPE_IPV6, frame),
+                _ => return None,
+            },
+            LINKTYPE_LINUX_SLL => (read_u16(frame, 14)?, frame.get(16..)?),
+            LINKTYPE_LINUX_SLL2 => (read_u16(frame, 0)?, frame.get(20..)?),
+            _ => return None,
+        };
+
+        match ethertype {
+            ETHERTYPE_IPV4 => udp_in_ipv4(network),
+            ETHERTYPE_IPV6 => udp_in_ipv6(network),
+            _ => None,
+        }
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *bytes.get(offset)?,
+        *bytes.get(offset + 1)?,
+    ]))
+}
+
+fn udp_in_ipv4(packet: &[u8]) -> Option<UdpDatagram<'_>> {
+    let header_length = usize::from(packet.first()? & 0x0F) * 4;
+    let total_length = usize::from(read_u16(packet, 2)?);
+    let fragment = read_u16(packet, 6)?;
+    let more_fragments = fragment & 0x2000 != 0;
+    let fragment_offset = fragment & 0x1FFF;
+
+    if *packet.get(9)? != IP_PROTOCOL_UDP || more_fragments || fragment_offset != 0 {
+        return None;
+    }
+
+    let addresses = packet.get(12..20)?;
+    let source = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+    let destination = Ipv4Addr::new(addresses[4], addresses[5], addresses[6], addresses[7]);
+    let end = total_length.min(packet.len());
+    udp(
+        IpAddr::V4(source),
+        IpAddr::V4(destination),
+        packet.get(header_length..end)?,
+    )
+}
+
+fn udp_in_ipv6(packet: &[u8]) -> Option<UdpDatagram<'_>> {
+    // Skip the extension headers preceding the UDP header. Fragmented
+    // packets are not reassembled, and encrypted ones can't be looked into.
+    let mut next_header = *packet.get(6)?;
+    let mut offset = 40;
+    while next_header != IP_PROTOCOL_UDP {
+        let header_length = match next_header {
+            IP_PROTOCOL_HOP_BY_HOP | IP_PROTOCOL_ROUTING | IP_PROTOCOL_DESTINATION_OPTIONS => {
+                (usize::from(*packet.get(offset + 1)?) + 1) * 8
+            }
+            IP_PROTOCOL_FRAGMENT => {
+                let fragment = read_u16(packet, offset + 2)?;
+                let more_fragments = fragment & 0x0001 != 0;
+                let fragment_offset = fragment >> 3;
+                if more_fragments || fragment_offset != 0 {
+                    return None;
+                }
+                8
+            }
+            IP_PROTOCOL_AUTHENTICATION => (usize::from(*packet.get(offset + 1)?) + 2) * 4,
+            _ => return None,
+        };
+        next_header = *packet.get(offset)?;
+        offset += header_length;
+    }
+
+    let mut source = [0; 16];
+    source.copy_from_slice(packet.get(8..24)?);
+    let mut destination = [0; 16];
+    destination.copy_from_slice(packet.get(24..40)?);
+
+    let end = (40 + usize::from(read_u16(packet, 4)?)).min(packet.len());
+    udp(
+        IpAddr::V6(Ipv6Addr::from(source)),
+        IpAddr::V6(Ipv6Addr::from(destination)),
+        packet.get(offset..end)?,
+    )
+}
+
+fn udp(source: IpAddr, destination: IpAddr, segment: &[u8]) -> Option<UdpDatagram<'_>> {
+    let source_port = read_u16(segment, 0)?;
+    let destination_port = read_u16(segment, 2)?;
+    let length = usize::from(read_u16(segment, 4)?).min(segment.len());
+
+    Some(UdpDatagram {
+        source: SocketAddr::new(source, source_port),
+        destination: SocketAddr::new(destination, destination_port),
+        payload: segment.get(8..length)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ethernet_ipv4_udp_frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![
+            0x01, 0x00, 0x5e, 0x7f, 0x00, 0x01, // destination MAC
+            0x02, 0x42, 0xac, 0x11, 0x00, 0x02, // source MAC
+            0x08, 0x00, // IPv4
+        ];
+        let total_length = (20 + 8 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x00, 0x40, 0x00, 0x01, IP_PROTOCOL_UDP, 0x00, 0x00]);
+        frame.extend_from_slice(&[10, 0, 0, 1]);
+        frame.extend_from_slice(&[239, 255, 0, 1]);
+        frame.extend_from_slice(&7410u16.to_be_bytes());
+        frame.extend_from_slice(&7400u16.to_be_bytes());
+        frame.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x00]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn little_endian_capture(link_type: u32, frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut capture = vec![];
+        capture.extend_from_slice(&MAGIC_MICROSECONDS.to_le_bytes());
+        capture.extend_from_slice(&2u16.to_le_bytes());
+        capture.extend_from_slice(&4u16.to_le_bytes());
+        capture.extend_from_slice(&[0; 8]);
+        capture.extend_from_slice(&65535u32.to_le_bytes());
+        capture.extend_from_slice(&link_type.to_le_bytes());
+        for (i, frame) in frames.iter().enumerate() {
+            capture.extend_from_slice(&(1_600_000_000 + i as u32).to_le_bytes());
+            capture.extend_from_slice(&250_000u32.to_le_bytes());
+            capture.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            capture.extend_from_slice(frame);
+        }
+        capture
+    }
+
+    #[test]
+    fn reads_all_packets_of_a_capture() {
+        let frames = vec![
+            ethernet_ipv4_udp_frame(b"RTPS"),
+            ethernet_ipv4_udp_frame(b"SPTR"),
+        ];
+        let capture = little_endian_capture(LINKTYPE_ETHERNET, &frames);
+        let mut reader = PcapReader::new(&capture[..]).unwrap();
+
+        assert_eq!(LINKTYPE_ETHERNET, reader.link_type);
+
+        let first = reader.next_packet().unwrap().unwrap();
+        assert_eq!(Duration::new(1_600_000_000, 250_000_000), first.timestamp);
+        assert_eq!(frames[0], first.data);
+
+        let second = reader.next_packet().unwrap().unwrap();
+        assert_eq!(frames[1], second.data);
+
+        assert_eq!(None, reader.next_packet().unwrap());
+    }
+
+    #[test]
+    fn rejects_packet_longer_than_snapshot_length() {
+        let mut capture = little_endian_capture(LINKTYPE_ETHERNET, &[vec![0; 64]]);
+        capture[16..20].copy_from_slice(&32u32.to_le_bytes());
+        let mut reader = PcapReader::new(&capture[..]).unwrap();
+
+        let err = reader.next_packet().unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn rejects_unknown_file_format() {
+        let capture = [0x0A, 0x0D, 0x0D, 0x0A].repeat(6);
+        assert!(PcapReader::new(&capture[..]).is_err());
+    }
+
+    #[test]
+    fn extracts_udp_payload_from_ethernet_frame() {
+        let packet = Packet {
+            timestamp: Duration::default(),
+            link_type: LINKTYPE_ETHERNET,
+            data: ethernet_ipv4_udp_frame(b"RTPS\x02\x01"),
+        };
+
+        assert_eq!(
+            Some(UdpDatagram {
+                source: "10.0.0.1:7410".parse().unwrap(),
+                destination: "239.255.0.1:7400".parse().unwrap(),
+                payload: b"RTPS\x02\x01",
+            }),
+            packet.udp_datagram()
+        );
+    }
+
+    #[test]
+    fn ignores_ip_fragments() {
+        let mut data = ethernet_ipv4_udp_frame(b"RTPS");
+        data[14 + 6] |= 0x20; // more fragments
+        let packet = Packet {
+            timestamp: Duration::default(),
+            link_type: LINKTYPE_ETHERNET,
+            data,
+        };
+
+        assert_eq!(None, packet.udp_datagram());
+    }
+
+    #[test]
+    fn extracts_udp_payload_from_raw_ip_packet() {
+        let packet = Packet {
+            timestamp: Duration::default(),
+            link_type: LINKTYPE_RAW,
+            data: ethernet_ipv4_udp_frame(b"RTPS")[14..].to_vec(),
+        };
+
+        assert_eq!(b"RTPS", packet.udp_datagram().unwrap().payload);
+    }
+
+    fn raw_ipv6_packet(extension_headers: &[u8], next_header: u8, udp_segment: &[u8]) -> Vec<u8> {
+        let payload_length = (extension_headers.len() + udp_segment.len()) as u16;
+        let mut packet = vec![0x60, 0x00, 0x00, 0x00];
+        packet.extend_from_slice(&payload_length.to_be_bytes());
+        packet.extend_from_slice(&[next_header, 64]);
+        packet.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        packet.extend_from_slice(&[0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        packet.extend_from_slice(extension_headers);
+        packet.extend_from_slice(udp_segment);
+        packet
+    }
+
+    #[test]
+    fn skips_ipv6_extension_headers() {
+        let udp_segment = ethernet_ipv4_udp_frame(b"RTPS")[34..].to_vec();
+        // Hop-by-hop options of 8 octets, then a fragment header of a packet
+        // that is not fragmented.
+        let hop_by_hop = [
+            IP_PROTOCOL_FRAGMENT,
+            0x00,
+            0x05,
+            0x02,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        let fragment = [IP_PROTOCOL_UDP, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78];
+        let extension_headers = [hop_by_hop, fragment].concat();
+        let packet = Packet {
+            timestamp: Duration::default(),
+            link_type: LINKTYPE_RAW,
+            data: raw_ipv6_packet(&extension_headers, IP_PROTOCOL_HOP_BY_HOP, &udp_segment),
+        };
+
+        let datagram = packet.udp_datagram().unwrap();
+        assert_eq!(
+            "[fe80::1]:7410".parse::<SocketAddr>().unwrap(),
+            datagram.source
+        );
+        assert_eq!(b"RTPS", datagram.payload);
+
+        // The first fragment of a fragmented packet is ignored.
+        let mut data = packet.data;
+        data[40 + 8 + 3] = 0x01; // more fragments
+        let packet = Packet { data, ..packet };
+        assert_eq!(None, packet.udp_datagram());
+    }
+}
@@ -40,8 +40,15 @@ impl<'a, C: Context> Readable<'a, C> for BitSetRef {
         unsafe {
             let inner = bit_vec.storage_mut();
             for _ in 0..number_of_bytes {
-                inner.push(reader.read_u32()?);
+                // The first bit of the set is the most significant bit of
+                // the first long, while BitVec stores it in the least
+                // significant one.
+                inner.push(reader.read_u32()?.reverse_bits());
             }
+            // storage_mut() does not update the length of the BitVec, without
+            // it the read bits would not be visible through contains() or
+            // the iterators
+            bit_vec.set_len(number_of_bytes as usize * 32);
         }
 
         Ok(BitSetRef(BitSet::from_bit_vec(bit_vec)))
@@ -59,7 +66,7 @@ impl<C: Context> Writable<C> for BitSetRef {
         let bytes = self.0.get_ref().storage();
         writer.write_u32((bytes.len() * 32) as u32)?;
         for byte in bytes {
-            writer.write_u32(*byte)?;
+            writer.write_u32(byte.reverse_bits())?;
         }
         Ok(())
     }
@@ -68,6 +75,23 @@ impl<C: Context> Writable<C> for BitSetRef {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use speedy::Endianness;
+
+    #[test]
+    fn deserialized_bits_are_visible() {
+        let serialized = [0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x81, 0x00, 0x00, 0x20, 0x00];
+        let set: BitSetRef =
+            Readable::read_from_buffer_with_ctx(Endianness::LittleEndian, &serialized).unwrap();
+
+        assert!(set.contains(0));
+        assert!(set.contains(7));
+        assert!(set.contains(42));
+        assert_eq!(3, set.len());
+        assert_eq!(
+            vec![true, false, false, false, false, false, false, true],
+            set.get_ref().iter().take(8).collect::<Vec<bool>>()
+        );
+    }
 
     serialization_test!( type = BitSetRef,
     {
@@ -86,10 +110,10 @@ mod tests {
             set
         })(),
         le = [0x40, 0x00, 0x00, 0x00,
-              0x81, 0x00, 0x00, 0x00,
-              0x00, 0x04, 0x00, 0x00],
-        be = [0x00, 0x00, 0x00, 0x40,
               0x00, 0x00, 0x00, 0x81,
-              0x00, 0x00, 0x04, 0x00]
+              0x00, 0x00, 0x20, 0x00],
+        be = [0x00, 0x00, 0x00, 0x40,
+              0x81, 0x00, 0x00, 0x00,
+              0x00, 0x20, 0x00, 0x00]
     });
 }
@@ -1,6 +1,9 @@
 pub mod actor;
 pub mod bit_set;
+pub mod md5;
 pub mod ranged_bit_set;
+#[cfg(feature = "serde")]
+pub(crate) mod serde_support;
 pub mod shared_state;
 pub mod size_tracking_context;
 pub mod validity_trait;
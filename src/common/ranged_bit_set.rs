@@ -1,6 +1,7 @@
 use crate::common::bit_set::BitSetRef;
 use num_traits::{CheckedAdd, CheckedSub, FromPrimitive, NumCast, ToPrimitive};
 use speedy::{Readable, Writable};
+use std::fmt;
 
 #[derive(Debug, PartialEq, Readable, Writable)]
 pub struct RangedBitSet<B> {
@@ -32,6 +33,106 @@ where
             None => false,
         }
     }
+
+    pub fn base(&self) -> &B {
+        &self.base
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = B> + '_ {
+        self.set
+            .iter()
+            .map(move |i| B::from_usize(i).unwrap().checked_add(&self.base).unwrap())
+    }
+}
+
+/// Written as the base followed by the members, e.g. `1000:[1001,1003]`.
+impl<B> fmt::Display for RangedBitSet<B>
+where
+    B: CheckedAdd + CheckedSub + ToPrimitive + FromPrimitive + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:[", self.base)?;
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Serialized as the base and the members of the set, e.g.
+/// `{"base": 1000, "set": [1001, 1003]}`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "RangedBitSet")]
+struct RangedBitSetMembers<B> {
+    base: B,
+    set: Vec<B>,
+}
+
+#[cfg(feature = "serde")]
+impl<B> serde::Serialize for RangedBitSet<B>
+where
+    B: CheckedAdd + CheckedSub + ToPrimitive + FromPrimitive + Copy + serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RangedBitSetMembers {
+            base: self.base,
+            set: self.iter().collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, B> serde::Deserialize<'de> for RangedBitSet<B>
+where
+    B: CheckedAdd + CheckedSub + ToPrimitive + FromPrimitive + Copy + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let members = RangedBitSetMembers::<B>::deserialize(deserializer)?;
+        let mut set = RangedBitSet::new(members.base);
+        for member in members.set {
+            if set.normalize(member).is_none() {
+                return Err(serde::de::Error::custom(
+                    "member outside of the range of the set",
+                ));
+            }
+            set.insert(member);
+        }
+        Ok(set)
+    }
+}
+
+/// Generates sets with up to 16 members anywhere in the range of the set.
+#[cfg(any(test, feature = "proptest"))]
+impl<B> proptest::arbitrary::Arbitrary for RangedBitSet<B>
+where
+    B: CheckedAdd + CheckedSub + ToPrimitive + FromPrimitive,
+    B: proptest::arbitrary::Arbitrary + fmt::Debug + 'static,
+{
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (any::<B>(), proptest::collection::vec(0..256_usize, 0..16))
+            .prop_map(|(base, offsets)| {
+                let mut set = RangedBitSet::new(base);
+                for offset in offsets {
+                    // Members past the maximum of B are skipped.
+                    if let Some(value) =
+                        B::from_usize(offset).and_then(|o| o.checked_add(&set.base))
+                    {
+                        set.insert(value);
+                    }
+                }
+                set
+            })
+            .boxed()
+    }
 }
 
 pub struct RangedBitSetIntoIter<B> {
@@ -115,6 +216,28 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    fn iter_test() {
+        let mut set: RangedBitSet<i64> = RangedBitSet::new(100);
+
+        assert!(set.insert(102));
+        assert!(set.insert(100));
+        assert!(set.insert(100 + 255));
+
+        assert_eq!(&100, set.base());
+        assert_eq!(vec![100, 102, 100 + 255], set.iter().collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn display_test() {
+        let mut set: RangedBitSet<i64> = RangedBitSet::new(100);
+        assert_eq!("100:[]", set.to_string());
+
+        set.insert(100);
+        set.insert(103);
+        assert_eq!("100:[100,103]", set.to_string());
+    }
+
     serialization_test!( type = RangedBitSet<i64>,
     {
         empty_ranged_bit_set,
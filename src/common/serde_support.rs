@@ -0,0 +1,203 @@
+//! Helpers for the types whose serde representation differs between human
+//! readable formats, like JSON or YAML, and binary formats.
+//!
+//! Human readable formats use the `Display`/`FromStr` text of the type, e.g.
+//! `udpv4://10.0.0.1:7410` for a locator. Binary formats use its big endian
+//! RTPS wire encoding.
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, Serializer};
+use speedy::{Endianness, Readable, Writable};
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Display + Writable<Endianness>,
+    S: Serializer,
+{
+    if serializer.is_human_readable() {
+        serializer.collect_str(value)
+    } else {
+        let bytes = value
+            .write_to_vec_with_ctx(Endianness::BigEndian)
+            .map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr + for<'a> Readable<'a, Endianness>,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(TextVisitor(PhantomData))
+    } else {
+        deserializer.deserialize_bytes(BytesVisitor(PhantomData))
+    }
+}
+
+struct TextVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for TextVisitor<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        value.parse().map_err(E::custom)
+    }
+}
+
+struct BytesVisitor<T>(PhantomData<T>);
+
+impl<T> BytesVisitor<T>
+where
+    T: for<'a> Readable<'a, Endianness>,
+{
+    fn read<E: de::Error>(bytes: &[u8]) -> Result<T, E> {
+        T::read_from_buffer_owned_with_ctx(Endianness::BigEndian, bytes).map_err(E::custom)
+    }
+}
+
+impl<'de, T> Visitor<'de> for BytesVisitor<T>
+where
+    T: for<'a> Readable<'a, Endianness>,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte array")
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<T, E> {
+        Self::read(value)
+    }
+
+    // Some formats, e.g. bincode or JSON, don't distinguish byte arrays from
+    // sequences of integers.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Self::read(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::messages::ack_nack::AckNack;
+    use crate::messages::header::Header;
+    use crate::structure::count::Count_t;
+    use crate::structure::entity_id::EntityId_t;
+    use crate::structure::guid::GUID_t;
+    use crate::structure::guid_prefix::GuidPrefix_t;
+    use crate::structure::locator::{IpAddr, Ipv6Addr, Locator_t, SocketAddr};
+    use crate::structure::sequence_number::SequenceNumber_t;
+    use crate::structure::sequence_number_set::SequenceNumberSet_t;
+    use serde_test::{assert_de_tokens_error, assert_tokens, Configure, Token};
+
+    const GUID_PREFIX: [u8; 12] = [
+        0x01, 0x0f, 0xbb, 0x1d, 0xdf, 0x2b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn guid_is_text_in_human_readable_formats() {
+        let guid = GUID_t {
+            guid_prefix: GuidPrefix_t::from(GUID_PREFIX),
+            entity_id: EntityId_t::ENTITYID_PARTICIPANT,
+        };
+        assert_tokens(
+            &guid.readable(),
+            &[Token::Str(
+                "01.0f.bb.1d.df.2b.00.00.00.00.00.00|00.00.01.c1",
+            )],
+        );
+    }
+
+    #[test]
+    fn guid_is_wire_encoding_in_binary_formats() {
+        let guid = GUID_t {
+            guid_prefix: GuidPrefix_t::from(GUID_PREFIX),
+            entity_id: EntityId_t::ENTITYID_PARTICIPANT,
+        };
+        assert_tokens(
+            &guid.compact(),
+            &[Token::Bytes(&[
+                0x01, 0x0f, 0xbb, 0x1d, 0xdf, 0x2b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x01, 0xc1,
+            ])],
+        );
+    }
+
+    #[test]
+    fn entity_id_uses_builtin_names() {
+        assert_tokens(
+            &EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER.readable(),
+            &[Token::Str("SPDP_BUILTIN_PARTICIPANT_WRITER")],
+        );
+    }
+
+    #[test]
+    fn locator_is_url_in_human_readable_formats() {
+        let locator = Locator_t::from(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 7400));
+        assert_tokens(&locator.readable(), &[Token::Str("udpv6://[::1]:7400")]);
+    }
+
+    #[test]
+    fn invalid_text_is_rejected() {
+        assert_de_tokens_error::<serde_test::Readable<Locator_t>>(
+            &[Token::Str("tcpv4://10.0.0.1:7410")],
+            "unknown locator kind \"tcpv4\"",
+        );
+    }
+
+    #[test]
+    fn ack_nack_to_json() {
+        let mut reader_sn_state = SequenceNumberSet_t::new(SequenceNumber_t::from(1000));
+        reader_sn_state.insert(SequenceNumber_t::from(1001));
+        reader_sn_state.insert(SequenceNumber_t::from(1003));
+        let ack_nack = AckNack {
+            reader_id: EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER,
+            writer_id: EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_WRITER,
+            reader_sn_state,
+            count: Count_t::from(3),
+        };
+
+        let json = serde_json::to_string(&ack_nack).unwrap();
+        assert_eq!(
+            "{\"reader_id\":\"SEDP_BUILTIN_PUBLICATIONS_READER\",\
+             \"writer_id\":\"SEDP_BUILTIN_PUBLICATIONS_WRITER\",\
+             \"reader_sn_state\":{\"base\":1000,\"set\":[1001,1003]},\
+             \"count\":{\"value\":3}}",
+            json
+        );
+        assert_eq!(ack_nack, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn header_json_round_trip() {
+        let header = Header::new(GuidPrefix_t::from(GUID_PREFIX));
+
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(json.contains("\"guid_prefix\":\"01.0f.bb.1d.df.2b.00.00.00.00.00.00\""));
+        assert_eq!(header, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn sequence_number_set_members_must_be_in_range() {
+        assert!(
+            serde_json::from_str::<SequenceNumberSet_t>("{\"base\":1000,\"set\":[1256]}").is_err()
+        );
+    }
+}
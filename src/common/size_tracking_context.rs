@@ -4,13 +4,15 @@ use speedy::{Context, Endianness};
 /// This is a speedy::Context for deserializing from a Reader while tracking
 /// the number of bytes used so far.
 pub trait SizeTrackingContext : Context {
-    fn subtract_from_remaining(&mut self, length: usize);
+    /// Accounts for length bytes read, failing if fewer than that remained.
+    fn subtract_from_remaining(&mut self, length: usize) -> Result<(), Self::Error>;
     fn length_remaining(&self) -> usize;
 }
 
 impl SizeTrackingContext for Endianness {
-    fn subtract_from_remaining(&mut self, _: usize) {
+    fn subtract_from_remaining(&mut self, _: usize) -> Result<(), Self::Error> {
         warn!("this function is not implemented");
+        Ok(())
     }
 
     fn length_remaining(&self) -> usize {
@@ -18,3 +20,53 @@ impl SizeTrackingContext for Endianness {
         0
     }
 }
+
+
+/// This is a speedy::Context for deserializing a buffer of a known length
+/// with the given endianness.
+pub struct SizeTrackingEndianness {
+    endianness: Endianness,
+    length_remaining: usize,
+}
+
+impl SizeTrackingEndianness {
+    pub fn new(endianness: Endianness, length_remaining: usize) -> SizeTrackingEndianness {
+        SizeTrackingEndianness {
+            endianness,
+            length_remaining,
+        }
+    }
+}
+
+impl Context for SizeTrackingEndianness {
+    type Error = speedy::Error;
+
+    fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+}
+
+impl SizeTrackingContext for SizeTrackingEndianness {
+    fn subtract_from_remaining(&mut self, length: usize) -> Result<(), Self::Error> {
+        self.length_remaining = subtract_length(self.length_remaining, length)?;
+        Ok(())
+    }
+
+    fn length_remaining(&self) -> usize {
+        self.length_remaining
+    }
+}
+
+/// The length remaining once length bytes are read, or an error if they
+/// extend past the end of the buffer.
+pub(crate) fn subtract_length(
+    length_remaining: usize,
+    length: usize,
+) -> Result<usize, speedy::Error> {
+    length_remaining.checked_sub(length).ok_or_else(|| {
+        speedy::Error::custom(format!(
+            "read {} bytes with only {} remaining",
+            length, length_remaining
+        ))
+    })
+}
@@ -0,0 +1,281 @@
+use crate::structure::cache_change::CacheChange;
+use crate::structure::change_kind::ChangeKind_t;
+use crate::structure::guid::GUID_t;
+use crate::structure::instance_handle::InstanceHandle_t;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Whether an instance is being written, as seen by a DataReader.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InstanceStateKind {
+    /// There are live writers writing the instance, and it was not disposed.
+    ALIVE,
+
+    /// The instance was explicitly disposed by a writer.
+    NOT_ALIVE_DISPOSED,
+
+    /// The instance was not disposed, but no writer is writing it anymore.
+    NOT_ALIVE_NO_WRITERS,
+}
+
+/// State kept for each instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceState {
+    pub instance_state: InstanceStateKind,
+
+    /// Number of times the instance became ALIVE after being disposed.
+    pub disposed_generation_count: i32,
+
+    /// Number of times the instance became ALIVE after having no writers.
+    pub no_writers_generation_count: i32,
+
+    /// The writers that registered the instance and did not unregister it.
+    writers: BTreeSet<GUID_t>,
+}
+
+impl InstanceState {
+    fn new() -> InstanceState {
+        InstanceState {
+            instance_state: InstanceStateKind::ALIVE,
+            disposed_generation_count: 0,
+            no_writers_generation_count: 0,
+            writers: BTreeSet::new(),
+        }
+    }
+
+    pub fn writers(&self) -> impl Iterator<Item = &GUID_t> {
+        self.writers.iter()
+    }
+
+    /// The writer registers the instance, either explicitly or by writing it.
+    fn register(&mut self, writer_guid: GUID_t) {
+        match self.instance_state {
+            InstanceStateKind::ALIVE => (),
+            InstanceStateKind::NOT_ALIVE_DISPOSED => self.disposed_generation_count += 1,
+            InstanceStateKind::NOT_ALIVE_NO_WRITERS => self.no_writers_generation_count += 1,
+        }
+        self.instance_state = InstanceStateKind::ALIVE;
+        self.writers.insert(writer_guid);
+    }
+
+    fn dispose(&mut self) {
+        self.instance_state = InstanceStateKind::NOT_ALIVE_DISPOSED;
+    }
+
+    fn unregister(&mut self, writer_guid: &GUID_t) {
+        self.writers.remove(writer_guid);
+        if self.writers.is_empty() && self.instance_state == InstanceStateKind::ALIVE {
+            self.instance_state = InstanceStateKind::NOT_ALIVE_NO_WRITERS;
+        }
+    }
+}
+
+/// Bookkeeping of the lifecycle of the instances of a topic with a key, kept by
+/// writers for the instances they register and by readers for the instances
+/// they receive changes of.
+#[derive(Debug, Default)]
+pub struct Instances {
+    instances: BTreeMap<InstanceHandle_t, InstanceState>,
+}
+
+impl Instances {
+    pub fn new() -> Instances {
+        Instances {
+            instances: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, instance_handle: &InstanceHandle_t) -> Option<&InstanceState> {
+        self.instances.get(instance_handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Tells whether the writer registered the instance and did not unregister
+    /// it since.
+    pub fn is_registered(&self, instance_handle: &InstanceHandle_t, writer_guid: &GUID_t) -> bool {
+        self.instances
+            .get(instance_handle)
+            .is_some_and(|instance| instance.writers.contains(writer_guid))
+    }
+
+    /// Registers the instance for the writer, which makes it ALIVE.
+    pub fn register(&mut self, instance_handle: InstanceHandle_t, writer_guid: GUID_t) {
+        self.instances
+            .entry(instance_handle)
+            .or_insert_with(InstanceState::new)
+            .register(writer_guid);
+    }
+
+    /// Applies a change, written or received, to the state of its instance
+    /// and returns the new state. An ALIVE change registers its writer, a
+    /// disposing change makes the instance NOT_ALIVE_DISPOSED, and once the
+    /// last writer unregistered an instance that is still ALIVE it becomes
+    /// NOT_ALIVE_NO_WRITERS.
+    pub fn apply(&mut self, change: &CacheChange) -> InstanceStateKind {
+        let instance = self
+            .instances
+            .entry(change.instance_handle)
+            .or_insert_with(InstanceState::new);
+
+        match change.kind {
+            ChangeKind_t::ALIVE => instance.register(change.writer_guid),
+            ChangeKind_t::NOT_ALIVE_DISPOSED => {
+                instance.writers.insert(change.writer_guid);
+                instance.dispose();
+            }
+            ChangeKind_t::NOT_ALIVE_UNREGISTERED => instance.unregister(&change.writer_guid),
+            ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED => {
+                instance.dispose();
+                instance.unregister(&change.writer_guid);
+            }
+        }
+        instance.instance_state
+    }
+
+    /// Unregisters all the instances of a writer that is gone, e.g. because
+    /// its liveliness was lost.
+    pub fn remove_writer(&mut self, writer_guid: &GUID_t) {
+        for instance in self.instances.values_mut() {
+            instance.unregister(writer_guid);
+        }
+    }
+
+    /// Forgets the instance, once the samples of a NOT_ALIVE instance were
+    /// all taken.
+    pub fn remove(&mut self, instance_handle: &InstanceHandle_t) -> Option<InstanceState> {
+        self.instances.remove(instance_handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::sequence_number::SequenceNumber_t;
+
+    fn writer(key: u8) -> GUID_t {
+        GUID_t {
+            guid_prefix: crate::structure::guid_prefix::GuidPrefix_t {
+                entity_key: [key; 12],
+            },
+            ..GUID_t::GUID_UNKNOWN
+        }
+    }
+
+    fn change(kind: ChangeKind_t, writer_guid: GUID_t) -> CacheChange {
+        CacheChange::new(
+            kind,
+            writer_guid,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(1),
+            None,
+        )
+    }
+
+    #[test]
+    fn dispose_and_write_again() {
+        let mut instances = Instances::new();
+        let instance_handle = InstanceHandle_t::default();
+
+        assert_eq!(
+            InstanceStateKind::ALIVE,
+            instances.apply(&change(ChangeKind_t::ALIVE, writer(1)))
+        );
+        assert_eq!(
+            InstanceStateKind::NOT_ALIVE_DISPOSED,
+            instances.apply(&change(ChangeKind_t::NOT_ALIVE_DISPOSED, writer(1)))
+        );
+        assert_eq!(
+            InstanceStateKind::ALIVE,
+            instances.apply(&change(ChangeKind_t::ALIVE, writer(1)))
+        );
+
+        let instance = instances.get(&instance_handle).unwrap();
+        assert_eq!(1, instance.disposed_generation_count);
+        assert_eq!(0, instance.no_writers_generation_count);
+    }
+
+    #[test]
+    fn unregistered_by_all_writers() {
+        let mut instances = Instances::new();
+        let instance_handle = InstanceHandle_t::default();
+
+        instances.apply(&change(ChangeKind_t::ALIVE, writer(1)));
+        instances.register(instance_handle, writer(2));
+        assert!(instances.is_registered(&instance_handle, &writer(2)));
+
+        assert_eq!(
+            InstanceStateKind::ALIVE,
+            instances.apply(&change(ChangeKind_t::NOT_ALIVE_UNREGISTERED, writer(1)))
+        );
+        assert!(!instances.is_registered(&instance_handle, &writer(1)));
+        assert_eq!(
+            InstanceStateKind::NOT_ALIVE_NO_WRITERS,
+            instances.apply(&change(ChangeKind_t::NOT_ALIVE_UNREGISTERED, writer(2)))
+        );
+
+        assert_eq!(
+            InstanceStateKind::ALIVE,
+            instances.apply(&change(ChangeKind_t::ALIVE, writer(2)))
+        );
+        let instance = instances.get(&instance_handle).unwrap();
+        assert_eq!(0, instance.disposed_generation_count);
+        assert_eq!(1, instance.no_writers_generation_count);
+        assert_eq!(vec![&writer(2)], instance.writers().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn disposed_instance_stays_disposed_when_unregistered() {
+        let mut instances = Instances::new();
+
+        instances.apply(&change(ChangeKind_t::ALIVE, writer(1)));
+        assert_eq!(
+            InstanceStateKind::NOT_ALIVE_DISPOSED,
+            instances.apply(&change(
+                ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED,
+                writer(1)
+            ))
+        );
+        assert_eq!(
+            0,
+            instances
+                .get(&InstanceHandle_t::default())
+                .unwrap()
+                .writers()
+                .count()
+        );
+    }
+
+    #[test]
+    fn lost_writer_is_unregistered() {
+        let mut instances = Instances::new();
+        let other_instance = InstanceHandle_t {
+            entity_key: [0x01; 16],
+        };
+
+        instances.apply(&change(ChangeKind_t::ALIVE, writer(1)));
+        instances.register(other_instance, writer(1));
+        instances.register(other_instance, writer(2));
+        instances.remove_writer(&writer(1));
+
+        assert_eq!(
+            InstanceStateKind::NOT_ALIVE_NO_WRITERS,
+            instances
+                .get(&InstanceHandle_t::default())
+                .unwrap()
+                .instance_state
+        );
+        assert_eq!(
+            InstanceStateKind::ALIVE,
+            instances.get(&other_instance).unwrap().instance_state
+        );
+
+        assert!(instances.remove(&InstanceHandle_t::default()).is_some());
+        assert_eq!(1, instances.len());
+    }
+}
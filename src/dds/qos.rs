@@ -0,0 +1,104 @@
+/// Special value of the resource limits meaning that there is no limit.
+pub const LENGTH_UNLIMITED: i32 = -1;
+
+/// Controls the behavior of the Service when the value of an instance changes
+/// before it is finally communicated to some of its existing DataReader
+/// entities.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HistoryQosPolicy {
+    /// The Service will only attempt to keep the latest values of the
+    /// instance and discard the older ones. The depth indicates how many
+    /// values are kept for each instance and must be positive.
+    KeepLast { depth: i32 },
+
+    /// The Service will attempt to keep all the values of the instance until
+    /// they can be delivered, within the limits of the ResourceLimitsQosPolicy.
+    KeepAll,
+}
+
+impl Default for HistoryQosPolicy {
+    fn default() -> Self {
+        HistoryQosPolicy::KeepLast { depth: 1 }
+    }
+}
+
+/// Controls whether the Service keeps the data-samples written by a
+/// DataWriter for the DataReaders that join after they were written.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DurabilityQosPolicy {
+    /// The Service does not keep the data-samples once they were
+    /// acknowledged by the DataReaders that were there when they were written.
+    #[default]
+    Volatile,
+
+    /// The Service keeps the data-samples in the history of the DataWriter,
+    /// for the late-joining DataReaders.
+    TransientLocal,
+
+    /// The Service keeps the data-samples in memory beyond the lifetime of the
+    /// DataWriter.
+    Transient,
+
+    /// The Service keeps the data-samples in permanent storage.
+    Persistent,
+}
+
+/// Controls whether the DataWriter sends the data-samples from the write
+/// call, or from a task of its own.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PublishModeQosPolicy {
+    /// The write call sends the data-sample before returning, in the task of
+    /// the caller.
+    #[default]
+    Synchronous,
+
+    /// The write call returns once the data-sample is in the history of the
+    /// DataWriter, and a task of the DataWriter sends it, through its flow
+    /// controller if it has one.
+    Asynchronous,
+}
+
+/// Specifies the resources that the Service can consume in order to meet the
+/// requested QoS. Each limit is either positive or LENGTH_UNLIMITED.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceLimitsQosPolicy {
+    /// Maximum number of data-samples, from all the instances, that can be
+    /// held.
+    pub max_samples: i32,
+
+    /// Maximum number of instances that can be managed.
+    pub max_instances: i32,
+
+    /// Maximum number of data-samples that can be held for any single
+    /// instance.
+    pub max_samples_per_instance: i32,
+
+    /// Maximum size in octets of the serialized payload of a data-sample. The
+    /// fragmented data-samples announcing a larger one are not reassembled.
+    pub max_sample_size: i32,
+}
+
+impl ResourceLimitsQosPolicy {
+    pub const UNLIMITED: ResourceLimitsQosPolicy = ResourceLimitsQosPolicy {
+        max_samples: LENGTH_UNLIMITED,
+        max_instances: LENGTH_UNLIMITED,
+        max_samples_per_instance: LENGTH_UNLIMITED,
+        max_sample_size: LENGTH_UNLIMITED,
+    };
+}
+
+impl Default for ResourceLimitsQosPolicy {
+    fn default() -> Self {
+        ResourceLimitsQosPolicy::UNLIMITED
+    }
+}
+
+/// Tells whether `count` items have reached the `limit`, which is either a
+/// positive number or LENGTH_UNLIMITED.
+pub(crate) fn limit_reached(count: usize, limit: i32) -> bool {
+    limit != LENGTH_UNLIMITED && count >= limit as usize
+}
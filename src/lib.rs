@@ -14,9 +14,14 @@ extern crate tokio_util;
 mod serialization_test;
 #[macro_use]
 mod checked_impl;
-mod behavior;
+#[macro_use]
+mod serde_text_impl;
+#[macro_use]
+mod arbitrary_impl;
+pub mod behavior;
 pub mod common;
-mod dds;
+pub mod dds;
 mod discovery;
 pub mod messages;
 pub mod structure;
+pub mod transport;
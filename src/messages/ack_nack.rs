@@ -11,6 +11,7 @@ use speedy::{Readable, Writable};
 /// missing. This Submessage can be used to do both positive
 /// and negative acknowledgments
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AckNack {
     /// Identifies the Reader entity that acknowledges receipt of certain
     /// sequence numbers and/or requests to receive certain sequence numbers.
@@ -36,6 +37,22 @@ pub struct AckNack {
     pub count: Count_t,
 }
 
+arbitrary_impl!(
+    AckNack,
+    (
+        any::<EntityId_t>(),
+        any::<EntityId_t>(),
+        any::<SequenceNumberSet_t>(),
+        any::<Count_t>()
+    )
+        .prop_map(|(reader_id, writer_id, reader_sn_state, count)| AckNack {
+            reader_id,
+            writer_id,
+            reader_sn_state,
+            count,
+        })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,4 +80,6 @@ mod tests {
               0x00, 0x00, 0x00, 0x00,
               0x00, 0x00, 0x00, 0x01]
     });
+
+    serialization_property_test!(type = AckNack);
 }
@@ -1,4 +1,4 @@
-use crate::common::size_tracking_context::SizeTrackingContext;
+use crate::common::size_tracking_context::{subtract_length, SizeTrackingContext};
 use crate::messages::data_submessage_flags::DataSubmessageFlags;
 use crate::messages::submessage_elements::parameter_list::ParameterList;
 use crate::messages::submessage_elements::serialized_payload::SerializedPayload;
@@ -23,6 +23,10 @@ impl DataContext {
             length_remaining,
         }
     }
+
+    pub fn flags(&self) -> DataSubmessageFlags {
+        self.flags
+    }
 }
 
 impl Context for DataContext {
@@ -34,8 +38,9 @@ impl Context for DataContext {
 }
 
 impl SizeTrackingContext for DataContext {
-    fn subtract_from_remaining(&mut self, length: usize) {
-        self.length_remaining -= length;
+    fn subtract_from_remaining(&mut self, length: usize) -> Result<(), Self::Error> {
+        self.length_remaining = subtract_length(self.length_remaining, length)?;
+        Ok(())
     }
 
     fn length_remaining(&self) -> usize {
@@ -51,6 +56,7 @@ impl SizeTrackingContext for DataContext {
 /// include both changes in value as well as changes to the lifecycle
 /// of the data-object.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Data {
     /// Identifies the RTPS Reader entity that is being informed of the change
     /// to the data-object.
@@ -89,22 +95,22 @@ impl<'a> Readable<'a, DataContext> for Data {
 
         // skip over "extra flags" and "octets to inline qos"
         reader.skip_bytes(4)?;
-        reader.context_mut().subtract_from_remaining(4);
+        reader.context_mut().subtract_from_remaining(4)?;
 
         let reader_id: EntityId_t = reader.read_value()?;
         reader.context_mut().subtract_from_remaining(
             <EntityId_t as Readable<DataContext>>::minimum_bytes_needed()
-        );
+        )?;
 
         let writer_id: EntityId_t = reader.read_value()?;
         reader.context_mut().subtract_from_remaining(
             <EntityId_t as Readable<DataContext>>::minimum_bytes_needed()
-        );
+        )?;
 
         let writer_sn: SequenceNumber_t = reader.read_value()?;
         reader.context_mut().subtract_from_remaining(
             <SequenceNumber_t as Readable<DataContext>>::minimum_bytes_needed()
-        );
+        )?;
 
         let inline_qos: Option<ParameterList> =
             match flags.inline_qos() {
@@ -160,3 +166,111 @@ impl<C: Context> Writable<C> for Data {
         Ok(())
     }
 }
+
+arbitrary_impl!(
+    Data,
+    (
+        any::<EntityId_t>(),
+        any::<EntityId_t>(),
+        any::<SequenceNumber_t>(),
+        any::<Option<ParameterList>>(),
+        any::<Option<SerializedPayload>>()
+    )
+        .prop_map(
+            |(reader_id, writer_id, writer_sn, inline_qos, serialized_payload)| Data {
+                reader_id,
+                writer_id,
+                writer_sn,
+                inline_qos,
+                serialized_payload,
+            }
+        )
+);
+
+/// Flags of a generated Data submessage. The endianness and the kind of the
+/// payload are taken from the random bits, the rest matches the elements
+/// present in the submessage.
+#[cfg(any(test, feature = "proptest"))]
+pub(crate) fn arbitrary_flags(data: &Data, random: u8) -> SubmessageFlag {
+    let mut flags = SubmessageFlag {
+        flags: random & 0x01,
+    };
+    if data.inline_qos.is_some() {
+        flags.set_flag(DataSubmessageFlags::INLINE_QOS_FLAG_MASK);
+    }
+    if data.serialized_payload.is_some() {
+        if random & DataSubmessageFlags::DATA_FLAG_MASK != 0 {
+            flags.set_flag(DataSubmessageFlags::DATA_FLAG_MASK);
+        } else {
+            flags.set_flag(DataSubmessageFlags::KEY_FLAG_MASK);
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::submessage_elements::serialized_payload::SerializedPayloadContent;
+    use crate::structure::parameter_id::ParameterId;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parameter_list_payload_uses_endianness_of_representation() {
+        // A big endian submessage carrying a PL_CDR_LE payload.
+        let bytes = [
+            0x00, 0x00, 0x00, 0x10, // extra flags, octets to inline QoS
+            0x00, 0x01, 0x00, 0xC7, // reader id
+            0x00, 0x01, 0x00, 0xC2, // writer id
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // writer sn
+            0x00, 0x03, 0x00, 0x00, // PL_CDR_LE
+            0x15, 0x00, 0x04, 0x00, 0x02, 0x04, 0x00, 0x00, // PID_PROTOCOL_VERSION
+            0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+        ];
+        let flags = SubmessageFlag {
+            flags: DataSubmessageFlags::DATA_FLAG_MASK,
+        };
+
+        let data =
+            Data::read_from_buffer_with_ctx(DataContext::new(flags, bytes.len()), &bytes).unwrap();
+        assert_eq!(bytes.to_vec(), data.write_to_vec_with_ctx(flags).unwrap());
+
+        let payload = data.serialized_payload.unwrap();
+        match payload.content {
+            SerializedPayloadContent::ParameterList(parameter_list) => {
+                let parameter = parameter_list.iter().next().unwrap();
+                assert_eq!(ParameterId::PID_PROTOCOL_VERSION, parameter.get_id());
+                assert_eq!(&[0x02, 0x04, 0x00, 0x00], parameter.get_value());
+            }
+            content => panic!("unexpected payload {:?}", content),
+        }
+    }
+
+    #[test]
+    fn submessage_shorter_than_its_elements_is_rejected() {
+        let bytes = [
+            0x00, 0x00, 0x10, 0x00, // extra flags, octets to inline QoS
+            0x00, 0x00, 0x01, 0x07, // reader id
+            0x00, 0x00, 0x01, 0x02, // writer id
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // writer sn
+        ];
+        let flags = SubmessageFlag { flags: 0x01 };
+
+        // The submessage header tells the writer sn is past the end.
+        let context = DataContext::new(flags, 12);
+        assert!(Data::read_from_buffer_with_ctx(context, &bytes).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn serialize_deserialize(original in any::<Data>(), random in any::<u8>()) {
+            let flags = arbitrary_flags(&original, random);
+
+            let serialized = original.write_to_vec_with_ctx(flags).unwrap();
+            let context = DataContext::new(flags, serialized.len());
+            let deserialized = Data::read_from_buffer_with_ctx(context, &serialized).unwrap();
+
+            prop_assert_eq!(original, deserialized);
+        }
+    }
+}
@@ -1,52 +1,234 @@
+use crate::common::size_tracking_context::SizeTrackingContext;
+use crate::messages::data::DataContext;
 use crate::messages::fragment_number::FragmentNumber_t;
 use crate::messages::submessage_elements::parameter_list::ParameterList;
-use crate::messages::submessage_elements::serialized_payload::SerializedPayload;
 use crate::structure::entity_id::EntityId_t;
 use crate::structure::sequence_number::SequenceNumber_t;
+use speedy::{Context, Readable, Reader, Writable, Writer};
 
 /// The DataFrag Submessage extends the Data Submessage by enabling the
 /// serializedData to be fragmented and sent as multiple DataFrag Submessages.
 /// The fragments contained in the DataFrag Submessages are then re-assembled by
 /// the RTPS Reader.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataFrag {
     /// Identifies the RTPS Reader entity that is being informed of the change
     /// to the data-object.
-    reader_id: EntityId_t,
+    pub reader_id: EntityId_t,
 
     /// Identifies the RTPS Writer entity that made the change to the
     /// data-object.
-    writer_id: EntityId_t,
+    pub writer_id: EntityId_t,
 
     /// Uniquely identifies the change and the relative order for all changes
     /// made by the RTPS Writer identified by the writerGuid.
     /// Each change gets a consecutive sequence number.
     /// Each RTPS Writer maintains is own sequence number.
-    writer_sn: SequenceNumber_t,
+    pub writer_sn: SequenceNumber_t,
 
     /// Indicates the starting fragment for the series of fragments in
     /// serialized_data. Fragment numbering starts with number 1.
-    fragment_starting_num: FragmentNumber_t,
+    pub fragment_starting_num: FragmentNumber_t,
 
     /// The number of consecutive fragments contained in this Submessage,
     /// starting at fragment_starting_num.
-    fragments_in_submessage: u16,
+    pub fragments_in_submessage: u16,
 
     /// The total size in bytes of the original data before fragmentation.
-    data_size: u32,
+    pub data_size: u32,
 
     /// The size of an individual fragment in bytes. The maximum fragment size
     /// equals 64K.
-    fragment_size: u16,
+    pub fragment_size: u16,
 
     /// Contains QoS that may affect the interpretation of the message.
     /// Present only if the InlineQosFlag is set in the header.
-    inline_qos: ParameterList,
+    pub inline_qos: Option<ParameterList>,
 
     /// Encapsulation of a consecutive series of fragments, starting at
     /// fragment_starting_num for a total of fragments_in_submessage.
     /// Represents part of the new value of the data-object
-    /// after the change. Present only if either the DataFlag or the KeyFlag are
-    /// set in the header. Present only if DataFlag is set in the header.
-    serialized_payload: SerializedPayload,
+    /// after the change.
+    ///
+    /// The fragments are kept as sent; only the first one starts with the
+    /// SerializedPayloadHeader of the reassembled data.
+    pub serialized_payload: Box<[u8]>,
+}
+
+impl DataFrag {
+    /// Indicates that the inline_qos is present.
+    pub const INLINE_QOS_FLAG_MASK: u8 = 0x02;
+
+    /// Indicates that the fragments are of the serialized key of the
+    /// data-object rather than of its value.
+    pub const KEY_FLAG_MASK: u8 = 0x04;
+}
+
+impl<'a> Readable<'a, DataContext> for DataFrag {
+    #[inline]
+    fn read_from<R: Reader<'a, DataContext>>(
+        reader: &mut R,
+    ) -> Result<Self, <DataContext as Context>::Error> {
+        let flags = reader.context().flags();
+
+        // skip over "extra flags" and "octets to inline qos"
+        reader.skip_bytes(4)?;
+
+        let reader_id: EntityId_t = reader.read_value()?;
+        let writer_id: EntityId_t = reader.read_value()?;
+        let writer_sn: SequenceNumber_t = reader.read_value()?;
+        let fragment_starting_num: FragmentNumber_t = reader.read_value()?;
+        let fragments_in_submessage = reader.read_u16()?;
+        let fragment_size = reader.read_u16()?;
+        let data_size = reader.read_u32()?;
+        reader.context_mut().subtract_from_remaining(32)?;
+
+        let inline_qos = if flags.inline_qos() {
+            let parameter_list: ParameterList = reader.read_value()?;
+            Some(parameter_list)
+        } else {
+            None
+        };
+
+        let length = reader.context().length_remaining();
+        let mut serialized_payload = vec![0; length].into_boxed_slice();
+        reader.read_bytes(&mut serialized_payload)?;
+        reader.context_mut().subtract_from_remaining(length)?;
+
+        Ok(DataFrag {
+            reader_id,
+            writer_id,
+            writer_sn,
+            fragment_starting_num,
+            fragments_in_submessage,
+            data_size,
+            fragment_size,
+            inline_qos,
+            serialized_payload,
+        })
+    }
+}
+
+impl<C: Context> Writable<C> for DataFrag {
+    #[inline]
+    fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+        writer.write_u8(0)?;
+        writer.write_u8(0)?;
+
+        // Write "octets to inline QoS", which will always be 28 bytes.
+        writer.write_u16(28)?;
+
+        writer.write_value(&self.reader_id)?;
+        writer.write_value(&self.writer_id)?;
+        writer.write_value(&self.writer_sn)?;
+        writer.write_value(&self.fragment_starting_num)?;
+        writer.write_u16(self.fragments_in_submessage)?;
+        writer.write_u16(self.fragment_size)?;
+        writer.write_u32(self.data_size)?;
+
+        if let Some(ref inline_qos) = self.inline_qos {
+            writer.write_value(inline_qos)?;
+        }
+
+        writer.write_bytes(&self.serialized_payload)?;
+
+        Ok(())
+    }
+}
+
+arbitrary_impl!(
+    DataFrag,
+    (
+        (
+            any::<EntityId_t>(),
+            any::<EntityId_t>(),
+            any::<SequenceNumber_t>()
+        ),
+        (
+            any::<FragmentNumber_t>(),
+            any::<u16>(),
+            any::<u32>(),
+            any::<u16>()
+        ),
+        any::<Option<ParameterList>>(),
+        proptest::collection::vec(any::<u8>(), 0..64)
+    )
+        .prop_map(
+            |(
+                (reader_id, writer_id, writer_sn),
+                (fragment_starting_num, fragments_in_submessage, data_size, fragment_size),
+                inline_qos,
+                serialized_payload,
+            )| DataFrag {
+                reader_id,
+                writer_id,
+                writer_sn,
+                fragment_starting_num,
+                fragments_in_submessage,
+                data_size,
+                fragment_size,
+                inline_qos,
+                serialized_payload: serialized_payload.into_boxed_slice(),
+            }
+        )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::submessage_flag::SubmessageFlag;
+    use proptest::prelude::*;
+
+    #[test]
+    fn read_first_fragment() {
+        let bytes = [
+            0x00, 0x00, 0x1C, 0x00, // extra flags, octets to inline QoS
+            0x00, 0x00, 0x01, 0x07, // reader id
+            0x00, 0x00, 0x01, 0x02, // writer id
+            0x00, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, // writer sn
+            0x01, 0x00, 0x00, 0x00, // fragment starting num
+            0x01, 0x00, 0x08, 0x00, // fragments in submessage, fragment size
+            0x0C, 0x00, 0x00, 0x00, // data size
+            0x00, 0x01, 0x00, 0x00, 0x2A, 0x00, 0x00, 0x00, // first fragment
+        ];
+        let flags = SubmessageFlag { flags: 0x01 };
+
+        let data_frag =
+            DataFrag::read_from_buffer_with_ctx(DataContext::new(flags, bytes.len()), &bytes)
+                .unwrap();
+
+        assert_eq!(
+            DataFrag {
+                reader_id: "00.00.01.07".parse().unwrap(),
+                writer_id: "00.00.01.02".parse().unwrap(),
+                writer_sn: SequenceNumber_t::from(7),
+                fragment_starting_num: FragmentNumber_t::from(1),
+                fragments_in_submessage: 1,
+                data_size: 12,
+                fragment_size: 8,
+                inline_qos: None,
+                serialized_payload: vec![0x00, 0x01, 0x00, 0x00, 0x2A, 0x00, 0x00, 0x00]
+                    .into_boxed_slice(),
+            },
+            data_frag
+        );
+        assert_eq!(bytes.to_vec(), data_frag.write_to_vec_with_ctx(flags).unwrap());
+    }
+
+    proptest! {
+        #[test]
+        fn serialize_deserialize(original in any::<DataFrag>(), little_endian in any::<bool>()) {
+            let mut flags = SubmessageFlag { flags: little_endian as u8 };
+            if original.inline_qos.is_some() {
+                flags.set_flag(0x02);
+            }
+
+            let serialized = original.write_to_vec_with_ctx(flags).unwrap();
+            let context = DataContext::new(flags, serialized.len());
+            let deserialized = DataFrag::read_from_buffer_with_ctx(context, &serialized).unwrap();
+
+            prop_assert_eq!(original, deserialized);
+        }
+    }
 }
@@ -17,6 +17,7 @@ use std::convert::From;
     FromPrimitive,
     ToPrimitive,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FragmentNumber_t(u32);
 
 impl Default for FragmentNumber_t {
@@ -42,6 +43,11 @@ checked_impl!(CheckedSub, checked_sub, FragmentNumber_t);
 checked_impl!(CheckedMul, checked_mul, FragmentNumber_t);
 checked_impl!(CheckedDiv, checked_div, FragmentNumber_t);
 
+arbitrary_impl!(
+    FragmentNumber_t,
+    any::<u32>().prop_map(FragmentNumber_t::from)
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,7 +61,7 @@ mod tests {
     fn conversion_test() {
         assert_eq!(
             u32::from(FragmentNumber_t::from(77)),
-            FragmentNumber_t::from(77).into()
+            Into::<u32>::into(FragmentNumber_t::from(77))
         );
     }
 
@@ -78,4 +84,6 @@ mod tests {
         le = [0xEF, 0xBE, 0xAD, 0xDE],
         be = [0xDE, 0xAD, 0xBE, 0xEF]
     });
+
+    serialization_property_test!(type = FragmentNumber_t);
 }
@@ -31,9 +31,11 @@ mod tests {
         })(),
         le = [0xE8, 0x03, 0x00, 0x00,
               0x20, 0x00, 0x00, 0x00,
-              0x5A, 0x25, 0x00, 0x00],
+              0x00, 0x00, 0xA4, 0x5A],
         be = [0x00, 0x00, 0x03, 0xE8,
               0x00, 0x00, 0x00, 0x20,
-              0x00, 0x00, 0x25, 0x5A]
+              0x5A, 0xA4, 0x00, 0x00]
     });
+
+    serialization_property_test!(type = FragmentNumberSet_t);
 }
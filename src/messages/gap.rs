@@ -8,6 +8,7 @@ use speedy::{Readable, Writable};
 /// is no longer relevant. The set may be a contiguous range of
 /// sequence numbers or a specific set of sequence numbers.
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gap {
     /// Identifies the Reader Entity that is being informed of the
     /// irrelevance of a set of sequence numbers.
@@ -29,6 +30,22 @@ pub struct Gap {
     pub gap_list: SequenceNumberSet_t,
 }
 
+arbitrary_impl!(
+    Gap,
+    (
+        any::<EntityId_t>(),
+        any::<EntityId_t>(),
+        any::<SequenceNumber_t>(),
+        any::<SequenceNumberSet_t>()
+    )
+        .prop_map(|(reader_id, writer_id, gap_start, gap_list)| Gap {
+            reader_id,
+            writer_id,
+            gap_start,
+            gap_list,
+        })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,4 +74,6 @@ mod tests {
               0x00, 0x00, 0x00, 0x07,
               0x00, 0x00, 0x00, 0x00]
     });
+
+    serialization_property_test!(type = Gap);
 }
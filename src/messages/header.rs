@@ -6,6 +6,7 @@ use crate::structure::guid_prefix::GuidPrefix_t;
 use speedy::{Readable, Writable};
 
 #[derive(Debug, Readable, Writable, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     pub protocol_id: ProtocolId_t,
     pub protocol_version: ProtocolVersion_t,
@@ -31,6 +32,23 @@ impl Validity for Header {
     }
 }
 
+// Generates only valid headers, i.e. ones a MessageReceiver accepts.
+arbitrary_impl!(
+    Header,
+    (
+        1..=ProtocolVersion_t::PROTOCOLVERSION.major,
+        any::<u8>(),
+        any::<VendorId_t>(),
+        any::<GuidPrefix_t>()
+    )
+        .prop_map(|(major, minor, vendor_id, guid_prefix)| Header {
+            protocol_id: ProtocolId_t::PROTOCOL_RTPS,
+            protocol_version: ProtocolVersion_t { major, minor },
+            vendor_id,
+            guid_prefix,
+        })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,4 +92,6 @@ mod tests {
               0x00, 0x00, 0x00, 0x00,
               0x00, 0x00]
     });
+
+    serialization_property_test!(type = Header);
 }
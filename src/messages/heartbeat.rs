@@ -8,6 +8,7 @@ use speedy::{Readable, Writable};
 /// is no longer relevant. The set may be a contiguous range of
 /// sequence numbers or a specific set of sequence numbers.
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Heartbeat {
     /// Identifies the Reader Entity that is being informed of the
     /// availability of a set of sequence numbers.
@@ -37,6 +38,35 @@ pub struct Heartbeat {
     pub count: Count_t,
 }
 
+impl Heartbeat {
+    /// Indicates that the Reader is not required to respond to the Heartbeat.
+    pub const FINAL_FLAG_MASK: u8 = 0x02;
+
+    /// Indicates that the DDS DataWriter associated with the RTPS Writer of
+    /// the message has manually asserted its LIVELINESS.
+    pub const LIVELINESS_FLAG_MASK: u8 = 0x04;
+}
+
+arbitrary_impl!(
+    Heartbeat,
+    (
+        any::<EntityId_t>(),
+        any::<EntityId_t>(),
+        any::<SequenceNumber_t>(),
+        any::<SequenceNumber_t>(),
+        any::<Count_t>()
+    )
+        .prop_map(
+            |(reader_id, writer_id, first_sn, last_sn, count)| Heartbeat {
+                reader_id,
+                writer_id,
+                first_sn,
+                last_sn,
+                count,
+            }
+        )
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,4 +96,6 @@ mod tests {
               0x00, 0x00, 0x00, 0x07,
               0x00, 0x00, 0x00, 0x09]
     });
+
+    serialization_property_test!(type = Heartbeat);
 }
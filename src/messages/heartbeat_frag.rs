@@ -11,6 +11,7 @@ use speedy::{Readable, Writable};
 ///
 /// Once all fragments are available, a regular Heartbeat message is used.
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HeartbeatFrag {
     /// Identifies the Reader Entity that is being informed of the availability
     /// of fragments. Can be set to ENTITYID_UNKNOWN to indicate all readers for
@@ -35,6 +36,26 @@ pub struct HeartbeatFrag {
     pub count: Count_t,
 }
 
+arbitrary_impl!(
+    HeartbeatFrag,
+    (
+        any::<EntityId_t>(),
+        any::<EntityId_t>(),
+        any::<SequenceNumber_t>(),
+        any::<FragmentNumber_t>(),
+        any::<Count_t>()
+    )
+        .prop_map(
+            |(reader_id, writer_id, writer_sn, last_fragment_num, count)| HeartbeatFrag {
+                reader_id,
+                writer_id,
+                writer_sn,
+                last_fragment_num,
+                count,
+            }
+        )
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,4 +83,6 @@ mod tests {
               0x00, 0x00, 0x00, 0x63,
               0x00, 0x00, 0x00, 0x06]
     });
+
+    serialization_property_test!(type = HeartbeatFrag);
 }
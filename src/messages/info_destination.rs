@@ -5,6 +5,7 @@ use speedy::{Readable, Writable};
 /// to modify the GuidPrefix used to interpret the Reader entityIds
 /// appearing in the Submessages that follow it.
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InfoDestination {
     /// Provides the GuidPrefix that should be used to reconstruct the GUIDs
     /// of all the RTPS Reader entities whose EntityIds appears
@@ -12,6 +13,11 @@ pub struct InfoDestination {
     pub guid_prefix: GuidPrefix_t,
 }
 
+arbitrary_impl!(
+    InfoDestination,
+    any::<GuidPrefix_t>().prop_map(|guid_prefix| InfoDestination { guid_prefix })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,4 +39,6 @@ mod tests {
               0x7E, 0x07, 0x00, 0x00,
               0x01, 0x00, 0x00, 0x00]
     });
+
+    serialization_property_test!(type = InfoDestination);
 }
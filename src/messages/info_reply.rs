@@ -4,6 +4,7 @@ use crate::structure::locator::LocatorList_t;
 /// It contains explicit information on where to send a reply
 /// to the Submessages that follow it within the same message.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InfoReply {
     /// Indicates an alternative set of unicast addresses that
     /// the Writershould use to reach the Readers when
@@ -17,3 +18,18 @@ pub struct InfoReply {
     /// Only present when the MulticastFlag is set.
     pub multicast_locator_list: Option<LocatorList_t>,
 }
+
+arbitrary_impl!(
+    InfoReply,
+    (
+        proptest::collection::vec(any::<crate::structure::locator::Locator_t>(), 0..4),
+        proptest::option::of(proptest::collection::vec(
+            any::<crate::structure::locator::Locator_t>(),
+            0..4
+        ))
+    )
+        .prop_map(|(unicast_locator_list, multicast_locator_list)| InfoReply {
+            unicast_locator_list,
+            multicast_locator_list,
+        })
+);
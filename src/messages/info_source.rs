@@ -7,6 +7,7 @@ use crate::structure::guid_prefix::GuidPrefix_t;
 /// This message modifies the logical source of the Submessages
 /// that follow.
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InfoSource {
     /// Indicates the protocol used to encapsulate subsequent Submessages
     pub protocol_version: ProtocolVersion_t,
@@ -20,6 +21,20 @@ pub struct InfoSource {
     pub guid_prefix: GuidPrefix_t,
 }
 
+arbitrary_impl!(
+    InfoSource,
+    (
+        any::<ProtocolVersion_t>(),
+        any::<VendorId_t>(),
+        any::<GuidPrefix_t>()
+    )
+        .prop_map(|(protocol_version, vendor_id, guid_prefix)| InfoSource {
+            protocol_version,
+            vendor_id,
+            guid_prefix,
+        })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -47,4 +62,6 @@ mod tests {
               0x7E, 0x07, 0x00, 0x00,
               0x01, 0x00, 0x00, 0x00]
     });
+
+    serialization_property_test!(type = InfoSource);
 }
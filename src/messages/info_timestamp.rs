@@ -3,6 +3,7 @@ use crate::structure::time::Timestamp;
 /// This message modifies the logical source of the Submessages
 /// that follow.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InfoTimestamp {
     /// Contains the timestamp that should be used to interpret the
     /// subsequent Submessages
@@ -10,3 +11,8 @@ pub struct InfoTimestamp {
     /// Present only if the InvalidateFlag is not set in the header.
     pub timestamp: Option<Timestamp>,
 }
+
+arbitrary_impl!(
+    InfoTimestamp,
+    any::<Option<Timestamp>>().prop_map(|timestamp| InfoTimestamp { timestamp })
+);
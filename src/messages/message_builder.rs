@@ -0,0 +1,185 @@
+use crate::messages::header::Header;
+use crate::messages::info_destination::InfoDestination;
+use crate::messages::submessage::EntitySubmessage;
+use crate::messages::submessage_flag::SubmessageFlag;
+use crate::messages::submessage_header::SubmessageHeader;
+use crate::messages::submessage_kind::SubmessageKind;
+use crate::structure::guid_prefix::GuidPrefix_t;
+use crate::structure::time::Time_t;
+use bytes::Bytes;
+use speedy::{Endianness, Writable};
+
+/// Serializes an RTPS Message: the Header followed by the Submessages, each
+/// one written with the endianness given by its flags.
+pub struct MessageBuilder {
+    buffer: Vec<u8>,
+    endianness_flag: SubmessageFlag,
+}
+
+impl MessageBuilder {
+    /// Starts a message from the participant with the GuidPrefix_t. The
+    /// Submessages that carry no flags of their own are written in little
+    /// endian.
+    pub fn new(guid_prefix: GuidPrefix_t) -> MessageBuilder {
+        let buffer = Header::new(guid_prefix)
+            .write_to_vec_with_ctx(Endianness::LittleEndian)
+            .expect("the header is always serializable");
+
+        MessageBuilder {
+            buffer,
+            endianness_flag: SubmessageFlag { flags: 0x01 },
+        }
+    }
+
+    /// Sets the destination of the Submessages that follow.
+    pub fn info_destination(&mut self, guid_prefix: GuidPrefix_t) -> Result<(), speedy::Error> {
+        let content =
+            InfoDestination { guid_prefix }.write_to_vec_with_ctx(self.endianness_flag)?;
+        self.submessage(SubmessageKind::INFO_DST, self.endianness_flag, content)
+    }
+
+    /// Sets the source timestamp of the Submessages that follow, or
+    /// invalidates it if there is none.
+    pub fn info_timestamp(&mut self, timestamp: Option<Time_t>) -> Result<(), speedy::Error> {
+        let mut flags = self.endianness_flag;
+        let content = match timestamp {
+            Some(timestamp) => timestamp.write_to_vec_with_ctx(flags)?,
+            None => {
+                flags.set_flag(0x02);
+                vec![]
+            }
+        };
+        self.submessage(SubmessageKind::INFO_TS, flags, content)
+    }
+
+    pub fn entity_submessage(
+        &mut self,
+        submessage: &EntitySubmessage,
+    ) -> Result<(), speedy::Error> {
+        let endianness_flag = self.endianness_flag;
+        let (submessage_id, flags, content) = match submessage {
+            EntitySubmessage::AckNack(ack_nack, flags) => (
+                SubmessageKind::ACKNACK,
+                *flags,
+                ack_nack.write_to_vec_with_ctx(*flags)?,
+            ),
+            EntitySubmessage::Data(data, flags) => (
+                SubmessageKind::DATA,
+                *flags,
+                data.write_to_vec_with_ctx(*flags)?,
+            ),
+            EntitySubmessage::DataFrag(data_frag, flags) => (
+                SubmessageKind::DATA_FRAG,
+                *flags,
+                data_frag.write_to_vec_with_ctx(*flags)?,
+            ),
+            EntitySubmessage::Gap(gap) => (
+                SubmessageKind::GAP,
+                endianness_flag,
+                gap.write_to_vec_with_ctx(endianness_flag)?,
+            ),
+            EntitySubmessage::Heartbeat(heartbeat, flags) => (
+                SubmessageKind::HEARTBEAT,
+                *flags,
+                heartbeat.write_to_vec_with_ctx(*flags)?,
+            ),
+            EntitySubmessage::HeartbeatFrag(heartbeat_frag) => (
+                SubmessageKind::HEARTBEAT_FRAG,
+                endianness_flag,
+                heartbeat_frag.write_to_vec_with_ctx(endianness_flag)?,
+            ),
+            EntitySubmessage::NackFrag(nack_frag) => (
+                SubmessageKind::NACK_FRAG,
+                endianness_flag,
+                nack_frag.write_to_vec_with_ctx(endianness_flag)?,
+            ),
+        };
+        self.submessage(submessage_id, flags, content)
+    }
+
+    pub fn build(self) -> Bytes {
+        Bytes::from(self.buffer)
+    }
+
+    fn submessage(
+        &mut self,
+        submessage_id: SubmessageKind,
+        flags: SubmessageFlag,
+        content: Vec<u8>,
+    ) -> Result<(), speedy::Error> {
+        if content.len() > usize::from(u16::MAX) {
+            return Err(speedy::Error::custom(format!(
+                "Submessage of {} octets does not fit in a Submessage",
+                content.len()
+            )));
+        }
+
+        let submessage_header = SubmessageHeader {
+            submessage_id,
+            flags,
+            submessage_length: content.len() as u16,
+        };
+        self.buffer
+            .extend(submessage_header.write_to_vec_with_ctx(flags)?);
+        self.buffer.extend(content);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::gap::Gap;
+    use crate::messages::heartbeat::Heartbeat;
+    use crate::messages::receiver::MessageReceiver;
+    use crate::structure::count::Count_t;
+    use crate::structure::entity_id::EntityId_t;
+    use crate::structure::locator::LocatorKind_t;
+    use crate::structure::sequence_number::SequenceNumber_t;
+    use crate::structure::sequence_number_set::SequenceNumberSet_t;
+    use bytes::BytesMut;
+    use tokio_util::codec::Decoder;
+
+    #[test]
+    fn built_message_is_decoded() {
+        let heartbeat = EntitySubmessage::Heartbeat(
+            Heartbeat {
+                reader_id: EntityId_t::ENTITYID_UNKNOWN,
+                writer_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+                first_sn: SequenceNumber_t::from(1),
+                last_sn: SequenceNumber_t::from(3),
+                count: Count_t::from(1),
+            },
+            SubmessageFlag { flags: 0x00 },
+        );
+        let gap = EntitySubmessage::Gap(Gap {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+            gap_start: SequenceNumber_t::from(1),
+            gap_list: SequenceNumberSet_t::new(SequenceNumber_t::from(2)),
+        });
+
+        let mut message_builder = MessageBuilder::new(GuidPrefix_t::GUIDPREFIX_UNKNOWN);
+        message_builder
+            .info_destination(GuidPrefix_t::GUIDPREFIX_UNKNOWN)
+            .unwrap();
+        message_builder
+            .info_timestamp(Some(Time_t::TIME_ZERO))
+            .unwrap();
+        message_builder.entity_submessage(&gap).unwrap();
+        message_builder.info_timestamp(None).unwrap();
+        message_builder.entity_submessage(&heartbeat).unwrap();
+        let message = message_builder.build();
+
+        assert_eq!(b"RTPS", &message[..4]);
+
+        let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_INVALID);
+        let mut bytes = BytesMut::from(&message[..]);
+        assert_eq!(Some(gap), message_receiver.decode(&mut bytes).unwrap());
+        assert_eq!(
+            Some(heartbeat),
+            message_receiver.decode(&mut bytes).unwrap()
+        );
+        assert_eq!(None, message_receiver.decode(&mut bytes).unwrap());
+    }
+}
@@ -12,6 +12,7 @@ pub mod info_destination;
 pub mod info_reply;
 pub mod info_source;
 pub mod info_timestamp;
+pub mod message_builder;
 pub mod nack_frag;
 pub mod pad;
 pub mod protocol_id;
@@ -13,6 +13,7 @@ use speedy::{Readable, Writable};
 /// differs from an AckNack Submessage, which includes both positive and
 /// negative acknowledgements.
 #[derive(Debug, PartialEq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NackFrag {
     ///  Identifies the Reader entity that requests to receive certain
     /// fragments.
@@ -37,6 +38,26 @@ pub struct NackFrag {
     pub count: Count_t,
 }
 
+arbitrary_impl!(
+    NackFrag,
+    (
+        any::<EntityId_t>(),
+        any::<EntityId_t>(),
+        any::<SequenceNumber_t>(),
+        any::<FragmentNumberSet_t>(),
+        any::<Count_t>()
+    )
+        .prop_map(
+            |(reader_id, writer_id, writer_sn, fragment_number_state, count)| NackFrag {
+                reader_id,
+                writer_id,
+                writer_sn,
+                fragment_number_state,
+                count,
+            }
+        )
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,4 +88,6 @@ mod tests {
               0x00, 0x00, 0x00, 0x00,
               0x00, 0x00, 0x00, 0x06]
     });
+
+    serialization_property_test!(type = NackFrag);
 }
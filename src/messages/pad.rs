@@ -1,4 +1,5 @@
 /// The purpose of this Submessage is to allow the introduction of any padding
 /// necessary to meet any desired memory-alignment requirements. Its has no
 /// other meaning.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pad;
@@ -2,6 +2,7 @@ use crate::common::validity_trait::Validity;
 use speedy::{Context, Readable, Reader, Writable, Writer};
 
 #[derive(Debug, PartialOrd, PartialEq, Ord, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProtocolId_t {
     protocol_id: [char; 4],
 }
@@ -1,6 +1,7 @@
 use speedy::{Readable, Writable};
 
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProtocolVersion_t {
     pub major: u8,
     pub minor: u8,
@@ -23,6 +24,11 @@ impl Default for ProtocolVersion_t {
     }
 }
 
+arbitrary_impl!(
+    ProtocolVersion_t,
+    any::<(u8, u8)>().prop_map(|(major, minor)| ProtocolVersion_t { major, minor })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,4 +82,6 @@ mod tests {
         le = [0x02, 0x04],
         be = [0x02, 0x04]
     });
+
+    serialization_property_test!(type = ProtocolVersion_t);
 }
@@ -1,5 +1,6 @@
 use crate::common::validity_trait::Validity;
 use crate::messages::data::{Data, DataContext};
+use crate::messages::data_frag::DataFrag;
 use crate::messages::heartbeat::Heartbeat;
 use crate::messages::heartbeat_frag::HeartbeatFrag;
 use crate::messages::info_destination::InfoDestination;
@@ -75,6 +76,12 @@ impl MessageReceiver {
         }
     }
 
+    /// The state of the receiver once the last submessage was decoded, i.e.
+    /// the source of the message and its destination and timestamp.
+    pub fn receiver(&self) -> &Receiver {
+        &self.receiver
+    }
+
     fn decode_one(
         &mut self,
         bytes: &mut BytesMut
@@ -98,6 +105,7 @@ impl MessageReceiver {
                 self.receiver.source_guid_prefix = header.guid_prefix;
                 self.receiver.source_version = header.protocol_version;
                 self.receiver.source_vendor_id = header.vendor_id;
+                self.receiver.dest_guid_prefix = GuidPrefix_t::GUIDPREFIX_UNKNOWN;
                 self.receiver.have_timestamp = false;
 
                 self.state = DeserializationState::ReadingSubmessage;
@@ -117,13 +125,15 @@ impl MessageReceiver {
                         <SubmessageHeader as Readable<Endianness>>::minimum_bytes_needed(),
                     ),
                 )
-                .and_then(|submessage_header| {
+                .and_then(|mut submessage_header| {
                     if submessage_header.submessage_length == 0
                         && submessage_header.submessage_id != SubmessageKind::INFO_TS
                         && submessage_header.submessage_id != SubmessageKind::PAD
                     {
-                        // This is a last submessage
+                        // This is a last submessage, which extends up to the
+                        // end of the message
                         self.state = DeserializationState::ReadingHeader;
+                        submessage_header.submessage_length = bytes.len() as u16;
                     }
                     Ok(submessage_header)
                 })
@@ -153,7 +163,18 @@ impl MessageReceiver {
                         )))
                     }
                     SubmessageKind::DATA_FRAG => {
-                        unimplemented!();
+                        let data_context = DataContext::new(
+                            submessage_header.flags,
+                            submessage_header.submessage_length as usize,
+                        );
+                        let data_frag = DataFrag::read_from_buffer_with_ctx(
+                            data_context,
+                            &bytes.split_to(submessage_header.submessage_length.into()),
+                        )?;
+                        Ok(Some(EntitySubmessage::DataFrag(
+                            data_frag,
+                            submessage_header.flags,
+                        )))
                     }
                     SubmessageKind::GAP => {
                         let gap = Gap::read_from_buffer_with_ctx(
@@ -323,6 +344,9 @@ impl Decoder for MessageReceiver {
     }
 }
 
+#[cfg(test)]
+mod interop_tests;
+
 #[cfg(test)]
 mod tests {
     use speedy::Writable;
@@ -331,11 +355,13 @@ mod tests {
     use crate::messages::fragment_number::FragmentNumber_t;
     use crate::messages::fragment_number_set::FragmentNumberSet_t;
     use crate::messages::header::Header;
+    use crate::messages::submessage::InterpreterSubmessage;
     use crate::messages::submessage_flag::SubmessageFlag;
     use crate::structure::count::Count_t;
     use crate::structure::entity_id::EntityId_t;
     use crate::structure::sequence_number::SequenceNumber_t;
     use crate::structure::sequence_number_set::SequenceNumberSet_t;
+    use proptest::prelude::*;
 
     struct EntitySubmessageIterator<'a> {
         message_receiver: &'a mut MessageReceiver,
@@ -677,4 +703,213 @@ mod tests {
             ..Receiver::new(LocatorKind_t::LOCATOR_KIND_INVALID)
         }
     );
+
+    message_decoding_test!(
+        test_name = last_heartbeat_extends_to_end_of_message,
+        bytes = BytesMut::from(
+            &[
+                0x52, 0x54, 0x50, 0x53, 0x02, 0x03, 0x01, 0x0f, 0x01, 0x0f, 0xbb, 0x1d, 0xdf, 0x2b,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00
+            ][..]
+        ),
+        expected_notifications = [Ok(EntitySubmessage::Heartbeat(
+            Heartbeat {
+                reader_id: EntityId_t::ENTITYID_UNKNOWN,
+                writer_id: "00.00.01.02".parse().unwrap(),
+                first_sn: SequenceNumber_t::from(1),
+                last_sn: SequenceNumber_t::from(3),
+                count: Count_t::from(1)
+            },
+            SubmessageFlag { flags: 0b0000_0001 }
+        ))]
+    );
+
+    /// A submessage of a generated message.
+    #[derive(Debug)]
+    enum Submessage {
+        Entity(EntitySubmessage),
+        Interpreter(InterpreterSubmessage),
+    }
+
+    fn submessage() -> impl Strategy<Value = Submessage> {
+        prop_oneof![
+            any::<EntitySubmessage>().prop_map(Submessage::Entity),
+            any::<InterpreterSubmessage>().prop_map(Submessage::Interpreter),
+        ]
+    }
+
+    /// Encodes the submessage; the endianness is used only by the submessages
+    /// which carry no flags.
+    fn encode_submessage(submessage: &Submessage, little_endian: bool) -> Vec<u8> {
+        let endianness_flag = SubmessageFlag {
+            flags: little_endian as u8,
+        };
+        let (submessage_id, flags, content) = match submessage {
+            Submessage::Entity(EntitySubmessage::AckNack(ack_nack, flags)) => (
+                SubmessageKind::ACKNACK,
+                *flags,
+                ack_nack.write_to_vec_with_ctx(*flags).unwrap(),
+            ),
+            Submessage::Entity(EntitySubmessage::Data(data, flags)) => (
+                SubmessageKind::DATA,
+                *flags,
+                data.write_to_vec_with_ctx(*flags).unwrap(),
+            ),
+            Submessage::Entity(EntitySubmessage::DataFrag(data_frag, flags)) => (
+                SubmessageKind::DATA_FRAG,
+                *flags,
+                data_frag.write_to_vec_with_ctx(*flags).unwrap(),
+            ),
+            Submessage::Entity(EntitySubmessage::Gap(gap)) => (
+                SubmessageKind::GAP,
+                endianness_flag,
+                gap.write_to_vec_with_ctx(endianness_flag).unwrap(),
+            ),
+            Submessage::Entity(EntitySubmessage::Heartbeat(heartbeat, flags)) => (
+                SubmessageKind::HEARTBEAT,
+                *flags,
+                heartbeat.write_to_vec_with_ctx(*flags).unwrap(),
+            ),
+            Submessage::Entity(EntitySubmessage::HeartbeatFrag(heartbeat_frag)) => (
+                SubmessageKind::HEARTBEAT_FRAG,
+                endianness_flag,
+                heartbeat_frag
+                    .write_to_vec_with_ctx(endianness_flag)
+                    .unwrap(),
+            ),
+            Submessage::Entity(EntitySubmessage::NackFrag(nack_frag)) => (
+                SubmessageKind::NACK_FRAG,
+                endianness_flag,
+                nack_frag.write_to_vec_with_ctx(endianness_flag).unwrap(),
+            ),
+            Submessage::Interpreter(InterpreterSubmessage::InfoSource(info_source)) => (
+                SubmessageKind::INFO_SRC,
+                endianness_flag,
+                info_source.write_to_vec_with_ctx(endianness_flag).unwrap(),
+            ),
+            Submessage::Interpreter(InterpreterSubmessage::InfoDestination(info_destination)) => (
+                SubmessageKind::INFO_DST,
+                endianness_flag,
+                info_destination
+                    .write_to_vec_with_ctx(endianness_flag)
+                    .unwrap(),
+            ),
+            Submessage::Interpreter(InterpreterSubmessage::InfoReply(info_reply, flags)) => {
+                let mut content = info_reply
+                    .unicast_locator_list
+                    .write_to_vec_with_ctx(*flags)
+                    .unwrap();
+                if let Some(ref multicast_locator_list) = info_reply.multicast_locator_list {
+                    content.extend(
+                        multicast_locator_list
+                            .write_to_vec_with_ctx(*flags)
+                            .unwrap(),
+                    );
+                }
+                (SubmessageKind::INFO_REPLY, *flags, content)
+            }
+            Submessage::Interpreter(InterpreterSubmessage::InfoTimestamp(
+                info_timestamp,
+                flags,
+            )) => {
+                let content = match info_timestamp.timestamp {
+                    Some(timestamp) => timestamp.write_to_vec_with_ctx(*flags).unwrap(),
+                    None => vec![],
+                };
+                (SubmessageKind::INFO_TS, *flags, content)
+            }
+        };
+
+        let mut serialized = SubmessageHeader {
+            submessage_id,
+            flags,
+            submessage_length: content.len() as u16,
+        }
+        .write_to_vec_with_ctx(flags)
+        .unwrap();
+        serialized.extend(content);
+        serialized
+    }
+
+    /// Applies the interpreter submessage to the receiver, as described in
+    /// section 8.3.7 of the specification.
+    fn interpret(receiver: &mut Receiver, submessage: InterpreterSubmessage) {
+        match submessage {
+            InterpreterSubmessage::InfoSource(info_source) => {
+                receiver.source_guid_prefix = info_source.guid_prefix;
+                receiver.source_version = info_source.protocol_version;
+                receiver.source_vendor_id = info_source.vendor_id;
+                receiver.unicast_reply_locator_list = vec![Locator_t::LOCATOR_INVALID];
+                receiver.multicast_reply_locator_list = vec![Locator_t::LOCATOR_INVALID];
+                receiver.have_timestamp = false;
+            }
+            InterpreterSubmessage::InfoDestination(info_destination) => {
+                if info_destination.guid_prefix != GuidPrefix_t::GUIDPREFIX_UNKNOWN {
+                    receiver.dest_guid_prefix = info_destination.guid_prefix;
+                }
+            }
+            InterpreterSubmessage::InfoReply(info_reply, _) => {
+                receiver.unicast_reply_locator_list = info_reply.unicast_locator_list;
+                receiver.multicast_reply_locator_list =
+                    info_reply.multicast_locator_list.unwrap_or_default();
+            }
+            InterpreterSubmessage::InfoTimestamp(info_timestamp, _) => {
+                receiver.have_timestamp = info_timestamp.timestamp.is_some();
+                if let Some(timestamp) = info_timestamp.timestamp {
+                    receiver.timestamp = timestamp;
+                }
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn decode_generated_messages(
+            messages in proptest::collection::vec(
+                (
+                    any::<Header>(),
+                    proptest::collection::vec((submessage(), any::<bool>()), 1..8),
+                ),
+                1..4,
+            )
+        ) {
+            let mut message_receiver = MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_INVALID);
+            let mut expected_receiver = Receiver::new(LocatorKind_t::LOCATOR_KIND_INVALID);
+
+            // Every message is received in a datagram of its own.
+            for (header, submessages) in messages {
+                let mut serialized = header.write_to_vec_with_ctx(Endianness::NATIVE).unwrap();
+                for (submessage, little_endian) in &submessages {
+                    serialized.extend(encode_submessage(submessage, *little_endian));
+                }
+                let mut bytes = BytesMut::from(&serialized[..]);
+
+                let mut decoded = vec![];
+                while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+                    decoded.push(submessage);
+                }
+
+                expected_receiver.source_guid_prefix = header.guid_prefix;
+                expected_receiver.source_version = header.protocol_version;
+                expected_receiver.source_vendor_id = header.vendor_id;
+                expected_receiver.dest_guid_prefix = GuidPrefix_t::GUIDPREFIX_UNKNOWN;
+                expected_receiver.have_timestamp = false;
+
+                let mut expected = vec![];
+                for (submessage, _) in submessages {
+                    match submessage {
+                        Submessage::Entity(submessage) => expected.push(submessage),
+                        Submessage::Interpreter(submessage) => {
+                            interpret(&mut expected_receiver, submessage)
+                        }
+                    }
+                }
+
+                prop_assert_eq!(expected, decoded);
+                prop_assert_eq!(&expected_receiver, &message_receiver.receiver);
+            }
+        }
+    }
 }
@@ -0,0 +1,710 @@
+//! Decodes the messages of the interoperability corpus, see corpus/README.md.
+//! Until its hand-written messages are replaced by captures, these tests only
+//! check the decoder against the format the corpus was written after.
+
+use super::*;
+use crate::messages::fragment_number::FragmentNumber_t;
+use crate::messages::submessage_elements::parameter_list::ParameterList;
+use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+use crate::messages::submessage_elements::serialized_payload::{
+    SerializedPayload, SerializedPayloadContent,
+};
+use crate::messages::submessage_flag::SubmessageFlag;
+use crate::structure::count::Count_t;
+use crate::structure::duration::Duration_t;
+use crate::structure::entity_id::EntityId_t;
+use crate::structure::guid::GUID_t;
+use crate::structure::parameter_id::ParameterId;
+use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::sequence_number_set::SequenceNumberSet_t;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const FASTDDS_PREFIX: &str = "01.0f.45.d2.b3.f5.f2.c0.01.00.00.00";
+const CYCLONEDDS_PREFIX: &str = "01.10.2b.a4.3e.c6.5f.1e.a9.6b.7d.21";
+const OPENDDS_PREFIX: &str = "01.03.02.42.ac.11.00.03.4d.2f.00.00";
+const CONNEXT_PREFIX: &str = "c0.a8.01.0b.00.00.2f.64.00.00.00.01";
+
+/// Parses the hex dump of a corpus file, skipping the `#` comments.
+fn corpus_message(hex: &str) -> BytesMut {
+    let bytes: Vec<u8> = hex
+        .lines()
+        .flat_map(|line| line.split('#').next().unwrap().split_whitespace())
+        .map(|octet| u8::from_str_radix(octet, 16).unwrap())
+        .collect();
+    BytesMut::from(&bytes[..])
+}
+
+/// Decodes all the entity submessages of the messages, which are received in
+/// datagrams of their own.
+fn decode(message_receiver: &mut MessageReceiver, hex: &[&str]) -> Vec<EntitySubmessage> {
+    let mut submessages = vec![];
+    for hex in hex {
+        let mut bytes = corpus_message(hex);
+        while let Some(submessage) = message_receiver.decode(&mut bytes).unwrap() {
+            submessages.push(submessage);
+        }
+        assert!(bytes.is_empty());
+    }
+    submessages
+}
+
+fn decode_one(message_receiver: &mut MessageReceiver, hex: &str) -> EntitySubmessage {
+    let mut submessages = decode(message_receiver, &[hex]);
+    assert_eq!(1, submessages.len());
+    submessages.remove(0)
+}
+
+fn message_receiver() -> MessageReceiver {
+    MessageReceiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4)
+}
+
+fn prefix(text: &str) -> GuidPrefix_t {
+    text.parse().unwrap()
+}
+
+fn entity_id(text: &str) -> EntityId_t {
+    text.parse().unwrap()
+}
+
+fn guid(text: &str) -> GUID_t {
+    text.parse().unwrap()
+}
+
+fn locator(text: &str) -> Locator_t {
+    text.parse().unwrap()
+}
+
+/// Rounds the timestamp to the nearest millisecond, as the fractions of the
+/// corpus are not exact in nanoseconds.
+fn milliseconds_since_epoch(timestamp: Time_t) -> u128 {
+    let since_epoch = SystemTime::try_from(timestamp)
+        .unwrap()
+        .duration_since(UNIX_EPOCH)
+        .unwrap();
+    (since_epoch.as_micros() + 500) / 1000
+}
+
+fn parameter_list(serialized_payload: &SerializedPayload) -> &ParameterList {
+    match serialized_payload.content {
+        SerializedPayloadContent::ParameterList(ref parameter_list) => parameter_list,
+        ref content => panic!("expected a parameter list, got {:?}", content),
+    }
+}
+
+fn user_defined(serialized_payload: &SerializedPayload) -> &[u8] {
+    match serialized_payload.content {
+        SerializedPayloadContent::UserDefined(ref payload) => payload,
+        ref content => panic!("expected user defined data, got {:?}", content),
+    }
+}
+
+fn parameter_ids(parameter_list: &ParameterList) -> Vec<u16> {
+    parameter_list
+        .iter()
+        .map(|parameter| u16::from(parameter.get_id()))
+        .collect()
+}
+
+fn value(parameter_list: &ParameterList, parameter_id: ParameterId) -> &[u8] {
+    parameter_list
+        .iter()
+        .find(|parameter| parameter.get_id() == parameter_id)
+        .unwrap_or_else(|| panic!("missing parameter {:?}", parameter_id))
+        .get_value()
+}
+
+/// Reads a little endian parameter value.
+fn read<'a, T: Readable<'a, Endianness>>(value: &'a [u8]) -> T {
+    T::read_from_buffer_with_ctx(Endianness::LittleEndian, value).unwrap()
+}
+
+/// Reads a little endian CDR string, without the terminating NUL.
+fn string(value: &[u8]) -> &str {
+    let length = read::<u32>(value) as usize;
+    std::str::from_utf8(&value[4..4 + length - 1]).unwrap()
+}
+
+#[test]
+fn fastdds_spdp_participant() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/fastdds/spdp_participant.hex"),
+    ) {
+        EntitySubmessage::Data(data, flags) => {
+            assert_eq!(SubmessageFlag { flags: 0x05 }, flags);
+            data
+        }
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(
+        prefix(FASTDDS_PREFIX),
+        message_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        ProtocolVersion_t { major: 2, minor: 3 },
+        message_receiver.receiver.source_version
+    );
+    assert_eq!(
+        VendorId_t::from([0x01, 0x0f]),
+        message_receiver.receiver.source_vendor_id
+    );
+    assert!(message_receiver.receiver.have_timestamp);
+    assert_eq!(
+        1_634_567_890_240,
+        milliseconds_since_epoch(message_receiver.receiver.timestamp)
+    );
+
+    assert_eq!(
+        EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER,
+        data.reader_id
+    );
+    assert_eq!(
+        EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+        data.writer_id
+    );
+    assert_eq!(SequenceNumber_t::from(1), data.writer_sn);
+    assert_eq!(None, data.inline_qos);
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    assert_eq!(
+        RepresentationIdentifier::PL_CDR_LE,
+        serialized_payload.header.representation_identifier
+    );
+    let parameters = parameter_list(&serialized_payload);
+    assert_eq!(
+        vec![0x0015, 0x0016, 0x0050, 0x0032, 0x0033, 0x0031, 0x0002, 0x0058, 0x0062],
+        parameter_ids(parameters)
+    );
+    assert_eq!(
+        &[0x02, 0x03, 0x00, 0x00],
+        value(parameters, ParameterId::PID_PROTOCOL_VERSION)
+    );
+    assert_eq!(
+        &[0x01, 0x0f, 0x00, 0x00],
+        value(parameters, ParameterId::PID_VENDOR_ID)
+    );
+    assert_eq!(
+        guid("01.0f.45.d2.b3.f5.f2.c0.01.00.00.00|PARTICIPANT"),
+        read(value(parameters, ParameterId::PID_PARTICIPANT_GUID))
+    );
+    assert_eq!(
+        locator("udpv4://192.168.1.20:7410"),
+        read(value(
+            parameters,
+            ParameterId::PID_METATRAFFIC_UNICAST_LOCATOR
+        ))
+    );
+    assert_eq!(
+        locator("udpv4://239.255.0.1:7400"),
+        read(value(
+            parameters,
+            ParameterId::PID_METATRAFFIC_MULTICAST_LOCATOR
+        ))
+    );
+    assert_eq!(
+        locator("udpv4://192.168.1.20:7411"),
+        read(value(parameters, ParameterId::PID_DEFAULT_UNICAST_LOCATOR))
+    );
+    assert_eq!(
+        Duration::from_secs(20),
+        Duration::from(read::<Duration_t>(value(
+            parameters,
+            ParameterId::PID_PARTICIPANT_LEASE_DURATION
+        )))
+    );
+    assert_eq!(
+        0x0000_0c3f,
+        read::<u32>(value(parameters, ParameterId::PID_BUILTIN_ENDPOINT_SET))
+    );
+    assert_eq!(
+        "RTPSParticipant",
+        string(value(parameters, ParameterId::PID_ENTITY_NAME))
+    );
+}
+
+#[test]
+fn fastdds_sedp_publication() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/fastdds/sedp_publication.hex"),
+    ) {
+        EntitySubmessage::Data(data, flags) => {
+            assert_eq!(SubmessageFlag { flags: 0x05 }, flags);
+            data
+        }
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(
+        prefix(FASTDDS_PREFIX),
+        message_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        prefix(CYCLONEDDS_PREFIX),
+        message_receiver.receiver.dest_guid_prefix
+    );
+    assert_eq!(
+        1_634_567_891_040,
+        milliseconds_since_epoch(message_receiver.receiver.timestamp)
+    );
+
+    assert_eq!(
+        EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER,
+        data.reader_id
+    );
+    assert_eq!(
+        EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_WRITER,
+        data.writer_id
+    );
+    assert_eq!(SequenceNumber_t::from(1), data.writer_sn);
+    assert_eq!(None, data.inline_qos);
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    let parameters = parameter_list(&serialized_payload);
+    assert_eq!(
+        vec![0x0050, 0x005a, 0x0005, 0x0007, 0x001d, 0x001a, 0x0016],
+        parameter_ids(parameters)
+    );
+    assert_eq!(
+        guid("01.0f.45.d2.b3.f5.f2.c0.01.00.00.00|PARTICIPANT"),
+        read(value(parameters, ParameterId::PID_PARTICIPANT_GUID))
+    );
+    assert_eq!(
+        guid("01.0f.45.d2.b3.f5.f2.c0.01.00.00.00|00.00.01.03"),
+        read(value(parameters, ParameterId::PID_ENDPOINT_GUID))
+    );
+    assert_eq!(
+        "Square",
+        string(value(parameters, ParameterId::PID_TOPIC_NAME))
+    );
+    assert_eq!(
+        "ShapeType",
+        string(value(parameters, ParameterId::PID_TYPE_NAME))
+    );
+    // TRANSIENT_LOCAL
+    assert_eq!(
+        1,
+        read::<u32>(value(parameters, ParameterId::PID_DURABILITY))
+    );
+
+    let reliability = value(parameters, ParameterId::PID_RELIABILITY);
+    // RELIABLE, with a max blocking time of 100 ms, in 1/2^32 seconds
+    assert_eq!(2, read::<u32>(reliability));
+    assert_eq!(0, read::<i32>(&reliability[4..]));
+    assert_eq!(0x1999_9999, read::<u32>(&reliability[8..]));
+}
+
+#[test]
+fn fastdds_heartbeat_cyclonedds_acknack() {
+    let mut fastdds_receiver = message_receiver();
+    let heartbeat = decode_one(
+        &mut fastdds_receiver,
+        include_str!("../../../corpus/fastdds/heartbeat.hex"),
+    );
+    assert_eq!(
+        EntitySubmessage::Heartbeat(
+            Heartbeat {
+                reader_id: entity_id("00.00.01.07"),
+                writer_id: entity_id("00.00.01.03"),
+                first_sn: SequenceNumber_t::from(1),
+                last_sn: SequenceNumber_t::from(5),
+                count: Count_t::from(4),
+            },
+            SubmessageFlag { flags: 0x01 }
+        ),
+        heartbeat
+    );
+    assert_eq!(
+        prefix(FASTDDS_PREFIX),
+        fastdds_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        prefix(CYCLONEDDS_PREFIX),
+        fastdds_receiver.receiver.dest_guid_prefix
+    );
+    assert!(!fastdds_receiver.receiver.have_timestamp);
+
+    let mut cyclonedds_receiver = message_receiver();
+    let ack_nack = decode_one(
+        &mut cyclonedds_receiver,
+        include_str!("../../../corpus/cyclonedds/acknack.hex"),
+    );
+    let mut reader_sn_state = SequenceNumberSet_t::new(SequenceNumber_t::from(3));
+    reader_sn_state.insert(SequenceNumber_t::from(3));
+    reader_sn_state.insert(SequenceNumber_t::from(5));
+    assert_eq!(
+        EntitySubmessage::AckNack(
+            AckNack {
+                reader_id: entity_id("00.00.01.07"),
+                writer_id: entity_id("00.00.01.03"),
+                reader_sn_state,
+                count: Count_t::from(2),
+            },
+            SubmessageFlag { flags: 0x01 }
+        ),
+        ack_nack
+    );
+    assert_eq!(
+        prefix(CYCLONEDDS_PREFIX),
+        cyclonedds_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        prefix(FASTDDS_PREFIX),
+        cyclonedds_receiver.receiver.dest_guid_prefix
+    );
+    assert_eq!(
+        ProtocolVersion_t { major: 2, minor: 1 },
+        cyclonedds_receiver.receiver.source_version
+    );
+}
+
+#[test]
+fn cyclonedds_spdp_participant() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/cyclonedds/spdp_participant.hex"),
+    ) {
+        EntitySubmessage::Data(data, flags) => {
+            assert_eq!(SubmessageFlag { flags: 0x05 }, flags);
+            data
+        }
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(
+        prefix(CYCLONEDDS_PREFIX),
+        message_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        VendorId_t::from([0x01, 0x10]),
+        message_receiver.receiver.source_vendor_id
+    );
+    assert_eq!(
+        1_634_567_890_500,
+        milliseconds_since_epoch(message_receiver.receiver.timestamp)
+    );
+
+    assert_eq!(EntityId_t::ENTITYID_UNKNOWN, data.reader_id);
+    assert_eq!(
+        EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+        data.writer_id
+    );
+    assert_eq!(SequenceNumber_t::from(1), data.writer_sn);
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    let parameters = parameter_list(&serialized_payload);
+    assert_eq!(
+        vec![0x000f, 0x0015, 0x0016, 0x0050, 0x0058, 0x0077, 0x0031, 0x0032, 0x0002, 0x8007],
+        parameter_ids(parameters)
+    );
+    assert_eq!(
+        0,
+        read::<u32>(value(parameters, ParameterId::PID_DOMAIN_ID))
+    );
+    assert_eq!(
+        &[0x02, 0x01, 0x00, 0x00],
+        value(parameters, ParameterId::PID_PROTOCOL_VERSION)
+    );
+    assert_eq!(
+        guid("01.10.2b.a4.3e.c6.5f.1e.a9.6b.7d.21|PARTICIPANT"),
+        read(value(parameters, ParameterId::PID_PARTICIPANT_GUID))
+    );
+    assert_eq!(
+        0,
+        read::<u32>(value(parameters, ParameterId::PID_BUILTIN_ENDPOINT_QOS))
+    );
+    assert_eq!(
+        locator("udpv4://192.168.1.21:48751"),
+        read(value(parameters, ParameterId::PID_DEFAULT_UNICAST_LOCATOR))
+    );
+    assert_eq!(
+        locator("udpv4://192.168.1.21:48751"),
+        read(value(
+            parameters,
+            ParameterId::PID_METATRAFFIC_UNICAST_LOCATOR
+        ))
+    );
+    assert_eq!(
+        Duration::from_secs(10),
+        Duration::from(read::<Duration_t>(value(
+            parameters,
+            ParameterId::PID_PARTICIPANT_LEASE_DURATION
+        )))
+    );
+
+    // The vendor specific parameters are kept as they are.
+    let version_info = parameters
+        .iter()
+        .find(|parameter| u16::from(parameter.get_id()) == 0x8007)
+        .unwrap()
+        .get_value();
+    assert_eq!(44, version_info.len());
+    assert_eq!("Linux/0.10.2/cyclonedds", string(&version_info[16..]));
+}
+
+#[test]
+fn cyclonedds_dispose() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/cyclonedds/dispose.hex"),
+    ) {
+        EntitySubmessage::Data(data, flags) => {
+            // E, Q and K
+            assert_eq!(SubmessageFlag { flags: 0x0b }, flags);
+            data
+        }
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(EntityId_t::ENTITYID_UNKNOWN, data.reader_id);
+    assert_eq!(entity_id("00.00.01.02"), data.writer_id);
+    assert_eq!(SequenceNumber_t::from(6), data.writer_sn);
+
+    let inline_qos = data.inline_qos.unwrap();
+    assert_eq!(vec![0x0071], parameter_ids(&inline_qos));
+    // DISPOSED
+    assert_eq!(
+        &[0x00, 0x00, 0x00, 0x01],
+        value(&inline_qos, ParameterId::PID_STATUS_INFO)
+    );
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    assert_eq!(
+        RepresentationIdentifier::CDR_LE,
+        serialized_payload.header.representation_identifier
+    );
+    assert_eq!("RED", string(user_defined(&serialized_payload)));
+}
+
+#[test]
+fn opendds_spdp_participant() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/opendds/spdp_participant.hex"),
+    ) {
+        EntitySubmessage::Data(data, _) => data,
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(
+        prefix(OPENDDS_PREFIX),
+        message_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        ProtocolVersion_t { major: 2, minor: 4 },
+        message_receiver.receiver.source_version
+    );
+    assert_eq!(
+        VendorId_t::from([0x01, 0x03]),
+        message_receiver.receiver.source_vendor_id
+    );
+
+    assert_eq!(
+        EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER,
+        data.reader_id
+    );
+    assert_eq!(
+        EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+        data.writer_id
+    );
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    let parameters = parameter_list(&serialized_payload);
+    assert_eq!(
+        vec![0x0015, 0x0016, 0x0050, 0x0058, 0x0032, 0x0031, 0x0034, 0x0002],
+        parameter_ids(parameters)
+    );
+    assert_eq!(
+        guid("01.03.02.42.ac.11.00.03.4d.2f.00.00|PARTICIPANT"),
+        read(value(parameters, ParameterId::PID_PARTICIPANT_GUID))
+    );
+    assert_eq!(
+        locator("udpv4://172.17.0.3:7410"),
+        read(value(
+            parameters,
+            ParameterId::PID_METATRAFFIC_UNICAST_LOCATOR
+        ))
+    );
+    assert_eq!(
+        locator("udpv4://172.17.0.3:42713"),
+        read(value(parameters, ParameterId::PID_DEFAULT_UNICAST_LOCATOR))
+    );
+    assert_eq!(
+        0,
+        read::<i32>(value(
+            parameters,
+            ParameterId::PID_PARTICIPANT_MANUAL_LIVELINESS_COUNT
+        ))
+    );
+    assert_eq!(
+        Duration::from_secs(300),
+        Duration::from(read::<Duration_t>(value(
+            parameters,
+            ParameterId::PID_PARTICIPANT_LEASE_DURATION
+        )))
+    );
+}
+
+#[test]
+fn opendds_sedp_publication() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/opendds/sedp_publication.hex"),
+    ) {
+        EntitySubmessage::Data(data, _) => data,
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(
+        1_634_567_892_125,
+        milliseconds_since_epoch(message_receiver.receiver.timestamp)
+    );
+    assert_eq!(
+        EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER,
+        data.reader_id
+    );
+    assert_eq!(
+        EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_WRITER,
+        data.writer_id
+    );
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    let parameters = parameter_list(&serialized_payload);
+    assert_eq!(
+        vec![0x0005, 0x0007, 0x001a, 0x001d, 0x005a],
+        parameter_ids(parameters)
+    );
+    // The names are padded to a multiple of 4 octets.
+    assert_eq!(28, value(parameters, ParameterId::PID_TOPIC_NAME).len());
+    assert_eq!(
+        "Movie Discussion List",
+        string(value(parameters, ParameterId::PID_TOPIC_NAME))
+    );
+    assert_eq!(
+        "Messenger::Message",
+        string(value(parameters, ParameterId::PID_TYPE_NAME))
+    );
+    assert_eq!(
+        2,
+        read::<u32>(value(parameters, ParameterId::PID_RELIABILITY))
+    );
+    assert_eq!(
+        0,
+        read::<u32>(value(parameters, ParameterId::PID_DURABILITY))
+    );
+    assert_eq!(
+        guid("01.03.02.42.ac.11.00.03.4d.2f.00.00|00.00.01.02"),
+        read(value(parameters, ParameterId::PID_ENDPOINT_GUID))
+    );
+}
+
+#[test]
+fn connext_data_with_inline_qos() {
+    let mut message_receiver = message_receiver();
+    let data = match decode_one(
+        &mut message_receiver,
+        include_str!("../../../corpus/connext/data_with_inline_qos.hex"),
+    ) {
+        EntitySubmessage::Data(data, flags) => {
+            // E, Q and D
+            assert_eq!(SubmessageFlag { flags: 0x07 }, flags);
+            data
+        }
+        submessage => panic!("unexpected submessage {:?}", submessage),
+    };
+
+    assert_eq!(
+        prefix(CONNEXT_PREFIX),
+        message_receiver.receiver.source_guid_prefix
+    );
+    assert_eq!(
+        VendorId_t::from([0x01, 0x01]),
+        message_receiver.receiver.source_vendor_id
+    );
+    assert_eq!(
+        1_634_567_893_420,
+        milliseconds_since_epoch(message_receiver.receiver.timestamp)
+    );
+
+    assert_eq!(EntityId_t::ENTITYID_UNKNOWN, data.reader_id);
+    assert_eq!(entity_id("80.00.00.02"), data.writer_id);
+    assert_eq!(SequenceNumber_t::from(12), data.writer_sn);
+
+    let inline_qos = data.inline_qos.unwrap();
+    assert_eq!(vec![0x0070], parameter_ids(&inline_qos));
+    assert_eq!(
+        &[
+            0x00, 0x00, 0x00, 0x05, 0x42, 0x4c, 0x55, 0x45, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00
+        ],
+        value(&inline_qos, ParameterId::PID_KEY_HASH)
+    );
+
+    let serialized_payload = data.serialized_payload.unwrap();
+    assert_eq!(
+        RepresentationIdentifier::CDR_LE,
+        serialized_payload.header.representation_identifier
+    );
+    let shape = user_defined(&serialized_payload);
+    assert_eq!(24, shape.len());
+    assert_eq!("BLUE", string(shape));
+    assert_eq!(112, read::<i32>(&shape[12..]));
+    assert_eq!(81, read::<i32>(&shape[16..]));
+    assert_eq!(30, read::<i32>(&shape[20..]));
+}
+
+#[test]
+fn connext_data_frag() {
+    let mut message_receiver = message_receiver();
+    let fragments = decode(
+        &mut message_receiver,
+        &[
+            include_str!("../../../corpus/connext/data_frag_1.hex"),
+            include_str!("../../../corpus/connext/data_frag_2.hex"),
+            include_str!("../../../corpus/connext/data_frag_3.hex"),
+        ],
+    );
+    assert_eq!(3, fragments.len());
+
+    let mut sample = vec![];
+    for (fragment_number, fragment) in (1..).zip(fragments) {
+        let data_frag = match fragment {
+            EntitySubmessage::DataFrag(data_frag, flags) => {
+                assert_eq!(SubmessageFlag { flags: 0x01 }, flags);
+                data_frag
+            }
+            submessage => panic!("unexpected submessage {:?}", submessage),
+        };
+
+        assert_eq!(EntityId_t::ENTITYID_UNKNOWN, data_frag.reader_id);
+        assert_eq!(entity_id("00.00.01.03"), data_frag.writer_id);
+        assert_eq!(SequenceNumber_t::from(3), data_frag.writer_sn);
+        assert_eq!(
+            FragmentNumber_t::from(fragment_number),
+            data_frag.fragment_starting_num
+        );
+        assert_eq!(1, data_frag.fragments_in_submessage);
+        assert_eq!(256, data_frag.fragment_size);
+        assert_eq!(600, data_frag.data_size);
+        assert_eq!(None, data_frag.inline_qos);
+
+        sample.extend_from_slice(&data_frag.serialized_payload);
+    }
+
+    assert_eq!(600, sample.len());
+    // CDR_LE
+    assert_eq!(&[0x00, 0x01, 0x00, 0x00], &sample[..4]);
+    assert_eq!(592, read::<u32>(&sample[4..]));
+    assert!(sample[8..]
+        .iter()
+        .enumerate()
+        .all(|(i, octet)| *octet == i as u8));
+}
@@ -12,6 +12,7 @@ use crate::messages::nack_frag::NackFrag;
 use crate::messages::submessage_flag::SubmessageFlag;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EntitySubmessage {
     AckNack(AckNack, SubmessageFlag),
     Data(Data, SubmessageFlag),
@@ -23,6 +24,7 @@ pub enum EntitySubmessage {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterpreterSubmessage {
     InfoSource(InfoSource),
     InfoDestination(InfoDestination),
@@ -30,3 +32,55 @@ pub enum InterpreterSubmessage {
     InfoTimestamp(InfoTimestamp, SubmessageFlag),
     // Pad(Pad),
 }
+
+/// Random flags, restricted to the ones set in the mask.
+#[cfg(any(test, feature = "proptest"))]
+fn arbitrary_flags(mask: u8) -> impl proptest::strategy::Strategy<Value = SubmessageFlag> {
+    use proptest::prelude::*;
+    any::<u8>().prop_map(move |flags| SubmessageFlag {
+        flags: flags & mask,
+    })
+}
+
+arbitrary_impl!(
+    EntitySubmessage,
+    prop_oneof![
+        (any::<AckNack>(), arbitrary_flags(0x03))
+            .prop_map(|(ack_nack, flags)| EntitySubmessage::AckNack(ack_nack, flags)),
+        (any::<Data>(), any::<u8>()).prop_map(|(data, random)| {
+            let flags = crate::messages::data::arbitrary_flags(&data, random);
+            EntitySubmessage::Data(data, flags)
+        }),
+        (any::<DataFrag>(), arbitrary_flags(0x05)).prop_map(|(data_frag, mut flags)| {
+            if data_frag.inline_qos.is_some() {
+                flags.set_flag(0x02);
+            }
+            EntitySubmessage::DataFrag(data_frag, flags)
+        }),
+        any::<Gap>().prop_map(EntitySubmessage::Gap),
+        (any::<Heartbeat>(), arbitrary_flags(0x07))
+            .prop_map(|(heartbeat, flags)| EntitySubmessage::Heartbeat(heartbeat, flags)),
+        any::<HeartbeatFrag>().prop_map(EntitySubmessage::HeartbeatFrag),
+        any::<NackFrag>().prop_map(EntitySubmessage::NackFrag),
+    ]
+);
+
+arbitrary_impl!(
+    InterpreterSubmessage,
+    prop_oneof![
+        any::<InfoSource>().prop_map(InterpreterSubmessage::InfoSource),
+        any::<InfoDestination>().prop_map(InterpreterSubmessage::InfoDestination),
+        (any::<InfoReply>(), arbitrary_flags(0x01)).prop_map(|(info_reply, mut flags)| {
+            if info_reply.multicast_locator_list.is_some() {
+                flags.set_flag(0x02);
+            }
+            InterpreterSubmessage::InfoReply(info_reply, flags)
+        }),
+        (any::<InfoTimestamp>(), arbitrary_flags(0x01)).prop_map(|(info_timestamp, mut flags)| {
+            if info_timestamp.timestamp.is_none() {
+                flags.set_flag(0x02);
+            }
+            InterpreterSubmessage::InfoTimestamp(info_timestamp, flags)
+        }),
+    ]
+);
@@ -2,7 +2,8 @@ use crate::common::size_tracking_context::SizeTrackingContext;
 use crate::structure::parameter_id::ParameterId;
 use speedy::{Context, Readable, Reader, Writable, Writer};
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Parameter {
     /// Uniquely identifies the type of parameter
     parameter_id: ParameterId,
@@ -12,10 +13,19 @@ pub struct Parameter {
 }
 
 impl Parameter {
+    /// Creates a parameter with the value, which is padded to a multiple of 4
+    /// octets when written.
+    pub fn new(parameter_id: ParameterId, value: Vec<u8>) -> Parameter {
+        Parameter {
+            parameter_id,
+            value,
+        }
+    }
+
     pub fn new_sentinel() -> Parameter {
         Parameter {
             parameter_id: ParameterId::PID_SENTINEL,
-            value: vec![0; 2],
+            value: vec![],
         }
     }
 
@@ -23,6 +33,10 @@ impl Parameter {
         self.parameter_id
     }
 
+    pub fn get_value(&self) -> &[u8] {
+        &self.value
+    }
+
     pub fn is_sentinel(&self) -> bool {
         self.parameter_id == ParameterId::PID_SENTINEL
     }
@@ -32,20 +46,17 @@ impl<'a, C: SizeTrackingContext> Readable<'a, C> for Parameter {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
         let parameter_id: ParameterId = reader.read_value()?;
-        reader.context_mut().subtract_from_remaining(ParameterId::serialized_length());
+        reader.context_mut().subtract_from_remaining(ParameterId::serialized_length())?;
 
         let length = reader.read_u16()?;
-        reader.context_mut().subtract_from_remaining(2);
-
-        let alignment = length % 4;
-
-        let mut value = Vec::with_capacity((length + alignment) as usize);
+        reader.context_mut().subtract_from_remaining(2)?;
 
-        for _ in 0..(length + alignment) {
-            let byte = reader.read_u8()?;
-            value.push(byte);
-        }
-        reader.context_mut().subtract_from_remaining((length + alignment) as usize);
+        // The length includes the padding of the value to a multiple of 4.
+        let mut value = vec![0; length as usize];
+        reader.read_bytes(&mut value)?;
+        reader
+            .context_mut()
+            .subtract_from_remaining(length as usize)?;
 
         Ok(Parameter {
             parameter_id,
@@ -60,14 +71,12 @@ impl<C: Context> Writable<C> for Parameter {
         writer.write_value(&self.parameter_id)?;
 
         let length = self.value.len();
-        let alignment = length % 4;
-        writer.write_u16((length + alignment) as u16)?;
+        let padding = (4 - length % 4) % 4;
+        writer.write_u16((length + padding) as u16)?;
 
-        for byte in &self.value {
-            writer.write_u8(*byte)?;
-        }
+        writer.write_bytes(&self.value)?;
 
-        for _ in 0..alignment {
+        for _ in 0..padding {
             writer.write_u8(0x00)?;
         }
 
@@ -75,6 +84,22 @@ impl<C: Context> Writable<C> for Parameter {
     }
 }
 
+// Generates neither sentinels nor padding, which a ParameterList drops, and
+// only values which need no padding.
+arbitrary_impl!(
+    Parameter,
+    (
+        any::<ParameterId>().prop_filter("reserved parameter id", |parameter_id| {
+            *parameter_id != ParameterId::PID_SENTINEL && *parameter_id != ParameterId::PID_PAD
+        }),
+        proptest::collection::vec(any::<[u8; 4]>(), 0..8)
+    )
+        .prop_map(|(parameter_id, words)| Parameter {
+            parameter_id,
+            value: words.concat(),
+        })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,4 +161,37 @@ mod tests {
               0xFF, 0xFF, 0xFF, 0x7F,
               0xFF, 0xFF, 0xFF, 0xFF]
     });
+
+    #[test]
+    fn value_is_padded_to_a_multiple_of_four() {
+        let parameter = Parameter {
+            parameter_id: ParameterId::PID_TOPIC_NAME,
+            value: vec![0x01, 0x02, 0x03, 0x04, 0x05],
+        };
+        let serialized = parameter
+            .write_to_vec_with_ctx(speedy::Endianness::BigEndian)
+            .unwrap();
+        assert_eq!(
+            vec![0x00, 0x05, 0x00, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00],
+            serialized
+        );
+
+        // The padding is read as part of the value.
+        let deserialized: Parameter =
+            Readable::read_from_buffer_with_ctx(speedy::Endianness::BigEndian, &serialized)
+                .unwrap();
+        assert_eq!(&serialized[4..], deserialized.get_value());
+    }
+
+    #[test]
+    fn sentinel_has_no_value() {
+        assert_eq!(
+            vec![0x01, 0x00, 0x00, 0x00],
+            Parameter::new_sentinel()
+                .write_to_vec_with_ctx(speedy::Endianness::LittleEndian)
+                .unwrap()
+        );
+    }
+
+    serialization_property_test!(type = Parameter);
 }
@@ -7,11 +7,54 @@ use speedy::{Context, Readable, Reader, Writable, Writer};
 /// QoS parameters that may affect the interpretation of the message.
 /// The encapsulation of the parameters follows a mechanism that allows
 /// extensions to the QoS without breaking backwards compatibility.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParameterList {
     parameters: Vec<Parameter>,
 }
 
+impl ParameterList {
+    pub fn new() -> ParameterList {
+        ParameterList {
+            parameters: Vec::new(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Parameter> {
+        self.parameters.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    pub fn push(&mut self, parameter: Parameter) {
+        self.parameters.push(parameter)
+    }
+
+    /// Returns the first parameter with the id.
+    pub fn get(&self, parameter_id: ParameterId) -> Option<&Parameter> {
+        self.parameters
+            .iter()
+            .find(|parameter| parameter.get_id() == parameter_id)
+    }
+
+    /// Removes and returns the first parameter with the id.
+    pub fn remove(&mut self, parameter_id: ParameterId) -> Option<Parameter> {
+        let index = self
+            .parameters
+            .iter()
+            .position(|parameter| parameter.get_id() == parameter_id)?;
+        Some(self.parameters.remove(index))
+    }
+}
+
+impl From<Vec<Parameter>> for ParameterList {
+    fn from(parameters: Vec<Parameter>) -> Self {
+        ParameterList { parameters }
+    }
+}
+
 impl<'a, C: SizeTrackingContext> Readable<'a, C> for ParameterList {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
@@ -59,3 +102,32 @@ impl<C: Context> Writable<C> for ParameterList {
         Ok(())
     }
 }
+
+arbitrary_impl!(
+    ParameterList,
+    proptest::collection::vec(any::<Parameter>(), 0..8)
+        .prop_map(|parameters| ParameterList { parameters })
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentinel_ends_the_list() {
+        let bytes = [
+            0x15, 0x00, 0x04, 0x00, 0x02, 0x01, 0x00, 0x00, // PID_PROTOCOL_VERSION
+            0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, // PID_PAD
+            0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+            0x16, 0x00, 0x04, 0x00, 0x01, 0x02, 0x03, 0x04, // PID_VENDOR_ID
+        ];
+        let parameter_list =
+            ParameterList::read_from_buffer_with_ctx(speedy::Endianness::LittleEndian, &bytes)
+                .unwrap();
+
+        let ids: Vec<ParameterId> = parameter_list.iter().map(Parameter::get_id).collect();
+        assert_eq!(vec![ParameterId::PID_PROTOCOL_VERSION], ids);
+    }
+
+    serialization_property_test!(type = ParameterList);
+}
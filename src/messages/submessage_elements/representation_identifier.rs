@@ -4,6 +4,7 @@ use std::convert::TryFrom;
 /// The RepresentationIdentifier is used to identify the data representation
 /// used.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RepresentationIdentifier {
     CDR_BE,
     CDR_LE,
@@ -138,3 +139,20 @@ impl<C: Context> Writable<C> for RepresentationIdentifier {
         Ok(())
     }
 }
+
+arbitrary_impl!(
+    RepresentationIdentifier,
+    prop_oneof![
+        Just(RepresentationIdentifier::CDR_BE),
+        Just(RepresentationIdentifier::CDR_LE),
+        Just(RepresentationIdentifier::PL_CDR_BE),
+        Just(RepresentationIdentifier::PL_CDR_LE),
+        Just(RepresentationIdentifier::CDR2_BE),
+        Just(RepresentationIdentifier::CDR2_LE),
+        Just(RepresentationIdentifier::PL_CDR2_BE),
+        Just(RepresentationIdentifier::PL_CDR2_LE),
+        Just(RepresentationIdentifier::D_CDR_BE),
+        Just(RepresentationIdentifier::D_CDR_LE),
+        Just(RepresentationIdentifier::XML),
+    ]
+);
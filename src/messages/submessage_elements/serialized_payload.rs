@@ -1,4 +1,4 @@
-use crate::common::size_tracking_context::SizeTrackingContext;
+use crate::common::size_tracking_context::{SizeTrackingContext, SizeTrackingEndianness};
 use crate::messages::submessage_elements::parameter_list::ParameterList;
 use crate::messages::submessage_elements::serialized_payload_header::SerializedPayloadHeader;
 use speedy::{Context, Readable, Reader, Writable, Writer};
@@ -6,6 +6,7 @@ use speedy::{Context, Readable, Reader, Writable, Writer};
 /// A SerializedPayload is either a ParameterList or user-defined data in an
 /// unspecified format.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SerializedPayloadContent {
     ParameterList(ParameterList),
     UserDefined(Box<[u8]>),
@@ -15,6 +16,7 @@ pub enum SerializedPayloadContent {
 /// either value of an application-defined data-object or
 /// the value of the key that uniquely identifies the data-object
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializedPayload {
     pub header: SerializedPayloadHeader,
     pub content: SerializedPayloadContent,
@@ -26,19 +28,30 @@ impl<'a, C: SizeTrackingContext> Readable<'a, C> for SerializedPayload {
         let header: SerializedPayloadHeader = reader.read_value()?;
         reader.context_mut().subtract_from_remaining(
             <SerializedPayloadHeader as Readable<C>>::minimum_bytes_needed()
-        );
+        )?;
 
         let representation_identifier = header.representation_identifier;
         let content = if representation_identifier.is_parameter_list() {
             // The contents of the SerializedPayload are to be parsed as a
-            // ParameterList.
-            let parameter_list: ParameterList = reader.read_value()?;
+            // ParameterList, with the endianness of the RepresentationIdentifier
+            // rather than the one of the SubmessageHeader.
+            let length = reader.context().length_remaining();
+            let mut bytes = vec![0; length];
+            reader.read_bytes(&mut bytes)?;
+            reader.context_mut().subtract_from_remaining(length)?;
+
+            let context =
+                SizeTrackingEndianness::new(representation_identifier.endianness(), length);
+            let parameter_list: ParameterList =
+                Readable::read_from_buffer_with_ctx(context, &bytes)?;
             SerializedPayloadContent::ParameterList(parameter_list)
         } else {
             // The contents of the SerializedPayload are to be parsed as user-
             // defined data in an unspecified format.
-            let mut payload = vec![0; reader.context().length_remaining()].into_boxed_slice();
+            let length = reader.context().length_remaining();
+            let mut payload = vec![0; length].into_boxed_slice();
             reader.read_bytes(&mut payload)?;
+            reader.context_mut().subtract_from_remaining(length)?;
             SerializedPayloadContent::UserDefined(payload)
         };
 
@@ -75,3 +88,29 @@ impl<C: Context> Writable<C> for SerializedPayload {
         Ok(())
     }
 }
+
+arbitrary_impl!(
+    SerializedPayload,
+    any::<SerializedPayloadHeader>().prop_flat_map(|header| {
+        let content = if header.representation_identifier.is_parameter_list() {
+            any::<ParameterList>()
+                .prop_map(SerializedPayloadContent::ParameterList)
+                .boxed()
+        } else {
+            proptest::collection::vec(any::<u8>(), 0..64)
+                .prop_map(|bytes| SerializedPayloadContent::UserDefined(bytes.into_boxed_slice()))
+                .boxed()
+        };
+        let SerializedPayloadHeader {
+            representation_identifier,
+            representation_options,
+        } = header;
+        content.prop_map(move |content| SerializedPayload {
+            header: SerializedPayloadHeader {
+                representation_identifier,
+                representation_options,
+            },
+            content,
+        })
+    })
+);
@@ -10,6 +10,7 @@ use speedy::{Context, Readable, Reader, Writable, Writer};
 /// RepresentationIdentifier, such that each RepresentationIdentifier may define
 /// the representation_options that it requires.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializedPayloadHeader {
     pub representation_identifier: RepresentationIdentifier,
     pub representation_options: [u8;2],
@@ -51,3 +52,13 @@ impl<C: Context> Writable<C> for SerializedPayloadHeader {
         Ok(())
     }
 }
+
+arbitrary_impl!(
+    SerializedPayloadHeader,
+    (any::<RepresentationIdentifier>(), any::<[u8; 2]>()).prop_map(
+        |(representation_identifier, representation_options)| SerializedPayloadHeader {
+            representation_identifier,
+            representation_options,
+        }
+    )
+);
@@ -8,6 +8,7 @@ use speedy::{Context, Endianness, Readable, Writable};
 /// flags are interpreted differently depending on the kind
 /// of Submessage and are described separately for each Submessage.
 #[derive(Clone, Copy, Debug, PartialOrd, PartialEq, Ord, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubmessageFlag {
     pub flags: u8,
 }
@@ -41,6 +42,11 @@ impl Context for SubmessageFlag {
     }
 }
 
+arbitrary_impl!(
+    SubmessageFlag,
+    any::<u8>().prop_map(|flags| SubmessageFlag { flags })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,4 +100,6 @@ mod tests {
         le = [0b10110100_u8],
         be = [0b10110100_u8]
     });
+
+    serialization_property_test!(type = SubmessageFlag);
 }
@@ -3,6 +3,7 @@ use crate::messages::submessage_kind::SubmessageKind;
 use speedy::{Context, Endianness, Readable, Reader, Writable, Writer};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubmessageHeader {
     pub submessage_id: SubmessageKind,
     pub flags: SubmessageFlag,
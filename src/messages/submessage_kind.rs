@@ -1,6 +1,7 @@
 use speedy::{Readable, Writable};
 
-#[derive(Debug, PartialEq, Eq, Readable, Writable)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubmessageKind {
     value: u8,
 }
@@ -19,12 +20,46 @@ impl SubmessageKind {
     pub const HEARTBEAT_FRAG: SubmessageKind = SubmessageKind { value: 0x13 };
     pub const DATA: SubmessageKind = SubmessageKind { value: 0x15 };
     pub const DATA_FRAG: SubmessageKind = SubmessageKind { value: 0x16 };
+
+    /// Returns the name of the submessage kind as used in the specification,
+    /// or None for vendor-specific and unknown submessages.
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            SubmessageKind::PAD => Some("PAD"),
+            SubmessageKind::ACKNACK => Some("ACKNACK"),
+            SubmessageKind::HEARTBEAT => Some("HEARTBEAT"),
+            SubmessageKind::GAP => Some("GAP"),
+            SubmessageKind::INFO_TS => Some("INFO_TS"),
+            SubmessageKind::INFO_SRC => Some("INFO_SRC"),
+            SubmessageKind::INFO_REPLY_IP4 => Some("INFO_REPLY_IP4"),
+            SubmessageKind::INFO_DST => Some("INFO_DST"),
+            SubmessageKind::INFO_REPLY => Some("INFO_REPLY"),
+            SubmessageKind::NACK_FRAG => Some("NACK_FRAG"),
+            SubmessageKind::HEARTBEAT_FRAG => Some("HEARTBEAT_FRAG"),
+            SubmessageKind::DATA => Some("DATA"),
+            SubmessageKind::DATA_FRAG => Some("DATA_FRAG"),
+            _ => None,
+        }
+    }
+}
+
+impl From<SubmessageKind> for u8 {
+    fn from(submessage_kind: SubmessageKind) -> Self {
+        submessage_kind.value
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn name_of_submessage_kind() {
+        assert_eq!(Some("DATA_FRAG"), SubmessageKind::DATA_FRAG.name());
+        assert_eq!(None, SubmessageKind { value: 0x80 }.name());
+        assert_eq!(0x80, u8::from(SubmessageKind { value: 0x80 }));
+    }
+
     serialization_test!( type = SubmessageKind,
     {
         submessage_kind_pad,
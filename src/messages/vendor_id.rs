@@ -1,6 +1,7 @@
 use speedy::{Context, Readable, Reader, Writable, Writer};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VendorId_t {
     pub vendor_id: [u8; 2],
 }
@@ -51,6 +52,8 @@ impl<C: Context> Writable<C> for VendorId_t {
     }
 }
 
+arbitrary_impl!(VendorId_t, any::<[u8; 2]>().prop_map(VendorId_t::from));
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +74,6 @@ mod tests {
         le = [0x00, 0x00],
         be = [0x00, 0x00]
     });
+
+    serialization_property_test!(type = VendorId_t);
 }
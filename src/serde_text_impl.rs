@@ -0,0 +1,19 @@
+/// Implements `Serialize` and `Deserialize` with the helpers of
+/// `common::serde_support`, when the `serde` feature is enabled.
+macro_rules! serde_text_impl {
+    ($type:ty) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                crate::common::serde_support::serialize(self, serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                crate::common::serde_support::deserialize(deserializer)
+            }
+        }
+    };
+}
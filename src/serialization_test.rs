@@ -41,3 +41,34 @@ macro_rules! serialization_test {
         })+
     }
 }
+
+/// Checks that every value generated by the `Arbitrary` implementation of the
+/// type is deserialized back to itself, in both endiannesses.
+#[cfg(test)]
+macro_rules! serialization_property_test {
+    (type = $type:ty) => {
+        mod serialization_properties {
+            use super::*;
+            use proptest::prelude::*;
+            use speedy::{Readable, Writable, Endianness};
+
+            proptest! {
+                #[test]
+                fn serialize_deserialize_little_endian(original in any::<$type>()) {
+                    let serialized = original.write_to_vec_with_ctx(Endianness::LittleEndian).unwrap();
+                    let deserialized: $type = Readable::read_from_buffer_with_ctx(Endianness::LittleEndian, &serialized).unwrap();
+
+                    prop_assert_eq!(original, deserialized);
+                }
+
+                #[test]
+                fn serialize_deserialize_big_endian(original in any::<$type>()) {
+                    let serialized = original.write_to_vec_with_ctx(Endianness::BigEndian).unwrap();
+                    let deserialized: $type = Readable::read_from_buffer_with_ctx(Endianness::BigEndian, &serialized).unwrap();
+
+                    prop_assert_eq!(original, deserialized);
+                }
+            }
+        }
+    };
+}
@@ -1,14 +1,525 @@
+use crate::messages::data::Data as DataSubmessage;
+use crate::messages::data_frag::DataFrag;
+use crate::messages::data_submessage_flags::DataSubmessageFlags;
+use crate::messages::fragment_number::FragmentNumber_t;
+use crate::messages::receiver::Receiver;
+use crate::messages::submessage_elements::parameter::Parameter;
+use crate::messages::submessage_elements::parameter_list::ParameterList;
+use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+use crate::messages::submessage_elements::serialized_payload::SerializedPayload;
+use crate::messages::submessage_flag::SubmessageFlag;
 use crate::structure::change_kind::ChangeKind_t;
 use crate::structure::data::Data;
+use crate::structure::entity_id::EntityId_t;
 use crate::structure::guid::GUID_t;
 use crate::structure::instance_handle::InstanceHandle_t;
+use crate::structure::parameter_id::ParameterId;
+use crate::structure::sample_identity::SampleIdentity;
 use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::time::Time_t;
+use crate::structure::topic_kind::TopicKind_t;
+use speedy::{Context, Endianness, Readable, Writable};
+use std::convert::TryFrom;
 
-#[derive(Debug, PartialOrd, PartialEq, Ord, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct CacheChange {
     pub kind: ChangeKind_t,
     pub writer_guid: GUID_t,
     pub instance_handle: InstanceHandle_t,
     pub sequence_number: SequenceNumber_t,
-    pub data_value: Data,
+
+    /// The serialized value of the data-object, or of its key. Absent when
+    /// the change only affects the lifecycle of the instance.
+    pub data_value: Option<Data>,
+
+    /// The inline QoS of the change, without the parameters held by the other
+    /// fields.
+    pub inline_qos: Option<ParameterList>,
+
+    /// When the change was made, as given by the writer.
+    pub source_timestamp: Option<Time_t>,
+
+    /// When the change was received, on the reader side.
+    pub reception_timestamp: Option<Time_t>,
+
+    /// The sample this change relates to, e.g. the request it replies to.
+    pub related_sample_identity: Option<SampleIdentity>,
+}
+
+impl CacheChange {
+    pub fn new(
+        kind: ChangeKind_t,
+        writer_guid: GUID_t,
+        instance_handle: InstanceHandle_t,
+        sequence_number: SequenceNumber_t,
+        data_value: Option<Data>,
+    ) -> CacheChange {
+        CacheChange {
+            kind,
+            writer_guid,
+            instance_handle,
+            sequence_number,
+            data_value,
+            inline_qos: None,
+            source_timestamp: None,
+            reception_timestamp: None,
+            related_sample_identity: None,
+        }
+    }
+
+    /// Creates the change from a received Data submessage. The writer GUID
+    /// prefix and the source timestamp are taken from the state of the
+    /// receiver when the submessage was interpreted. The kind of the change
+    /// is given by the PID_STATUS_INFO of the inline QoS, if any. The instance
+    /// handle is the PID_KEY_HASH of the inline QoS, if any, which is checked
+    /// against the serialized key when the submessage carries one in big
+    /// endian CDR; otherwise it can be checked against the key of the data
+    /// with `InstanceHandle_t::is_key_hash_of`.
+    pub fn from_data(
+        data: DataSubmessage,
+        flags: SubmessageFlag,
+        receiver: &Receiver,
+        reception_timestamp: Time_t,
+    ) -> Result<CacheChange, speedy::Error> {
+        let flags = DataSubmessageFlags::from(flags);
+        let endianness = flags.endianness();
+
+        let mut inline_qos = data.inline_qos;
+        let key_hash = match inline_qos
+            .as_mut()
+            .and_then(|inline_qos| inline_qos.remove(ParameterId::PID_KEY_HASH))
+        {
+            Some(parameter) if parameter.get_value().len() == 16 => Some(
+                InstanceHandle_t::read_from_buffer_with_ctx(endianness, parameter.get_value())?,
+            ),
+            Some(parameter) => {
+                return Err(speedy::Error::custom(format!(
+                    "PID_KEY_HASH of {} octets",
+                    parameter.get_value().len()
+                )))
+            }
+            None => None,
+        };
+        let kind = match inline_qos
+            .as_mut()
+            .and_then(|inline_qos| inline_qos.remove(ParameterId::PID_STATUS_INFO))
+        {
+            Some(parameter) => ChangeKind_t::from_status_info(
+                <[u8; 4]>::read_from_buffer_with_ctx(endianness, parameter.get_value())?,
+            ),
+            None => ChangeKind_t::ALIVE,
+        };
+        let related_sample_identity = match inline_qos
+            .as_mut()
+            .and_then(|inline_qos| inline_qos.remove(ParameterId::PID_RELATED_SAMPLE_IDENTITY))
+        {
+            Some(parameter) => Some(SampleIdentity::read_from_buffer_with_ctx(
+                endianness,
+                parameter.get_value(),
+            )?),
+            None => None,
+        };
+
+        let data_value = data.serialized_payload.map(Data::try_from).transpose()?;
+
+        // The maximum size of the serialized key of the type is not known
+        // here, so the KeyHash may be either the zero-padded key or its MD5
+        // digest.
+        if let (Some(key_hash), Some(key)) = (key_hash, data_value.as_ref()) {
+            let serialized_key = &key.value[..];
+            if flags.key_payload()
+                && key.representation_identifier == RepresentationIdentifier::CDR_BE
+                && !key_hash.is_key_hash_of(serialized_key, serialized_key.len())
+                && !key_hash.is_key_hash_of(serialized_key, usize::MAX)
+            {
+                return Err(speedy::Error::custom(
+                    "PID_KEY_HASH does not match the serialized key",
+                ));
+            }
+        }
+
+        Ok(CacheChange {
+            kind,
+            writer_guid: GUID_t {
+                guid_prefix: receiver.source_guid_prefix,
+                entity_id: data.writer_id,
+            },
+            instance_handle: key_hash.unwrap_or_default(),
+            sequence_number: data.writer_sn,
+            data_value,
+            inline_qos: inline_qos.filter(|inline_qos| !inline_qos.is_empty()),
+            source_timestamp: if receiver.have_timestamp {
+                Some(receiver.timestamp)
+            } else {
+                None
+            },
+            reception_timestamp: Some(reception_timestamp),
+            related_sample_identity,
+        })
+    }
+
+    /// Creates the Data submessage sending the change to the reader, with its
+    /// flags. The instance handle is sent as PID_KEY_HASH for the topics with
+    /// a key, and the kind of the change as PID_STATUS_INFO unless ALIVE. The
+    /// source timestamp is not part of the submessage and has to be sent in
+    /// an InfoTimestamp submessage before it.
+    pub fn to_data(
+        &self,
+        reader_id: EntityId_t,
+        topic_kind: &TopicKind_t,
+        endianness: Endianness,
+    ) -> Result<(DataSubmessage, SubmessageFlag), speedy::Error> {
+        let mut flags = SubmessageFlag { flags: 0 };
+        if endianness == Endianness::LittleEndian {
+            flags.set_flag(0x01);
+        }
+
+        let mut inline_qos = self.inline_qos.clone().unwrap_or_default();
+        if *topic_kind == TopicKind_t::WITH_KEY {
+            inline_qos.push(Parameter::new(
+                ParameterId::PID_KEY_HASH,
+                self.instance_handle.entity_key.to_vec(),
+            ));
+        }
+        if self.kind != ChangeKind_t::ALIVE {
+            inline_qos.push(Parameter::new(
+                ParameterId::PID_STATUS_INFO,
+                self.kind.status_info().to_vec(),
+            ));
+        }
+        if let Some(related_sample_identity) = self.related_sample_identity {
+            inline_qos.push(Parameter::new(
+                ParameterId::PID_RELATED_SAMPLE_IDENTITY,
+                related_sample_identity.write_to_vec_with_ctx(endianness)?,
+            ));
+        }
+        if !inline_qos.is_empty() {
+            flags.set_flag(DataSubmessageFlags::INLINE_QOS_FLAG_MASK);
+        }
+
+        if self.data_value.is_some() {
+            flags.set_flag(match self.kind {
+                ChangeKind_t::ALIVE => DataSubmessageFlags::DATA_FLAG_MASK,
+                _ => DataSubmessageFlags::KEY_FLAG_MASK,
+            });
+        }
+
+        let data = DataSubmessage {
+            reader_id,
+            writer_id: self.writer_guid.entity_id,
+            writer_sn: self.sequence_number,
+            inline_qos: Some(inline_qos).filter(|inline_qos| !inline_qos.is_empty()),
+            serialized_payload: self
+                .data_value
+                .as_ref()
+                .map(SerializedPayload::try_from)
+                .transpose()?,
+        };
+        Ok((data, flags))
+    }
+
+    /// Splits the Data submessage sending the change to the reader into
+    /// DataFrag submessages, one per fragment of fragment_size octets of the
+    /// serialized payload, with their flags. Each of them carries the inline
+    /// QoS. Returns None if the serialized payload fits in a single fragment,
+    /// in which case the change is sent as a Data submessage.
+    pub fn to_data_frags(
+        &self,
+        reader_id: EntityId_t,
+        topic_kind: &TopicKind_t,
+        endianness: Endianness,
+        fragment_size: u16,
+    ) -> Result<Option<Vec<(DataFrag, SubmessageFlag)>>, speedy::Error> {
+        let (data, data_flags) = self.to_data(reader_id, topic_kind, endianness)?;
+        let serialized_payload = match data.serialized_payload {
+            Some(ref serialized_payload) => serialized_payload.write_to_vec_with_ctx(endianness)?,
+            None => return Ok(None),
+        };
+        if fragment_size == 0 || serialized_payload.len() <= usize::from(fragment_size) {
+            return Ok(None);
+        }
+        let data_size = u32::try_from(serialized_payload.len())
+            .map_err(|_| speedy::Error::custom("serialized payload larger than 4 GiB"))?;
+
+        let mut flags = SubmessageFlag { flags: 0 };
+        if endianness == Endianness::LittleEndian {
+            flags.set_flag(0x01);
+        }
+        if data.inline_qos.is_some() {
+            flags.set_flag(DataFrag::INLINE_QOS_FLAG_MASK);
+        }
+        if DataSubmessageFlags::from(data_flags).key_payload() {
+            flags.set_flag(DataFrag::KEY_FLAG_MASK);
+        }
+
+        let data_frags = serialized_payload
+            .chunks(usize::from(fragment_size))
+            .enumerate()
+            .map(|(index, fragment)| {
+                let data_frag = DataFrag {
+                    reader_id,
+                    writer_id: data.writer_id,
+                    writer_sn: data.writer_sn,
+                    fragment_starting_num: FragmentNumber_t::from(index as u32 + 1),
+                    fragments_in_submessage: 1,
+                    data_size,
+                    fragment_size,
+                    inline_qos: data.inline_qos.clone(),
+                    serialized_payload: fragment.into(),
+                };
+                (data_frag, flags)
+            })
+            .collect();
+        Ok(Some(data_frags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+    use crate::structure::locator::LocatorKind_t;
+    use bytes::Bytes;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn receiver() -> Receiver {
+        let mut receiver = Receiver::new(LocatorKind_t::LOCATOR_KIND_UDPv4);
+        receiver.source_guid_prefix = "01.02.03.04.05.06.07.08.09.0a.0b.0c".parse().unwrap();
+        receiver
+    }
+
+    fn data_value() -> Data {
+        Data::new(
+            RepresentationIdentifier::CDR_LE,
+            Bytes::from_static(&[0x2a, 0x00, 0x00, 0x00]),
+        )
+    }
+
+    #[test]
+    fn data_round_trip() {
+        let mut cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.01.02"
+                .parse()
+                .unwrap(),
+            InstanceHandle_t::from_serialized_key(&[0x00, 0x00, 0x00, 0x2a], 4),
+            SequenceNumber_t::from(5),
+            Some(data_value()),
+        );
+        cache_change.inline_qos = Some(ParameterList::from(vec![Parameter::new(
+            ParameterId::PID_TOPIC_NAME,
+            vec![0x02, 0x00, 0x00, 0x00, 0x41, 0x00, 0x00, 0x00],
+        )]));
+        cache_change.related_sample_identity = Some(SampleIdentity {
+            writer_guid: "0c.0b.0a.09.08.07.06.05.04.03.02.01|00.00.01.03"
+                .parse()
+                .unwrap(),
+            sequence_number: SequenceNumber_t::from(2),
+        });
+
+        let reader_id: EntityId_t = "00.00.01.07".parse().unwrap();
+        let (data, flags) = cache_change
+            .to_data(reader_id, &TopicKind_t::WITH_KEY, Endianness::BigEndian)
+            .unwrap();
+        // Q and D
+        assert_eq!(SubmessageFlag { flags: 0x06 }, flags);
+        assert_eq!(reader_id, data.reader_id);
+        assert_eq!(cache_change.writer_guid.entity_id, data.writer_id);
+        assert_eq!(SequenceNumber_t::from(5), data.writer_sn);
+        assert_eq!(3, data.inline_qos.as_ref().unwrap().iter().count());
+        assert_eq!(
+            &[
+                0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00
+            ],
+            data.inline_qos
+                .as_ref()
+                .unwrap()
+                .get(ParameterId::PID_KEY_HASH)
+                .unwrap()
+                .get_value()
+        );
+
+        let mut receiver = receiver();
+        let source_timestamp = Time_t::from(UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+        receiver.have_timestamp = true;
+        receiver.timestamp = source_timestamp;
+        let reception_timestamp = Time_t::from(UNIX_EPOCH + Duration::from_secs(1_600_000_001));
+
+        let received = CacheChange::from_data(data, flags, &receiver, reception_timestamp).unwrap();
+        assert_eq!(
+            CacheChange {
+                source_timestamp: Some(source_timestamp),
+                reception_timestamp: Some(reception_timestamp),
+                ..cache_change
+            },
+            received
+        );
+    }
+
+    #[test]
+    fn from_data_without_timestamp_and_inline_qos() {
+        let data = DataSubmessage {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: "00.00.01.02".parse().unwrap(),
+            writer_sn: SequenceNumber_t::from(1),
+            inline_qos: None,
+            serialized_payload: Some(SerializedPayload::try_from(&data_value()).unwrap()),
+        };
+
+        let cache_change = CacheChange::from_data(
+            data,
+            SubmessageFlag { flags: 0x05 },
+            &receiver(),
+            Time_t::TIME_ZERO,
+        )
+        .unwrap();
+        assert_eq!(
+            "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.01.02"
+                .parse::<GUID_t>()
+                .unwrap(),
+            cache_change.writer_guid
+        );
+        assert_eq!(Some(data_value()), cache_change.data_value);
+        assert_eq!(None, cache_change.inline_qos);
+        assert_eq!(None, cache_change.source_timestamp);
+        assert_eq!(None, cache_change.related_sample_identity);
+    }
+
+    #[test]
+    fn to_data_of_key_only_change() {
+        let cache_change = CacheChange::new(
+            ChangeKind_t::NOT_ALIVE_DISPOSED,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(1),
+            Some(data_value()),
+        );
+
+        let (data, flags) = cache_change
+            .to_data(
+                EntityId_t::ENTITYID_UNKNOWN,
+                &TopicKind_t::NO_KEY,
+                Endianness::LittleEndian,
+            )
+            .unwrap();
+        // E, Q and K
+        assert_eq!(SubmessageFlag { flags: 0x0b }, flags);
+        assert_eq!(
+            &[0x00, 0x00, 0x00, 0x01],
+            data.inline_qos
+                .as_ref()
+                .unwrap()
+                .get(ParameterId::PID_STATUS_INFO)
+                .unwrap()
+                .get_value()
+        );
+        assert!(data.serialized_payload.is_some());
+
+        let received = CacheChange::from_data(data, flags, &receiver(), Time_t::TIME_ZERO).unwrap();
+        assert_eq!(ChangeKind_t::NOT_ALIVE_DISPOSED, received.kind);
+        assert_eq!(None, received.inline_qos);
+    }
+
+    #[test]
+    fn to_data_frags_splits_large_payloads() {
+        let cache_change = CacheChange::new(
+            ChangeKind_t::NOT_ALIVE_DISPOSED,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(1),
+            Some(data_value()),
+        );
+        let to_data_frags = |fragment_size| {
+            cache_change
+                .to_data_frags(
+                    EntityId_t::ENTITYID_UNKNOWN,
+                    &TopicKind_t::NO_KEY,
+                    Endianness::LittleEndian,
+                    fragment_size,
+                )
+                .unwrap()
+        };
+
+        // The SerializedPayloadHeader and the value make 8 octets.
+        assert_eq!(None, to_data_frags(8));
+        let data_frags = to_data_frags(3).unwrap();
+        assert_eq!(3, data_frags.len());
+        for (index, (data_frag, flags)) in data_frags.iter().enumerate() {
+            // E, Q and K
+            assert_eq!(SubmessageFlag { flags: 0x07 }, *flags);
+            assert_eq!(
+                FragmentNumber_t::from(index as u32 + 1),
+                data_frag.fragment_starting_num
+            );
+            assert_eq!(8, data_frag.data_size);
+            assert!(data_frag.inline_qos.is_some());
+        }
+        assert_eq!(&[0x00, 0x00][..], &data_frags[2].0.serialized_payload[..]);
+    }
+
+    #[test]
+    fn from_data_rejects_malformed_key_hash() {
+        let data = DataSubmessage {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: "00.00.01.02".parse().unwrap(),
+            writer_sn: SequenceNumber_t::from(1),
+            inline_qos: Some(ParameterList::from(vec![Parameter::new(
+                ParameterId::PID_KEY_HASH,
+                vec![0x01, 0x02, 0x03, 0x04],
+            )])),
+            serialized_payload: None,
+        };
+
+        assert!(CacheChange::from_data(
+            data,
+            SubmessageFlag { flags: 0x03 },
+            &receiver(),
+            Time_t::TIME_ZERO
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn from_data_checks_key_hash_against_the_key() {
+        let key = Data::new(
+            RepresentationIdentifier::CDR_BE,
+            Bytes::from_static(&[0x00, 0x00, 0x00, 0x2a]),
+        );
+        let dispose = |key_hash: InstanceHandle_t| DataSubmessage {
+            reader_id: EntityId_t::ENTITYID_UNKNOWN,
+            writer_id: "00.00.01.02".parse().unwrap(),
+            writer_sn: SequenceNumber_t::from(1),
+            inline_qos: Some(ParameterList::from(vec![
+                Parameter::new(ParameterId::PID_KEY_HASH, key_hash.entity_key.to_vec()),
+                Parameter::new(ParameterId::PID_STATUS_INFO, vec![0x00, 0x00, 0x00, 0x01]),
+            ])),
+            serialized_payload: Some(SerializedPayload::try_from(&key).unwrap()),
+        };
+        let flags = SubmessageFlag {
+            flags: 0x01
+                | DataSubmessageFlags::INLINE_QOS_FLAG_MASK
+                | DataSubmessageFlags::KEY_FLAG_MASK,
+        };
+
+        let key_hash = InstanceHandle_t::from_serialized_key(&key.value, 4);
+        let change =
+            CacheChange::from_data(dispose(key_hash), flags, &receiver(), Time_t::TIME_ZERO)
+                .unwrap();
+        assert_eq!(key_hash, change.instance_handle);
+
+        let digest = InstanceHandle_t::from_serialized_key(&key.value, 64);
+        assert!(
+            CacheChange::from_data(dispose(digest), flags, &receiver(), Time_t::TIME_ZERO).is_ok()
+        );
+
+        let other_key_hash = InstanceHandle_t::from_serialized_key(&[0x00, 0x00, 0x00, 0x2b], 4);
+        assert!(CacheChange::from_data(
+            dispose(other_key_hash),
+            flags,
+            &receiver(),
+            Time_t::TIME_ZERO
+        )
+        .is_err());
+    }
 }
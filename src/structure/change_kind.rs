@@ -1,6 +1,79 @@
-#[derive(Debug, PartialOrd, PartialEq, Ord, Eq)]
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChangeKind_t {
     ALIVE,
     NOT_ALIVE_DISPOSED,
     NOT_ALIVE_UNREGISTERED,
+    NOT_ALIVE_DISPOSED_UNREGISTERED,
+}
+
+impl ChangeKind_t {
+    /// Flag of the StatusInfo_t telling that the instance was disposed.
+    pub const STATUS_INFO_DISPOSED_FLAG: u8 = 0x01;
+    /// Flag of the StatusInfo_t telling that the instance was unregistered.
+    pub const STATUS_INFO_UNREGISTERED_FLAG: u8 = 0x02;
+
+    /// The StatusInfo_t sent as PID_STATUS_INFO in the inline QoS of a change
+    /// of this kind. The flags are in the last octet regardless of the
+    /// endianness.
+    pub fn status_info(self) -> [u8; 4] {
+        let flags = match self {
+            ChangeKind_t::ALIVE => 0,
+            ChangeKind_t::NOT_ALIVE_DISPOSED => Self::STATUS_INFO_DISPOSED_FLAG,
+            ChangeKind_t::NOT_ALIVE_UNREGISTERED => Self::STATUS_INFO_UNREGISTERED_FLAG,
+            ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED => {
+                Self::STATUS_INFO_DISPOSED_FLAG | Self::STATUS_INFO_UNREGISTERED_FLAG
+            }
+        };
+        [0x00, 0x00, 0x00, flags]
+    }
+
+    /// The kind of a change received with the StatusInfo_t. Unknown flags are
+    /// ignored.
+    pub fn from_status_info(status_info: [u8; 4]) -> ChangeKind_t {
+        let disposed = status_info[3] & Self::STATUS_INFO_DISPOSED_FLAG != 0;
+        let unregistered = status_info[3] & Self::STATUS_INFO_UNREGISTERED_FLAG != 0;
+        match (disposed, unregistered) {
+            (false, false) => ChangeKind_t::ALIVE,
+            (true, false) => ChangeKind_t::NOT_ALIVE_DISPOSED,
+            (false, true) => ChangeKind_t::NOT_ALIVE_UNREGISTERED,
+            (true, true) => ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_info() {
+        assert_eq!([0, 0, 0, 0], ChangeKind_t::ALIVE.status_info());
+        assert_eq!([0, 0, 0, 1], ChangeKind_t::NOT_ALIVE_DISPOSED.status_info());
+        assert_eq!(
+            [0, 0, 0, 2],
+            ChangeKind_t::NOT_ALIVE_UNREGISTERED.status_info()
+        );
+        assert_eq!(
+            [0, 0, 0, 3],
+            ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED.status_info()
+        );
+    }
+
+    #[test]
+    fn from_status_info() {
+        for kind in &[
+            ChangeKind_t::ALIVE,
+            ChangeKind_t::NOT_ALIVE_DISPOSED,
+            ChangeKind_t::NOT_ALIVE_UNREGISTERED,
+            ChangeKind_t::NOT_ALIVE_DISPOSED_UNREGISTERED,
+        ] {
+            assert_eq!(*kind, ChangeKind_t::from_status_info(kind.status_info()));
+        }
+        // The filtered flag of 9.6.3.9 doesn't change the kind.
+        assert_eq!(
+            ChangeKind_t::NOT_ALIVE_DISPOSED,
+            ChangeKind_t::from_status_info([0, 0, 0, 0x05])
+        );
+    }
 }
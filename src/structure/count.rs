@@ -2,6 +2,7 @@ use speedy::{Readable, Writable};
 use std::convert::From;
 
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Count_t {
     value: i32,
 }
@@ -18,6 +19,24 @@ impl From<Count_t> for i32 {
     }
 }
 
+impl Count_t {
+    /// The count incremented by value, which wraps around from i32::MAX to
+    /// i32::MIN.
+    pub fn wrapping_add(self, value: i32) -> Count_t {
+        Count_t {
+            value: self.value.wrapping_add(value),
+        }
+    }
+
+    /// Tells whether the count comes after the other one, once they wrapped
+    /// around as well, provided they are less than 2^31 apart.
+    pub fn is_after(self, other: Count_t) -> bool {
+        self.value.wrapping_sub(other.value) > 0
+    }
+}
+
+arbitrary_impl!(Count_t, any::<i32>().prop_map(Count_t::from));
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,4 +67,18 @@ mod tests {
             be = [0x1E, 0xAD, 0xBE, 0xFF]
         }
     );
+
+    serialization_property_test!(type = Count_t);
+
+    #[test]
+    fn counts_are_ordered_across_the_wrap_around() {
+        let last = Count_t::from(i32::MAX);
+        let next = last.wrapping_add(1);
+        assert_eq!(Count_t::from(i32::MIN), next);
+        assert!(next.is_after(last));
+        assert!(!last.is_after(next));
+        assert!(!last.is_after(last));
+        assert!(Count_t::from(3).is_after(Count_t::from(1)));
+        assert!(!Count_t::from(1).is_after(Count_t::from(3)));
+    }
 }
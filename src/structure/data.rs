@@ -1,2 +1,155 @@
-#[derive(Debug, PartialOrd, PartialEq, Ord, Eq)]
-pub struct Data {}
+use crate::common::size_tracking_context::SizeTrackingEndianness;
+use crate::messages::submessage_elements::parameter_list::ParameterList;
+use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+use crate::messages::submessage_elements::serialized_payload::{
+    SerializedPayload, SerializedPayloadContent,
+};
+use crate::messages::submessage_elements::serialized_payload_header::SerializedPayloadHeader;
+use bytes::Bytes;
+use speedy::{Context, Readable, Writable};
+use std::convert::TryFrom;
+
+/// Serialized value of a data-object, or of its key, as held in a
+/// CacheChange.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Data {
+    pub representation_identifier: RepresentationIdentifier,
+    pub representation_options: [u8; 2],
+
+    /// The serialized value, which follows the SerializedPayloadHeader in a
+    /// SerializedPayload.
+    pub value: Bytes,
+}
+
+impl Data {
+    pub fn new(representation_identifier: RepresentationIdentifier, value: Bytes) -> Data {
+        Data {
+            representation_identifier,
+            representation_options: [0; 2],
+            value,
+        }
+    }
+}
+
+/// A ParameterList payload is serialized back with the endianness of its
+/// RepresentationIdentifier.
+impl TryFrom<SerializedPayload> for Data {
+    type Error = speedy::Error;
+
+    fn try_from(serialized_payload: SerializedPayload) -> Result<Self, Self::Error> {
+        let SerializedPayload { header, content } = serialized_payload;
+        let value = match content {
+            SerializedPayloadContent::ParameterList(parameter_list) => {
+                Bytes::from(parameter_list.write_to_vec_with_ctx(header.representation_identifier)?)
+            }
+            SerializedPayloadContent::UserDefined(payload) => Bytes::from(payload),
+        };
+
+        Ok(Data {
+            representation_identifier: header.representation_identifier,
+            representation_options: header.representation_options,
+            value,
+        })
+    }
+}
+
+/// The value is parsed as a ParameterList if the RepresentationIdentifier
+/// says so.
+impl TryFrom<&Data> for SerializedPayload {
+    type Error = speedy::Error;
+
+    fn try_from(data: &Data) -> Result<Self, Self::Error> {
+        let representation_identifier = data.representation_identifier;
+        let content = if representation_identifier.is_parameter_list() {
+            let context = SizeTrackingEndianness::new(
+                representation_identifier.endianness(),
+                data.value.len(),
+            );
+            SerializedPayloadContent::ParameterList(ParameterList::read_from_buffer_with_ctx(
+                context,
+                &data.value,
+            )?)
+        } else {
+            SerializedPayloadContent::UserDefined(data.value.to_vec().into_boxed_slice())
+        };
+
+        Ok(SerializedPayload {
+            header: SerializedPayloadHeader {
+                representation_identifier,
+                representation_options: data.representation_options,
+            },
+            content,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::submessage_elements::parameter::Parameter;
+    use crate::structure::parameter_id::ParameterId;
+
+    #[test]
+    fn user_defined_payload() {
+        let serialized_payload = SerializedPayload {
+            header: SerializedPayloadHeader {
+                representation_identifier: RepresentationIdentifier::CDR_BE,
+                representation_options: [0x00, 0x02],
+            },
+            content: SerializedPayloadContent::UserDefined(vec![0x2a, 0x00, 0x00].into()),
+        };
+
+        let data = Data::try_from(serialized_payload).unwrap();
+        assert_eq!(
+            Data {
+                representation_identifier: RepresentationIdentifier::CDR_BE,
+                representation_options: [0x00, 0x02],
+                value: Bytes::from_static(&[0x2a, 0x00, 0x00]),
+            },
+            data
+        );
+
+        let serialized_payload = SerializedPayload::try_from(&data).unwrap();
+        assert_eq!(
+            SerializedPayloadContent::UserDefined(vec![0x2a, 0x00, 0x00].into()),
+            serialized_payload.content
+        );
+    }
+
+    #[test]
+    fn parameter_list_payload() {
+        let parameter_list = ParameterList::from(vec![Parameter::new(
+            ParameterId::PID_TOPIC_NAME,
+            vec![0x02, 0x00, 0x00, 0x00, 0x41, 0x00],
+        )]);
+        let serialized_payload = SerializedPayload {
+            header: SerializedPayloadHeader {
+                representation_identifier: RepresentationIdentifier::PL_CDR_BE,
+                representation_options: [0x00, 0x00],
+            },
+            content: SerializedPayloadContent::ParameterList(parameter_list),
+        };
+
+        let data = Data::try_from(serialized_payload).unwrap();
+        assert_eq!(
+            Bytes::from_static(&[
+                0x00, 0x05, 0x00, 0x08, // PID_TOPIC_NAME
+                0x02, 0x00, 0x00, 0x00, 0x41, 0x00, 0x00, 0x00, // padded value
+                0x00, 0x01, 0x00, 0x00, // PID_SENTINEL
+            ]),
+            data.value
+        );
+
+        let serialized_payload = SerializedPayload::try_from(&data).unwrap();
+        match serialized_payload.content {
+            SerializedPayloadContent::ParameterList(parameter_list) => assert_eq!(
+                &[0x02, 0x00, 0x00, 0x00, 0x41, 0x00, 0x00, 0x00],
+                parameter_list
+                    .get(ParameterId::PID_TOPIC_NAME)
+                    .unwrap()
+                    .get_value()
+            ),
+            content => panic!("expected a parameter list, got {:?}", content),
+        }
+    }
+}
@@ -2,7 +2,8 @@ use speedy::{Readable, Writable};
 use std::convert::From;
 use std::time::Duration;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Readable, Writable)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Duration_t {
     seconds: i32,
     fraction: u32,
@@ -38,6 +39,11 @@ impl From<Duration_t> for Duration {
     }
 }
 
+arbitrary_impl!(
+    Duration_t,
+    any::<(i32, u32)>().prop_map(|(seconds, fraction)| Duration_t { seconds, fraction })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,4 +109,6 @@ mod tests {
             Duration::from_nanos(1_519_152_760 * NANOS_PER_SEC + 1_328_210_046)
         );
     }
+
+    serialization_property_test!(type = Duration_t);
 }
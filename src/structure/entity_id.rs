@@ -1,5 +1,8 @@
+use crate::structure::parse_error::{parse_octets, write_octets, ParseError};
 use speedy::{Context, Readable, Reader, Writable, Writer};
+use std::fmt;
 use std::mem::size_of;
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq)]
 pub struct EntityId_t {
@@ -58,6 +61,100 @@ impl EntityId_t {
     };
 }
 
+impl EntityId_t {
+    const BUILTIN: [(EntityId_t, &'static str); 12] = [
+        (EntityId_t::ENTITYID_UNKNOWN, "UNKNOWN"),
+        (EntityId_t::ENTITYID_PARTICIPANT, "PARTICIPANT"),
+        (
+            EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_WRITER,
+            "SEDP_BUILTIN_TOPIC_WRITER",
+        ),
+        (
+            EntityId_t::ENTITYID_SEDP_BUILTIN_TOPIC_READER,
+            "SEDP_BUILTIN_TOPIC_READER",
+        ),
+        (
+            EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_WRITER,
+            "SEDP_BUILTIN_PUBLICATIONS_WRITER",
+        ),
+        (
+            EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER,
+            "SEDP_BUILTIN_PUBLICATIONS_READER",
+        ),
+        (
+            EntityId_t::ENTITYID_SEDP_BUILTIN_SUBSCRIPTIONS_WRITER,
+            "SEDP_BUILTIN_SUBSCRIPTIONS_WRITER",
+        ),
+        (
+            EntityId_t::ENTITYID_SEDP_BUILTIN_SUBSCRIPTIONS_READER,
+            "SEDP_BUILTIN_SUBSCRIPTIONS_READER",
+        ),
+        (
+            EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+            "SPDP_BUILTIN_PARTICIPANT_WRITER",
+        ),
+        (
+            EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER,
+            "SPDP_BUILTIN_PARTICIPANT_READER",
+        ),
+        (
+            EntityId_t::ENTITYID_P2P_BUILTIN_PARTICIPANT_MESSAGE_WRITER,
+            "P2P_BUILTIN_PARTICIPANT_MESSAGE_WRITER",
+        ),
+        (
+            EntityId_t::ENTITYID_P2P_BUILTIN_PARTICIPANT_MESSAGE_READER,
+            "P2P_BUILTIN_PARTICIPANT_MESSAGE_READER",
+        ),
+    ];
+
+    /// Name of the predefined entity, without the `ENTITYID_` prefix.
+    pub fn name(&self) -> Option<&'static str> {
+        EntityId_t::BUILTIN
+            .iter()
+            .find(|(entity_id, _)| entity_id == self)
+            .map(|(_, name)| *name)
+    }
+
+    /// Writes the entity id as dot separated octets, ignoring its name.
+    pub(crate) fn fmt_octets(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(f, &self.entity_key)?;
+        write!(f, ".{:02x}", self.entity_kind)
+    }
+}
+
+/// Predefined entities are written by name, e.g.
+/// `SPDP_BUILTIN_PARTICIPANT_WRITER`, the others as octets, e.g. `00.00.04.c7`.
+impl fmt::Display for EntityId_t {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => self.fmt_octets(f),
+        }
+    }
+}
+
+/// Accepts the names of the predefined entities, with or without the
+/// `ENTITYID_` prefix, and dot separated octets.
+impl FromStr for EntityId_t {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("ENTITYID_").unwrap_or(s);
+        if let Some((entity_id, _)) = EntityId_t::BUILTIN.iter().find(|(_, known)| *known == name) {
+            return Ok(*entity_id);
+        }
+
+        let mut octets = [0; 4];
+        parse_octets(s, &mut octets)?;
+        Ok(EntityId_t {
+            entity_key: [octets[0], octets[1], octets[2]],
+            entity_kind: octets[3],
+        })
+    }
+}
+
+serde_text_impl!(EntityId_t);
+
 impl Default for EntityId_t {
     fn default() -> EntityId_t {
         EntityId_t::ENTITYID_UNKNOWN
@@ -91,10 +188,60 @@ impl<C: Context> Writable<C> for EntityId_t {
     }
 }
 
+arbitrary_impl!(
+    EntityId_t,
+    any::<([u8; 3], u8)>().prop_map(|(entity_key, entity_kind)| EntityId_t {
+        entity_key,
+        entity_kind,
+    })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn display_predefined_entity_by_name() {
+        assert_eq!(
+            "SPDP_BUILTIN_PARTICIPANT_WRITER",
+            EntityId_t::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER.to_string()
+        );
+        assert_eq!("PARTICIPANT", EntityId_t::ENTITYID_PARTICIPANT.to_string());
+        assert_eq!("UNKNOWN", EntityId_t::ENTITYID_UNKNOWN.to_string());
+    }
+
+    #[test]
+    fn display_user_entity_as_octets() {
+        let entity_id = EntityId_t {
+            entity_key: [0x00, 0x00, 0x12],
+            entity_kind: 0x07,
+        };
+        assert_eq!(None, entity_id.name());
+        assert_eq!("00.00.12.07", entity_id.to_string());
+    }
+
+    #[test]
+    fn parse_entity_id() {
+        assert_eq!(
+            Ok(EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER),
+            "SEDP_BUILTIN_PUBLICATIONS_READER".parse()
+        );
+        assert_eq!(
+            Ok(EntityId_t::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER),
+            "ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER".parse()
+        );
+        assert_eq!(Ok(EntityId_t::ENTITYID_PARTICIPANT), "00.00.01.c1".parse());
+        assert_eq!(
+            Ok(EntityId_t {
+                entity_key: [0x12, 0x34, 0x56],
+                entity_kind: 0x02,
+            }),
+            "12.34.56.02".parse()
+        );
+        assert!("SPDP_BUILTIN_WRITER".parse::<EntityId_t>().is_err());
+        assert!("00.00.01".parse::<EntityId_t>().is_err());
+    }
+
     serialization_test!( type = EntityId_t,
         {
             entity_unknown,
@@ -175,4 +322,6 @@ mod tests {
             be = [0x00, 0x02, 0x00, 0xC7]
         }
     );
+
+    serialization_property_test!(type = EntityId_t);
 }
@@ -1,6 +1,9 @@
 use crate::structure::entity_id::EntityId_t;
 use crate::structure::guid_prefix::GuidPrefix_t;
+use crate::structure::parse_error::ParseError;
 use speedy::{Readable, Writable};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Readable, Writable)]
 pub struct GUID_t {
@@ -15,6 +18,40 @@ impl GUID_t {
     };
 }
 
+/// Written as the prefix and the entity id octets separated by a bar, e.g.
+/// `01.0f.bb.1d.df.2b.00.00.00.00.00.00|00.00.01.c1`.
+impl fmt::Display for GUID_t {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}|", self.guid_prefix)?;
+        self.entity_id.fmt_octets(f)
+    }
+}
+
+/// The entity id may also be given by name, as accepted by `EntityId_t`.
+impl FromStr for GUID_t {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = s
+            .find('|')
+            .ok_or_else(|| ParseError::MissingSeparator("|", s.to_owned()))?;
+        Ok(GUID_t {
+            guid_prefix: s[..separator].parse()?,
+            entity_id: s[separator + 1..].parse()?,
+        })
+    }
+}
+
+serde_text_impl!(GUID_t);
+
+arbitrary_impl!(
+    GUID_t,
+    (any::<GuidPrefix_t>(), any::<EntityId_t>()).prop_map(|(guid_prefix, entity_id)| GUID_t {
+        guid_prefix,
+        entity_id,
+    })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -30,6 +67,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_and_parse() {
+        let guid = GUID_t {
+            guid_prefix: GuidPrefix_t::from([
+                0x01, 0x0f, 0xbb, 0x1d, 0xdf, 0x2b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ]),
+            entity_id: EntityId_t::ENTITYID_PARTICIPANT,
+        };
+        let text = "01.0f.bb.1d.df.2b.00.00.00.00.00.00|00.00.01.c1";
+
+        assert_eq!(text, guid.to_string());
+        assert_eq!(Ok(guid), text.parse());
+        assert_eq!(
+            Ok(guid),
+            "01.0f.bb.1d.df.2b.00.00.00.00.00.00|PARTICIPANT".parse()
+        );
+    }
+
+    #[test]
+    fn parse_invalid_guid() {
+        assert_eq!(
+            Err(ParseError::MissingSeparator(
+                "|",
+                "01.0f.bb.1d.df.2b.00.00.00.00.00.00".to_owned()
+            )),
+            "01.0f.bb.1d.df.2b.00.00.00.00.00.00".parse::<GUID_t>()
+        );
+        assert!("01.0f|00.00.01.c1".parse::<GUID_t>().is_err());
+    }
+
     serialization_test!( type = GUID_t,
         {
             guid_unknown,
@@ -59,4 +126,6 @@ mod tests {
                   0x00, 0x00, 0x01, 0xC1]
         }
     );
+
+    serialization_property_test!(type = GUID_t);
 }
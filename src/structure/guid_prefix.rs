@@ -1,4 +1,7 @@
+use crate::structure::parse_error::{parse_octets, write_octets, ParseError};
 use speedy::{Context, Readable, Reader, Writable, Writer};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq)]
 pub struct GuidPrefix_t {
@@ -25,6 +28,24 @@ impl From<[u8; 12]> for GuidPrefix_t {
     }
 }
 
+impl fmt::Display for GuidPrefix_t {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(f, &self.entity_key)
+    }
+}
+
+impl FromStr for GuidPrefix_t {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut guid_prefix = GuidPrefix_t::default();
+        parse_octets(s, &mut guid_prefix.entity_key)?;
+        Ok(guid_prefix)
+    }
+}
+
+serde_text_impl!(GuidPrefix_t);
+
 impl<'a, C: Context> Readable<'a, C> for GuidPrefix_t {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
@@ -51,6 +72,8 @@ impl<C: Context> Writable<C> for GuidPrefix_t {
     }
 }
 
+arbitrary_impl!(GuidPrefix_t, any::<[u8; 12]>().prop_map(GuidPrefix_t::from));
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,6 +87,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_and_parse() {
+        let guid_prefix = GuidPrefix_t::from([
+            0x01, 0x0f, 0xbb, 0x1d, 0xdf, 0x2b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        let text = "01.0f.bb.1d.df.2b.00.00.00.00.00.00";
+
+        assert_eq!(text, guid_prefix.to_string());
+        assert_eq!(Ok(guid_prefix), text.parse());
+        assert_eq!(
+            Ok(GuidPrefix_t::GUIDPREFIX_UNKNOWN),
+            "00.00.00.00.00.00.00.00.00.00.00.00".parse()
+        );
+    }
+
+    #[test]
+    fn parse_invalid_guid_prefix() {
+        assert!("01.0f.bb.1d".parse::<GuidPrefix_t>().is_err());
+        assert!("010fbb1ddf2b000000000000".parse::<GuidPrefix_t>().is_err());
+    }
+
     serialization_test!( type = GuidPrefix_t,
     {
         guid_prefix_unknown,
@@ -88,4 +132,6 @@ mod tests {
         be = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
               0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB]
     });
+
+    serialization_property_test!(type = GuidPrefix_t);
 }
@@ -1,124 +1,642 @@
+use crate::dds::qos::{limit_reached, HistoryQosPolicy, ResourceLimitsQosPolicy};
 use crate::structure::cache_change::CacheChange;
+use crate::structure::guid::GUID_t;
+use crate::structure::history_store::HistoryStore;
+use crate::structure::instance_handle::InstanceHandle_t;
 use crate::structure::sequence_number::SequenceNumber_t;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use log::error;
+use num_traits::CheckedAdd;
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::RangeBounds;
 
-struct HistoryCache {
-    changes: Vec<CacheChange>,
+/// Outcome of adding a change to the HistoryCache.
+#[derive(Debug, PartialEq)]
+pub enum AddChangeResult {
+    /// The change was added.
+    Added,
+
+    /// The change was added in place of the oldest change of its instance,
+    /// which is returned, as the KEEP_LAST depth was reached.
+    ReplacedOldest(CacheChange),
+
+    /// The change was not added and is given back.
+    Rejected(CacheChange, RejectedReason),
+}
+
+/// Why a change was not added to the HistoryCache.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RejectedReason {
+    /// A change with the same writer and sequence number is already held.
+    Duplicate,
+
+    /// The change is of a new instance and max_instances was reached.
+    InstancesLimit,
+
+    /// max_samples was reached.
+    SamplesLimit,
+
+    /// The KEEP_ALL history of the instance reached max_samples_per_instance.
+    SamplesPerInstanceLimit,
+
+    /// The store of the HistoryCache failed to store the change, with an
+    /// error of the kind.
+    StoreFailed(io::ErrorKind),
+}
+
+/// Change to the HistoryCache, as notified to its subscribers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HistoryCacheEvent {
+    /// The change was added.
+    Added {
+        writer_guid: GUID_t,
+        sequence_number: SequenceNumber_t,
+        instance_handle: InstanceHandle_t,
+    },
+
+    /// The change was removed, or replaced by a newer change of its instance.
+    Removed {
+        writer_guid: GUID_t,
+        sequence_number: SequenceNumber_t,
+        instance_handle: InstanceHandle_t,
+    },
+
+    /// All the changes of the writer up to and including the sequence number
+    /// were acknowledged by all the matched readers.
+    Acknowledged {
+        writer_guid: GUID_t,
+        sequence_number: SequenceNumber_t,
+    },
+}
+
+/// Stream of the events of a HistoryCache, returned by
+/// [`HistoryCache::subscribe`]. It ends when the HistoryCache is dropped.
+pub type HistoryCacheEvents = UnboundedReceiver<HistoryCacheEvent>;
+
+/// Container class used to temporarily store and manage sets of changes to
+/// data-objects.
+///
+/// The changes are indexed by the GUID of their writer and then by their
+/// sequence number, so that the lookups, the minimum and maximum sequence
+/// numbers and the removal of the oldest changes don't depend on the number
+/// of changes held. On the writer side all the changes come from the same
+/// writer; on the reader side there is one index per matched writer.
+///
+/// The changes are also indexed by instance, in the order they were added, to
+/// apply the HISTORY and RESOURCE_LIMITS QoS.
+///
+/// A HistoryCache created with a [`HistoryStore`] keeps it in step with the
+/// changes it holds, so that they outlive the process: a change the store
+/// fails to store is not added, and one it fails to remove is not removed.
+#[derive(Debug)]
+pub struct HistoryCache {
+    changes: BTreeMap<GUID_t, BTreeMap<SequenceNumber_t, Entry>>,
+    instances: BTreeMap<InstanceHandle_t, BTreeMap<u64, (GUID_t, SequenceNumber_t)>>,
+    history: HistoryQosPolicy,
+    resource_limits: ResourceLimitsQosPolicy,
+    len: usize,
+    next_order: u64,
+    subscribers: Vec<UnboundedSender<HistoryCacheEvent>>,
+    store: Option<Box<dyn HistoryStore>>,
+}
+
+impl Default for HistoryCache {
+    fn default() -> Self {
+        HistoryCache::new()
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Position of the change in the order the changes were added.
+    order: u64,
+    change: CacheChange,
 }
 
 impl HistoryCache {
-    fn new() -> HistoryCache {
+    /// Creates a HistoryCache keeping all the changes, without limits.
+    pub fn new() -> HistoryCache {
+        HistoryCache::with_qos(
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy::UNLIMITED,
+        )
+    }
+
+    pub fn with_qos(
+        history: HistoryQosPolicy,
+        resource_limits: ResourceLimitsQosPolicy,
+    ) -> HistoryCache {
         HistoryCache {
-            changes: Vec::new(),
+            changes: BTreeMap::new(),
+            instances: BTreeMap::new(),
+            history,
+            resource_limits,
+            len: 0,
+            next_order: 0,
+            subscribers: Vec::new(),
+            store: None,
+        }
+    }
+
+    /// Creates the HistoryCache of the writer, holding its changes from the
+    /// store within the limits of the QoS, which then stores the changes added
+    /// to the HistoryCache and removes the ones removed from it. The stored
+    /// changes that don't fit are removed from the store, and the ones of
+    /// other writers sharing the store are left to them.
+    pub fn with_store(
+        writer_guid: &GUID_t,
+        history: HistoryQosPolicy,
+        resource_limits: ResourceLimitsQosPolicy,
+        mut store: Box<dyn HistoryStore>,
+    ) -> io::Result<HistoryCache> {
+        let mut history_cache = HistoryCache::with_qos(history, resource_limits);
+        let mut dropped = Vec::new();
+        for change in store.load(writer_guid)? {
+            match history_cache.add_change(change) {
+                AddChangeResult::Added => {}
+                AddChangeResult::ReplacedOldest(change) | AddChangeResult::Rejected(change, _) => {
+                    dropped.push(change)
+                }
+            }
         }
+        for change in dropped {
+            store.remove(&change.writer_guid, change.sequence_number)?;
+        }
+
+        history_cache.store = Some(store);
+        Ok(history_cache)
+    }
+
+    /// The highest sequence number of the changes of the writer ever added to
+    /// the store of the HistoryCache, if it has one, even if they were removed
+    /// since.
+    pub fn last_stored_sequence_number(&self, writer_guid: &GUID_t) -> Option<SequenceNumber_t> {
+        self.store
+            .as_ref()
+            .and_then(|store| store.last_sequence_number(writer_guid))
+    }
+
+    pub fn history(&self) -> HistoryQosPolicy {
+        self.history
+    }
+
+    pub fn resource_limits(&self) -> ResourceLimitsQosPolicy {
+        self.resource_limits
     }
 
-    fn add_change(&mut self, change: CacheChange) {
-        self.changes.push(change)
+    /// Returns a stream of the changes made to the HistoryCache from now on.
+    /// Each subscriber gets all the events; a subscriber that is dropped is
+    /// forgotten at the next event.
+    pub fn subscribe(&mut self) -> HistoryCacheEvents {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(sender);
+        receiver
     }
 
-    fn get_change(&self, sequence_number: SequenceNumber_t) -> Option<&CacheChange> {
+    fn notify(&mut self, event: HistoryCacheEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(event).is_ok());
+    }
+
+    /// Notifies the subscribers that all the changes of the writer up to and
+    /// including the sequence number were acknowledged by all the matched
+    /// readers. The changes are kept.
+    pub fn acknowledge_changes_up_to(
+        &mut self,
+        writer_guid: GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) {
+        self.notify(HistoryCacheEvent::Acknowledged {
+            writer_guid,
+            sequence_number,
+        });
+    }
+
+    /// Number of changes held, from all the writers.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of instances with at least one change held.
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Number of changes held for the instance.
+    pub fn instance_len(&self, instance_handle: &InstanceHandle_t) -> usize {
+        self.instances.get(instance_handle).map_or(0, BTreeMap::len)
+    }
+
+    /// Inserts the change, according to the HISTORY and RESOURCE_LIMITS QoS.
+    ///
+    /// With KEEP_LAST, once the instance holds depth changes its oldest one
+    /// makes room for the new one. With KEEP_ALL, the change is rejected once
+    /// the instance holds max_samples_per_instance changes. In both cases the
+    /// change is rejected if it would exceed max_samples or max_instances, or
+    /// if the store of the HistoryCache fails to store it.
+    pub fn add_change(&mut self, change: CacheChange) -> AddChangeResult {
+        if self
+            .get_writer_change(&change.writer_guid, change.sequence_number)
+            .is_some()
+        {
+            return AddChangeResult::Rejected(change, RejectedReason::Duplicate);
+        }
+
+        let instance_len = self.instance_len(&change.instance_handle);
+        if instance_len == 0
+            && limit_reached(self.instances.len(), self.resource_limits.max_instances)
+        {
+            return AddChangeResult::Rejected(change, RejectedReason::InstancesLimit);
+        }
+
+        let replace_oldest = match self.history {
+            HistoryQosPolicy::KeepLast { depth } => {
+                instance_len > 0 && limit_reached(instance_len, depth)
+            }
+            HistoryQosPolicy::KeepAll => {
+                if limit_reached(instance_len, self.resource_limits.max_samples_per_instance) {
+                    return AddChangeResult::Rejected(
+                        change,
+                        RejectedReason::SamplesPerInstanceLimit,
+                    );
+                }
+                false
+            }
+        };
+        if !replace_oldest && limit_reached(self.len, self.resource_limits.max_samples) {
+            return AddChangeResult::Rejected(change, RejectedReason::SamplesLimit);
+        }
+
+        if let Some(store) = self.store.as_mut() {
+            if let Err(e) = store.store(&change) {
+                return AddChangeResult::Rejected(change, RejectedReason::StoreFailed(e.kind()));
+            }
+        }
+
+        // The oldest change is replaced even if the store fails to remove it:
+        // it is then dropped once the stored changes are loaded again, as it
+        // doesn't fit in the history.
+        let replaced = if replace_oldest {
+            let (writer_guid, sequence_number) = *self.instances[&change.instance_handle]
+                .values()
+                .next()
+                .unwrap();
+            if let Some(store) = self.store.as_mut() {
+                if let Err(e) = store.remove(&writer_guid, sequence_number) {
+                    error!(
+                        "failed to remove stored change {} of writer {}: {}",
+                        sequence_number, writer_guid, e
+                    );
+                }
+            }
+            self.take_writer_change(&writer_guid, sequence_number)
+        } else {
+            None
+        };
+
+        self.notify(HistoryCacheEvent::Added {
+            writer_guid: change.writer_guid,
+            sequence_number: change.sequence_number,
+            instance_handle: change.instance_handle,
+        });
+
+        let order = self.next_order;
+        self.next_order += 1;
+        self.instances
+            .entry(change.instance_handle)
+            .or_default()
+            .insert(order, (change.writer_guid, change.sequence_number));
         self.changes
-            .iter()
-            .find(|x| x.sequence_number == sequence_number)
+            .entry(change.writer_guid)
+            .or_default()
+            .insert(change.sequence_number, Entry { order, change });
+        self.len += 1;
+
+        match replaced {
+            Some(replaced) => AddChangeResult::ReplacedOldest(replaced),
+            None => AddChangeResult::Added,
+        }
     }
 
-    fn remove_change(&mut self, sequence_number: SequenceNumber_t) {
+    /// Looks up the change with the sequence number, from whichever writer.
+    /// Meant for the writer side, where all the changes have the same writer.
+    pub fn get_change(&self, sequence_number: SequenceNumber_t) -> Option<&CacheChange> {
         self.changes
-            .retain(|x| x.sequence_number != sequence_number)
+            .values()
+            .find_map(|changes| changes.get(&sequence_number))
+            .map(|entry| &entry.change)
     }
 
-    fn get_seq_num_min(&self) -> Option<&SequenceNumber_t> {
+    pub fn get_writer_change(
+        &self,
+        writer_guid: &GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) -> Option<&CacheChange> {
         self.changes
+            .get(writer_guid)
+            .and_then(|changes| changes.get(&sequence_number))
+            .map(|entry| &entry.change)
+    }
+
+    /// Removes the change with the sequence number, from whichever writer.
+    pub fn remove_change(
+        &mut self,
+        sequence_number: SequenceNumber_t,
+    ) -> io::Result<Option<CacheChange>> {
+        let writer_guid = match self
+            .changes
             .iter()
-            .map(|x| &x.sequence_number)
-            .min_by(|x, y| x.cmp(&y))
+            .find(|(_, changes)| changes.contains_key(&sequence_number))
+        {
+            Some((writer_guid, _)) => *writer_guid,
+            None => return Ok(None),
+        };
+        self.remove_writer_change(&writer_guid, sequence_number)
+    }
+
+    /// Removes the change of the writer with the sequence number, unless the
+    /// store of the HistoryCache fails to remove it.
+    pub fn remove_writer_change(
+        &mut self,
+        writer_guid: &GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) -> io::Result<Option<CacheChange>> {
+        if self
+            .get_writer_change(writer_guid, sequence_number)
+            .is_none()
+        {
+            return Ok(None);
+        }
+        if let Some(store) = self.store.as_mut() {
+            store.remove(writer_guid, sequence_number)?;
+        }
+        Ok(self.take_writer_change(writer_guid, sequence_number))
     }
 
-    fn get_seq_num_max(&self) -> Option<&SequenceNumber_t> {
+    /// Removes the changes of all the writers with a sequence number up to and
+    /// including the given one, returning how many were removed.
+    pub fn remove_changes_up_to(&mut self, sequence_number: SequenceNumber_t) -> io::Result<usize> {
+        let writer_guids: Vec<GUID_t> = self.changes.keys().copied().collect();
+        let mut removed = 0;
+        for writer_guid in &writer_guids {
+            removed += self.remove_writer_changes_up_to(writer_guid, sequence_number)?;
+        }
+        Ok(removed)
+    }
+
+    /// Removes the changes of the writer with a sequence number up to and
+    /// including the given one, returning how many were removed. If the store
+    /// of the HistoryCache fails to remove one, it is kept along with the
+    /// following ones.
+    pub fn remove_writer_changes_up_to(
+        &mut self,
+        writer_guid: &GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) -> io::Result<usize> {
+        let changes = match self.changes.get_mut(writer_guid) {
+            Some(changes) => changes,
+            None => return Ok(0),
+        };
+
+        let mut removed = match changes.keys().next() {
+            Some(&first) if first <= sequence_number => {
+                let kept = match sequence_number.checked_add(&SequenceNumber_t::from(1)) {
+                    Some(next) => changes.split_off(&next),
+                    None => BTreeMap::new(),
+                };
+                std::mem::replace(changes, kept)
+            }
+            _ => BTreeMap::new(),
+        };
+
+        let mut result = Ok(());
+        if let Some(store) = self.store.as_mut() {
+            let failed = removed.keys().copied().find(|sequence_number| {
+                match store.remove(writer_guid, *sequence_number) {
+                    Ok(()) => false,
+                    Err(e) => {
+                        result = Err(e);
+                        true
+                    }
+                }
+            });
+            if let Some(failed) = failed {
+                changes.append(&mut removed.split_off(&failed));
+            }
+        }
+
+        if changes.is_empty() {
+            self.changes.remove(writer_guid);
+        }
+        for entry in removed.values() {
+            self.unindex(entry);
+        }
+        result.map(|()| removed.len())
+    }
+
+    /// Removes the change of the writer from the HistoryCache, but not from
+    /// its store.
+    fn take_writer_change(
+        &mut self,
+        writer_guid: &GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) -> Option<CacheChange> {
+        let changes = self.changes.get_mut(writer_guid)?;
+        let entry = changes.remove(&sequence_number);
+        if changes.is_empty() {
+            self.changes.remove(writer_guid);
+        }
+
+        let entry = entry?;
+        self.unindex(&entry);
+        Some(entry.change)
+    }
+
+    /// Removes the change of the entry from the instance index and notifies
+    /// its removal.
+    fn unindex(&mut self, entry: &Entry) {
+        let instance_handle = &entry.change.instance_handle;
+        if let Some(instance) = self.instances.get_mut(instance_handle) {
+            instance.remove(&entry.order);
+            if instance.is_empty() {
+                self.instances.remove(instance_handle);
+            }
+        }
+        self.len -= 1;
+
+        self.notify(HistoryCacheEvent::Removed {
+            writer_guid: entry.change.writer_guid,
+            sequence_number: entry.change.sequence_number,
+            instance_handle: entry.change.instance_handle,
+        });
+    }
+
+    /// Smallest sequence number among the changes of all the writers.
+    pub fn get_seq_num_min(&self) -> Option<&SequenceNumber_t> {
         self.changes
-            .iter()
-            .map(|x| &x.sequence_number)
-            .max_by(|x, y| x.cmp(&y))
+            .values()
+            .filter_map(|changes| changes.keys().next())
+            .min()
+    }
+
+    /// Largest sequence number among the changes of all the writers.
+    pub fn get_seq_num_max(&self) -> Option<&SequenceNumber_t> {
+        self.changes
+            .values()
+            .filter_map(|changes| changes.keys().next_back())
+            .max()
+    }
+
+    pub fn get_writer_seq_num_min(&self, writer_guid: &GUID_t) -> Option<&SequenceNumber_t> {
+        self.changes
+            .get(writer_guid)
+            .and_then(|changes| changes.keys().next())
+    }
+
+    pub fn get_writer_seq_num_max(&self, writer_guid: &GUID_t) -> Option<&SequenceNumber_t> {
+        self.changes
+            .get(writer_guid)
+            .and_then(|changes| changes.keys().next_back())
+    }
+
+    /// All the changes, ordered by writer and then by sequence number.
+    pub fn changes(&self) -> impl Iterator<Item = &CacheChange> {
+        self.changes
+            .values()
+            .flat_map(BTreeMap::values)
+            .map(|entry| &entry.change)
+    }
+
+    /// The changes with a sequence number in the range, ordered by writer and
+    /// then by sequence number.
+    pub fn changes_in_range<R>(&self, range: R) -> impl Iterator<Item = &CacheChange>
+    where
+        R: RangeBounds<SequenceNumber_t> + Clone,
+    {
+        self.changes
+            .values()
+            .flat_map(move |changes| changes.range(range.clone()).map(|(_, entry)| &entry.change))
+    }
+
+    /// The changes of the writer with a sequence number in the range, in
+    /// sequence number order.
+    pub fn writer_changes_in_range<R>(
+        &self,
+        writer_guid: &GUID_t,
+        range: R,
+    ) -> impl Iterator<Item = &CacheChange>
+    where
+        R: RangeBounds<SequenceNumber_t>,
+    {
+        self.changes
+            .get(writer_guid)
+            .map(|changes| changes.range(range).map(|(_, entry)| &entry.change))
+            .into_iter()
+            .flatten()
+    }
+
+    /// The changes of the instance, in the order they were added.
+    pub fn instance_changes(
+        &self,
+        instance_handle: &InstanceHandle_t,
+    ) -> impl Iterator<Item = &CacheChange> {
+        self.instances
+            .get(instance_handle)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+            .map(move |(writer_guid, sequence_number)| {
+                &self.changes[writer_guid][sequence_number].change
+            })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dds::qos::LENGTH_UNLIMITED;
     use crate::structure::change_kind::ChangeKind_t;
-    use crate::structure::data::Data;
     use crate::structure::entity_id::EntityId_t;
     use crate::structure::guid::GUID_t;
     use crate::structure::guid_prefix::GuidPrefix_t;
+    use crate::structure::history_store::FileHistoryStore;
     use crate::structure::instance_handle::InstanceHandle_t;
+    use futures::executor::block_on;
+    use futures::stream::StreamExt;
 
     #[test]
     fn add_change_test() {
         let mut history_cache = HistoryCache::new();
-        let cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::SEQUENCENUMBER_UNKNOWN,
-            data_value: Data {},
-        };
+        let cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::SEQUENCENUMBER_UNKNOWN,
+            None,
+        );
 
-        assert_eq!(0, history_cache.changes.len());
+        assert_eq!(0, history_cache.len());
 
         history_cache.add_change(cache_change);
-        assert_eq!(1, history_cache.changes.len());
+        assert_eq!(1, history_cache.len());
     }
 
     #[test]
     fn remove_change_test() {
         let mut history_cache = HistoryCache::new();
 
-        assert_eq!(0, history_cache.changes.len());
+        assert_eq!(0, history_cache.len());
 
-        let cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(10),
-            data_value: Data {},
-        };
+        let cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(10),
+            None,
+        );
         history_cache.add_change(cache_change);
-        assert_eq!(1, history_cache.changes.len());
+        assert_eq!(1, history_cache.len());
 
-        let cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(7),
-            data_value: Data {},
-        };
+        let cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(7),
+            None,
+        );
         history_cache.add_change(cache_change);
-        assert_eq!(2, history_cache.changes.len());
+        assert_eq!(2, history_cache.len());
 
-        history_cache.remove_change(SequenceNumber_t::from(7));
-        assert_eq!(1, history_cache.changes.len());
+        history_cache
+            .remove_change(SequenceNumber_t::from(7))
+            .unwrap();
+        assert_eq!(1, history_cache.len());
     }
 
     #[test]
     fn get_seq_num_min() {
         let mut history_cache = HistoryCache::new();
 
-        let small_cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(1),
-            data_value: Data {},
-        };
+        let small_cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(1),
+            None,
+        );
         history_cache.add_change(small_cache_change);
 
-        let big_cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(7),
-            data_value: Data {},
-        };
+        let big_cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(7),
+            None,
+        );
         history_cache.add_change(big_cache_change);
 
         let smalles_cache_change = history_cache.get_seq_num_min();
@@ -131,27 +649,27 @@ mod tests {
     fn get_seq_num_max() {
         let mut history_cache = HistoryCache::new();
 
-        let small_cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t::GUID_UNKNOWN,
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(1),
-            data_value: Data {},
-        };
+        let small_cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t::GUID_UNKNOWN,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(1),
+            None,
+        );
         history_cache.add_change(small_cache_change);
 
-        let big_cache_change = CacheChange {
-            kind: ChangeKind_t::ALIVE,
-            writer_guid: GUID_t {
+        let big_cache_change = CacheChange::new(
+            ChangeKind_t::ALIVE,
+            GUID_t {
                 entity_id: EntityId_t::ENTITYID_UNKNOWN,
                 guid_prefix: GuidPrefix_t {
                     entity_key: [0x00; 12],
                 },
             },
-            instance_handle: InstanceHandle_t::default(),
-            sequence_number: SequenceNumber_t::from(7),
-            data_value: Data {},
-        };
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(7),
+            None,
+        );
         history_cache.add_change(big_cache_change);
 
         let biggest_cache_change = history_cache.get_seq_num_max();
@@ -159,4 +677,589 @@ mod tests {
         assert_eq!(true, biggest_cache_change.is_some());
         assert_eq!(&SequenceNumber_t::from(7), biggest_cache_change.unwrap());
     }
+
+    fn cache_change(writer_guid: GUID_t, sequence_number: i64) -> CacheChange {
+        CacheChange::new(
+            ChangeKind_t::ALIVE,
+            writer_guid,
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(sequence_number),
+            None,
+        )
+    }
+
+    fn sequence_numbers<'a>(changes: impl Iterator<Item = &'a CacheChange>) -> Vec<i64> {
+        changes
+            .map(|change| i64::from(change.sequence_number))
+            .collect()
+    }
+
+    #[test]
+    fn get_change_test() {
+        let mut history_cache = HistoryCache::new();
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 3));
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1));
+
+        assert_eq!(
+            Some(&cache_change(GUID_t::GUID_UNKNOWN, 3)),
+            history_cache.get_change(SequenceNumber_t::from(3))
+        );
+        assert_eq!(None, history_cache.get_change(SequenceNumber_t::from(2)));
+    }
+
+    #[test]
+    fn add_change_rejects_same_sequence_number() {
+        let mut history_cache = HistoryCache::new();
+
+        assert_eq!(
+            AddChangeResult::Added,
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1))
+        );
+        assert_eq!(
+            AddChangeResult::Rejected(
+                cache_change(GUID_t::GUID_UNKNOWN, 1),
+                RejectedReason::Duplicate
+            ),
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1))
+        );
+        assert_eq!(1, history_cache.len());
+    }
+
+    #[test]
+    fn changes_are_ordered_by_sequence_number() {
+        let mut history_cache = HistoryCache::new();
+        for sequence_number in &[5, 2, 9, 1, 7] {
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, *sequence_number));
+        }
+
+        assert_eq!(
+            vec![1, 2, 5, 7, 9],
+            sequence_numbers(history_cache.changes())
+        );
+        assert_eq!(
+            vec![2, 5, 7],
+            sequence_numbers(
+                history_cache
+                    .changes_in_range(SequenceNumber_t::from(2)..SequenceNumber_t::from(9))
+            )
+        );
+        assert_eq!(
+            vec![7, 9],
+            sequence_numbers(history_cache.changes_in_range(SequenceNumber_t::from(6)..))
+        );
+    }
+
+    #[test]
+    fn remove_changes_up_to_test() {
+        let mut history_cache = HistoryCache::new();
+        for sequence_number in 1..=10 {
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, sequence_number));
+        }
+
+        assert_eq!(
+            0,
+            history_cache
+                .remove_changes_up_to(SequenceNumber_t::from(0))
+                .unwrap()
+        );
+        assert_eq!(
+            4,
+            history_cache
+                .remove_changes_up_to(SequenceNumber_t::from(4))
+                .unwrap()
+        );
+        assert_eq!(
+            0,
+            history_cache
+                .remove_changes_up_to(SequenceNumber_t::from(4))
+                .unwrap()
+        );
+        assert_eq!(
+            Some(&SequenceNumber_t::from(5)),
+            history_cache.get_seq_num_min()
+        );
+        assert_eq!(
+            Some(&SequenceNumber_t::from(10)),
+            history_cache.get_seq_num_max()
+        );
+
+        assert_eq!(
+            6,
+            history_cache
+                .remove_changes_up_to(SequenceNumber_t::from(20))
+                .unwrap()
+        );
+        assert!(history_cache.is_empty());
+        assert_eq!(None, history_cache.get_seq_num_min());
+        assert_eq!(None, history_cache.get_seq_num_max());
+    }
+
+    #[test]
+    fn changes_are_indexed_by_writer() {
+        let writer_1: GUID_t = "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.01.02"
+            .parse()
+            .unwrap();
+        let writer_2: GUID_t = "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.02.02"
+            .parse()
+            .unwrap();
+
+        let mut history_cache = HistoryCache::new();
+        for sequence_number in 1..=3 {
+            history_cache.add_change(cache_change(writer_1, sequence_number));
+        }
+        for sequence_number in 2..=6 {
+            history_cache.add_change(cache_change(writer_2, sequence_number));
+        }
+        assert_eq!(8, history_cache.len());
+
+        assert_eq!(
+            Some(&cache_change(writer_2, 3)),
+            history_cache.get_writer_change(&writer_2, SequenceNumber_t::from(3))
+        );
+        assert_eq!(
+            None,
+            history_cache.get_writer_change(&writer_1, SequenceNumber_t::from(4))
+        );
+        assert_eq!(
+            Some(&SequenceNumber_t::from(3)),
+            history_cache.get_writer_seq_num_max(&writer_1)
+        );
+        assert_eq!(
+            Some(&SequenceNumber_t::from(2)),
+            history_cache.get_writer_seq_num_min(&writer_2)
+        );
+        assert_eq!(
+            Some(&SequenceNumber_t::from(1)),
+            history_cache.get_seq_num_min()
+        );
+        assert_eq!(
+            Some(&SequenceNumber_t::from(6)),
+            history_cache.get_seq_num_max()
+        );
+        assert_eq!(
+            vec![4, 5],
+            sequence_numbers(history_cache.writer_changes_in_range(
+                &writer_2,
+                SequenceNumber_t::from(4)..=SequenceNumber_t::from(5)
+            ))
+        );
+
+        assert_eq!(
+            2,
+            history_cache
+                .remove_writer_changes_up_to(&writer_2, SequenceNumber_t::from(3))
+                .unwrap()
+        );
+        assert_eq!(
+            vec![1, 2, 3, 4, 5, 6],
+            sequence_numbers(history_cache.changes())
+        );
+
+        assert_eq!(
+            Some(cache_change(writer_1, 2)),
+            history_cache
+                .remove_writer_change(&writer_1, SequenceNumber_t::from(2))
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            history_cache
+                .remove_writer_change(&writer_2, SequenceNumber_t::from(2))
+                .unwrap()
+        );
+        assert_eq!(5, history_cache.len());
+    }
+
+    #[test]
+    fn many_changes() {
+        let mut history_cache = HistoryCache::new();
+        for sequence_number in 1..=100_000 {
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, sequence_number));
+        }
+
+        for sequence_number in (1..=100_000).step_by(1000) {
+            assert!(history_cache
+                .get_change(SequenceNumber_t::from(sequence_number))
+                .is_some());
+        }
+        assert_eq!(
+            99_990,
+            history_cache
+                .remove_changes_up_to(SequenceNumber_t::from(99_990))
+                .unwrap()
+        );
+        assert_eq!(
+            (99_991..=100_000).collect::<Vec<_>>(),
+            sequence_numbers(history_cache.changes())
+        );
+    }
+
+    fn instance(key: u8) -> InstanceHandle_t {
+        InstanceHandle_t {
+            entity_key: [key; 16],
+        }
+    }
+
+    fn instance_change(key: u8, sequence_number: i64) -> CacheChange {
+        CacheChange {
+            instance_handle: instance(key),
+            ..cache_change(GUID_t::GUID_UNKNOWN, sequence_number)
+        }
+    }
+
+    #[test]
+    fn keep_last_replaces_oldest_change_of_instance() {
+        let mut history_cache = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepLast { depth: 2 },
+            ResourceLimitsQosPolicy::UNLIMITED,
+        );
+
+        assert_eq!(
+            AddChangeResult::Added,
+            history_cache.add_change(instance_change(1, 1))
+        );
+        assert_eq!(
+            AddChangeResult::Added,
+            history_cache.add_change(instance_change(2, 2))
+        );
+        assert_eq!(
+            AddChangeResult::Added,
+            history_cache.add_change(instance_change(1, 3))
+        );
+        assert_eq!(
+            AddChangeResult::ReplacedOldest(instance_change(1, 1)),
+            history_cache.add_change(instance_change(1, 4))
+        );
+        assert_eq!(
+            AddChangeResult::ReplacedOldest(instance_change(1, 3)),
+            history_cache.add_change(instance_change(1, 5))
+        );
+
+        assert_eq!(3, history_cache.len());
+        assert_eq!(2, history_cache.instance_count());
+        assert_eq!(
+            vec![4, 5],
+            sequence_numbers(history_cache.instance_changes(&instance(1)))
+        );
+        assert_eq!(
+            vec![2],
+            sequence_numbers(history_cache.instance_changes(&instance(2)))
+        );
+    }
+
+    #[test]
+    fn keep_last_is_bounded_by_max_samples() {
+        let mut history_cache = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepLast { depth: 1 },
+            ResourceLimitsQosPolicy {
+                max_samples: 2,
+                ..ResourceLimitsQosPolicy::UNLIMITED
+            },
+        );
+
+        history_cache.add_change(instance_change(1, 1));
+        history_cache.add_change(instance_change(2, 2));
+        assert_eq!(
+            AddChangeResult::Rejected(instance_change(3, 3), RejectedReason::SamplesLimit),
+            history_cache.add_change(instance_change(3, 3))
+        );
+        assert_eq!(
+            AddChangeResult::ReplacedOldest(instance_change(2, 2)),
+            history_cache.add_change(instance_change(2, 4))
+        );
+    }
+
+    #[test]
+    fn keep_all_rejects_changes_over_the_limits() {
+        let mut history_cache = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy {
+                max_samples: 4,
+                max_instances: 2,
+                max_samples_per_instance: 3,
+                max_sample_size: LENGTH_UNLIMITED,
+            },
+        );
+
+        for sequence_number in 1..=3 {
+            assert_eq!(
+                AddChangeResult::Added,
+                history_cache.add_change(instance_change(1, sequence_number))
+            );
+        }
+        assert_eq!(
+            AddChangeResult::Rejected(
+                instance_change(1, 4),
+                RejectedReason::SamplesPerInstanceLimit
+            ),
+            history_cache.add_change(instance_change(1, 4))
+        );
+        assert_eq!(
+            AddChangeResult::Added,
+            history_cache.add_change(instance_change(2, 4))
+        );
+        assert_eq!(
+            AddChangeResult::Rejected(instance_change(2, 5), RejectedReason::SamplesLimit),
+            history_cache.add_change(instance_change(2, 5))
+        );
+
+        history_cache
+            .remove_changes_up_to(SequenceNumber_t::from(2))
+            .unwrap();
+        assert_eq!(
+            AddChangeResult::Rejected(instance_change(3, 5), RejectedReason::InstancesLimit),
+            history_cache.add_change(instance_change(3, 5))
+        );
+
+        history_cache
+            .remove_changes_up_to(SequenceNumber_t::from(3))
+            .unwrap();
+        assert_eq!(1, history_cache.instance_count());
+        assert_eq!(
+            AddChangeResult::Added,
+            history_cache.add_change(instance_change(3, 5))
+        );
+        assert_eq!(0, history_cache.instance_len(&instance(1)));
+        assert_eq!(1, history_cache.instance_len(&instance(3)));
+    }
+
+    #[test]
+    fn unlimited_resource_limits() {
+        let mut history_cache = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy {
+                max_samples: LENGTH_UNLIMITED,
+                max_instances: 1,
+                max_samples_per_instance: LENGTH_UNLIMITED,
+                max_sample_size: LENGTH_UNLIMITED,
+            },
+        );
+
+        for sequence_number in 1..=1000 {
+            assert_eq!(
+                AddChangeResult::Added,
+                history_cache.add_change(instance_change(1, sequence_number))
+            );
+        }
+        assert_eq!(1000, history_cache.instance_len(&instance(1)));
+    }
+
+    fn added(sequence_number: i64) -> HistoryCacheEvent {
+        HistoryCacheEvent::Added {
+            writer_guid: GUID_t::GUID_UNKNOWN,
+            sequence_number: SequenceNumber_t::from(sequence_number),
+            instance_handle: InstanceHandle_t::default(),
+        }
+    }
+
+    fn removed(sequence_number: i64) -> HistoryCacheEvent {
+        HistoryCacheEvent::Removed {
+            writer_guid: GUID_t::GUID_UNKNOWN,
+            sequence_number: SequenceNumber_t::from(sequence_number),
+            instance_handle: InstanceHandle_t::default(),
+        }
+    }
+
+    #[test]
+    fn subscribers_get_all_events() {
+        let mut history_cache = HistoryCache::with_qos(
+            HistoryQosPolicy::KeepLast { depth: 2 },
+            ResourceLimitsQosPolicy::UNLIMITED,
+        );
+        let first = history_cache.subscribe();
+
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1));
+        let second = history_cache.subscribe();
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 2));
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 3));
+        history_cache.acknowledge_changes_up_to(GUID_t::GUID_UNKNOWN, SequenceNumber_t::from(3));
+        history_cache
+            .remove_changes_up_to(SequenceNumber_t::from(3))
+            .unwrap();
+        drop(history_cache);
+
+        let acknowledged = HistoryCacheEvent::Acknowledged {
+            writer_guid: GUID_t::GUID_UNKNOWN,
+            sequence_number: SequenceNumber_t::from(3),
+        };
+        assert_eq!(
+            vec![
+                added(1),
+                added(2),
+                removed(1),
+                added(3),
+                acknowledged,
+                removed(2),
+                removed(3)
+            ],
+            block_on(first.collect::<Vec<_>>())
+        );
+        assert_eq!(
+            vec![
+                added(2),
+                removed(1),
+                added(3),
+                acknowledged,
+                removed(2),
+                removed(3)
+            ],
+            block_on(second.collect::<Vec<_>>())
+        );
+    }
+
+    #[test]
+    fn rejected_changes_are_not_notified() {
+        let mut history_cache = HistoryCache::new();
+        let mut events = history_cache.subscribe();
+
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1));
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1));
+        history_cache
+            .remove_change(SequenceNumber_t::from(2))
+            .unwrap();
+
+        assert_eq!(added(1), events.try_recv().unwrap());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn dropped_subscribers_are_forgotten() {
+        let mut history_cache = HistoryCache::new();
+        let events = history_cache.subscribe();
+        let mut kept_events = history_cache.subscribe();
+        drop(events);
+
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1));
+        assert_eq!(1, history_cache.subscribers.len());
+        assert_eq!(added(1), kept_events.try_recv().unwrap());
+    }
+
+    #[test]
+    fn subscriber_is_woken_up_by_new_change() {
+        let mut history_cache = HistoryCache::new();
+        let mut events = history_cache.subscribe();
+
+        let waiting = std::thread::spawn(move || block_on(events.next()));
+        history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 1));
+
+        assert_eq!(Some(added(1)), waiting.join().unwrap());
+    }
+
+    /// HistoryStore failing to store or remove the changes with the sequence
+    /// numbers.
+    #[derive(Debug)]
+    struct FailingStore {
+        failing: Vec<SequenceNumber_t>,
+    }
+
+    impl FailingStore {
+        fn check(&self, sequence_number: SequenceNumber_t) -> io::Result<()> {
+            if self.failing.contains(&sequence_number) {
+                return Err(io::Error::other("failing"));
+            }
+            Ok(())
+        }
+    }
+
+    impl HistoryStore for FailingStore {
+        fn store(&mut self, change: &CacheChange) -> io::Result<()> {
+            self.check(change.sequence_number)
+        }
+
+        fn remove(
+            &mut self,
+            _writer_guid: &GUID_t,
+            sequence_number: SequenceNumber_t,
+        ) -> io::Result<()> {
+            self.check(sequence_number)
+        }
+
+        fn load(&mut self, _writer_guid: &GUID_t) -> io::Result<Vec<CacheChange>> {
+            Ok(vec![])
+        }
+
+        fn last_sequence_number(&self, _writer_guid: &GUID_t) -> Option<SequenceNumber_t> {
+            None
+        }
+    }
+
+    #[test]
+    fn store_failures_leave_the_changes_in_step() {
+        let store = FailingStore { failing: vec![] };
+        let mut history_cache = HistoryCache::with_store(
+            &GUID_t::GUID_UNKNOWN,
+            HistoryQosPolicy::KeepAll,
+            ResourceLimitsQosPolicy::UNLIMITED,
+            Box::new(store),
+        )
+        .unwrap();
+        for sequence_number in 1..=4 {
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, sequence_number));
+        }
+        history_cache.store = Some(Box::new(FailingStore {
+            failing: vec![SequenceNumber_t::from(3), SequenceNumber_t::from(5)],
+        }));
+
+        // A change that is not stored is not added.
+        assert_eq!(
+            AddChangeResult::Rejected(
+                cache_change(GUID_t::GUID_UNKNOWN, 5),
+                RejectedReason::StoreFailed(io::ErrorKind::Other)
+            ),
+            history_cache.add_change(cache_change(GUID_t::GUID_UNKNOWN, 5))
+        );
+
+        // The changes that are not removed from the store are kept.
+        assert!(history_cache
+            .remove_change(SequenceNumber_t::from(3))
+            .is_err());
+        assert!(history_cache
+            .remove_changes_up_to(SequenceNumber_t::from(4))
+            .is_err());
+        assert_eq!(vec![3, 4], sequence_numbers(history_cache.changes()));
+    }
+
+    #[test]
+    fn changes_are_kept_in_the_store() {
+        let directory =
+            std::env::temp_dir().join(format!("rtps-history-cache-{}-store", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let open = |history| {
+            let store = FileHistoryStore::open(&directory).unwrap();
+            HistoryCache::with_store(
+                &GUID_t::GUID_UNKNOWN,
+                history,
+                ResourceLimitsQosPolicy::UNLIMITED,
+                Box::new(store),
+            )
+            .unwrap()
+        };
+
+        let mut history_cache = open(HistoryQosPolicy::KeepAll);
+        history_cache.add_change(instance_change(1, 1));
+        history_cache.add_change(instance_change(2, 2));
+        history_cache.add_change(instance_change(1, 3));
+        history_cache.add_change(instance_change(2, 4));
+        history_cache
+            .remove_change(SequenceNumber_t::from(4))
+            .unwrap();
+        drop(history_cache);
+
+        // The changes over the depth are removed from the store as well.
+        let history_cache = open(HistoryQosPolicy::KeepLast { depth: 1 });
+        assert_eq!(vec![2, 3], sequence_numbers(history_cache.changes()));
+        assert_eq!(
+            Some(SequenceNumber_t::from(4)),
+            history_cache.last_stored_sequence_number(&GUID_t::GUID_UNKNOWN)
+        );
+        drop(history_cache);
+
+        let history_cache = open(HistoryQosPolicy::KeepAll);
+        assert_eq!(vec![2, 3], sequence_numbers(history_cache.changes()));
+        assert_eq!(
+            None,
+            HistoryCache::new().last_stored_sequence_number(&GUID_t::GUID_UNKNOWN)
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
@@ -0,0 +1,628 @@
+use crate::common::size_tracking_context::SizeTrackingEndianness;
+use crate::messages::submessage_elements::parameter_list::ParameterList;
+use crate::messages::submessage_elements::representation_identifier::RepresentationIdentifier;
+use crate::structure::cache_change::CacheChange;
+use crate::structure::change_kind::ChangeKind_t;
+use crate::structure::data::Data;
+use crate::structure::guid::GUID_t;
+use crate::structure::instance_handle::InstanceHandle_t;
+use crate::structure::sample_identity::SampleIdentity;
+use crate::structure::sequence_number::SequenceNumber_t;
+use crate::structure::time::Time_t;
+use bytes::Bytes;
+use speedy::{Endianness, Readable, Writable};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Storage backend of a HistoryCache, which keeps its changes beyond the
+/// lifetime of the process, as needed by the PERSISTENT durability.
+///
+/// The HistoryCache stores every change added to it and removes every change
+/// removed from it, and is filled with the stored changes when it is created
+/// with [`HistoryCache::with_store`](super::history_cache::HistoryCache::with_store).
+pub trait HistoryStore: fmt::Debug + Send {
+    /// Stores the change, which was added to the HistoryCache.
+    fn store(&mut self, change: &CacheChange) -> io::Result<()>;
+
+    /// Removes the change of the writer with the sequence number, which was
+    /// removed from the HistoryCache. Unknown changes are ignored.
+    fn remove(&mut self, writer_guid: &GUID_t, sequence_number: SequenceNumber_t)
+        -> io::Result<()>;
+
+    /// Loads the stored changes of the writer, ordered by sequence number.
+    fn load(&mut self, writer_guid: &GUID_t) -> io::Result<Vec<CacheChange>>;
+
+    /// The highest sequence number of the changes of the writer ever stored,
+    /// even if they were removed since, so that a writer with the same GUID
+    /// resumes its sequence numbers after it.
+    fn last_sequence_number(&self, writer_guid: &GUID_t) -> Option<SequenceNumber_t>;
+}
+
+/// HistoryStore appending the changes and their removals to segment files in
+/// a directory, indexed by writer and sequence number.
+///
+/// The records are appended to the last segment, and a new segment is
+/// started once it reaches segment_size octets. The oldest segments are
+/// deleted once all their changes are removed. Each segment starts with the
+/// last sequence number of every writer, so that it is kept when the
+/// segments holding the changes are deleted.
+///
+/// The index is rebuilt from the segments when the store is opened. A record
+/// cut short by the end of the last segment, as left by a process stopped
+/// while writing it, is discarded.
+#[derive(Debug)]
+pub struct FileHistoryStore {
+    directory: PathBuf,
+    segment_size: u64,
+
+    /// Where the records of the stored changes are.
+    index: BTreeMap<GUID_t, BTreeMap<SequenceNumber_t, Location>>,
+
+    /// The number of stored changes in each segment, by segment number.
+    segments: BTreeMap<u64, usize>,
+
+    last_sequence_numbers: BTreeMap<GUID_t, SequenceNumber_t>,
+
+    /// The last segment, which the records are appended to, and its length.
+    current: File,
+    current_len: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Location {
+    segment: u64,
+    offset: u64,
+}
+
+#[derive(Debug, Readable, Writable)]
+enum Record {
+    Change(StoredChange),
+    Removed(GUID_t, SequenceNumber_t),
+    LastSequenceNumber(GUID_t, SequenceNumber_t),
+}
+
+#[derive(Debug, Readable, Writable)]
+struct StoredChange {
+    status_info: [u8; 4],
+    writer_guid: GUID_t,
+    instance_handle: InstanceHandle_t,
+    sequence_number: SequenceNumber_t,
+    data_value: Option<StoredData>,
+    inline_qos: Option<Vec<u8>>,
+    source_timestamp: Option<Time_t>,
+    reception_timestamp: Option<Time_t>,
+    related_sample_identity: Option<SampleIdentity>,
+}
+
+#[derive(Debug, Readable, Writable)]
+struct StoredData {
+    representation_identifier: RepresentationIdentifier,
+    representation_options: [u8; 2],
+    value: Vec<u8>,
+}
+
+/// The records are written with a fixed endianness, regardless of the
+/// platform.
+const ENDIANNESS: Endianness = Endianness::LittleEndian;
+
+/// Length of the header of a record, giving the length of the record that
+/// follows.
+const RECORD_HEADER_LEN: u64 = 4;
+
+impl StoredChange {
+    fn new(change: &CacheChange) -> Result<StoredChange, speedy::Error> {
+        Ok(StoredChange {
+            status_info: change.kind.status_info(),
+            writer_guid: change.writer_guid,
+            instance_handle: change.instance_handle,
+            sequence_number: change.sequence_number,
+            data_value: change.data_value.as_ref().map(|data_value| StoredData {
+                representation_identifier: data_value.representation_identifier,
+                representation_options: data_value.representation_options,
+                value: data_value.value.to_vec(),
+            }),
+            inline_qos: change
+                .inline_qos
+                .as_ref()
+                .map(|inline_qos| inline_qos.write_to_vec_with_ctx(ENDIANNESS))
+                .transpose()?,
+            source_timestamp: change.source_timestamp,
+            reception_timestamp: change.reception_timestamp,
+            related_sample_identity: change.related_sample_identity,
+        })
+    }
+
+    fn into_change(self) -> Result<CacheChange, speedy::Error> {
+        let inline_qos = match self.inline_qos {
+            Some(inline_qos) => Some(ParameterList::read_from_buffer_with_ctx(
+                SizeTrackingEndianness::new(ENDIANNESS, inline_qos.len()),
+                &inline_qos,
+            )?),
+            None => None,
+        };
+
+        Ok(CacheChange {
+            kind: ChangeKind_t::from_status_info(self.status_info),
+            writer_guid: self.writer_guid,
+            instance_handle: self.instance_handle,
+            sequence_number: self.sequence_number,
+            data_value: self.data_value.map(|data_value| Data {
+                representation_identifier: data_value.representation_identifier,
+                representation_options: data_value.representation_options,
+                value: Bytes::from(data_value.value),
+            }),
+            inline_qos,
+            source_timestamp: self.source_timestamp,
+            reception_timestamp: self.reception_timestamp,
+            related_sample_identity: self.related_sample_identity,
+        })
+    }
+}
+
+fn invalid_data(error: speedy::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl FileHistoryStore {
+    /// Starts a new segment past this number of octets.
+    pub const DEFAULT_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+    const SEGMENT_EXTENSION: &'static str = "segment";
+
+    /// Opens the store in the directory, which is created if needed, and
+    /// rebuilds its index from the segments found there.
+    pub fn open<P: AsRef<Path>>(directory: P) -> io::Result<FileHistoryStore> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+
+        let mut segment_numbers = Vec::new();
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str())
+                != Some(Self::SEGMENT_EXTENSION)
+            {
+                continue;
+            }
+            if let Some(segment) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                segment_numbers.push(segment);
+            }
+        }
+        segment_numbers.sort_unstable();
+
+        let last_segment = segment_numbers.last().copied().unwrap_or(1);
+        let current_path = Self::segment_path(&directory, last_segment);
+        let current = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&current_path)?;
+
+        let mut store = FileHistoryStore {
+            directory,
+            segment_size: Self::DEFAULT_SEGMENT_SIZE,
+            index: BTreeMap::new(),
+            segments: BTreeMap::new(),
+            last_sequence_numbers: BTreeMap::new(),
+            current,
+            current_len: 0,
+        };
+        store.segments.insert(last_segment, 0);
+        for segment in segment_numbers {
+            store.scan_segment(segment, segment == last_segment)?;
+        }
+        Ok(store)
+    }
+
+    /// Sets the number of octets past which a new segment is started.
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segment_size = segment_size;
+    }
+
+    /// The number of segment files in use.
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn segment_path(directory: &Path, segment: u64) -> PathBuf {
+        directory.join(format!("{:020}.{}", segment, Self::SEGMENT_EXTENSION))
+    }
+
+    fn current_segment(&self) -> u64 {
+        *self.segments.keys().next_back().unwrap()
+    }
+
+    /// Applies the records of the segment to the index. A truncated record
+    /// at the end of the last segment is cut off.
+    fn scan_segment(&mut self, segment: u64, is_last: bool) -> io::Result<()> {
+        let path = Self::segment_path(&self.directory, segment);
+        let buffer = fs::read(&path)?;
+        self.segments.entry(segment).or_insert(0);
+
+        let mut offset = 0;
+        while offset < buffer.len() {
+            let (record, len) = match Self::record_at(&buffer, offset) {
+                Some(record) => (
+                    Record::read_from_buffer_with_ctx(ENDIANNESS, record).map_err(invalid_data)?,
+                    RECORD_HEADER_LEN as usize + record.len(),
+                ),
+                None if is_last => {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&path)?
+                        .set_len(offset as u64)?;
+                    break;
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("truncated record in {}", path.display()),
+                    ))
+                }
+            };
+
+            let location = Location {
+                segment,
+                offset: offset as u64,
+            };
+            match record {
+                Record::Change(change) => {
+                    self.index_change(change.writer_guid, change.sequence_number, location)
+                }
+                Record::Removed(writer_guid, sequence_number) => {
+                    self.unindex_change(&writer_guid, sequence_number);
+                }
+                Record::LastSequenceNumber(writer_guid, sequence_number) => {
+                    self.update_last_sequence_number(writer_guid, sequence_number)
+                }
+            }
+            offset += len;
+        }
+
+        if is_last {
+            self.current_len = offset as u64;
+        }
+        Ok(())
+    }
+
+    /// The record at the offset of the buffer, without its header, or None if
+    /// the buffer ends before it does.
+    fn record_at(buffer: &[u8], offset: usize) -> Option<&[u8]> {
+        let start = offset + RECORD_HEADER_LEN as usize;
+        let header = buffer.get(offset..start)?;
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        buffer.get(start..start + len as usize)
+    }
+
+    fn index_change(
+        &mut self,
+        writer_guid: GUID_t,
+        sequence_number: SequenceNumber_t,
+        location: Location,
+    ) {
+        let replaced = self
+            .index
+            .entry(writer_guid)
+            .or_default()
+            .insert(sequence_number, location);
+        if let Some(replaced) = replaced {
+            self.segment_change_removed(replaced.segment);
+        }
+        *self.segments.entry(location.segment).or_insert(0) += 1;
+        self.update_last_sequence_number(writer_guid, sequence_number);
+    }
+
+    /// Removes the change from the index, returning whether it was stored.
+    fn unindex_change(&mut self, writer_guid: &GUID_t, sequence_number: SequenceNumber_t) -> bool {
+        let changes = match self.index.get_mut(writer_guid) {
+            Some(changes) => changes,
+            None => return false,
+        };
+        let location = changes.remove(&sequence_number);
+        if changes.is_empty() {
+            self.index.remove(writer_guid);
+        }
+
+        match location {
+            Some(location) => {
+                self.segment_change_removed(location.segment);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn segment_change_removed(&mut self, segment: u64) {
+        if let Some(count) = self.segments.get_mut(&segment) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    fn update_last_sequence_number(
+        &mut self,
+        writer_guid: GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) {
+        let last_sequence_number = self
+            .last_sequence_numbers
+            .entry(writer_guid)
+            .or_insert(sequence_number);
+        if *last_sequence_number < sequence_number {
+            *last_sequence_number = sequence_number;
+        }
+    }
+
+    /// Appends the record to the current segment, returning where it was
+    /// written.
+    fn append(&mut self, record: &Record) -> io::Result<Location> {
+        let record = record
+            .write_to_vec_with_ctx(ENDIANNESS)
+            .map_err(invalid_data)?;
+        let mut buffer = Vec::with_capacity(RECORD_HEADER_LEN as usize + record.len());
+        buffer.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&record);
+        self.current.write_all(&buffer)?;
+
+        let location = Location {
+            segment: self.current_segment(),
+            offset: self.current_len,
+        };
+        self.current_len += buffer.len() as u64;
+        Ok(location)
+    }
+
+    /// Starts a new segment if the current one is full, beginning with the
+    /// last sequence number of every writer.
+    fn roll_segment(&mut self) -> io::Result<()> {
+        if self.current_len < self.segment_size {
+            return Ok(());
+        }
+
+        let segment = self.current_segment() + 1;
+        self.current = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::segment_path(&self.directory, segment))?;
+        self.current_len = 0;
+        self.segments.insert(segment, 0);
+
+        let last_sequence_numbers: Vec<_> = self
+            .last_sequence_numbers
+            .iter()
+            .map(|(writer_guid, sequence_number)| (*writer_guid, *sequence_number))
+            .collect();
+        for (writer_guid, sequence_number) in last_sequence_numbers {
+            self.append(&Record::LastSequenceNumber(writer_guid, sequence_number))?;
+        }
+        self.delete_unused_segments()
+    }
+
+    /// Deletes the oldest segments without stored changes. A later segment is
+    /// kept as long as an earlier one is, for the removals it records.
+    fn delete_unused_segments(&mut self) -> io::Result<()> {
+        let current_segment = self.current_segment();
+        while let Some((&segment, &count)) = self.segments.iter().next() {
+            if segment == current_segment || count > 0 {
+                break;
+            }
+            fs::remove_file(Self::segment_path(&self.directory, segment))?;
+            self.segments.remove(&segment);
+        }
+        Ok(())
+    }
+
+    fn read_change(&self, location: Location) -> io::Result<CacheChange> {
+        let mut file = File::open(Self::segment_path(&self.directory, location.segment))?;
+        file.seek(SeekFrom::Start(location.offset))?;
+        let mut header = [0; RECORD_HEADER_LEN as usize];
+        file.read_exact(&mut header)?;
+        let mut record = vec![0; u32::from_le_bytes(header) as usize];
+        file.read_exact(&mut record)?;
+
+        match Record::read_from_buffer_with_ctx(ENDIANNESS, &record).map_err(invalid_data)? {
+            Record::Change(change) => change.into_change().map_err(invalid_data),
+            record => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a change, found {:?}", record),
+            )),
+        }
+    }
+}
+
+impl HistoryStore for FileHistoryStore {
+    fn store(&mut self, change: &CacheChange) -> io::Result<()> {
+        self.roll_segment()?;
+        let record = Record::Change(StoredChange::new(change).map_err(invalid_data)?);
+        let location = self.append(&record)?;
+        self.index_change(change.writer_guid, change.sequence_number, location);
+        Ok(())
+    }
+
+    fn remove(
+        &mut self,
+        writer_guid: &GUID_t,
+        sequence_number: SequenceNumber_t,
+    ) -> io::Result<()> {
+        if !self.unindex_change(writer_guid, sequence_number) {
+            return Ok(());
+        }
+        self.roll_segment()?;
+        self.append(&Record::Removed(*writer_guid, sequence_number))?;
+        self.delete_unused_segments()
+    }
+
+    fn load(&mut self, writer_guid: &GUID_t) -> io::Result<Vec<CacheChange>> {
+        self.index
+            .get(writer_guid)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+            .map(|location| self.read_change(*location))
+            .collect()
+    }
+
+    fn last_sequence_number(&self, writer_guid: &GUID_t) -> Option<SequenceNumber_t> {
+        self.last_sequence_numbers.get(writer_guid).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::submessage_elements::parameter::Parameter;
+    use crate::structure::parameter_id::ParameterId;
+
+    /// An empty directory of the system temporary directory, unique to the
+    /// test.
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "rtps-history-store-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn writer_guid() -> GUID_t {
+        "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.01.02"
+            .parse()
+            .unwrap()
+    }
+
+    fn change(sequence_number: i64) -> CacheChange {
+        CacheChange::new(
+            ChangeKind_t::ALIVE,
+            writer_guid(),
+            InstanceHandle_t::default(),
+            SequenceNumber_t::from(sequence_number),
+            Some(Data::new(
+                RepresentationIdentifier::CDR_LE,
+                Bytes::from(vec![sequence_number as u8; 8]),
+            )),
+        )
+    }
+
+    fn sequence_numbers(changes: &[CacheChange]) -> Vec<i64> {
+        changes
+            .iter()
+            .map(|change| i64::from(change.sequence_number))
+            .collect()
+    }
+
+    #[test]
+    fn changes_survive_reopening() {
+        let directory = directory("reopen");
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        let mut disposed = change(2);
+        disposed.kind = ChangeKind_t::NOT_ALIVE_DISPOSED;
+        let mut inline_qos = ParameterList::default();
+        inline_qos.push(Parameter::new(ParameterId::PID_TOPIC_NAME, vec![0x61; 4]));
+        disposed.inline_qos = Some(inline_qos);
+        disposed.source_timestamp = Some(Time_t::TIME_ZERO);
+        store.store(&change(1)).unwrap();
+        store.store(&disposed).unwrap();
+        store.store(&change(3)).unwrap();
+        store
+            .remove(&writer_guid(), SequenceNumber_t::from(3))
+            .unwrap();
+        let other_writer_guid = "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.02.02"
+            .parse()
+            .unwrap();
+        let mut other_writer_change = change(4);
+        other_writer_change.writer_guid = other_writer_guid;
+        store.store(&other_writer_change).unwrap();
+        drop(store);
+
+        // Only the changes of the writer are loaded.
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        assert_eq!(
+            vec![change(1), disposed],
+            store.load(&writer_guid()).unwrap()
+        );
+        assert_eq!(
+            vec![other_writer_change],
+            store.load(&other_writer_guid).unwrap()
+        );
+        assert_eq!(
+            Some(SequenceNumber_t::from(3)),
+            store.last_sequence_number(&writer_guid())
+        );
+        assert_eq!(None, store.last_sequence_number(&GUID_t::GUID_UNKNOWN));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn segments_without_changes_are_deleted() {
+        let directory = directory("segments");
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        store.set_segment_size(1);
+        for sequence_number in 1..=4 {
+            store.store(&change(sequence_number)).unwrap();
+        }
+        assert_eq!(4, store.segment_count());
+
+        // The first segment is only deleted once it has no change left, and
+        // the later ones once the earlier ones are deleted.
+        store
+            .remove(&writer_guid(), SequenceNumber_t::from(2))
+            .unwrap();
+        assert_eq!(5, store.segment_count());
+        store
+            .remove(&writer_guid(), SequenceNumber_t::from(1))
+            .unwrap();
+        store
+            .remove(&writer_guid(), SequenceNumber_t::from(3))
+            .unwrap();
+        store
+            .remove(&writer_guid(), SequenceNumber_t::from(4))
+            .unwrap();
+        assert_eq!(1, store.segment_count());
+        drop(store);
+
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        assert!(store.load(&writer_guid()).unwrap().is_empty());
+        assert_eq!(
+            Some(SequenceNumber_t::from(4)),
+            store.last_sequence_number(&writer_guid())
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn truncated_record_is_discarded() {
+        let directory = directory("truncated");
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        store.store(&change(1)).unwrap();
+        store.store(&change(2)).unwrap();
+        drop(store);
+
+        let path = FileHistoryStore::segment_path(&directory, 1);
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        assert_eq!(
+            vec![1],
+            sequence_numbers(&store.load(&writer_guid()).unwrap())
+        );
+        store.store(&change(2)).unwrap();
+        drop(store);
+
+        let mut store = FileHistoryStore::open(&directory).unwrap();
+        assert_eq!(
+            vec![1, 2],
+            sequence_numbers(&store.load(&writer_guid()).unwrap())
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,8 +1,10 @@
+use crate::common::md5::md5;
 use speedy::{Context, Readable, Reader, Writable, Writer};
 
 /// Type used to represent the identity of a data-object whose changes in value
 /// are communicated by the RTPS protocol.
-#[derive(Debug, PartialOrd, PartialEq, Ord, Eq)]
+#[derive(Copy, Clone, Debug, Hash, PartialOrd, PartialEq, Ord, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InstanceHandle_t {
     pub entity_key: [u8; 16],
 }
@@ -15,6 +17,32 @@ impl Default for InstanceHandle_t {
     }
 }
 
+impl InstanceHandle_t {
+    /// Computes the KeyHash of an instance, following 9.6.3.8, from the big
+    /// endian CDR serialization of its key fields, without encapsulation
+    /// header. The serialized key is zero-padded if the maximum size of the
+    /// serialized key of the type is at most 16 octets; otherwise the KeyHash
+    /// is the MD5 digest of the serialized key.
+    pub fn from_serialized_key(
+        serialized_key: &[u8],
+        max_serialized_key_size: usize,
+    ) -> InstanceHandle_t {
+        let mut entity_key = [0x00; 16];
+        if max_serialized_key_size <= entity_key.len() && serialized_key.len() <= entity_key.len() {
+            entity_key[..serialized_key.len()].copy_from_slice(serialized_key);
+        } else {
+            entity_key = md5(serialized_key);
+        }
+        InstanceHandle_t { entity_key }
+    }
+
+    /// Tells whether this is the KeyHash of the serialized key, as computed by
+    /// `from_serialized_key`.
+    pub fn is_key_hash_of(&self, serialized_key: &[u8], max_serialized_key_size: usize) -> bool {
+        *self == InstanceHandle_t::from_serialized_key(serialized_key, max_serialized_key_size)
+    }
+}
+
 impl<'a, C: Context> Readable<'a, C> for InstanceHandle_t {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
@@ -40,6 +68,53 @@ impl<C: Context> Writable<C> for InstanceHandle_t {
 mod tests {
     use super::*;
 
+    #[test]
+    fn key_hash_of_small_key_is_zero_padded() {
+        // A key of type long, with value 0x12345678.
+        let serialized_key = [0x12, 0x34, 0x56, 0x78];
+        let key_hash = InstanceHandle_t::from_serialized_key(&serialized_key, 4);
+
+        assert_eq!(
+            [
+                0x12, 0x34, 0x56, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00
+            ],
+            key_hash.entity_key
+        );
+        assert!(key_hash.is_key_hash_of(&serialized_key, 4));
+        assert!(!key_hash.is_key_hash_of(&[0x12, 0x34, 0x56, 0x79], 4));
+    }
+
+    #[test]
+    fn key_hash_of_unbounded_key_is_md5() {
+        // A key of type string, with value "BLUE".
+        let serialized_key = [0x00, 0x00, 0x00, 0x05, 0x42, 0x4c, 0x55, 0x45, 0x00];
+
+        assert_eq!(
+            InstanceHandle_t {
+                entity_key: md5(&serialized_key)
+            },
+            InstanceHandle_t::from_serialized_key(&serialized_key, 256)
+        );
+        assert_eq!(
+            [
+                0xca, 0xc2, 0x17, 0xc3, 0x18, 0x36, 0x3f, 0x8e, 0xf1, 0x16, 0x0e, 0xee, 0xde, 0xf9,
+                0xe8, 0x86
+            ],
+            InstanceHandle_t::from_serialized_key(&serialized_key, 256).entity_key
+        );
+    }
+
+    #[test]
+    fn key_hash_of_key_longer_than_announced_is_md5() {
+        let serialized_key = [0x01; 20];
+
+        assert_eq!(
+            md5(&serialized_key),
+            InstanceHandle_t::from_serialized_key(&serialized_key, 16).entity_key
+        );
+    }
+
     serialization_test!( type = InstanceHandle_t,
         {
             instance_handle_default,
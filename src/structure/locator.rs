@@ -1,11 +1,12 @@
 use crate::structure::locator_udp_v4::LocatorUDPv4_t;
+use crate::structure::parse_error::ParseError;
 use speedy::{Context, Readable, Reader, Writable, Writer};
 pub use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::{convert::From, net::SocketAddrV4};
+use std::{convert::From, fmt, net::SocketAddrV4, str::FromStr};
 
 pub use crate::structure::locator_kind::LocatorKind_t;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Locator_t {
     pub kind: LocatorKind_t,
     pub port: u32,
@@ -77,6 +78,100 @@ impl From<LocatorUDPv4_t> for Locator_t {
     }
 }
 
+impl Locator_t {
+    const KIND_NAMES: [(LocatorKind_t, &'static str); 4] = [
+        (LocatorKind_t::LOCATOR_KIND_INVALID, "invalid"),
+        (LocatorKind_t::LOCATOR_KIND_RESERVED, "reserved"),
+        (LocatorKind_t::LOCATOR_KIND_UDPv4, "udpv4"),
+        (LocatorKind_t::LOCATOR_KIND_UDPv6, "udpv6"),
+    ];
+}
+
+/// Written as an URL, e.g. `udpv4://10.0.0.1:7410` or `udpv6://[::1]:7400`.
+/// The address of the kinds other than UDPv4 is written in IPv6 notation.
+impl fmt::Display for Locator_t {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (_, kind) = Locator_t::KIND_NAMES
+            .iter()
+            .find(|(kind, _)| *kind == self.kind)
+            .unwrap();
+        match self.kind {
+            LocatorKind_t::LOCATOR_KIND_UDPv4 => write!(
+                f,
+                "{}://{}:{}",
+                kind,
+                Ipv4Addr::new(
+                    self.address[12],
+                    self.address[13],
+                    self.address[14],
+                    self.address[15]
+                ),
+                self.port
+            ),
+            _ => write!(
+                f,
+                "{}://[{}]:{}",
+                kind,
+                Ipv6Addr::from(self.address),
+                self.port
+            ),
+        }
+    }
+}
+
+impl FromStr for Locator_t {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = s
+            .find("://")
+            .ok_or_else(|| ParseError::MissingSeparator("://", s.to_owned()))?;
+        let (kind, address_and_port) = (&s[..separator], &s[separator + 3..]);
+
+        let kind = Locator_t::KIND_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(kind))
+            .map(|(kind, _)| *kind)
+            .ok_or_else(|| ParseError::UnknownLocatorKind(kind.to_owned()))?;
+
+        let separator = address_and_port
+            .rfind(':')
+            .ok_or_else(|| ParseError::MissingSeparator(":", s.to_owned()))?;
+        let (address, port) = (
+            &address_and_port[..separator],
+            &address_and_port[separator + 1..],
+        );
+
+        let port = port
+            .parse::<u32>()
+            .map_err(|_| ParseError::InvalidPort(port.to_owned()))?;
+
+        let invalid_address = || ParseError::InvalidAddress(address.to_owned());
+        let address = match kind {
+            LocatorKind_t::LOCATOR_KIND_UDPv4 => address
+                .parse::<Ipv4Addr>()
+                .map_err(|_| invalid_address())?
+                .to_ipv6_compatible()
+                .octets(),
+            _ => address
+                .strip_prefix('[')
+                .and_then(|address| address.strip_suffix(']'))
+                .ok_or_else(invalid_address)?
+                .parse::<Ipv6Addr>()
+                .map_err(|_| invalid_address())?
+                .octets(),
+        };
+
+        Ok(Locator_t {
+            kind,
+            port,
+            address,
+        })
+    }
+}
+
+serde_text_impl!(Locator_t);
+
 impl<'a, C: Context> Readable<'a, C> for Locator_t {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
@@ -102,6 +197,17 @@ impl<C: Context> Writable<C> for Locator_t {
     }
 }
 
+arbitrary_impl!(
+    Locator_t,
+    (any::<LocatorKind_t>(), any::<u32>(), any::<[u8; 16]>()).prop_map(|(kind, port, address)| {
+        Locator_t {
+            kind,
+            port,
+            address,
+        }
+    })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,6 +250,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_and_parse_udp_v4() {
+        let locator = Locator_t::from(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            7410,
+        ));
+        assert_eq!("udpv4://10.0.0.1:7410", locator.to_string());
+        assert_eq!(Ok(locator), "udpv4://10.0.0.1:7410".parse());
+    }
+
+    #[test]
+    fn display_and_parse_udp_v6() {
+        let locator = Locator_t::from(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 7400));
+        assert_eq!("udpv6://[::1]:7400", locator.to_string());
+        assert_eq!(Ok(locator), "udpv6://[::1]:7400".parse());
+        assert_eq!(
+            Ok(Locator_t::from(SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+                7400
+            ))),
+            "UDPv6://[0:0:0:0:0:0:0:1]:7400".parse()
+        );
+    }
+
+    #[test]
+    fn display_and_parse_invalid() {
+        assert_eq!("invalid://[::]:0", Locator_t::LOCATOR_INVALID.to_string());
+        assert_eq!(Ok(Locator_t::LOCATOR_INVALID), "invalid://[::]:0".parse());
+    }
+
+    #[test]
+    fn parse_malformed_locator() {
+        assert_eq!(
+            Err(ParseError::UnknownLocatorKind("tcpv4".to_owned())),
+            "tcpv4://10.0.0.1:7410".parse::<Locator_t>()
+        );
+        assert_eq!(
+            Err(ParseError::MissingSeparator(
+                "://",
+                "10.0.0.1:7410".to_owned()
+            )),
+            "10.0.0.1:7410".parse::<Locator_t>()
+        );
+        assert_eq!(
+            Err(ParseError::InvalidPort("port".to_owned())),
+            "udpv4://10.0.0.1:port".parse::<Locator_t>()
+        );
+        assert_eq!(
+            Err(ParseError::InvalidAddress("::1".to_owned())),
+            "udpv6://::1:7400".parse::<Locator_t>()
+        );
+        assert_eq!(
+            Err(ParseError::InvalidAddress("[::1]".to_owned())),
+            "udpv4://[::1]:7400".parse::<Locator_t>()
+        );
+    }
+
     macro_rules! conversion_test {
         ($({ $name:ident, $left:expr, $right:expr }),+) => {
             $(mod $name {
@@ -282,4 +445,6 @@ mod tests {
             ]
         }
     );
+
+    serialization_property_test!(type = Locator_t);
 }
@@ -2,6 +2,7 @@ use speedy::{Readable, Writable};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Readable, Writable)]
 #[speedy(tag_type = u32)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocatorKind_t {
     LOCATOR_KIND_INVALID = 0xFFFFFFFF,
     LOCATOR_KIND_RESERVED = 0,
@@ -9,6 +10,16 @@ pub enum LocatorKind_t {
     LOCATOR_KIND_UDPv6 = 2,
 }
 
+arbitrary_impl!(
+    LocatorKind_t,
+    prop_oneof![
+        Just(LocatorKind_t::LOCATOR_KIND_INVALID),
+        Just(LocatorKind_t::LOCATOR_KIND_RESERVED),
+        Just(LocatorKind_t::LOCATOR_KIND_UDPv4),
+        Just(LocatorKind_t::LOCATOR_KIND_UDPv6),
+    ]
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -39,4 +50,6 @@ mod tests {
             be = [0x00, 0x00, 0x00, 0x02]
         }
     );
+
+    serialization_property_test!(type = LocatorKind_t);
 }
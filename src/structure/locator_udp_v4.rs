@@ -6,6 +6,7 @@ use std::net::{Ipv4Addr, SocketAddrV4};
 /// LOCATOR_KIND_UDPv4. Need only be able to hold an IPv4 address and a port
 /// number.
 #[derive(Debug, PartialEq, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocatorUDPv4_t {
     /// The mapping between the dot-notation “a.b.c.d” of an IPv4 address and
     /// its representation as an unsigned long is as follows:
@@ -39,6 +40,11 @@ impl From<LocatorUDPv4_t> for SocketAddrV4 {
     }
 }
 
+arbitrary_impl!(
+    LocatorUDPv4_t,
+    any::<(u32, u32)>().prop_map(|(address, port)| LocatorUDPv4_t { address, port })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,4 +71,6 @@ mod tests {
             ]
         }
     );
+
+    serialization_property_test!(type = LocatorUDPv4_t);
 }
@@ -9,13 +9,16 @@ pub mod entity_id;
 pub mod guid;
 pub mod guid_prefix;
 pub mod history_cache;
+pub mod history_store;
 pub mod instance_handle;
 pub mod locator;
 pub mod locator_kind;
 pub mod locator_udp_v4;
 pub mod parameter_id;
+pub mod parse_error;
 pub mod participant;
 pub mod reliability_kind;
+pub mod sample_identity;
 pub mod sequence_number;
 pub mod sequence_number_set;
 pub mod time;
@@ -1,7 +1,8 @@
 use speedy::{Readable, Writable};
 use std::mem::size_of;
 
-#[derive(Clone, Copy, Debug, PartialEq, Readable, Writable)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParameterId {
     value: u16,
 }
@@ -51,6 +52,7 @@ impl ParameterId {
     pub const PID_PARTICIPANT_LEASE_DURATION: ParameterId = ParameterId { value: 0x0002 };
     pub const PID_CONTENT_FILTER_PROPERTY: ParameterId = ParameterId { value: 0x0035 };
     pub const PID_PARTICIPANT_GUID: ParameterId = ParameterId { value: 0x0050 };
+    pub const PID_ENDPOINT_GUID: ParameterId = ParameterId { value: 0x005a };
     pub const PID_GROUP_GUID: ParameterId = ParameterId { value: 0x0052 };
     pub const PID_GROUP_ENTITYID: ParameterId = ParameterId { value: 0x0053 };
     pub const PID_BUILTIN_ENDPOINT_SET: ParameterId = ParameterId { value: 0x0058 };
@@ -59,16 +61,121 @@ impl ParameterId {
     pub const PID_ENTITY_NAME: ParameterId = ParameterId { value: 0x0062 };
     pub const PID_KEY_HASH: ParameterId = ParameterId { value: 0x0070 };
     pub const PID_STATUS_INFO: ParameterId = ParameterId { value: 0x0071 };
+    pub const PID_BUILTIN_ENDPOINT_QOS: ParameterId = ParameterId { value: 0x0077 };
+    pub const PID_DOMAIN_ID: ParameterId = ParameterId { value: 0x000f };
+    pub const PID_DOMAIN_TAG: ParameterId = ParameterId { value: 0x4014 };
+    pub const PID_RELATED_SAMPLE_IDENTITY: ParameterId = ParameterId { value: 0x0083 };
 
     pub fn serialized_length() -> usize {
         size_of::<Self>()
     }
+
+    /// Returns the name of the parameter as used in the specification, or
+    /// None if the id is not one of the predefined ones.
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            ParameterId::PID_PAD => Some("PID_PAD"),
+            ParameterId::PID_SENTINEL => Some("PID_SENTINEL"),
+            ParameterId::PID_USER_DATA => Some("PID_USER_DATA"),
+            ParameterId::PID_TOPIC_NAME => Some("PID_TOPIC_NAME"),
+            ParameterId::PID_TYPE_NAME => Some("PID_TYPE_NAME"),
+            ParameterId::PID_GROUP_DATA => Some("PID_GROUP_DATA"),
+            ParameterId::PID_TOPIC_DATA => Some("PID_TOPIC_DATA"),
+            ParameterId::PID_DURABILITY => Some("PID_DURABILITY"),
+            ParameterId::PID_DURABILITY_SERVICE => Some("PID_DURABILITY_SERVICE"),
+            ParameterId::PID_DEADLINE => Some("PID_DEADLINE"),
+            ParameterId::PID_LATENCY_BUDGET => Some("PID_LATENCY_BUDGET"),
+            ParameterId::PID_LIVELINESS => Some("PID_LIVELINESS"),
+            ParameterId::PID_RELIABILITY => Some("PID_RELIABILITY"),
+            ParameterId::PID_LIFESPAN => Some("PID_LIFESPAN"),
+            ParameterId::PID_DESTINATION_ORDER => Some("PID_DESTINATION_ORDER"),
+            ParameterId::PID_HISTORY => Some("PID_HISTORY"),
+            ParameterId::PID_RESOURCE_LIMITS => Some("PID_RESOURCE_LIMITS"),
+            ParameterId::PID_OWNERSHIP => Some("PID_OWNERSHIP"),
+            ParameterId::PID_OWNERSHIP_STRENGTH => Some("PID_OWNERSHIP_STRENGTH"),
+            ParameterId::PID_PRESENTATION => Some("PID_PRESENTATION"),
+            ParameterId::PID_PARTITION => Some("PID_PARTITION"),
+            ParameterId::PID_TIME_BASED_FILTER => Some("PID_TIME_BASED_FILTER"),
+            ParameterId::PID_TRANSPORT_PRIO => Some("PID_TRANSPORT_PRIO"),
+            ParameterId::PID_PROTOCOL_VERSION => Some("PID_PROTOCOL_VERSION"),
+            ParameterId::PID_VENDOR_ID => Some("PID_VENDOR_ID"),
+            ParameterId::PID_UNICAST_LOCATOR => Some("PID_UNICAST_LOCATOR"),
+            ParameterId::PID_MULTICAST_LOCATOR => Some("PID_MULTICAST_LOCATOR"),
+            ParameterId::PID_MULTICAST_IPADDRESS => Some("PID_MULTICAST_IPADDRESS"),
+            ParameterId::PID_DEFAULT_UNICAST_LOCATOR => Some("PID_DEFAULT_UNICAST_LOCATOR"),
+            ParameterId::PID_DEFAULT_MULTICAST_LOCATOR => Some("PID_DEFAULT_MULTICAST_LOCATOR"),
+            ParameterId::PID_METATRAFFIC_UNICAST_LOCATOR => Some("PID_METATRAFFIC_UNICAST_LOCATOR"),
+            ParameterId::PID_METATRAFFIC_MULTICAST_LOCATOR => {
+                Some("PID_METATRAFFIC_MULTICAST_LOCATOR")
+            }
+            ParameterId::PID_DEFAULT_UNICAST_IPADDRESS => Some("PID_DEFAULT_UNICAST_IPADDRESS"),
+            ParameterId::PID_DEFAULT_UNICAST_PORT => Some("PID_DEFAULT_UNICAST_PORT"),
+            ParameterId::PID_METATRAFFIC_UNICAST_IPADDRESS => {
+                Some("PID_METATRAFFIC_UNICAST_IPADDRESS")
+            }
+            ParameterId::PID_METATRAFFIC_UNICAST_PORT => Some("PID_METATRAFFIC_UNICAST_PORT"),
+            ParameterId::PID_METATRAFFIC_MULTICAST_IPADDRESS => {
+                Some("PID_METATRAFFIC_MULTICAST_IPADDRESS")
+            }
+            ParameterId::PID_METATRAFFIC_MULTICAST_PORT => Some("PID_METATRAFFIC_MULTICAST_PORT"),
+            ParameterId::PID_EXPECTS_INLINE_QOS => Some("PID_EXPECTS_INLINE_QOS"),
+            ParameterId::PID_PARTICIPANT_MANUAL_LIVELINESS_COUNT => {
+                Some("PID_PARTICIPANT_MANUAL_LIVELINESS_COUNT")
+            }
+            ParameterId::PID_PARTICIPANT_BUILTIN_ENDPOINTS => {
+                Some("PID_PARTICIPANT_BUILTIN_ENDPOINTS")
+            }
+            ParameterId::PID_PARTICIPANT_LEASE_DURATION => Some("PID_PARTICIPANT_LEASE_DURATION"),
+            ParameterId::PID_CONTENT_FILTER_PROPERTY => Some("PID_CONTENT_FILTER_PROPERTY"),
+            ParameterId::PID_PARTICIPANT_GUID => Some("PID_PARTICIPANT_GUID"),
+            ParameterId::PID_ENDPOINT_GUID => Some("PID_ENDPOINT_GUID"),
+            ParameterId::PID_GROUP_GUID => Some("PID_GROUP_GUID"),
+            ParameterId::PID_GROUP_ENTITYID => Some("PID_GROUP_ENTITYID"),
+            ParameterId::PID_BUILTIN_ENDPOINT_SET => Some("PID_BUILTIN_ENDPOINT_SET"),
+            ParameterId::PID_PROPERTY_LIST => Some("PID_PROPERTY_LIST"),
+            ParameterId::PID_TYPE_MAX_SIZE_SERIALIZED => Some("PID_TYPE_MAX_SIZE_SERIALIZED"),
+            ParameterId::PID_ENTITY_NAME => Some("PID_ENTITY_NAME"),
+            ParameterId::PID_KEY_HASH => Some("PID_KEY_HASH"),
+            ParameterId::PID_STATUS_INFO => Some("PID_STATUS_INFO"),
+            ParameterId::PID_BUILTIN_ENDPOINT_QOS => Some("PID_BUILTIN_ENDPOINT_QOS"),
+            ParameterId::PID_DOMAIN_ID => Some("PID_DOMAIN_ID"),
+            ParameterId::PID_DOMAIN_TAG => Some("PID_DOMAIN_TAG"),
+            ParameterId::PID_RELATED_SAMPLE_IDENTITY => Some("PID_RELATED_SAMPLE_IDENTITY"),
+            _ => None,
+        }
+    }
 }
 
+impl From<ParameterId> for u16 {
+    fn from(parameter_id: ParameterId) -> Self {
+        parameter_id.value
+    }
+}
+
+arbitrary_impl!(
+    ParameterId,
+    any::<u16>().prop_map(|value| ParameterId { value })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn name_of_predefined_parameter_id() {
+        assert_eq!(
+            Some("PID_PARTICIPANT_GUID"),
+            ParameterId::PID_PARTICIPANT_GUID.name()
+        );
+        assert_eq!(Some("PID_SENTINEL"), ParameterId::PID_SENTINEL.name());
+    }
+
+    #[test]
+    fn name_of_vendor_specific_parameter_id() {
+        assert_eq!(None, ParameterId { value: 0x8001 }.name());
+        assert_eq!(0x8001, u16::from(ParameterId { value: 0x8001 }));
+    }
+
     serialization_test!( type = ParameterId,
     {
         pid_pad,
@@ -381,5 +488,13 @@ mod tests {
         ParameterId::PID_STATUS_INFO,
         le = [0x71, 0x00],
         be = [0x00, 0x71]
+    },
+    {
+        pid_related_sample_identity,
+        ParameterId::PID_RELATED_SAMPLE_IDENTITY,
+        le = [0x83, 0x00],
+        be = [0x00, 0x83]
     });
+
+    serialization_property_test!(type = ParameterId);
 }
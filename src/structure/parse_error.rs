@@ -0,0 +1,102 @@
+use std::fmt;
+
+/// Error returned when parsing the text representation of the structure types.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidOctets(usize, String),
+    MissingSeparator(&'static str, String),
+    UnknownLocatorKind(String),
+    InvalidAddress(String),
+    InvalidPort(String),
+    InvalidSequenceNumber(String),
+    OutOfRange(i64, i64),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidOctets(count, s) => {
+                write!(f, "expected {} dot separated octets, found {:?}", count, s)
+            }
+            ParseError::MissingSeparator(separator, s) => {
+                write!(f, "expected {:?} separator in {:?}", separator, s)
+            }
+            ParseError::UnknownLocatorKind(kind) => write!(f, "unknown locator kind {:?}", kind),
+            ParseError::InvalidAddress(address) => {
+                write!(f, "invalid locator address {:?}", address)
+            }
+            ParseError::InvalidPort(port) => write!(f, "invalid locator port {:?}", port),
+            ParseError::InvalidSequenceNumber(sequence_number) => {
+                write!(f, "invalid sequence number {:?}", sequence_number)
+            }
+            ParseError::OutOfRange(sequence_number, base) => write!(
+                f,
+                "sequence number {} is outside of the set starting at {}",
+                sequence_number, base
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Writes octets as lowercase hex pairs separated by dots, e.g. `01.0f.bb`.
+pub(crate) fn write_octets(f: &mut fmt::Formatter, octets: &[u8]) -> fmt::Result {
+    for (i, octet) in octets.iter().enumerate() {
+        if i > 0 {
+            f.write_str(".")?;
+        }
+        write!(f, "{:02x}", octet)?;
+    }
+    Ok(())
+}
+
+/// Parses octets written by `write_octets` into `octets`, requiring exactly
+/// `octets.len()` of them.
+pub(crate) fn parse_octets(s: &str, octets: &mut [u8]) -> Result<(), ParseError> {
+    let count = octets.len();
+    let invalid = || ParseError::InvalidOctets(count, s.to_owned());
+
+    let mut parts = s.split('.');
+    for octet in octets.iter_mut() {
+        let part = parts.next().ok_or_else(invalid)?;
+        if part.len() != 2 {
+            return Err(invalid());
+        }
+        *octet = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+    }
+
+    match parts.next() {
+        Some(_) => Err(invalid()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_octets_requires_exact_count() {
+        let mut octets = [0; 3];
+        assert_eq!(Ok(()), parse_octets("01.0f.BB", &mut octets));
+        assert_eq!([0x01, 0x0f, 0xbb], octets);
+
+        assert!(parse_octets("01.0f", &mut octets).is_err());
+        assert!(parse_octets("01.0f.bb.00", &mut octets).is_err());
+        assert!(parse_octets("01.f.bbb", &mut octets).is_err());
+        assert!(parse_octets("01.0f.zz", &mut octets).is_err());
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            "expected 12 dot separated octets, found \"01.02\"",
+            ParseError::InvalidOctets(12, "01.02".to_owned()).to_string()
+        );
+        assert_eq!(
+            "sequence number 300 is outside of the set starting at 1",
+            ParseError::OutOfRange(300, 1).to_string()
+        );
+    }
+}
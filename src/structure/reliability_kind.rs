@@ -1,6 +1,7 @@
 use speedy::{Readable, Writable};
 
 #[derive(Debug, PartialEq, Eq, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReliabilityKind_t(u32);
 
 impl ReliabilityKind_t {
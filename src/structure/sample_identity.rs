@@ -0,0 +1,32 @@
+use crate::structure::guid::GUID_t;
+use crate::structure::sequence_number::SequenceNumber_t;
+use speedy::{Readable, Writable};
+
+/// Identifies a sample by the GUID of the writer that wrote it and its
+/// sequence number. Sent in the PID_RELATED_SAMPLE_IDENTITY inline QoS to
+/// relate a sample to another one, e.g. a reply to its request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Readable, Writable)]
+pub struct SampleIdentity {
+    pub writer_guid: GUID_t,
+    pub sequence_number: SequenceNumber_t,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    serialization_test!( type = SampleIdentity,
+    {
+        sample_identity,
+        SampleIdentity {
+            writer_guid: "01.02.03.04.05.06.07.08.09.0a.0b.0c|00.00.01.02".parse().unwrap(),
+            sequence_number: SequenceNumber_t::from(0x0000_0001_0000_0002),
+        },
+        le = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+              0x09, 0x0a, 0x0b, 0x0c, 0x00, 0x00, 0x01, 0x02,
+              0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00],
+        be = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+              0x09, 0x0a, 0x0b, 0x0c, 0x00, 0x00, 0x01, 0x02,
+              0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02]
+    });
+}
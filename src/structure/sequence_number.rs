@@ -1,11 +1,13 @@
 use num_derive::{FromPrimitive, NumOps, ToPrimitive};
 use speedy::{Context, Readable, Reader, Writable, Writer};
 use std::convert::From;
+use std::fmt;
 use std::mem::size_of;
 
 #[derive(
     Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, NumOps, FromPrimitive, ToPrimitive,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SequenceNumber_t(i64);
 
 impl SequenceNumber_t {
@@ -25,6 +27,12 @@ impl From<SequenceNumber_t> for i64 {
     }
 }
 
+impl fmt::Display for SequenceNumber_t {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 checked_impl!(CheckedAdd, checked_add, SequenceNumber_t);
 checked_impl!(CheckedSub, checked_sub, SequenceNumber_t);
 checked_impl!(CheckedMul, checked_mul, SequenceNumber_t);
@@ -60,6 +68,11 @@ impl Default for SequenceNumber_t {
     }
 }
 
+arbitrary_impl!(
+    SequenceNumber_t,
+    any::<i64>().prop_map(SequenceNumber_t::from)
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,4 +101,6 @@ mod tests {
         le = [0x33, 0x22, 0x11, 0x00, 0x77, 0x66, 0x55, 0x44],
         be = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]
     });
+
+    serialization_property_test!(type = SequenceNumber_t);
 }
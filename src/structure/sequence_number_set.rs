@@ -1,12 +1,83 @@
 use crate::common::ranged_bit_set::RangedBitSet;
+use crate::structure::parse_error::ParseError;
 use crate::structure::sequence_number::SequenceNumber_t;
+use std::str::FromStr;
 
 pub type SequenceNumberSet_t = RangedBitSet<SequenceNumber_t>;
 
+fn parse_sequence_number(s: &str) -> Result<SequenceNumber_t, ParseError> {
+    s.trim()
+        .parse::<i64>()
+        .map(SequenceNumber_t::from)
+        .map_err(|_| ParseError::InvalidSequenceNumber(s.to_owned()))
+}
+
+/// Parses the format written by `Display`, e.g. `1000:[1001,1003]`.
+impl FromStr for SequenceNumberSet_t {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = s
+            .find(':')
+            .ok_or_else(|| ParseError::MissingSeparator(":", s.to_owned()))?;
+        let base = parse_sequence_number(&s[..separator])?;
+        let members = s[separator + 1..]
+            .trim()
+            .strip_prefix('[')
+            .and_then(|members| members.strip_suffix(']'))
+            .ok_or_else(|| ParseError::MissingSeparator("[]", s.to_owned()))?;
+
+        let mut set = SequenceNumberSet_t::new(base);
+        for member in members
+            .split(',')
+            .filter(|member| !member.trim().is_empty())
+        {
+            let sequence_number = parse_sequence_number(member)?;
+            if sequence_number < base || i64::from(sequence_number) - i64::from(base) > 255 {
+                return Err(ParseError::OutOfRange(sequence_number.into(), base.into()));
+            }
+            set.insert(sequence_number);
+        }
+        Ok(set)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn display_and_parse() {
+        let mut set = SequenceNumberSet_t::new(SequenceNumber_t::from(1000));
+        set.insert(SequenceNumber_t::from(1001));
+        set.insert(SequenceNumber_t::from(1003));
+
+        assert_eq!("1000:[1001,1003]", set.to_string());
+        assert_eq!(Ok(set), "1000:[1001,1003]".parse());
+        assert_eq!(
+            Ok(SequenceNumberSet_t::new(SequenceNumber_t::from(7))),
+            "7:[]".parse()
+        );
+    }
+
+    #[test]
+    fn parse_invalid_set() {
+        assert_eq!(
+            Err(ParseError::OutOfRange(1256, 1000)),
+            "1000:[1256]".parse::<SequenceNumberSet_t>()
+        );
+        assert_eq!(
+            Err(ParseError::OutOfRange(999, 1000)),
+            "1000:[999]".parse::<SequenceNumberSet_t>()
+        );
+        assert_eq!(
+            Err(ParseError::InvalidSequenceNumber("x".to_owned())),
+            "1000:[x]".parse::<SequenceNumberSet_t>()
+        );
+        assert!("1000".parse::<SequenceNumberSet_t>().is_err());
+        assert!("1000:1001".parse::<SequenceNumberSet_t>().is_err());
+    }
+
     serialization_test!( type = SequenceNumberSet_t,
     {
         sequence_number_set_empty,
@@ -34,10 +105,12 @@ mod tests {
         le = [0x00, 0x00, 0x00, 0x00,
               0xE8, 0x03, 0x00, 0x00,
               0x20, 0x00, 0x00, 0x00,
-              0x5A, 0x25, 0x00, 0x00],
+              0x00, 0x00, 0xA4, 0x5A],
         be = [0x00, 0x00, 0x00, 0x00,
               0x00, 0x00, 0x03, 0xE8,
               0x00, 0x00, 0x00, 0x20,
-              0x00, 0x00, 0x25, 0x5A]
+              0x5A, 0xA4, 0x00, 0x00]
     });
+
+    serialization_property_test!(type = SequenceNumberSet_t);
 }
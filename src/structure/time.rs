@@ -7,6 +7,7 @@ use std::time::{Duration, SystemTime};
 /// expressed in seconds and fraction of seconds using the formula:
 /// time = seconds + (fraction / 2^(32))
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Readable, Writable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Time_t {
     seconds: i32,
     fraction: u32,
@@ -60,6 +61,11 @@ impl TryFrom<Time_t> for SystemTime {
     }
 }
 
+arbitrary_impl!(
+    Time_t,
+    any::<(i32, u32)>().prop_map(|(seconds, fraction)| Time_t { seconds, fraction })
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +204,6 @@ mod tests {
         le = [0x78, 0x6E, 0x8C, 0x5A, 0x7E, 0xE0, 0x2A, 0x4F],
         be = [0x5A, 0x8C, 0x6E, 0x78, 0x4F, 0x2A, 0xE0, 0x7E]
     });
+
+    serialization_property_test!(type = Time_t);
 }
@@ -2,6 +2,7 @@ use speedy::{Readable, Writable};
 
 #[derive(Debug, PartialEq, Eq, Readable, Writable)]
 #[speedy(tag_type = u32)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TopicKind_t {
     NO_KEY = 1,
     WITH_KEY = 2,
@@ -0,0 +1,86 @@
+use crate::structure::locator::Locator_t;
+use crate::transport::message_sender::MessageSender;
+use bytes::Bytes;
+use std::collections::VecDeque;
+
+/// Transport that keeps the sent messages in memory, in the order they were
+/// sent, until they are received. It connects the writers and readers of a
+/// process without sockets, e.g. in tests.
+#[derive(Debug, Default)]
+pub struct InMemoryTransport {
+    messages: VecDeque<(Locator_t, Bytes)>,
+}
+
+impl InMemoryTransport {
+    pub fn new() -> InMemoryTransport {
+        InMemoryTransport {
+            messages: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Takes the oldest message, along with the locator it was sent to.
+    pub fn receive(&mut self) -> Option<(Locator_t, Bytes)> {
+        self.messages.pop_front()
+    }
+
+    /// Takes all the messages sent to the locator, leaving the others.
+    pub fn receive_all(&mut self, locator: &Locator_t) -> Vec<Bytes> {
+        let (received, others) = self
+            .messages
+            .drain(..)
+            .partition(|(destination, _)| destination == locator);
+        self.messages = others;
+        received.into_iter().map(|(_, message)| message).collect()
+    }
+}
+
+impl MessageSender for InMemoryTransport {
+    fn send(&mut self, locator: &Locator_t, message: Bytes) -> std::io::Result<()> {
+        self.messages.push_back((*locator, message));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::locator::{Ipv4Addr, SocketAddr};
+
+    fn locator(port: u16) -> Locator_t {
+        Locator_t::from(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+    }
+
+    #[test]
+    fn messages_are_received_in_order() {
+        let mut transport = InMemoryTransport::new();
+        transport
+            .send(&locator(7400), Bytes::from_static(b"first"))
+            .unwrap();
+        transport
+            .send(&locator(7410), Bytes::from_static(b"second"))
+            .unwrap();
+        transport
+            .send(&locator(7400), Bytes::from_static(b"third"))
+            .unwrap();
+        assert_eq!(3, transport.len());
+
+        assert_eq!(
+            vec![Bytes::from_static(b"first"), Bytes::from_static(b"third")],
+            transport.receive_all(&locator(7400))
+        );
+        assert_eq!(
+            Some((locator(7410), Bytes::from_static(b"second"))),
+            transport.receive()
+        );
+        assert!(transport.is_empty());
+        assert_eq!(None, transport.receive());
+    }
+}
@@ -0,0 +1,8 @@
+use crate::structure::locator::Locator_t;
+use bytes::Bytes;
+
+/// Sends serialized RTPS Messages to locators, on behalf of the writers and
+/// readers of a participant.
+pub trait MessageSender {
+    fn send(&mut self, locator: &Locator_t, message: Bytes) -> std::io::Result<()>;
+}